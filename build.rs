@@ -0,0 +1,35 @@
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    // The gRPC service is described in Rust (see src/grpc.rs, mirroring
+    // proto/ocr.proto), so no protoc is needed to build it
+    #[cfg(feature = "grpc")]
+    {
+        use tonic_build::manual::{Builder, Method, Service};
+
+        let method = |name: &str, route: &str, input: &str, output: &str| {
+            Method::builder()
+                .name(name)
+                .route_name(route)
+                .input_type(format!("crate::grpc::proto::{}", input))
+                .output_type(format!("crate::grpc::proto::{}", output))
+                .codec_path("tonic_prost::ProstCodec")
+        };
+        let service = Service::builder()
+            .name("Ocr")
+            .package("sanskrit_ocr.v1")
+            .method(method("upload", "Upload", "UploadRequest", "UploadReply").build())
+            .method(
+                method(
+                    "watch_session",
+                    "WatchSession",
+                    "SessionRequest",
+                    "SessionUpdate",
+                )
+                .server_streaming()
+                .build(),
+            )
+            .method(method("get_result", "GetResult", "SessionRequest", "ResultReply").build())
+            .build();
+        Builder::new().build_client(false).compile(&[service]);
+    }
+}
@@ -0,0 +1,37 @@
+#!/usr/bin/env python3
+"""OCR helper for `ocr_worker_command`: keeps a tesseract instance per language
+loaded and reads one page per JSON line on stdin, answering each with one JSON
+line on stdout. Needs tesserocr (Debian: python3-tesserocr)."""
+
+import json
+import sys
+
+from tesserocr import PSM, PyTessBaseAPI
+
+# The header row tesseract writes above its TSV report; the API leaves it out
+TSV_HEADER = "level\tpage_num\tblock_num\tpar_num\tline_num\tword_num\tleft\ttop\twidth\theight\tconf\ttext\n"
+
+
+def main():
+    apis = {}
+    try:
+        for line in sys.stdin:
+            try:
+                request = json.loads(line)
+                language = request["language"]
+                if language not in apis:
+                    apis[language] = PyTessBaseAPI(lang=language)
+                api = apis[language]
+                api.SetPageSegMode(request.get("psm") or PSM.AUTO)
+                api.SetImageFile(request["image"])
+                response = {"text": api.GetUTF8Text(), "tsv": TSV_HEADER + api.GetTSVText(0)}
+            except Exception as e:
+                response = {"error": str(e)}
+            print(json.dumps(response, ensure_ascii=False), flush=True)
+    finally:
+        for api in apis.values():
+            api.End()
+
+
+if __name__ == "__main__":
+    main()
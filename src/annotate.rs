@@ -0,0 +1,55 @@
+use crate::dictionary::aligned_tokens;
+use crate::ocr::Word;
+use serde::{Deserialize, Serialize};
+
+/// How low-confidence words are marked in the annotated text.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HighlightStyle {
+    /// `⟦word⟧`, easy to search for in any editor.
+    Brackets,
+    /// `<span class="low-confidence" data-confidence="42.0">word</span>`, with the
+    /// rest of the text HTML-escaped.
+    Html,
+}
+
+impl HighlightStyle {
+    pub fn parse(name: &str) -> Option<HighlightStyle> {
+        match name.trim().to_lowercase().as_str() {
+            "brackets" => Some(HighlightStyle::Brackets),
+            "html" => Some(HighlightStyle::Html),
+            _ => None,
+        }
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Marks every word tesseract scored below `max_confidence` so proofreaders can
+/// jump straight to dubious readings.
+pub fn highlight(text: &str, words: &[Word], max_confidence: f32, style: HighlightStyle) -> String {
+    let mut out = String::with_capacity(text.len());
+    for (token, confidence) in aligned_tokens(text, words) {
+        let low = confidence.filter(|&confidence| confidence < max_confidence);
+        match (style, low) {
+            (HighlightStyle::Brackets, Some(_)) => {
+                out.push('⟦');
+                out.push_str(token);
+                out.push('⟧');
+            }
+            (HighlightStyle::Brackets, None) => out.push_str(token),
+            (HighlightStyle::Html, Some(confidence)) => out.push_str(&format!(
+                "<span class=\"low-confidence\" data-confidence=\"{:.1}\">{}</span>",
+                confidence,
+                escape_html(token)
+            )),
+            (HighlightStyle::Html, None) => out.push_str(&escape_html(token)),
+        }
+    }
+    out
+}
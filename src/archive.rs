@@ -0,0 +1,224 @@
+use crate::convert;
+use crate::pdf::{self, PageRanges};
+use std::cmp::Ordering;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+use zip::ZipArchive;
+
+/// Image types taken from an archive; everything else in it is ignored.
+const PAGE_EXTENSIONS: [&str; 9] = [
+    "png", "jpg", "jpeg", "tif", "tiff", "webp", "heic", "heif", "avif",
+];
+
+/// Orders file names the way people number scans: digit runs compare by value,
+/// so `page2.png` comes before `page10.png`.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a = a.chars().peekable();
+    let mut b = b.chars().peekable();
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let digits = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+                    let mut run = String::new();
+                    while let Some(c) = chars.next_if(char::is_ascii_digit) {
+                        run.push(c);
+                    }
+                    run
+                };
+                let (x, y) = (digits(&mut a), digits(&mut b));
+                let (x_value, y_value) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+                let order = x_value
+                    .len()
+                    .cmp(&y_value.len())
+                    .then_with(|| x_value.cmp(y_value))
+                    .then_with(|| x.len().cmp(&y.len()));
+                if order != Ordering::Equal {
+                    return order;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let order = x.to_lowercase().cmp(y.to_lowercase());
+                if order != Ordering::Equal {
+                    return order;
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+/// Whether an archive entry is a page image rather than a folder, macOS resource
+/// fork (`__MACOSX/`, `._name`) or some other file.
+fn is_page_image(name: &str) -> bool {
+    let file_name = name.rsplit('/').next().unwrap_or(name);
+    !name.starts_with("__MACOSX/")
+        && !file_name.starts_with('.')
+        && file_name.rsplit_once('.').is_some_and(|(_, extension)| {
+            PAGE_EXTENSIONS.contains(&extension.to_lowercase().as_str())
+        })
+}
+
+/// Writes one archive entry to `output`, stopping one byte past `budget` whatever
+/// size the archive declares. Returns the bytes written.
+fn extract_page(
+    archive: &mut ZipArchive<std::fs::File>,
+    name: &str,
+    output: &Path,
+    budget: u64,
+) -> Result<u64, String> {
+    let mut entry = archive
+        .by_name(name)
+        .map_err(|e| format!("Failed to read '{}': {}", name, e))?;
+    let mut file = std::fs::File::create(output)
+        .map_err(|e| format!("Failed to extract '{}': {}", name, e))?;
+    std::io::copy(&mut (&mut entry).take(budget + 1), &mut file)
+        .map_err(|e| format!("Failed to extract '{}': {}", name, e))
+}
+
+/// Opens a ZIP archive with the names of its page images, in page order.
+fn open(path: &Path) -> Result<(ZipArchive<std::fs::File>, Vec<String>), String> {
+    let file = std::fs::File::open(path).map_err(|e| format!("Failed to open ZIP: {}", e))?;
+    let archive = ZipArchive::new(file).map_err(|e| format!("Failed to read ZIP: {}", e))?;
+
+    let mut names: Vec<String> = archive
+        .file_names()
+        .filter(|name| is_page_image(name))
+        .map(str::to_string)
+        .collect();
+    if names.is_empty() {
+        return Err(format!(
+            "The archive holds no page images ({})",
+            PAGE_EXTENSIONS.join(", ")
+        ));
+    }
+    names.sort_by(|a, b| natural_cmp(a, b));
+    Ok((archive, names))
+}
+
+/// Number of page images in a ZIP archive.
+pub fn page_count(path: &Path) -> Result<usize, String> {
+    open(path).map(|(_, names)| names.len())
+}
+
+/// Page images of a ZIP archive extracted one at a time as they are iterated,
+/// in natural order of their names (folders included), those tesseract cannot
+/// read converted to PNG. Once more than the archive's byte budget has been
+/// extracted, every page left yields that error instead. Yields the page
+/// numbers with their image paths.
+pub struct ExtractedPages {
+    archive: ZipArchive<std::fs::File>,
+    names: Vec<String>,
+    work_dir: PathBuf,
+    prefix: String,
+    selected: std::vec::IntoIter<usize>,
+    max_bytes: u64,
+    extracted: u64,
+}
+
+impl ExtractedPages {
+    /// The conversion commands the selected pages need, with `{input}` and
+    /// `{output}` standing for the paths.
+    pub fn commands(&self) -> Vec<String> {
+        let mut commands = Vec::new();
+        for &page_number in self.selected.as_slice() {
+            if let Some(command) = convert::converter_command(&self.names[page_number - 1])
+                && !commands.contains(&command)
+            {
+                commands.push(command);
+            }
+        }
+        commands
+    }
+
+    fn extract(&mut self, page_number: usize) -> Result<PathBuf, String> {
+        let over_budget = || {
+            format!(
+                "The archive expands to more than {} MB",
+                self.max_bytes / (1024 * 1024)
+            )
+        };
+        if self.extracted > self.max_bytes {
+            return Err(over_budget());
+        }
+        let name = &self.names[page_number - 1];
+        let extension = name.rsplit_once('.').map_or("", |(_, extension)| extension);
+        let image_path = self
+            .work_dir
+            .join(format!("{}-{:04}.{}", self.prefix, page_number, extension));
+
+        let budget = self.max_bytes - self.extracted;
+        let page = extract_page(&mut self.archive, name, &image_path, budget).and_then(|written| {
+            self.extracted += written;
+            if self.extracted > self.max_bytes {
+                return Err(over_budget());
+            }
+            match convert::to_png(&image_path, &self.work_dir) {
+                Some(converted) => {
+                    let _ = std::fs::remove_file(&image_path);
+                    converted.map(|(png, _)| png)
+                }
+                None => Ok(image_path.clone()),
+            }
+        });
+        if page.is_err() {
+            let _ = std::fs::remove_file(&image_path);
+        }
+        page
+    }
+}
+
+impl Iterator for ExtractedPages {
+    /// A page's number with its image, or with why it could not be extracted.
+    type Item = convert::PageImage;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let page_number = self.selected.next()?;
+        Some(
+            self.extract(page_number)
+                .map(|image| (page_number, image))
+                .map_err(|e| (page_number, e)),
+        )
+    }
+
+    /// Passes over `n` pages without extracting them, for picking a document up
+    /// where it was left.
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        if n > 0 {
+            self.selected.nth(n - 1)?;
+        }
+        self.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.selected.size_hint()
+    }
+}
+
+impl ExactSizeIterator for ExtractedPages {}
+
+/// Opens a ZIP archive of page images for extracting all of them or those in
+/// `selection` under `work_dir`, one at a time, at most `max_bytes` in all; see
+/// [`ExtractedPages`].
+pub fn extract_pages(
+    path: &Path,
+    work_dir: &Path,
+    selection: Option<&PageRanges>,
+    max_bytes: u64,
+) -> Result<ExtractedPages, String> {
+    let (archive, names) = open(path)?;
+    let selected = pdf::selected_pages(selection, names.len())?;
+    Ok(ExtractedPages {
+        archive,
+        names,
+        work_dir: work_dir.to_path_buf(),
+        prefix: format!("zip_extract_{}", Uuid::new_v4()),
+        selected: selected.into_iter(),
+        max_bytes,
+        extracted: 0,
+    })
+}
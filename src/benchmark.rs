@@ -0,0 +1,604 @@
+use crate::archive;
+use crate::cancel::Cancel;
+use crate::config::Config;
+use crate::convert;
+use crate::djvu;
+use crate::lines;
+use crate::ocr;
+use crate::pdf::{self, PageContent, PageRanges};
+use crate::preprocess;
+use crate::script::Script;
+use serde::Serialize;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use uuid::Uuid;
+
+/// Most pages a benchmark reads; every setting reads all of them.
+pub const MAX_SAMPLE: usize = 10;
+
+/// Most settings compared in one benchmark.
+pub const MAX_SETTINGS: usize = 16;
+
+/// Resolutions PDF pages may be rendered at.
+const DPI_RANGE: RangeInclusive<u32> = 72..=600;
+
+/// Tesseract page segmentation modes that read text; 0 only detects the
+/// orientation and 2 is not implemented.
+const PSMS: [u8; 12] = [1, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13];
+
+/// How a page image is prepared and cut up before tesseract reads it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum Preprocessing {
+    /// The page as rendered.
+    None,
+    /// Dark borders and wide margins cropped off, as `crop_margins` does.
+    CropMargins,
+    /// Each text line read on its own, as `segment_lines` does; the page
+    /// segmentation mode does not apply.
+    SegmentLines,
+}
+
+impl Preprocessing {
+    pub fn parse(name: &str) -> Option<Preprocessing> {
+        match name.trim().to_lowercase().as_str() {
+            "none" => Some(Preprocessing::None),
+            "crop_margins" => Some(Preprocessing::CropMargins),
+            "segment_lines" => Some(Preprocessing::SegmentLines),
+            _ => None,
+        }
+    }
+}
+
+/// One combination of settings the sample pages are read with.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct Setting {
+    /// Resolution PDF pages were rendered at; unset for other documents, whose
+    /// pages are read at their own resolution.
+    pub dpi: Option<u32>,
+    /// Tesseract page segmentation mode; unset for `segment_lines`.
+    pub psm: Option<u8>,
+    pub preprocessing: Preprocessing,
+}
+
+/// What a benchmark compares: every resolution, segmentation mode and
+/// preprocessing listed, on `sample` pages spread evenly through the document.
+#[derive(Clone, Debug)]
+pub struct Plan {
+    pub sample: usize,
+    /// Resolutions to render PDF pages at; ignored for other documents.
+    pub dpis: Vec<u32>,
+    pub psms: Vec<u8>,
+    pub preprocessing: Vec<Preprocessing>,
+    /// Script the pages are written in, which picks the tesseract model.
+    pub script: Script,
+    /// Password of the PDF, when it is encrypted.
+    pub password: Option<String>,
+}
+
+impl Default for Plan {
+    fn default() -> Plan {
+        Plan {
+            sample: 3,
+            dpis: vec![150, 300],
+            psms: vec![3, 6],
+            preprocessing: vec![Preprocessing::None, Preprocessing::CropMargins],
+            script: Script::Devanagari,
+            password: None,
+        }
+    }
+}
+
+/// Splits a comma-separated form value into its non-empty items.
+fn items(value: &str) -> impl Iterator<Item = &str> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+}
+
+impl Plan {
+    /// Sets a field by its form name: `sample`, or `dpi`, `psm` and
+    /// `preprocessing` as comma-separated lists, e.g. `150,300`. Other names are
+    /// ignored.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        match name {
+            "sample" => {
+                self.sample = value
+                    .trim()
+                    .parse()
+                    .ok()
+                    .filter(|sample| (1..=MAX_SAMPLE).contains(sample))
+                    .ok_or_else(|| {
+                        format!(
+                            "Invalid sample '{}': expected 1 to {} pages",
+                            value, MAX_SAMPLE
+                        )
+                    })?;
+            }
+            "dpi" => {
+                self.dpis = items(value)
+                    .map(|dpi| {
+                        dpi.parse()
+                            .ok()
+                            .filter(|dpi| DPI_RANGE.contains(dpi))
+                            .ok_or_else(|| {
+                                format!(
+                                    "Invalid dpi '{}': expected {} to {}",
+                                    dpi,
+                                    DPI_RANGE.start(),
+                                    DPI_RANGE.end()
+                                )
+                            })
+                    })
+                    .collect::<Result<_, _>>()?;
+            }
+            "psm" => {
+                self.psms = items(value)
+                    .map(|psm| {
+                        psm.parse()
+                            .ok()
+                            .filter(|psm| PSMS.contains(psm))
+                            .ok_or_else(|| format!("Invalid psm '{}': expected 1 or 3 to 13", psm))
+                    })
+                    .collect::<Result<_, _>>()?;
+            }
+            "preprocessing" => {
+                self.preprocessing = items(value)
+                    .map(|name| {
+                        Preprocessing::parse(name).ok_or_else(|| {
+                            format!(
+                                "Unknown preprocessing '{}'. Use none, crop_margins or segment_lines.",
+                                name
+                            )
+                        })
+                    })
+                    .collect::<Result<_, _>>()?;
+            }
+            "script" => {
+                self.script = Script::parse(value).ok_or_else(|| {
+                    format!(
+                        "Unknown script '{}'. Use devanagari, grantha, sharada, telugu, malayalam or latin.",
+                        value
+                    )
+                })?;
+            }
+            // Not trimmed: spaces may be part of the password
+            "password" => self.password = Some(value.to_string()).filter(|v| !v.is_empty()),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Every combination to read the sample with, in the order listed; PDFs are
+    /// rendered at each resolution, other documents only as they are. Fails when
+    /// there are none or more than [`MAX_SETTINGS`].
+    pub fn settings(&self, pdf: bool) -> Result<Vec<Setting>, String> {
+        let mut dpis: Vec<Option<u32>> = match pdf {
+            true => self.dpis.iter().copied().map(Some).collect(),
+            false => vec![None],
+        };
+        dpis.dedup();
+        let mut settings: Vec<Setting> = Vec::new();
+        for &dpi in &dpis {
+            for &preprocessing in &self.preprocessing {
+                let psms: Vec<Option<u8>> = match preprocessing {
+                    Preprocessing::SegmentLines => vec![None],
+                    _ => self.psms.iter().copied().map(Some).collect(),
+                };
+                for psm in psms {
+                    let setting = Setting {
+                        dpi,
+                        psm,
+                        preprocessing,
+                    };
+                    if !settings.contains(&setting) {
+                        settings.push(setting);
+                    }
+                }
+            }
+        }
+        if settings.is_empty() {
+            return Err(
+                "No settings to compare: list at least one dpi, psm and preprocessing".to_string(),
+            );
+        }
+        if settings.len() > MAX_SETTINGS {
+            return Err(format!(
+                "{} settings requested; at most {} can be compared at once",
+                settings.len(),
+                MAX_SETTINGS
+            ));
+        }
+        Ok(settings)
+    }
+}
+
+/// How one sample page fared under a setting.
+#[derive(Serialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct SamplePage {
+    /// Page number in the uploaded document.
+    pub page: usize,
+    pub confidence: Option<f32>,
+    /// Time spent rendering, preparing and reading the page, in seconds.
+    pub seconds: f64,
+    /// Letters, marks and digits recognized; a confident reading of half the
+    /// page scores well on confidence alone.
+    pub characters: usize,
+    pub error: Option<String>,
+}
+
+/// How the sample fared under one setting.
+#[derive(Serialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct SettingReport {
+    #[serde(flatten)]
+    pub setting: Setting,
+    /// Mean of the page confidences, over the pages words were found on.
+    pub mean_confidence: Option<f32>,
+    /// Mean time per page read, rendering included.
+    pub seconds_per_page: Option<f64>,
+    pub characters: usize,
+    pub pages_failed: usize,
+    pub pages: Vec<SamplePage>,
+}
+
+impl SettingReport {
+    fn summarize(setting: Setting, pages: Vec<SamplePage>) -> SettingReport {
+        let confidences: Vec<f32> = pages.iter().filter_map(|page| page.confidence).collect();
+        let seconds: Vec<f64> = pages
+            .iter()
+            .filter(|page| page.error.is_none())
+            .map(|page| page.seconds)
+            .collect();
+        SettingReport {
+            setting,
+            mean_confidence: (!confidences.is_empty())
+                .then(|| confidences.iter().sum::<f32>() / confidences.len() as f32),
+            seconds_per_page: (!seconds.is_empty())
+                .then(|| seconds.iter().sum::<f64>() / seconds.len() as f64),
+            characters: pages.iter().map(|page| page.characters).sum(),
+            pages_failed: pages.iter().filter(|page| page.error.is_some()).count(),
+            pages,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct Report {
+    pub total_pages: usize,
+    /// Pages read, by page number in the uploaded document.
+    pub sample_pages: Vec<usize>,
+    pub settings: Vec<SettingReport>,
+    /// The setting with the highest mean confidence, the faster one on a tie;
+    /// unset when no words were found under any.
+    pub best: Option<Setting>,
+}
+
+/// `count` page numbers spread evenly through a `total`-page document, each the
+/// middle of its share of the pages.
+fn sample_pages(total: usize, count: usize) -> Vec<usize> {
+    let count = count.min(total);
+    (0..count)
+        .map(|index| (2 * index + 1) * total / (2 * count) + 1)
+        .collect()
+}
+
+/// Letters, marks and digits in `text`.
+fn characters(text: &str) -> usize {
+    text.chars().filter(|c| c.is_alphanumeric()).count()
+}
+
+/// A document's extension in lower case, which tells how its pages are read.
+fn kind(path: &Path) -> String {
+    path.extension()
+        .and_then(|s| s.to_str())
+        .map(|s| s.to_lowercase())
+        .unwrap_or_default()
+}
+
+/// Sample pages rendered one way: each page's number and its image with the
+/// seconds that took, or why it could not be rendered.
+type Rendered = Vec<(usize, Result<(PathBuf, f64), String>)>;
+
+/// Renders the sample pages of `path`: PDF pages at `dpi`, DjVu and ZIP pages
+/// as they are; a single image is its own page, converted if tesseract cannot
+/// read it.
+fn render(
+    path: &Path,
+    dpi: Option<u32>,
+    selection: &PageRanges,
+    plan: &Plan,
+    config: &Config,
+    work_dir: &Path,
+    cancel: &Cancel,
+) -> Result<Rendered, String> {
+    let pages: Box<dyn Iterator<Item = convert::PageImage>> = match kind(path).as_str() {
+        "pdf" => {
+            let pdfium = pdf::pdfium(config)?;
+            let pages = pdf::render(
+                path,
+                work_dir,
+                pdfium,
+                plan.password.as_deref(),
+                Some(selection),
+                false,
+            )?
+            .with_dpi(
+                dpi.unwrap_or(pdf::RENDER_DPI as u32) as f64,
+                config.max_page_pixels,
+            );
+            Box::new(pages.map(|page| match page {
+                Ok((page, PageContent::Image(image))) => Ok((page, image)),
+                Ok((page, PageContent::Text(_))) => {
+                    Err((page, "The page has no image".to_string()))
+                }
+                Err(e) => Err(e),
+            }))
+        }
+        "djvu" | "djv" => Box::new(djvu::render(path, work_dir, Some(selection), cancel)?),
+        "zip" => Box::new(archive::extract_pages(
+            path,
+            work_dir,
+            Some(selection),
+            config.max_archive_bytes,
+        )?),
+        _ => {
+            let started = Instant::now();
+            return Ok(match convert::to_png(path, work_dir) {
+                Some(converted) => {
+                    let (png, _) = converted?;
+                    vec![(1, Ok((png, started.elapsed().as_secs_f64())))]
+                }
+                None => vec![(1, Ok((path.to_path_buf(), 0.0)))],
+            });
+        }
+    };
+
+    let mut rendered = Vec::new();
+    let mut started = Instant::now();
+    for page in pages {
+        rendered.push(match page {
+            Ok((page, image)) => (page, Ok((image, started.elapsed().as_secs_f64()))),
+            Err((page, e)) => (page, Err(e)),
+        });
+        started = Instant::now();
+    }
+    Ok(rendered)
+}
+
+/// Reads one prepared page image under `setting` with the script's model.
+fn read_page(
+    image: &Path,
+    setting: &Setting,
+    language: &str,
+    config: &Config,
+    work_dir: &Path,
+    cancel: &Cancel,
+) -> Result<ocr::PageText, String> {
+    let worker = config.ocr_worker_command.as_deref();
+    match setting.preprocessing {
+        Preprocessing::SegmentLines => {
+            lines::recognize_lines(image, language, worker, work_dir, cancel)
+        }
+        _ => ocr::run_tesseract(
+            image,
+            language,
+            setting.psm,
+            false,
+            worker,
+            work_dir,
+            cancel,
+        ),
+    }
+}
+
+/// Reads the sample pages of one rendering under each setting for its
+/// resolution, adding a [`SamplePage`] to each setting's list.
+fn read_sample(
+    rendered: Rendered,
+    settings: &[Setting],
+    language: &str,
+    config: &Config,
+    work_dir: &Path,
+    cancel: &Cancel,
+) -> Vec<Vec<SamplePage>> {
+    let mut read: Vec<Vec<SamplePage>> = settings.iter().map(|_| Vec::new()).collect();
+    for (page, image) in rendered {
+        let failed = |error: &str| SamplePage {
+            page,
+            confidence: None,
+            seconds: 0.0,
+            characters: 0,
+            error: Some(error.to_string()),
+        };
+        let (image, render_seconds) = match image {
+            Ok(image) => image,
+            Err(e) => {
+                read.iter_mut().for_each(|pages| pages.push(failed(&e)));
+                continue;
+            }
+        };
+
+        let started = Instant::now();
+        let scaled = preprocess::downscale(
+            &image,
+            config.max_ocr_pixels,
+            config.max_ocr_dimension,
+            work_dir,
+        );
+        let page_image = match &scaled {
+            Ok(Some((scaled, _))) => scaled.clone(),
+            _ => image.clone(),
+        };
+        let prepare_seconds = render_seconds + started.elapsed().as_secs_f64();
+
+        let mut cropped: Option<(PathBuf, f64)> = None;
+        for (setting, pages) in settings.iter().zip(read.iter_mut()) {
+            if cancel.is_cancelled() {
+                pages.push(failed("Cancelled"));
+                continue;
+            }
+            let started = Instant::now();
+            let (input, crop_seconds) = match setting.preprocessing {
+                Preprocessing::CropMargins => cropped
+                    .get_or_insert_with(|| {
+                        let started = Instant::now();
+                        let crop = match preprocess::crop_margins(&page_image, work_dir) {
+                            Ok(Some(crop)) => crop,
+                            _ => page_image.clone(),
+                        };
+                        (crop, started.elapsed().as_secs_f64())
+                    })
+                    .clone(),
+                _ => (page_image.clone(), 0.0),
+            };
+            pages.push(
+                match read_page(&input, setting, language, config, work_dir, cancel) {
+                    Ok(text) => SamplePage {
+                        page,
+                        confidence: text.confidence,
+                        seconds: prepare_seconds + crop_seconds + started.elapsed().as_secs_f64(),
+                        characters: characters(&text.text),
+                        error: None,
+                    },
+                    Err(e) => failed(&e),
+                },
+            );
+        }
+
+        // The input of a single image is not ours to remove
+        let made = [Some(image), Some(page_image), cropped.map(|(crop, _)| crop)];
+        for made in made.into_iter().flatten() {
+            if made.starts_with(work_dir) {
+                let _ = std::fs::remove_file(made);
+            }
+        }
+    }
+    read
+}
+
+/// Reads sample pages of the document at `path` under every setting `plan`
+/// lists, timing each page and recording its confidence, so settings can be
+/// picked before a long run. Pages are read with the script's model alone,
+/// without fallbacks, and without any of the text stages after OCR. Files go
+/// to a directory of their own under `work_dir`, removed when done.
+pub fn run(
+    path: &Path,
+    plan: &Plan,
+    config: &Config,
+    work_dir: &Path,
+    cancel: &Cancel,
+) -> Result<Report, String> {
+    let scratch = work_dir.join(format!("benchmark_{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&scratch)
+        .map_err(|e| format!("Failed to create a work directory: {}", e))?;
+    let report = measure(path, plan, config, &scratch, cancel);
+    let _ = std::fs::remove_dir_all(&scratch);
+    report
+}
+
+fn measure(
+    path: &Path,
+    plan: &Plan,
+    config: &Config,
+    work_dir: &Path,
+    cancel: &Cancel,
+) -> Result<Report, String> {
+    let kind = kind(path);
+    let settings = plan.settings(kind == "pdf")?;
+    let language = config.script_model(plan.script).ok_or_else(|| {
+        format!(
+            "No tesseract model configured for {} script",
+            plan.script.name()
+        )
+    })?;
+
+    let total_pages = match kind.as_str() {
+        "pdf" => pdf::render(
+            path,
+            work_dir,
+            pdf::pdfium(config)?,
+            plan.password.as_deref(),
+            None,
+            false,
+        )?
+        .len(),
+        "djvu" | "djv" => djvu::page_count(path)?,
+        "zip" => archive::page_count(path)?,
+        _ => 1,
+    };
+    let sample = sample_pages(total_pages, plan.sample);
+    let spec = sample
+        .iter()
+        .map(usize::to_string)
+        .collect::<Vec<_>>()
+        .join(",");
+    let selection = PageRanges::parse(&spec)?;
+    println!(
+        "Benchmarking {} settings on page(s) {} of {}",
+        settings.len(),
+        spec,
+        total_pages
+    );
+
+    let mut reports: Vec<(Setting, Vec<SamplePage>)> = settings
+        .into_iter()
+        .map(|setting| (setting, Vec::new()))
+        .collect();
+    let mut dpis: Vec<Option<u32>> = reports.iter().map(|(setting, _)| setting.dpi).collect();
+    dpis.dedup();
+    for dpi in dpis {
+        let group: Vec<usize> = (0..reports.len())
+            .filter(|&index| reports[index].0.dpi == dpi)
+            .collect();
+        let rendered = render(path, dpi, &selection, plan, config, work_dir, cancel)?;
+        let settings: Vec<Setting> = group
+            .iter()
+            .map(|&index| reports[index].0.clone())
+            .collect();
+        let read = read_sample(rendered, &settings, &language, config, work_dir, cancel);
+        for (index, pages) in group.into_iter().zip(read) {
+            reports[index].1 = pages;
+        }
+    }
+
+    let settings: Vec<SettingReport> = reports
+        .into_iter()
+        .map(|(setting, pages)| SettingReport::summarize(setting, pages))
+        .collect();
+    let best = settings
+        .iter()
+        .filter(|report| report.mean_confidence.is_some())
+        .max_by(|a, b| {
+            a.mean_confidence
+                .partial_cmp(&b.mean_confidence)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| {
+                    b.seconds_per_page
+                        .partial_cmp(&a.seconds_per_page)
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                })
+        })
+        .map(|report| report.setting.clone());
+    for report in &settings {
+        println!(
+            "  📊 {:?}: confidence {:.1}, {:.2}s per page",
+            report.setting,
+            report.mean_confidence.unwrap_or(0.0),
+            report.seconds_per_page.unwrap_or(0.0)
+        );
+    }
+
+    Ok(Report {
+        total_pages,
+        sample_pages: sample,
+        settings,
+        best,
+    })
+}
@@ -0,0 +1,200 @@
+use crate::ProgressStatus;
+use flate2::write::GzEncoder;
+use sanskrit_ocr::pipeline::{OcrResult, PageResult, TextOrigin};
+use sanskrit_ocr::script::Script;
+use serde::Serialize;
+use std::io::{Cursor, Write};
+use zip::ZipWriter;
+use zip::write::SimpleFileOptions;
+
+/// Archive formats a session bundle comes in.
+#[derive(Clone, Copy)]
+pub enum Format {
+    Zip,
+    TarGz,
+}
+
+impl Format {
+    pub fn parse(value: &str) -> Option<Format> {
+        match value.trim().to_lowercase().as_str() {
+            "zip" => Some(Format::Zip),
+            "tar.gz" | "tgz" => Some(Format::TarGz),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Zip => "zip",
+            Format::TarGz => "tar.gz",
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Format::Zip => "application/zip",
+            Format::TarGz => "application/gzip",
+        }
+    }
+}
+
+/// What `metadata.json` holds: the session as `/status` reports it, and where
+/// each file's pages are in the bundle.
+#[derive(Serialize)]
+struct Metadata<'a> {
+    session_id: &'a str,
+    session: &'a ProgressStatus,
+    files: Vec<FileEntry<'a>>,
+}
+
+#[derive(Serialize)]
+struct FileEntry<'a> {
+    filename: &'a str,
+    /// Directory of the file's pages in the bundle.
+    directory: String,
+    pages: Vec<PageEntry<'a>>,
+}
+
+/// A page without its text, which is in the files it names.
+#[derive(Serialize)]
+struct PageEntry<'a> {
+    page_number: usize,
+    source_page: usize,
+    origin: TextOrigin,
+    confidence: Option<f32>,
+    model: &'a str,
+    script: Script,
+    error: Option<&'a str>,
+    text: String,
+    proofread_text: Option<String>,
+    hocr: Option<String>,
+}
+
+/// Writes `entries`, paths and contents, into an archive in memory. Tar
+/// entries are dated `modified`, in seconds since the Unix epoch, and ZIP
+/// entries carry no date, so the same session always makes the same bundle.
+fn pack(format: Format, entries: &[(String, Vec<u8>)], modified: u64) -> Result<Vec<u8>, String> {
+    match format {
+        Format::Zip => {
+            let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+            for (name, content) in entries {
+                zip.start_file(name, SimpleFileOptions::default())
+                    .and_then(|()| zip.write_all(content).map_err(Into::into))
+                    .map_err(|e| format!("Failed to write {}: {}", name, e))?;
+            }
+            zip.finish()
+                .map(Cursor::into_inner)
+                .map_err(|e| format!("Failed to finish bundle: {}", e))
+        }
+        Format::TarGz => {
+            let gzip = GzEncoder::new(Vec::new(), flate2::Compression::default());
+            let mut tar = tar::Builder::new(gzip);
+            for (name, content) in entries {
+                let mut header = tar::Header::new_gnu();
+                header.set_size(content.len() as u64);
+                header.set_mode(0o644);
+                header.set_mtime(modified);
+                header.set_cksum();
+                tar.append_data(&mut header, name, content.as_slice())
+                    .map_err(|e| format!("Failed to write {}: {}", name, e))?;
+            }
+            tar.into_inner()
+                .and_then(GzEncoder::finish)
+                .map_err(|e| format!("Failed to finish bundle: {}", e))
+        }
+    }
+}
+
+/// Packs a finished session for keeping: `metadata.json`, the session's text
+/// as `text.txt` (and `text.proofread.txt` once pages were proofread), and a
+/// directory per file with a `page-0001.txt` for each page, next to its
+/// `.proofread.txt` and `.hocr` when it has them.
+pub fn write(
+    format: Format,
+    session_id: &str,
+    status: &ProgressStatus,
+    text: &str,
+) -> Result<Vec<u8>, String> {
+    let mut entries = Vec::new();
+    let mut files = Vec::new();
+    for (index, result) in status.results.iter().enumerate() {
+        let directory = directory_name(index, result);
+        let mut pages = Vec::new();
+        for page in &result.pages {
+            let entry = page_entry(&directory, page);
+            entries.push((entry.text.clone(), page.text.clone().into_bytes()));
+            if let (Some(name), Some(text)) = (&entry.proofread_text, &page.proofread_text) {
+                entries.push((name.clone(), text.clone().into_bytes()));
+            }
+            if let (Some(name), Some(hocr)) = (&entry.hocr, &page.hocr) {
+                entries.push((name.clone(), hocr.clone().into_bytes()));
+            }
+            pages.push(entry);
+        }
+        files.push(FileEntry {
+            filename: &result.filename,
+            directory,
+            pages,
+        });
+    }
+
+    entries.push(("text.txt".to_string(), text.as_bytes().to_vec()));
+    let proofread = status.results.iter().any(|result| {
+        result
+            .pages
+            .iter()
+            .any(|page| page.proofread_text.is_some())
+    });
+    if proofread {
+        let text = status
+            .results
+            .iter()
+            .filter(|result| result.success)
+            .map(OcrResult::proofread_text)
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        entries.push(("text.proofread.txt".to_string(), text.into_bytes()));
+    }
+
+    let metadata = Metadata {
+        session_id,
+        session: status,
+        files,
+    };
+    let metadata = serde_json::to_vec_pretty(&metadata).map_err(|e| e.to_string())?;
+    entries.push(("metadata.json".to_string(), metadata));
+    pack(format, &entries, status.updated_at)
+}
+
+/// Directory of a file's pages: its position and name, so files uploaded
+/// under the same name stay apart.
+fn directory_name(index: usize, result: &OcrResult) -> String {
+    let stem = result
+        .filename
+        .rsplit_once('.')
+        .map_or(result.filename.as_str(), |(stem, _)| stem);
+    let stem: String = stem
+        .chars()
+        .map(|c| if matches!(c, '/' | '\\') { '_' } else { c })
+        .collect();
+    format!("{:03}-{}", index + 1, stem)
+}
+
+fn page_entry<'a>(directory: &str, page: &'a PageResult) -> PageEntry<'a> {
+    let base = format!("{}/page-{:04}", directory, page.page_number);
+    PageEntry {
+        page_number: page.page_number,
+        source_page: page.source_page,
+        origin: page.origin,
+        confidence: page.confidence,
+        model: &page.model,
+        script: page.script,
+        error: page.error.as_deref(),
+        text: format!("{}.txt", base),
+        proofread_text: page
+            .proofread_text
+            .is_some()
+            .then(|| format!("{}.proofread.txt", base)),
+        hocr: page.hocr.is_some().then(|| format!("{}.hocr", base)),
+    }
+}
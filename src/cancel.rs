@@ -0,0 +1,74 @@
+use crate::processes;
+use std::io::{self, Read};
+use std::process::{Command, Output, Stdio};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// How often a running subprocess is checked for cancellation.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Cancellation flag shared between a job and whoever may stop it. Clones share
+/// the flag, so the server keeps one and hands another to the pipeline.
+#[derive(Clone, Debug, Default)]
+pub struct Cancel(Arc<AtomicBool>);
+
+impl Cancel {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// Runs `command` to completion like [`Command::output`], killing it as soon as
+    /// the job is cancelled, once a [`processes`] slot is free and at the
+    /// [`processes::Priority`] set. A cancelled run, or
+    /// one not started because the job already was, fails with
+    /// [`io::ErrorKind::Interrupted`].
+    pub fn output(&self, command: &mut Command) -> io::Result<Output> {
+        let _slot = processes::acquire(self)?;
+        let mut child = processes::prioritized(command)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        // Drain both pipes on their own threads so a chatty tool cannot block
+        let stdout = drain(child.stdout.take());
+        let stderr = drain(child.stderr.take());
+
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            if self.is_cancelled() {
+                let _ = child.kill();
+                let _ = child.wait();
+                let _ = (stdout.join(), stderr.join());
+                return Err(cancelled());
+            }
+            thread::sleep(POLL_INTERVAL);
+        };
+        Ok(Output {
+            status,
+            stdout: stdout.join().unwrap_or_default(),
+            stderr: stderr.join().unwrap_or_default(),
+        })
+    }
+}
+
+fn cancelled() -> io::Error {
+    io::Error::new(io::ErrorKind::Interrupted, "Cancelled")
+}
+
+fn drain(pipe: Option<impl Read + Send + 'static>) -> JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut bytes = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut bytes);
+        }
+        bytes
+    })
+}
@@ -0,0 +1,155 @@
+use crate::{ErrorResponse, Jobs};
+use actix_web::http::header;
+use actix_web::{HttpResponse, get, web};
+use sanskrit_ocr::config::Config;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Serialize, ToSchema)]
+pub struct CapacityResponse {
+    /// Whether an upload sent now would be accepted.
+    accepting: bool,
+    /// Why uploads are refused, when they are.
+    reason: Option<String>,
+    /// Free space in the temp directory uploads and rendered pages go to;
+    /// unset when it cannot be read.
+    free_disk_bytes: Option<u64>,
+    min_free_disk_bytes: u64,
+    /// Sessions waiting for a worker.
+    queued_jobs: usize,
+    /// Sessions being processed.
+    running_jobs: usize,
+    max_concurrent_jobs: usize,
+    /// Queued sessions at which uploads are refused; 0 for no limit.
+    max_queued_jobs: usize,
+    /// Rough seconds until a session uploaded now would start: the work of the
+    /// sessions running and queued, spread over the workers. Unset while no
+    /// session has finished to judge queued ones by.
+    estimated_wait_seconds: Option<f64>,
+}
+
+fn free_disk_bytes(config: &Config) -> Option<u64> {
+    fs4::available_space(config.temp_dir()).ok()
+}
+
+/// Seconds until the workers are through the sessions running and queued, each
+/// running one taken at its ETA and each queued one at the files it has times
+/// the mean time finished files took.
+fn estimated_wait(jobs: &Jobs) -> Option<f64> {
+    let queued: Vec<String> = jobs
+        .queue
+        .lock()
+        .iter()
+        .map(|job| job.session_id.clone())
+        .collect();
+    let running: Vec<String> = jobs
+        .running
+        .read()
+        .keys()
+        .filter(|id| !queued.contains(id))
+        .cloned()
+        .collect();
+    let workers = jobs.config.max_concurrent_jobs.max(1);
+    if queued.is_empty() && running.len() < workers {
+        return Some(0.0);
+    }
+
+    let tracker = jobs.tracker.read();
+    let file_seconds: Vec<f64> = tracker
+        .values()
+        .filter(|status| status.complete)
+        .flat_map(|status| &status.results)
+        .filter_map(|result| result.estimated_time_seconds)
+        .collect();
+    let mean_file_seconds = (!file_seconds.is_empty())
+        .then(|| file_seconds.iter().sum::<f64>() / file_seconds.len() as f64);
+
+    let mut work = 0.0;
+    for id in &running {
+        let Some(status) = tracker.get(id) else {
+            continue;
+        };
+        work += match status.eta_seconds {
+            Some(eta) => eta,
+            None => status.total.saturating_sub(status.current) as f64 * mean_file_seconds?,
+        };
+    }
+    for id in &queued {
+        if let Some(status) = tracker.get(id) {
+            work += status.total as f64 * mean_file_seconds?;
+        }
+    }
+    Some(work / workers as f64)
+}
+
+/// Why the server cannot take another upload now: too little free disk space
+/// or too long a queue.
+pub fn refusal(jobs: &Jobs) -> Option<String> {
+    let config = &jobs.config;
+    if let Some(free) = free_disk_bytes(config)
+        && free < config.min_free_disk_bytes
+    {
+        return Some(format!(
+            "Only {} MB of disk space left; the server needs {} MB free to take uploads",
+            free / (1024 * 1024),
+            config.min_free_disk_bytes / (1024 * 1024)
+        ));
+    }
+    let queued = jobs.queue.lock().len();
+    if config.max_queued_jobs > 0 && queued >= config.max_queued_jobs {
+        return Some(format!(
+            "The job queue is full: {} waiting, the most the server takes",
+            queued
+        ));
+    }
+    None
+}
+
+/// Seconds clients refused for want of capacity are told to wait when there
+/// is no estimated wait to go by.
+const DEFAULT_RETRY_AFTER_SECONDS: u64 = 60;
+
+/// Seconds a refused client should wait before trying again: the estimated
+/// wait when there is one to wait for, [`DEFAULT_RETRY_AFTER_SECONDS`]
+/// otherwise.
+pub fn retry_after(jobs: &Jobs) -> u64 {
+    match estimated_wait(jobs).filter(|&wait| wait > 0.0) {
+        Some(wait) => (wait.ceil() as u64).max(1),
+        None => DEFAULT_RETRY_AFTER_SECONDS,
+    }
+}
+
+/// The HTTP 503 for an upload the server has no room for, with `Retry-After`
+/// set to [`retry_after`].
+pub fn unavailable(jobs: &Jobs, reason: String) -> HttpResponse {
+    HttpResponse::ServiceUnavailable()
+        .insert_header((header::RETRY_AFTER, retry_after(jobs).to_string()))
+        .json(ErrorResponse {
+            error: reason,
+            code: Some("OVER_CAPACITY".to_string()),
+        })
+}
+
+/// Whether the server takes uploads now, with the free disk space, the queue
+/// and the expected wait, so clients can hold back large books until there is
+/// room.
+#[utoipa::path(
+    tag = "ocr",
+    responses((status = 200, body = CapacityResponse))
+)]
+#[get("/capacity")]
+pub async fn capacity(jobs: web::Data<Jobs>) -> HttpResponse {
+    let reason = refusal(&jobs);
+    let queued_jobs = jobs.queue.lock().len();
+    HttpResponse::Ok().json(CapacityResponse {
+        accepting: reason.is_none(),
+        reason,
+        free_disk_bytes: free_disk_bytes(&jobs.config),
+        min_free_disk_bytes: jobs.config.min_free_disk_bytes,
+        queued_jobs,
+        running_jobs: jobs.running.read().len().saturating_sub(queued_jobs),
+        max_concurrent_jobs: jobs.config.max_concurrent_jobs,
+        max_queued_jobs: jobs.config.max_queued_jobs,
+        estimated_wait_seconds: estimated_wait(&jobs),
+    })
+}
@@ -0,0 +1,257 @@
+use serde::{Deserialize, Serialize};
+
+/// Result of scanning one verse.
+#[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct MeterAnalysis {
+    /// Best-matching meter, if any matched well enough.
+    pub meter: Option<String>,
+    /// Share of checked syllable positions that fit the meter (0-1).
+    pub score: f32,
+    pub syllables: usize,
+    /// Syllable weights per line, `L` for laghu and `G` for guru.
+    pub pattern: Vec<String>,
+    /// No known meter fits: often a sign of a misread or dropped akṣara.
+    pub irregular: bool,
+}
+
+/// Fixed-pattern (vṛtta) meters by pāda. `X` marks an anceps position; the last
+/// syllable of every pāda is treated as anceps regardless.
+const METERS: &[(&str, &str)] = &[
+    ("triṣṭubh (indravajrā/upendravajrā)", "XGLGGLLGLGG"),
+    ("rathoddhatā", "GLGLLLGLGLG"),
+    ("svāgatā", "GLGLLLGLLGG"),
+    ("jagatī (vaṃśastha/indravaṃśā)", "XGLGGLLGLGLG"),
+    ("drutavilambita", "LLLGLLGLLGLG"),
+    ("bhujaṅgaprayāta", "LGGLGGLGGLGG"),
+    ("toṭaka", "LLGLLGLLGLLG"),
+    ("vasantatilakā", "GGLGLLLGLLGLGG"),
+    ("mālinī", "LLLLLLGGGLGGLGG"),
+    ("śikhariṇī", "LGGGGGLLLLLGGLLLG"),
+    ("mandākrāntā", "GGGGLLLLLGGLGGLGG"),
+    ("pṛthvī", "LGLLLGLGLLLGLGGLG"),
+    ("hariṇī", "LLLLLGGGGGLGLLGLG"),
+    ("śārdūlavikrīḍita", "GGGLLGLGLLLGGGLGGLG"),
+    ("sragdharā", "GGGGLGGLLLLLLGGLGGLGG"),
+];
+
+/// Minimum score for a meter to be reported.
+const MIN_SCORE: f32 = 0.85;
+
+fn is_consonant(c: char) -> bool {
+    matches!(c, '\u{0915}'..='\u{0939}' | '\u{0958}'..='\u{095F}')
+}
+
+/// Long vowels as independent letters or dependent signs.
+fn is_long_vowel(c: char) -> bool {
+    matches!(
+        c,
+        'आ' | 'ई'
+            | 'ऊ'
+            | 'ॠ'
+            | 'ॡ'
+            | 'ए'
+            | 'ऐ'
+            | 'ओ'
+            | 'औ'
+            | 'ा'
+            | 'ी'
+            | 'ू'
+            | 'ॄ'
+            | 'ॣ'
+            | 'े'
+            | 'ै'
+            | 'ो'
+            | 'ौ'
+    )
+}
+
+fn is_short_vowel(c: char) -> bool {
+    matches!(c, 'अ' | 'इ' | 'उ' | 'ऋ' | 'ऌ' | 'ि' | 'ु' | 'ृ' | 'ॢ')
+}
+
+/// Syllable weights of a line (`true` = guru). Word boundaries are ignored since
+/// consonant clusters across words count toward position length.
+pub fn weights(line: &str) -> Vec<bool> {
+    let chars: Vec<char> = line.chars().collect();
+    // (long vowel or anusvāra/visarga, consonants following the vowel)
+    let mut syllables: Vec<(bool, usize)> = Vec::new();
+    let mut between = 0;
+
+    let vowel = |long: bool, between: &mut usize, syllables: &mut Vec<(bool, usize)>| {
+        if let Some(last) = syllables.last_mut() {
+            last.1 = *between;
+        }
+        syllables.push((long, 0));
+        *between = 0;
+    };
+
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if is_consonant(c) {
+            between += 1;
+            if chars.get(i + 1) == Some(&'़') {
+                i += 1;
+            }
+            match chars.get(i + 1).copied() {
+                Some('्') => i += 1,
+                Some(sign) if is_long_vowel(sign) || is_short_vowel(sign) => {
+                    vowel(is_long_vowel(sign), &mut between, &mut syllables);
+                    i += 1;
+                }
+                _ => vowel(false, &mut between, &mut syllables),
+            }
+        } else if is_long_vowel(c) || is_short_vowel(c) {
+            vowel(is_long_vowel(c), &mut between, &mut syllables);
+        } else if matches!(c, 'ं' | 'ः')
+            && let Some(last) = syllables.last_mut()
+        {
+            last.0 = true;
+        }
+        i += 1;
+    }
+    if let Some(last) = syllables.last_mut() {
+        last.1 = between;
+    }
+
+    let count = syllables.len();
+    syllables
+        .iter()
+        .enumerate()
+        .map(|(index, &(long, following))| {
+            long || following >= 2 || (index + 1 == count && following >= 1)
+        })
+        .collect()
+}
+
+fn pattern(weights: &[bool]) -> String {
+    weights.iter().map(|&g| if g { 'G' } else { 'L' }).collect()
+}
+
+/// Fraction of positions matching a fixed pāda pattern across all four pādas.
+fn vrtta_score(padas: &[&[bool]], meter: &str) -> f32 {
+    let expected: Vec<char> = meter.chars().collect();
+    let (mut checked, mut matched) = (0, 0);
+    for pada in padas {
+        for (position, &heavy) in pada.iter().enumerate().take(expected.len() - 1) {
+            let want = expected[position];
+            if want == 'X' {
+                continue;
+            }
+            checked += 1;
+            if (want == 'G') == heavy {
+                matched += 1;
+            }
+        }
+    }
+    if checked == 0 {
+        0.0
+    } else {
+        matched as f32 / checked as f32
+    }
+}
+
+/// Anuṣṭubh rules: syllables 5-7 are `L G L` in even pādas and `L G G` in odd ones
+/// (pathyā), and syllables 2-3 are never both light.
+fn anustubh_score(padas: &[&[bool]]) -> f32 {
+    let (mut checked, mut matched) = (0, 0);
+    let mut check = |ok: bool| {
+        checked += 1;
+        if ok {
+            matched += 1;
+        }
+    };
+    for (index, pada) in padas.iter().enumerate() {
+        check(pada[1] || pada[2]);
+        check(!pada[4]);
+        check(pada[5]);
+        check(if index % 2 == 1 { !pada[6] } else { pada[6] });
+    }
+    matched as f32 / checked as f32
+}
+
+/// Scans a verse's lines and names the most likely meter.
+pub fn analyze(lines: &[String]) -> MeterAnalysis {
+    let line_weights: Vec<Vec<bool>> = lines.iter().map(|line| weights(line)).collect();
+    let all: Vec<bool> = line_weights.iter().flatten().copied().collect();
+
+    let mut best: Option<(&str, f32)> = None;
+    if all.len().is_multiple_of(4) && !all.is_empty() {
+        let pada_length = all.len() / 4;
+        let padas: Vec<&[bool]> = all.chunks(pada_length).collect();
+
+        let mut candidates: Vec<(&str, f32)> = METERS
+            .iter()
+            .filter(|(_, meter)| meter.len() == pada_length)
+            .map(|(name, meter)| (*name, vrtta_score(&padas, meter)))
+            .collect();
+        if pada_length == 8 {
+            candidates.push(("anuṣṭubh", anustubh_score(&padas)));
+        }
+        best = candidates.into_iter().max_by(|a, b| a.1.total_cmp(&b.1));
+    }
+
+    let score = best.map(|(_, score)| score).unwrap_or(0.0);
+    let meter = best
+        .filter(|(_, score)| *score >= MIN_SCORE)
+        .map(|(name, _)| name.to_string());
+
+    MeterAnalysis {
+        irregular: meter.is_none(),
+        meter,
+        score,
+        syllables: all.len(),
+        pattern: line_weights.iter().map(|w| pattern(w)).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(verse: &[&str]) -> Vec<String> {
+        verse.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn weighs_syllables() {
+        // Long vowel, anusvāra and visarga, and a short vowel before a cluster
+        assert_eq!(pattern(&weights("रामः")), "GG");
+        assert_eq!(pattern(&weights("संस्कृतम्")), "GLG");
+        assert_eq!(pattern(&weights("कुरुक्षेत्रे")), "LGGG");
+    }
+
+    #[test]
+    fn names_an_anustubh() {
+        let analysis = analyze(&lines(&[
+            "धर्मक्षेत्रे कुरुक्षेत्रे समवेता युयुत्सवः",
+            "मामकाः पाण्डवाश्चैव किमकुर्वत सञ्जय",
+        ]));
+        assert_eq!(analysis.meter.as_deref(), Some("anuṣṭubh"));
+        assert_eq!(analysis.syllables, 32);
+        assert!(!analysis.irregular);
+    }
+
+    #[test]
+    fn names_a_mandakranta() {
+        let analysis = analyze(&lines(&[
+            "कश्चित्कान्ताविरहगुरुणा स्वाधिकारात्प्रमत्तः",
+            "शापेनास्तंगमितमहिमा वर्षभोग्येण भर्तुः",
+            "यक्षश्चक्रे जनकतनयास्नानपुण्योदकेषु",
+            "स्निग्धच्छायातरुषु वसतिं रामगिर्याश्रमेषु",
+        ]));
+        assert_eq!(analysis.meter.as_deref(), Some("mandākrāntā"));
+        assert_eq!(analysis.score, 1.0);
+    }
+
+    #[test]
+    fn flags_a_dropped_syllable() {
+        let analysis = analyze(&lines(&[
+            "धर्मक्षेत्रे कुरुक्षेत्रे समवेता युयुत्सवः",
+            "मामकाः पाण्डवाश्चैव किमकुर्वत सञ्ज",
+        ]));
+        assert_eq!(analysis.meter, None);
+        assert!(analysis.irregular);
+    }
+}
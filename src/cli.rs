@@ -0,0 +1,125 @@
+use clap::{Args, Parser, Subcommand};
+use sanskrit_ocr::convert;
+use sanskrit_ocr::{Config, Document, OcrEngine};
+use std::path::PathBuf;
+
+/// Sanskrit OCR: runs the web server, or reads files straight from the command
+/// line.
+#[derive(Parser)]
+#[command(version)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Start the web server on port 8080 (the default).
+    Serve,
+    /// OCR files without the server, writing each one's text next to it.
+    Ocr(OcrArgs),
+}
+
+#[derive(Args)]
+pub struct OcrArgs {
+    /// Images, PDFs, DjVu documents or ZIP archives of page images; a directory
+    /// stands for the supported files directly in it.
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+    /// A processing option as the upload form takes it, e.g. `-o pages=1-20` or
+    /// `-o script=grantha`; repeat for more.
+    #[arg(short = 'o', long = "option", value_name = "NAME=VALUE")]
+    options: Vec<String>,
+    /// Replace text files left by an earlier run instead of skipping their
+    /// inputs.
+    #[arg(long)]
+    overwrite: bool,
+}
+
+/// The inputs `paths` name, directories expanded to their supported files in
+/// name order.
+fn inputs(paths: &[PathBuf]) -> std::io::Result<Vec<PathBuf>> {
+    let mut inputs = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let mut found: Vec<PathBuf> = std::fs::read_dir(path)?
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|file| file.is_file() && convert::is_supported(&file.to_string_lossy()))
+                .collect();
+            found.sort();
+            inputs.extend(found);
+        } else {
+            inputs.push(path.clone());
+        }
+    }
+    Ok(inputs)
+}
+
+/// Runs the pipeline over every input in turn, as the server would for a single
+/// upload of them all. Fails once all are done if any of them failed.
+pub fn ocr(args: OcrArgs) -> std::io::Result<()> {
+    let engine = OcrEngine::load(Config::load()?)?;
+    let invalid = |e: String| std::io::Error::new(std::io::ErrorKind::InvalidInput, e);
+
+    let mut pipeline = engine.pipeline();
+    for option in &args.options {
+        let (name, value) = option
+            .split_once('=')
+            .ok_or_else(|| invalid(format!("Invalid option '{}': expected NAME=VALUE", option)))?;
+        pipeline = pipeline.option(name.trim(), value).map_err(invalid)?;
+    }
+
+    let inputs = inputs(&args.files)?;
+    let mut failed = 0;
+    for (index, input) in inputs.iter().enumerate() {
+        let text_path = input.with_extension("txt");
+        println!("📄 [{}/{}] {}", index + 1, inputs.len(), input.display());
+
+        let document = match Document::open(input) {
+            Ok(document) => document,
+            Err(e) => {
+                println!("❌ {}", e);
+                failed += 1;
+                continue;
+            }
+        };
+        if !args.overwrite && text_path.exists() {
+            println!(
+                "⏭️  Skipping {}: {} already exists",
+                input.display(),
+                text_path.display()
+            );
+            continue;
+        }
+
+        let mut result = pipeline.run(&document);
+        if !result.success {
+            println!(
+                "❌ {}: {}",
+                input.display(),
+                result.error.as_deref().unwrap_or("OCR failed")
+            );
+            failed += 1;
+            continue;
+        }
+
+        std::fs::write(&text_path, &result.text)?;
+        println!("✅ Wrote {}", text_path.display());
+        if let Some(written) = result.searchable_pdf.take() {
+            let pdf_path = input.with_extension("searchable.pdf");
+            std::fs::rename(&written, &pdf_path)
+                .or_else(|_| std::fs::copy(&written, &pdf_path).map(|_| ()))?;
+            let _ = std::fs::remove_file(&written);
+            println!("✅ Wrote {}", pdf_path.display());
+        }
+    }
+
+    if failed > 0 {
+        return Err(std::io::Error::other(format!(
+            "{} of {} files failed",
+            failed,
+            inputs.len()
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,34 @@
+use actix_web::Error;
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::middleware::Next;
+
+/// Marks the ETag of a compressed file download weak and withdraws its
+/// `Accept-Ranges`: both describe the file on disk, not the compressed bytes
+/// sent, so a resumed download would splice a range of the one onto the other.
+/// Weak validators fail `If-Range`, which makes clients fetch the whole file
+/// again. Wrap it around [`Compress`](actix_web::middleware::Compress).
+pub async fn weaken_validators(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let mut response = next.call(req).await?;
+    let headers = response.headers_mut();
+    let compressed = headers
+        .get(header::CONTENT_ENCODING)
+        .is_some_and(|encoding| encoding != "identity");
+    if !compressed {
+        return Ok(response);
+    }
+
+    headers.remove(header::ACCEPT_RANGES);
+    let weak = headers
+        .get(header::ETAG)
+        .filter(|etag| !etag.as_bytes().starts_with(b"W/"))
+        .and_then(|etag| HeaderValue::from_bytes(&[b"W/", etag.as_bytes()].concat()).ok());
+    if let Some(weak) = weak {
+        headers.insert(header::ETAG, weak);
+    }
+    Ok(response)
+}
@@ -0,0 +1,588 @@
+use crate::annotate::HighlightStyle;
+use crate::matter::MatterHandling;
+use crate::normalize::Punctuation;
+use crate::processes::{IoClass, Priority};
+use crate::replace::Rule;
+use crate::script::{Script, ScriptOutput};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Server configuration, read once at startup from a JSON file.
+///
+/// The file is located through the `SANSKRIT_OCR_CONFIG` environment variable and
+/// falls back to `./config.json`. Every field is optional; missing fields keep
+/// their defaults, and a missing default file means "all defaults".
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Primary tesseract language model used for every page.
+    pub language: String,
+    /// Models tried in order when the primary model's page confidence falls below
+    /// `fallback_min_confidence`, e.g. `["script/Devanagari", "hin"]`.
+    pub fallback_languages: Vec<String>,
+    /// Mean word confidence (0-100) under which the fallback chain kicks in.
+    pub fallback_min_confidence: f32,
+    /// Tesseract models per source script, e.g. `{"grantha": "gran_custom"}`. Telugu and
+    /// Malayalam default to the stock `script/` models; Devanagari uses `language`.
+    pub script_models: HashMap<Script, String>,
+    /// Default for per-page script detection, which picks each page's model from
+    /// tesseract's script guess; uploads may override it with `script=auto`.
+    pub detect_script: bool,
+    /// Default for whether non-Devanagari output is converted (`devanagari`) or kept
+    /// (`source`); uploads may override it.
+    pub script_output: ScriptOutput,
+    /// Default for reading pages line by line after a projection-profile line
+    /// segmentation, for tightly spaced manuscripts; uploads may override it.
+    pub segment_lines: bool,
+    /// Default for keeping tesseract's hOCR report of each page, for session
+    /// bundles; uploads may override it.
+    pub hocr: bool,
+    /// Default for skipping OCR on blank pages of multi-page documents; uploads
+    /// may override it.
+    pub skip_blank_pages: bool,
+    /// Pages with at most this percentage of ink pixels are blank.
+    pub blank_page_max_ink: f32,
+    /// Default for cutting two-page spreads of multi-page documents into single
+    /// pages; uploads may override it.
+    pub split_spreads: bool,
+    /// Default for cropping dark scanner borders and wide margins off pages before
+    /// OCR; uploads may override it.
+    pub crop_margins: bool,
+    /// Default for the Unicode normalization pass; uploads may override it.
+    pub normalize_unicode: bool,
+    /// Default digit, daṇḍa and avagraha conventions; uploads may override each one.
+    pub punctuation: Punctuation,
+    /// Find/replace rules applied to every page unless an upload picks a profile.
+    pub replacements: Vec<Rule>,
+    /// Named rule sets for particular print editions, selected per upload.
+    pub replacement_profiles: HashMap<String, Vec<Rule>>,
+    /// Default handling of detected front/back matter (`keep`, `exclude`, `separate`).
+    pub matter_handling: MatterHandling,
+    /// Default page separator of the plain-text export: `none`, `formfeed`, or
+    /// a template line with `{page}`, `{source_page}` and `{file}` in it.
+    pub page_separator: String,
+    /// PDFs with more pages than this are refused with `INPUT_TOO_LARGE`.
+    pub max_pdf_pages: usize,
+    /// PDFs larger than this many bytes are refused with `INPUT_TOO_LARGE`.
+    pub max_pdf_bytes: u64,
+    /// Pages that would render above this many pixels are refused with `SUSPICIOUS_PDF`.
+    pub max_page_pixels: u64,
+    /// ZIP uploads are refused once their page images expand past this many bytes.
+    pub max_archive_bytes: u64,
+    /// Files fetched by `POST /upload-url` are refused past this many bytes.
+    pub max_download_bytes: u64,
+    /// Files of a multipart upload larger than this many bytes are refused.
+    pub max_upload_file_bytes: u64,
+    /// Multipart requests larger than this many bytes, files and fields
+    /// together, are refused.
+    pub max_upload_bytes: u64,
+    /// Path of the pdfium shared library used to inspect and render PDFs; by
+    /// default `libpdfium` next to the executable or on the library path.
+    pub pdfium_library: Option<PathBuf>,
+    /// Default for taking a PDF page's embedded text instead of recognizing it;
+    /// uploads may override it.
+    pub use_text_layer: bool,
+    /// Default for the visarga/colon and anusvāra/candrabindu repair pass; uploads
+    /// may override it.
+    pub fix_marks: bool,
+    /// Wordlist used for dictionary correction (one word per line, optional frequency).
+    pub dictionary_path: Option<PathBuf>,
+    /// Default for the dictionary correction stage; uploads may override it.
+    pub dictionary_correction: bool,
+    /// Only words tesseract scored below this confidence are corrected.
+    pub correction_max_confidence: f32,
+    /// Largest edit distance accepted between a word and its correction.
+    pub correction_max_distance: usize,
+    /// Default for the sandhi-splitting stage; uploads may override it.
+    pub sandhi_split: bool,
+    /// External splitter (program and arguments) reading lines on stdin and printing
+    /// one segmented line per input line; the built-in rules are used when unset.
+    pub sandhi_command: Option<Vec<String>>,
+    /// Default for the morphological analysis stage; uploads may override it.
+    pub analyze_morphology: bool,
+    /// Where words are sent for morphological analysis.
+    pub morphology: MorphologyConfig,
+    /// Default for verse detection; uploads may override it.
+    pub detect_verses: bool,
+    /// Default for meter identification (implies verse detection); uploads may override it.
+    pub identify_meter: bool,
+    /// Directory uploads, rendered pages and other working files are written
+    /// to; the system temp directory when unset.
+    pub temp_dir: Option<PathBuf>,
+    /// Directory searchable PDFs, kept page images and generated downloads are
+    /// kept in, one directory per session under each kind.
+    pub conversions_dir: PathBuf,
+    /// Directory `/split` keeps its chunks in; `splits` under
+    /// `conversions_dir` when unset.
+    pub splits_dir: Option<PathBuf>,
+    /// Directory the frontend is served from.
+    pub public_dir: PathBuf,
+    /// Named working directories uploads may select with a `storage_profile` form
+    /// field, e.g. fast local scratch for large PDFs instead of network storage.
+    pub storage_profiles: HashMap<String, StorageProfile>,
+    /// Default marker style for `annotated_text`; unset means no annotated text.
+    /// Uploads may override it.
+    pub highlight: Option<HighlightStyle>,
+    /// Words tesseract scored below this confidence are highlighted.
+    pub highlight_max_confidence: f32,
+    /// Default for returning `parallel_lines` per page; uploads may override it.
+    pub parallel_lines: bool,
+    /// Default for returning `joined_text`; uploads may override it.
+    pub join_lines: bool,
+    /// Default for returning `provenance_text`; uploads may override it.
+    pub provenance_markers: bool,
+    /// Keep the image of every page read, with a thumbnail, until the session
+    /// is deleted.
+    pub keep_page_images: bool,
+    /// Jobs processed at once; later ones wait in a queue.
+    pub max_concurrent_jobs: usize,
+    /// Extra workers reserved for jobs of single images.
+    pub fast_lane_workers: usize,
+    /// Pages of a multi-page document read at once; 0 shares the CPU cores out
+    /// among `max_concurrent_jobs`.
+    pub page_workers: usize,
+    /// Page images with more pixels than this are scaled down before OCR; 0
+    /// never scales them for their size.
+    pub max_ocr_pixels: u64,
+    /// Page images with a longer side than this, in pixels, are scaled down
+    /// before OCR; 0 never scales them for it.
+    pub max_ocr_dimension: u32,
+    /// Long-lived OCR helper (program and arguments) reading pages as JSON lines
+    /// on stdin, kept running between pages; tesseract is started per page when
+    /// unset.
+    pub ocr_worker_command: Option<Vec<String>>,
+    /// External programs run at once across all jobs; 0 allows one per CPU core.
+    pub max_external_processes: usize,
+    /// Niceness external programs run at, e.g. 10 to leave the CPU to the web
+    /// UI and other services first; 0 keeps the server's.
+    pub process_niceness: i32,
+    /// Disk scheduling class external programs run in (`best_effort` or
+    /// `idle`); unset keeps the server's.
+    pub process_io_class: Option<IoClass>,
+    /// Threads each external program may start, through `OMP_THREAD_LIMIT`; 0
+    /// leaves the variable as the server has it.
+    pub omp_thread_limit: usize,
+    /// `/readyz` reports the server not ready while more sessions than this wait
+    /// in the queue; 0 never does.
+    pub ready_max_queued_jobs: usize,
+    /// Database sessions, their results and unfinished jobs are saved in, so
+    /// they survive a restart; unset keeps them in memory only.
+    pub state_store: Option<StateStoreConfig>,
+    /// Name this server instance claims its jobs under in a shared
+    /// `state_store`; the host name when unset. Must differ between instances.
+    pub instance_name: Option<String>,
+    /// Redis server session statuses are published to, e.g.
+    /// `redis://cache:6379/0`, so any server instance answers `/status` for
+    /// sessions another one runs; unset keeps them to this instance.
+    pub redis_url: Option<String>,
+    /// S3-compatible bucket uploads, kept page images and result files are
+    /// written to, with downloads served from it through presigned links;
+    /// unset keeps them on the local disk.
+    pub object_storage: Option<ObjectStorageConfig>,
+    /// Uploads and splits are refused while this many sessions wait in the
+    /// queue; 0 never.
+    pub max_queued_jobs: usize,
+    /// Uploads are refused while the temp directory has less free space than
+    /// this many bytes.
+    pub min_free_disk_bytes: u64,
+    /// Bytes the temporary files of a file being processed may take at once
+    /// before it is stopped; 0 for no limit.
+    pub max_job_temp_bytes: u64,
+    /// Bytes a session's uploads, temporary files and outputs may take
+    /// together before it is stopped; 0 for no limit.
+    pub max_session_bytes: u64,
+    /// Port of the gRPC service; unset leaves it off.
+    pub grpc_port: Option<u16>,
+    /// gRPC requests larger than this many bytes, files included, are refused.
+    pub grpc_max_message_bytes: usize,
+    /// `max-age` (seconds) sent for cacheable frontend assets.
+    pub static_cache_max_age: u64,
+    /// Serve `.br`/`.gz` siblings of frontend files to clients that accept them.
+    pub precompressed_static: bool,
+    /// Where to announce finished jobs.
+    pub notifications: NotificationConfig,
+    /// Limits on how often one client may upload and split.
+    pub rate_limit: RateLimitConfig,
+    /// When old `/split` chunks, finished sessions and leftover temporary
+    /// files are removed.
+    pub cleanup: CleanupConfig,
+}
+
+/// Retention of what the server leaves on disk, enforced by a sweep every
+/// `interval_seconds`. A retention of 0 keeps those files for good.
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct CleanupConfig {
+    /// Seconds between sweeps; 0 turns cleanup off.
+    pub interval_seconds: u64,
+    /// Seconds `/split` chunks are kept after the split.
+    pub split_retention_seconds: u64,
+    /// Seconds finished sessions are kept after their last update, with their
+    /// results, page images and downloads, as if deleted through the API.
+    pub session_retention_seconds: u64,
+    /// Seconds uploads and working files left in temp directories by jobs
+    /// that did not clean up, such as after a crash, are kept.
+    pub temp_retention_seconds: u64,
+}
+
+impl Default for CleanupConfig {
+    fn default() -> Self {
+        CleanupConfig {
+            interval_seconds: 3600,
+            split_retention_seconds: 24 * 3600,
+            session_retention_seconds: 0,
+            temp_retention_seconds: 24 * 3600,
+        }
+    }
+}
+
+/// Per-client limits on the upload and split endpoints, enforced with a token
+/// bucket per client address, or per API key for requests carrying a
+/// configured one.
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    /// Requests allowed per client address and minute; 0 leaves addresses
+    /// unlimited.
+    pub requests_per_minute: u32,
+    /// Requests a client may make at once after idling, `requests_per_minute`
+    /// when 0.
+    pub burst: u32,
+    /// Take the client address from `X-Forwarded-For`/`Forwarded`, for servers
+    /// behind a reverse proxy; clients can forge them otherwise.
+    pub trust_forwarded_for: bool,
+    /// Header carrying an API key, e.g. `X-Api-Key`.
+    pub key_header: Option<String>,
+    /// Requests per minute allowed for each API key, in place of the address
+    /// limit; 0 leaves a key unlimited. Unknown keys count against the address.
+    pub keys: HashMap<String, u32>,
+}
+
+/// A morphological analyzer, either local or an HTTP service; the command wins
+/// when both are set.
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct MorphologyConfig {
+    /// Program and arguments reading one word per line and printing one JSON array
+    /// of `{"lemma", "stem", "grammar"}` analyses per line.
+    pub command: Option<Vec<String>>,
+    /// Service receiving `{"words": [...]}` and answering `{"analyses": [[...], ...]}`.
+    pub url: Option<String>,
+}
+
+/// Database backend for saved sessions, picked by `backend`.
+#[derive(Clone, Deserialize)]
+#[serde(tag = "backend", rename_all = "lowercase")]
+pub enum StateStoreConfig {
+    /// A database file, created with its directory on first start, for one
+    /// server instance.
+    Sqlite { path: PathBuf },
+    /// A PostgreSQL database, e.g. `postgres://ocr:secret@db/ocr`, which several
+    /// server instances may share.
+    Postgres { url: String },
+}
+
+/// An S3 bucket, or one on an S3-compatible server such as MinIO.
+#[derive(Clone, Deserialize)]
+pub struct ObjectStorageConfig {
+    pub bucket: String,
+    /// Address of an S3-compatible server, e.g. `http://minio:9000`; AWS S3
+    /// when unset. Clients download from it too, so it must be reachable by
+    /// them.
+    pub endpoint: Option<String>,
+    /// `us-east-1` when unset.
+    pub region: Option<String>,
+    /// Taken from `AWS_ACCESS_KEY_ID` when unset.
+    pub access_key_id: Option<String>,
+    /// Taken from `AWS_SECRET_ACCESS_KEY` when unset.
+    pub secret_access_key: Option<String>,
+    /// Put before every key, e.g. `ocr/`, to share a bucket.
+    #[serde(default)]
+    pub prefix: String,
+    /// How long download links stay valid, 3600 seconds when unset.
+    pub presigned_url_seconds: Option<u64>,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct StorageProfile {
+    /// Directory for rendered pages and OCR intermediates; created on first use.
+    pub path: PathBuf,
+    /// When set, only requests carrying it in `X-Storage-Token` may use the profile.
+    pub token: Option<String>,
+}
+
+/// Completion notifications. Payloads are rendered with tera templates whose context
+/// is the job summary (session id, notes, per-file results and confidence scores).
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct NotificationConfig {
+    /// URL that receives a POST when a session finishes.
+    pub webhook_url: Option<String>,
+    /// Tera template for the webhook body; the summary is sent as JSON when unset.
+    pub webhook_template: Option<PathBuf>,
+    /// Content type of the rendered webhook body.
+    pub webhook_content_type: Option<String>,
+    pub email: Option<EmailConfig>,
+    /// External proofreading platform that receives the full results.
+    pub review: Option<ReviewConfig>,
+    /// Post the full results to an upload's `callback_url`; when off, only the
+    /// summary with links to fetch them.
+    pub callback_results: Option<bool>,
+    /// Tries per callback before giving up, 5 when unset.
+    pub callback_attempts: Option<u32>,
+    /// Address the server is reached at, e.g. `https://ocr.example.org`, for the
+    /// links in callbacks; they are paths alone when unset.
+    pub public_url: Option<String>,
+}
+
+/// Generic HTTP connector that submits finished results for proofreading.
+#[derive(Clone, Deserialize)]
+pub struct ReviewConfig {
+    pub url: String,
+    /// Tera template for the request body; the summary plus full `results` are
+    /// sent as JSON when unset.
+    pub template: Option<PathBuf>,
+    pub content_type: Option<String>,
+    /// Header carrying `auth_token`, `Authorization` by default.
+    pub auth_header: Option<String>,
+    /// Sent verbatim, e.g. `Bearer abc123` or an API key.
+    pub auth_token: Option<String>,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct EmailConfig {
+    pub smtp_host: String,
+    pub smtp_port: Option<u16>,
+    /// Use STARTTLS on the submission port instead of implicit TLS.
+    #[serde(default)]
+    pub starttls: bool,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub from: String,
+    pub to: Vec<String>,
+    /// Inline tera template for the subject line.
+    pub subject_template: Option<String>,
+    /// Tera template file for the plain-text body.
+    pub body_template: Option<PathBuf>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            language: "san".to_string(),
+            fallback_languages: Vec::new(),
+            fallback_min_confidence: 60.0,
+            script_models: HashMap::new(),
+            detect_script: false,
+            script_output: ScriptOutput::Devanagari,
+            segment_lines: false,
+            hocr: false,
+            skip_blank_pages: true,
+            blank_page_max_ink: 0.1,
+            split_spreads: false,
+            crop_margins: true,
+            normalize_unicode: true,
+            punctuation: Punctuation::default(),
+            replacements: Vec::new(),
+            replacement_profiles: HashMap::new(),
+            matter_handling: MatterHandling::Keep,
+            page_separator: "━━━ Page {page} ━━━".to_string(),
+            max_pdf_pages: 5000,
+            max_pdf_bytes: 1024 * 1024 * 1024,
+            max_page_pixels: 150_000_000,
+            max_archive_bytes: 2 * 1024 * 1024 * 1024,
+            max_download_bytes: 1024 * 1024 * 1024,
+            max_upload_file_bytes: 2 * 1024 * 1024 * 1024,
+            max_upload_bytes: 4 * 1024 * 1024 * 1024,
+            pdfium_library: None,
+            use_text_layer: true,
+            fix_marks: false,
+            dictionary_path: None,
+            dictionary_correction: false,
+            correction_max_confidence: 60.0,
+            correction_max_distance: 2,
+            sandhi_split: false,
+            sandhi_command: None,
+            analyze_morphology: false,
+            morphology: MorphologyConfig::default(),
+            detect_verses: false,
+            identify_meter: false,
+            temp_dir: None,
+            conversions_dir: PathBuf::from("./assets/conversions"),
+            splits_dir: None,
+            public_dir: PathBuf::from("./public"),
+            storage_profiles: HashMap::new(),
+            highlight: None,
+            highlight_max_confidence: 60.0,
+            parallel_lines: false,
+            join_lines: false,
+            provenance_markers: false,
+            keep_page_images: true,
+            max_concurrent_jobs: 2,
+            fast_lane_workers: 1,
+            page_workers: 0,
+            max_ocr_pixels: 40_000_000,
+            max_ocr_dimension: 10_000,
+            ocr_worker_command: None,
+            max_external_processes: 0,
+            process_niceness: 0,
+            process_io_class: None,
+            omp_thread_limit: 0,
+            ready_max_queued_jobs: 0,
+            max_queued_jobs: 0,
+            state_store: Some(StateStoreConfig::Sqlite {
+                path: PathBuf::from("./assets/sessions.db"),
+            }),
+            instance_name: None,
+            redis_url: None,
+            object_storage: None,
+            min_free_disk_bytes: 1024 * 1024 * 1024,
+            max_job_temp_bytes: 10 * 1024 * 1024 * 1024,
+            max_session_bytes: 20 * 1024 * 1024 * 1024,
+            grpc_port: None,
+            grpc_max_message_bytes: 256 * 1024 * 1024,
+            static_cache_max_age: 86400,
+            precompressed_static: true,
+            notifications: NotificationConfig::default(),
+            rate_limit: RateLimitConfig::default(),
+            cleanup: CleanupConfig::default(),
+        }
+    }
+}
+
+impl Config {
+    pub fn load() -> std::io::Result<Config> {
+        let (path, required) = match std::env::var("SANSKRIT_OCR_CONFIG") {
+            Ok(path) => (PathBuf::from(path), true),
+            Err(_) => (PathBuf::from("./config.json"), false),
+        };
+
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if !required && e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Config::default());
+            }
+            Err(e) => {
+                return Err(std::io::Error::new(
+                    e.kind(),
+                    format!("Failed to read config '{}': {}", path.display(), e),
+                ));
+            }
+        };
+
+        println!("Loading configuration from '{}'", path.display());
+        serde_json::from_str(&contents).map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Invalid config '{}': {}", path.display(), e),
+            )
+        })
+    }
+
+    /// Pages of a document to read at once: `page_workers`, or when that is 0
+    /// the CPU cores divided among the jobs that may run side by side.
+    pub fn page_workers(&self) -> usize {
+        match self.page_workers {
+            0 => {
+                let cores = std::thread::available_parallelism().map_or(1, |cores| cores.get());
+                (cores / self.max_concurrent_jobs.max(1)).max(1)
+            }
+            workers => workers,
+        }
+    }
+
+    /// External programs allowed to run at once: `max_external_processes`, or
+    /// when that is 0 the CPU cores.
+    pub fn external_processes(&self) -> usize {
+        match self.max_external_processes {
+            0 => std::thread::available_parallelism().map_or(1, |cores| cores.get()),
+            limit => limit,
+        }
+    }
+
+    /// How external programs are started: `process_niceness`,
+    /// `process_io_class` and `omp_thread_limit`.
+    pub fn process_priority(&self) -> Priority {
+        Priority {
+            niceness: self.process_niceness,
+            io_class: self.process_io_class,
+            thread_limit: self.omp_thread_limit,
+        }
+    }
+
+    /// `temp_dir`, or when unset the system temp directory.
+    pub fn temp_dir(&self) -> PathBuf {
+        self.temp_dir.clone().unwrap_or_else(std::env::temp_dir)
+    }
+
+    /// `splits_dir`, or when unset `splits` under `conversions_dir`.
+    pub fn splits_dir(&self) -> PathBuf {
+        self.splits_dir
+            .clone()
+            .unwrap_or_else(|| self.conversions_dir.join("splits"))
+    }
+
+    /// Creates the temp, conversions and splits directories if they are
+    /// missing and checks they can be written to, so a misconfigured path
+    /// stops the server at startup rather than failing the first job that
+    /// writes there.
+    pub fn create_dirs(&self) -> std::io::Result<()> {
+        let dirs = [
+            ("temp", self.temp_dir()),
+            ("conversions", self.conversions_dir.clone()),
+            ("splits", self.splits_dir()),
+        ];
+        for (name, dir) in dirs {
+            std::fs::create_dir_all(&dir).map_err(|e| {
+                std::io::Error::new(
+                    e.kind(),
+                    format!(
+                        "Failed to create {} directory '{}': {}",
+                        name,
+                        dir.display(),
+                        e
+                    ),
+                )
+            })?;
+            let probe = dir.join(format!(".write-test-{}", std::process::id()));
+            std::fs::write(&probe, b"")
+                .and_then(|()| std::fs::remove_file(&probe))
+                .map_err(|e| {
+                    std::io::Error::new(
+                        e.kind(),
+                        format!(
+                            "Cannot write to {} directory '{}': {}",
+                            name,
+                            dir.display(),
+                            e
+                        ),
+                    )
+                })?;
+        }
+        Ok(())
+    }
+
+    /// `instance_name`, or when unset the host name.
+    pub fn instance_name(&self) -> String {
+        self.instance_name
+            .clone()
+            .or_else(|| std::env::var("HOSTNAME").ok())
+            .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| "sanskrit-ocr".to_string())
+    }
+
+    /// Tesseract model for pages written in `script`, if one is available.
+    pub fn script_model(&self, script: Script) -> Option<String> {
+        match script {
+            Script::Devanagari => Some(self.language.clone()),
+            _ => self
+                .script_models
+                .get(&script)
+                .cloned()
+                .or_else(|| script.default_model().map(str::to_string)),
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use crate::cancel::Cancel;
+use crate::ocr::command_line;
+use crate::processes;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use uuid::Uuid;
+
+/// File extensions accepted for OCR, documents first.
+pub const SUPPORTED_EXTENSIONS: [&str; 11] = [
+    "pdf", "djvu", "djv", "zip", "png", "jpg", "jpeg", "webp", "heic", "heif", "avif",
+];
+
+/// Whether a file name has one of the [`SUPPORTED_EXTENSIONS`].
+pub fn is_supported(filename: &str) -> bool {
+    filename.rsplit_once('.').is_some_and(|(_, extension)| {
+        SUPPORTED_EXTENSIONS.contains(&extension.to_lowercase().as_str())
+    })
+}
+
+/// A page image of a DjVu document or ZIP archive and its page number, or the
+/// page number and why the page could not be turned into an image.
+pub type PageImage = Result<(usize, PathBuf), (usize, String)>;
+
+/// The external program [`to_png`] converts `image` with, when its type needs
+/// one.
+fn converter(image: &Path, output: &Path) -> Option<Command> {
+    let extension = image.extension()?.to_str()?.to_lowercase();
+    match extension.as_str() {
+        "heic" | "heif" | "avif" => {
+            let mut command = Command::new("heif-convert");
+            command.arg(image).arg(output);
+            Some(command)
+        }
+        _ => None,
+    }
+}
+
+/// The command line [`to_png`] runs for a file named `name`, with `{input}`
+/// and `{output}` standing for the paths; `None` when it runs none.
+pub fn converter_command(name: &str) -> Option<String> {
+    let (input, output) = (Path::new(name), Path::new("{output}"));
+    converter(input, output).map(|command| command_line(&command, input, "{output}"))
+}
+
+/// Converts images tesseract cannot read (WebP, and HEIC/AVIF from phone cameras)
+/// to a PNG under `work_dir`. Returns `None` for formats tesseract reads itself;
+/// otherwise the PNG path and, for external converters, the command line used.
+pub fn to_png(image: &Path, work_dir: &Path) -> Option<Result<(PathBuf, Option<String>), String>> {
+    let extension = image.extension()?.to_str()?.to_lowercase();
+    let output = work_dir.join(format!("ocr_convert_{}.png", Uuid::new_v4()));
+
+    match extension.as_str() {
+        "webp" => Some(
+            image::open(image)
+                .and_then(|decoded| decoded.save(&output))
+                .map(|()| (output, None))
+                .map_err(|e| format!("Failed to convert WebP image: {}", e)),
+        ),
+        "heic" | "heif" | "avif" => {
+            let command = converter(image, &output)?;
+            let command_text = command_line(&command, image, &output.display().to_string());
+            let converted = match processes::acquire(&Cancel::default())
+                .and_then(|_slot| processes::prioritized(&command).output())
+            {
+                Ok(result) if result.status.success() => Ok((output, Some(command_text))),
+                Ok(result) => {
+                    let _ = std::fs::remove_file(&output);
+                    Err(format!(
+                        "Failed to convert {} image: {}",
+                        extension.to_uppercase(),
+                        String::from_utf8_lossy(&result.stderr).trim()
+                    ))
+                }
+                Err(e) => Err(format!(
+                    "Failed to execute heif-convert: {}. Install the libheif-examples package.",
+                    e
+                )),
+            };
+            Some(converted)
+        }
+        _ => None,
+    }
+}
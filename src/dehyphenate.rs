@@ -0,0 +1,110 @@
+use crate::dictionary::{Dictionary, is_word_char};
+
+const HYPHENS: &[char] = &['-', '\u{00AD}', '\u{2010}', '\u{2011}'];
+
+/// Word-character core of a token, without surrounding punctuation.
+fn core(token: &str) -> &str {
+    token.trim_matches(|c: char| !is_word_char(c))
+}
+
+/// Whether the word broken as `head`/`tail` across a line break should be
+/// written as one word.
+///
+/// A hyphenated break is joined unless both halves are words of their own and
+/// the joined form is not, which points at a genuinely hyphenated compound. An
+/// unmarked break is only joined when the dictionary knows the joined form and
+/// does not know both halves.
+fn should_join(head: &str, tail: &str, hyphenated: bool, dictionary: &Dictionary) -> bool {
+    // Punctuation at the break (a daṇḍa, a quote) means the word really ended
+    if !head.ends_with(is_word_char) || !tail.starts_with(is_word_char) {
+        return false;
+    }
+    let (head, tail) = (core(head), core(tail));
+    let joined = format!("{}{}", head, tail);
+    let halves_known = dictionary.contains(head) && dictionary.contains(tail);
+    if hyphenated {
+        dictionary.is_empty() || dictionary.contains(&joined) || !halves_known
+    } else {
+        dictionary.contains(&joined) && !halves_known
+    }
+}
+
+/// Rejoins the lines of each paragraph (blank lines separate paragraphs), merging
+/// words broken across line ends where `should_join` agrees and keeping the
+/// hyphen of compounds that were hyphenated anyway.
+pub fn join_lines(text: &str, dictionary: &Dictionary) -> String {
+    let mut paragraphs = Vec::new();
+    let mut current = String::new();
+
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() {
+            if !current.is_empty() {
+                paragraphs.push(std::mem::take(&mut current));
+            }
+            continue;
+        }
+        if current.is_empty() {
+            current.push_str(line);
+            continue;
+        }
+
+        let head_start = current
+            .char_indices()
+            .rev()
+            .find(|(_, c)| c.is_whitespace())
+            .map_or(0, |(i, c)| i + c.len_utf8());
+        let head = &current[head_start..];
+        let tail = line.split_whitespace().next().unwrap_or_default();
+        let unhyphenated = head.trim_end_matches(HYPHENS);
+        let hyphenated = unhyphenated.len() != head.len();
+
+        if should_join(unhyphenated, tail, hyphenated, dictionary) {
+            current.truncate(head_start + unhyphenated.len());
+        } else if !hyphenated || !unhyphenated.ends_with(is_word_char) {
+            current.push(' ');
+        }
+        current.push_str(line);
+    }
+    if !current.is_empty() {
+        paragraphs.push(current);
+    }
+
+    paragraphs.join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn joins_hyphenated_breaks() {
+        let none = Dictionary::default();
+        assert_eq!(join_lines("धर्मक्षे-\nत्रे कुरुक्षेत्रे", &none), "धर्मक्षेत्रे कुरुक्षेत्रे");
+        assert_eq!(join_lines("समवेता युयु\u{00AD}\nत्सवः", &none), "समवेता युयुत्सवः");
+    }
+
+    #[test]
+    fn keeps_the_hyphen_of_compounds() {
+        let dictionary = Dictionary::of(&["राम", "लक्ष्मण"]);
+        assert_eq!(
+            join_lines("राम-\nलक्ष्मण गच्छतः", &dictionary),
+            "राम-लक्ष्मण गच्छतः"
+        );
+    }
+
+    #[test]
+    fn joins_unmarked_breaks_the_dictionary_knows() {
+        let dictionary = Dictionary::of(&["धर्मक्षेत्रे"]);
+        assert_eq!(join_lines("धर्मक्षे\nत्रे", &dictionary), "धर्मक्षेत्रे");
+        assert_eq!(join_lines("धर्मक्षे\nत्रे", &Dictionary::default()), "धर्मक्षे त्रे");
+    }
+
+    #[test]
+    fn keeps_words_ended_by_punctuation_and_paragraphs() {
+        let none = Dictionary::default();
+        assert_eq!(
+            join_lines("रामः वनं गच्छति ।\nसीता\n\nलक्ष्मणः", &none),
+            "रामः वनं गच्छति । सीता\n\nलक्ष्मणः"
+        );
+    }
+}
@@ -0,0 +1,326 @@
+use crate::ocr::Word;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// A Sanskrit wordlist with optional corpus frequencies, indexed for fuzzy lookup.
+///
+/// The file holds one Devanagari word per line, optionally followed by whitespace
+/// and a frequency count; lines starting with `#` are ignored. A list derived from
+/// the Cologne digitization of Monier-Williams (headwords plus inflected forms)
+/// is the intended source.
+#[derive(Default)]
+pub struct Dictionary {
+    frequencies: HashMap<String, u32>,
+    tree: Option<Node>,
+}
+
+/// BK-tree node: children are keyed by their edit distance to this word.
+struct Node {
+    word: String,
+    children: HashMap<usize, Node>,
+}
+
+impl Node {
+    fn insert(&mut self, word: String) {
+        let distance = levenshtein(&self.word, &word);
+        if distance == 0 {
+            return;
+        }
+        match self.children.get_mut(&distance) {
+            Some(child) => child.insert(word),
+            None => {
+                self.children.insert(
+                    distance,
+                    Node {
+                        word,
+                        children: HashMap::new(),
+                    },
+                );
+            }
+        }
+    }
+
+    fn search<'a>(&'a self, word: &str, max_distance: usize, found: &mut Vec<(&'a str, usize)>) {
+        let distance = levenshtein(&self.word, word);
+        if distance <= max_distance {
+            found.push((&self.word, distance));
+        }
+        let low = distance.saturating_sub(max_distance);
+        let high = distance + max_distance;
+        for (edge, child) in &self.children {
+            if (low..=high).contains(edge) {
+                child.search(word, max_distance, found);
+            }
+        }
+    }
+}
+
+/// Character-level edit distance.
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, ca) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b.len()]
+}
+
+impl Dictionary {
+    pub fn load(path: &Path) -> std::io::Result<Dictionary> {
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            std::io::Error::new(
+                e.kind(),
+                format!("Failed to read wordlist '{}': {}", path.display(), e),
+            )
+        })?;
+
+        let mut dictionary = Dictionary::default();
+        for line in contents.lines() {
+            let mut columns = line.split_whitespace();
+            let Some(word) = columns.next().filter(|w| !w.starts_with('#')) else {
+                continue;
+            };
+            let frequency = columns.next().and_then(|f| f.parse().ok()).unwrap_or(1);
+            dictionary.insert(word, frequency);
+        }
+
+        println!(
+            "Loaded {} dictionary words from '{}'",
+            dictionary.len(),
+            path.display()
+        );
+        Ok(dictionary)
+    }
+
+    fn insert(&mut self, word: &str, frequency: u32) {
+        if self
+            .frequencies
+            .insert(word.to_string(), frequency)
+            .is_some()
+        {
+            return;
+        }
+        match &mut self.tree {
+            Some(root) => root.insert(word.to_string()),
+            None => {
+                self.tree = Some(Node {
+                    word: word.to_string(),
+                    children: HashMap::new(),
+                });
+            }
+        }
+    }
+
+    /// A dictionary of `words`, each seen once.
+    #[cfg(test)]
+    pub(crate) fn of(words: &[&str]) -> Dictionary {
+        let mut dictionary = Dictionary::default();
+        for word in words {
+            dictionary.insert(word, 1);
+        }
+        dictionary
+    }
+
+    pub fn len(&self) -> usize {
+        self.frequencies.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frequencies.is_empty()
+    }
+
+    pub fn contains(&self, word: &str) -> bool {
+        self.frequencies.contains_key(word)
+    }
+
+    pub fn frequency(&self, word: &str) -> u32 {
+        self.frequencies.get(word).copied().unwrap_or(0)
+    }
+
+    /// Dictionary words within `max_distance` edits of `word`, closest first and
+    /// more frequent first among equally close candidates.
+    pub fn candidates(&self, word: &str, max_distance: usize) -> Vec<(&str, usize)> {
+        let mut found = Vec::new();
+        if let Some(root) = &self.tree {
+            root.search(word, max_distance, &mut found);
+        }
+        found.sort_by(|a, b| {
+            a.1.cmp(&b.1)
+                .then_with(|| self.frequency(b.0).cmp(&self.frequency(a.0)))
+                .then_with(|| a.0.cmp(b.0))
+        });
+        found
+    }
+
+    /// The best dictionary form for `word`, if one is close enough and the word is
+    /// not already known.
+    pub fn closest(&self, word: &str, max_distance: usize) -> Option<&str> {
+        if self.contains(word) {
+            return None;
+        }
+        self.candidates(word, max_distance)
+            .first()
+            .map(|(candidate, _)| *candidate)
+    }
+}
+
+/// A low-confidence word replaced by its closest dictionary form.
+#[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct Correction {
+    pub original: String,
+    pub corrected: String,
+    pub confidence: f32,
+}
+
+/// A word missing from the dictionary, with its likeliest intended forms.
+#[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct Suggestion {
+    pub word: String,
+    /// Character offsets of the word in the submitted text.
+    pub start: usize,
+    pub end: usize,
+    pub candidates: Vec<Candidate>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct Candidate {
+    pub word: String,
+    pub distance: usize,
+    pub frequency: u32,
+}
+
+/// Devanagari letters and combining marks, i.e. everything except daṇḍas and digits.
+pub fn is_word_char(c: char) -> bool {
+    ('\u{0900}'..='\u{0963}').contains(&c) || ('\u{0971}'..='\u{097F}').contains(&c)
+}
+
+/// Splits `text` into alternating runs of whitespace and non-whitespace.
+pub fn tokens(text: &str) -> impl Iterator<Item = &str> {
+    let mut rest = text;
+    std::iter::from_fn(move || {
+        let first = rest.chars().next()?;
+        let end = rest
+            .find(|c: char| c.is_whitespace() != first.is_whitespace())
+            .unwrap_or(rest.len());
+        let (token, tail) = rest.split_at(end);
+        rest = tail;
+        Some(token)
+    })
+}
+
+/// Pairs every token of `text` with the confidence of its TSV word, if it could
+/// be matched. Tokens are matched to TSV words in order; a token that cannot be
+/// matched within a short lookahead gets no confidence rather than a guess.
+pub fn aligned_tokens<'a>(
+    text: &'a str,
+    words: &'a [Word],
+) -> impl Iterator<Item = (&'a str, Option<f32>)> {
+    let mut next_word = 0;
+    tokens(text).map(move |token| {
+        if token.starts_with(char::is_whitespace) {
+            return (token, None);
+        }
+        let confidence = words[next_word.min(words.len())..]
+            .iter()
+            .take(3)
+            .position(|word| word.text == token)
+            .map(|offset| {
+                next_word += offset + 1;
+                words[next_word - 1].confidence
+            });
+        (token, confidence)
+    })
+}
+
+/// Replaces every word tesseract scored below `max_confidence` with the closest
+/// dictionary form, keeping surrounding punctuation and spacing intact.
+pub fn correct_page(
+    text: &str,
+    words: &[Word],
+    dictionary: &Dictionary,
+    max_confidence: f32,
+    max_distance: usize,
+) -> (String, Vec<Correction>) {
+    let mut corrected = String::with_capacity(text.len());
+    let mut corrections = Vec::new();
+
+    for (token, confidence) in aligned_tokens(text, words) {
+        let core = token.trim_matches(|c: char| !is_word_char(c));
+        let replacement = match confidence {
+            Some(confidence) if confidence < max_confidence && core.chars().count() > 1 => {
+                dictionary
+                    .closest(core, max_distance)
+                    .map(|best| (best, confidence))
+            }
+            _ => None,
+        };
+
+        match replacement {
+            Some((best, confidence)) => {
+                corrections.push(Correction {
+                    original: core.to_string(),
+                    corrected: best.to_string(),
+                    confidence,
+                });
+                corrected.push_str(&token.replacen(core, best, 1));
+            }
+            None => corrected.push_str(token),
+        }
+    }
+
+    (corrected, corrections)
+}
+
+/// Lists every word of `text` the dictionary does not know, with up to `limit`
+/// candidates each, ranked by edit distance and then frequency.
+pub fn suggest(
+    text: &str,
+    dictionary: &Dictionary,
+    max_distance: usize,
+    limit: usize,
+) -> Vec<Suggestion> {
+    let mut suggestions = Vec::new();
+    let mut offset = 0;
+
+    for token in tokens(text) {
+        let length = token.chars().count();
+        let lead = token.chars().take_while(|&c| !is_word_char(c)).count();
+        let core = token.trim_matches(|c: char| !is_word_char(c));
+        offset += length;
+
+        if core.chars().count() < 2 || dictionary.contains(core) {
+            continue;
+        }
+        let candidates = dictionary
+            .candidates(core, max_distance)
+            .into_iter()
+            .take(limit)
+            .map(|(word, distance)| Candidate {
+                word: word.to_string(),
+                distance,
+                frequency: dictionary.frequency(word),
+            })
+            .collect();
+        let start = offset - length + lead;
+        suggestions.push(Suggestion {
+            word: core.to_string(),
+            start,
+            end: start + core.chars().count(),
+            candidates,
+        });
+    }
+
+    suggestions
+}
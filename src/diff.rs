@@ -0,0 +1,195 @@
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// How a run of words changed between the OCR text and the corrected one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum Op {
+    Equal,
+    /// OCR words the correction has others in place of.
+    Replace,
+    /// OCR words the correction drops.
+    Delete,
+    /// Words only the correction has.
+    Insert,
+}
+
+/// A run of words, space-separated, on either side of a diff.
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct Change {
+    pub op: Op,
+    /// The words as recognized; empty for insertions.
+    pub ocr: String,
+    /// The words as corrected; empty for deletions.
+    pub corrected: String,
+}
+
+/// Word-level diff of `ocr` against `corrected`, from their longest common
+/// subsequence of words. Line breaks count as spaces. Neighbouring deletions and
+/// insertions are reported together as one replacement.
+pub fn words(ocr: &str, corrected: &str) -> Vec<Change> {
+    let old: Vec<&str> = ocr.split_whitespace().collect();
+    let new: Vec<&str> = corrected.split_whitespace().collect();
+
+    // common[i][j]: length of the longest common subsequence of old[i..] and new[j..]
+    let mut common = vec![vec![0u32; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut changes: Vec<Change> = Vec::new();
+    let mut push = |op: Op, ocr: &[&str], corrected: &[&str]| {
+        if let Some(last) = changes.last_mut()
+            && (last.op == op || (op != Op::Equal && last.op != Op::Equal))
+        {
+            for (side, words) in [(&mut last.ocr, ocr), (&mut last.corrected, corrected)] {
+                for word in words {
+                    if !side.is_empty() {
+                        side.push(' ');
+                    }
+                    side.push_str(word);
+                }
+            }
+            if last.op != op {
+                last.op = Op::Replace;
+            }
+            return;
+        }
+        changes.push(Change {
+            op,
+            ocr: ocr.join(" "),
+            corrected: corrected.join(" "),
+        });
+    };
+
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            push(Op::Equal, &old[i..=i], &[]);
+            i += 1;
+            j += 1;
+        } else if j == new.len() || (i < old.len() && common[i + 1][j] >= common[i][j + 1]) {
+            push(Op::Delete, &old[i..=i], &[]);
+            i += 1;
+        } else {
+            push(Op::Insert, &[], &new[j..=j]);
+            j += 1;
+        }
+    }
+
+    // Equal runs read the same on both sides
+    for change in &mut changes {
+        if change.op == Op::Equal {
+            change.corrected = change.ocr.clone();
+        }
+    }
+    changes
+}
+
+/// How often the correction put the same words in place of the same OCR words.
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct Substitution {
+    pub ocr: String,
+    pub corrected: String,
+    pub count: usize,
+}
+
+/// Tallies the replacements among `changes`, most frequent first: the OCR's
+/// systematic errors.
+pub fn substitutions<'a>(changes: impl IntoIterator<Item = &'a Change>) -> Vec<Substitution> {
+    let mut counts: HashMap<(&str, &str), usize> = HashMap::new();
+    for change in changes {
+        if change.op == Op::Replace {
+            *counts
+                .entry((change.ocr.as_str(), change.corrected.as_str()))
+                .or_default() += 1;
+        }
+    }
+    let mut substitutions: Vec<Substitution> = counts
+        .into_iter()
+        .map(|((ocr, corrected), count)| Substitution {
+            ocr: ocr.to_string(),
+            corrected: corrected.to_string(),
+            count,
+        })
+        .collect();
+    substitutions.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then_with(|| a.ocr.cmp(&b.ocr))
+            .then_with(|| a.corrected.cmp(&b.corrected))
+    });
+    substitutions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(changes: &[Change]) -> Vec<(Op, &str, &str)> {
+        changes
+            .iter()
+            .map(|change| (change.op, change.ocr.as_str(), change.corrected.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn reports_equal_text_as_one_run() {
+        let changes = words("धर्मक्षेत्रे\nकुरुक्षेत्रे", "धर्मक्षेत्रे  कुरुक्षेत्रे");
+        assert_eq!(
+            summary(&changes),
+            [(Op::Equal, "धर्मक्षेत्रे कुरुक्षेत्रे", "धर्मक्षेत्रे कुरुक्षेत्रे")]
+        );
+    }
+
+    #[test]
+    fn pairs_deletions_with_insertions_as_replacements() {
+        let changes = words("समवेता युयुतसवः मामकाः", "समवेता युयुत्सवः मामकाः");
+        assert_eq!(
+            summary(&changes),
+            [
+                (Op::Equal, "समवेता", "समवेता"),
+                (Op::Replace, "युयुतसवः", "युयुत्सवः"),
+                (Op::Equal, "मामकाः", "मामकाः"),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_words_only_one_side_has() {
+        assert_eq!(
+            summary(&words("किम् अकुर्वत अकुर्वत सञ्जय", "किम् अकुर्वत सञ्जय")),
+            [
+                (Op::Equal, "किम् अकुर्वत", "किम् अकुर्वत"),
+                (Op::Delete, "अकुर्वत", ""),
+                (Op::Equal, "सञ्जय", "सञ्जय"),
+            ]
+        );
+        assert_eq!(
+            summary(&words("पाण्डवाः", "पाण्डवाः चैव")),
+            [(Op::Equal, "पाण्डवाः", "पाण्डवाः"), (Op::Insert, "", "चैव")]
+        );
+    }
+
+    #[test]
+    fn tallies_substitutions_most_frequent_first() {
+        let mut changes = words("रामः वनं गच्छति", "रामः वनम् गच्छति");
+        changes.extend(words("सीता वनं", "सीता वनम्"));
+        changes.extend(words("गच्छति", "गच्छतु"));
+        let substitutions = substitutions(&changes);
+        let tally: Vec<_> = substitutions
+            .iter()
+            .map(|s| (s.ocr.as_str(), s.corrected.as_str(), s.count))
+            .collect();
+        assert_eq!(tally, [("वनं", "वनम्", 2), ("गच्छति", "गच्छतु", 1)]);
+    }
+}
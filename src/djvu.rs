@@ -0,0 +1,142 @@
+use crate::cancel::Cancel;
+use crate::convert::PageImage;
+use crate::ocr::command_line;
+use crate::pdf::{self, PageRanges};
+use crate::processes;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use uuid::Uuid;
+
+/// Number of pages of a DjVu document, as reported by `djvused`.
+pub fn page_count(path: &Path) -> Result<usize, String> {
+    let _slot = processes::acquire(&Cancel::default()).map_err(|e| e.to_string())?;
+    let output = Command::new("djvused")
+        .arg("-e")
+        .arg("n")
+        .arg(path)
+        .output()
+        .map_err(|e| {
+            format!(
+                "Failed to execute djvused: {}. Install the djvulibre-bin package.",
+                e
+            )
+        })?;
+    if !output.status.success() {
+        return Err(format!(
+            "djvused error: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse()
+        .map_err(|_| "Could not determine DjVu page count".to_string())
+}
+
+/// Pages of a DjVu document rendered to TIFFs with `ddjvu` one at a time as
+/// they are iterated, at the document's own resolution, so OCR can start on the
+/// first page while the next is rendered. Yields the original page numbers with
+/// their image paths, in page order.
+pub struct RenderedPages {
+    path: PathBuf,
+    work_dir: PathBuf,
+    prefix: String,
+    selected: std::vec::IntoIter<usize>,
+    cancel: Cancel,
+}
+
+impl RenderedPages {
+    fn command(&self, page_number: usize, image_path: &Path) -> Command {
+        let mut command = Command::new("ddjvu");
+        command
+            .arg("-format=tiff")
+            .arg(format!("-page={}", page_number))
+            .arg(&self.path)
+            .arg(image_path);
+        command
+    }
+
+    /// The command line pages are rendered with, `{page}` standing for the page
+    /// number.
+    pub fn command_line(&self) -> String {
+        let image_path = self.work_dir.join(format!("{}-{{page}}.tif", self.prefix));
+        command_line(
+            &self.command(0, &image_path),
+            &self.path,
+            &image_path.display().to_string(),
+        )
+        .replace("-page=0", "-page={page}")
+    }
+
+    fn render(&self, page_number: usize) -> Result<PathBuf, String> {
+        let image_path = self
+            .work_dir
+            .join(format!("{}-{:04}.tif", self.prefix, page_number));
+        let failure = match self
+            .cancel
+            .output(&mut self.command(page_number, &image_path))
+        {
+            Ok(output) if output.status.success() => return Ok(image_path),
+            Ok(output) => format!(
+                "ddjvu error on page {}: {}",
+                page_number,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => e.to_string(),
+            Err(e) => format!(
+                "Failed to execute ddjvu: {}. Install the djvulibre-bin package.",
+                e
+            ),
+        };
+        let _ = std::fs::remove_file(&image_path);
+        Err(failure)
+    }
+}
+
+impl Iterator for RenderedPages {
+    /// A page's number with its image, or with why it could not be rendered.
+    type Item = PageImage;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let page_number = self.selected.next()?;
+        Some(
+            self.render(page_number)
+                .map(|image| (page_number, image))
+                .map_err(|e| (page_number, e)),
+        )
+    }
+
+    /// Passes over `n` pages without rendering them, for picking a document up
+    /// where it was left.
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        if n > 0 {
+            self.selected.nth(n - 1)?;
+        }
+        self.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.selected.size_hint()
+    }
+}
+
+impl ExactSizeIterator for RenderedPages {}
+
+/// Opens a DjVu document for rendering all its pages or those in `selection`
+/// under `work_dir`, one at a time; see [`RenderedPages`]. Rendering stops
+/// once `cancel` is set.
+pub fn render(
+    path: &Path,
+    work_dir: &Path,
+    selection: Option<&PageRanges>,
+    cancel: &Cancel,
+) -> Result<RenderedPages, String> {
+    let selected = pdf::selected_pages(selection, page_count(path)?)?;
+    Ok(RenderedPages {
+        path: path.to_path_buf(),
+        work_dir: work_dir.to_path_buf(),
+        prefix: format!("djvu_convert_{}", Uuid::new_v4()),
+        selected: selected.into_iter(),
+        cancel: cancel.clone(),
+    })
+}
@@ -0,0 +1,83 @@
+use crate::convert;
+use crate::pdf::PageRanges;
+use crate::pipeline;
+use std::path::{Path, PathBuf};
+
+/// A file to recognize: an image, PDF, DjVu document or ZIP archive of page
+/// images, with what is needed to read it.
+#[derive(Clone, Debug)]
+pub struct Document {
+    path: PathBuf,
+    filename: String,
+    password: Option<String>,
+    pages: Option<PageRanges>,
+}
+
+impl Document {
+    /// A document for the file at `path`, refused unless it exists and has one of
+    /// the [`convert::SUPPORTED_EXTENSIONS`].
+    pub fn open(path: impl Into<PathBuf>) -> Result<Document, String> {
+        let path = path.into();
+        let filename = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        if !path.is_file() {
+            return Err(format!("{}: no such file", path.display()));
+        }
+        if !convert::is_supported(&filename) {
+            return Err(format!(
+                "{}: unsupported file type; use one of {}",
+                path.display(),
+                convert::SUPPORTED_EXTENSIONS.join(", ")
+            ));
+        }
+        Ok(Document {
+            path,
+            filename,
+            password: None,
+            pages: None,
+        })
+    }
+
+    /// Names the document in results and progress reports; its file name by
+    /// default.
+    pub fn with_filename(mut self, filename: impl Into<String>) -> Document {
+        self.filename = filename.into();
+        self
+    }
+
+    /// Password of an encrypted PDF.
+    pub fn with_password(mut self, password: impl Into<String>) -> Document {
+        self.password = Some(password.into());
+        self
+    }
+
+    /// Pages to read, overriding the pipeline's `pages`; the others are left out.
+    /// Pages parsed with [`PageRanges::parse_order`] are read in the order listed.
+    pub fn with_pages(mut self, pages: PageRanges) -> Document {
+        self.pages = Some(pages);
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn filename(&self) -> &str {
+        &self.filename
+    }
+
+    pub fn password(&self) -> Option<&str> {
+        self.password.as_deref()
+    }
+
+    pub fn pages(&self) -> Option<&PageRanges> {
+        self.pages.as_ref()
+    }
+
+    /// Whether the document holds several pages rather than a single image.
+    pub fn is_paged(&self) -> bool {
+        pipeline::is_paged(&self.path)
+    }
+}
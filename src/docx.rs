@@ -0,0 +1,75 @@
+use std::io::{Cursor, Write};
+use zip::ZipWriter;
+use zip::write::SimpleFileOptions;
+
+const CONTENT_TYPES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/word/document.xml" ContentType="application/vnd.openxmlformats-officedocument.wordprocessingml.document.main+xml"/></Types>"#;
+
+const RELATIONSHIPS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="word/document.xml"/></Relationships>"#;
+
+/// Escapes the characters XML text cannot hold as they are, dropping control
+/// characters it cannot hold at all.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '\t' => escaped.push(c),
+            _ if c.is_control() => {}
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// One paragraph of a single run; `bold` ones serve as headings.
+fn paragraph(text: &str, bold: bool) -> String {
+    let properties = if bold {
+        "<w:rPr><w:b/><w:bCs/><w:sz w:val=\"28\"/><w:szCs w:val=\"28\"/></w:rPr>"
+    } else {
+        ""
+    };
+    format!(
+        "<w:p><w:r>{}<w:t xml:space=\"preserve\">{}</w:t></w:r></w:p>",
+        properties,
+        escape(text)
+    )
+}
+
+/// Builds a Word document of `sections`, each a heading over its text, with a
+/// paragraph per line. Sections without a heading start right with their text.
+pub fn write(sections: &[(Option<&str>, &str)]) -> Result<Vec<u8>, String> {
+    let mut body = String::new();
+    for (heading, text) in sections {
+        if let Some(heading) = heading {
+            body.push_str(&paragraph(heading, true));
+        }
+        for line in text.lines() {
+            body.push_str(&paragraph(line, false));
+        }
+    }
+    let document = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+         <w:document xmlns:w=\"http://schemas.openxmlformats.org/wordprocessingml/2006/main\">\
+         <w:body>{}</w:body></w:document>",
+        body
+    );
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let parts = [
+        ("[Content_Types].xml", CONTENT_TYPES),
+        ("_rels/.rels", RELATIONSHIPS),
+        ("word/document.xml", document.as_str()),
+    ];
+    for (name, content) in parts {
+        zip.start_file(name, SimpleFileOptions::default())
+            .and_then(|()| zip.write_all(content.as_bytes()).map_err(Into::into))
+            .map_err(|e| format!("Failed to write {}: {}", name, e))?;
+    }
+    zip.finish()
+        .map(Cursor::into_inner)
+        .map_err(|e| format!("Failed to finish document: {}", e))
+}
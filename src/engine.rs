@@ -0,0 +1,55 @@
+use crate::config::Config;
+use crate::dictionary::Dictionary;
+use crate::pipeline::{Pipeline, ProcessingOptions};
+use crate::processes;
+
+/// What recognition needs beyond the page images: the configuration (tesseract
+/// models, limits, defaults for every processing option) and the wordlist used
+/// for corrections. Set one up once and start a [`Pipeline`] from it per job.
+pub struct OcrEngine {
+    config: Config,
+    dictionary: Dictionary,
+}
+
+impl OcrEngine {
+    /// An engine with `config` and no wordlist. The config's
+    /// `max_external_processes` and the priority of external programs apply to
+    /// the whole process from here on.
+    pub fn new(config: Config) -> OcrEngine {
+        processes::set_limit(config.external_processes());
+        processes::set_priority(config.process_priority());
+        OcrEngine {
+            config,
+            dictionary: Dictionary::default(),
+        }
+    }
+
+    /// An engine with `config` and the wordlist its `dictionary_path` names; see
+    /// [`OcrEngine::new`].
+    pub fn load(config: Config) -> std::io::Result<OcrEngine> {
+        let dictionary = match &config.dictionary_path {
+            Some(path) => Dictionary::load(path)?,
+            None => Dictionary::default(),
+        };
+        Ok(OcrEngine::new(config).with_dictionary(dictionary))
+    }
+
+    /// Uses `dictionary` for corrections instead of the configured wordlist.
+    pub fn with_dictionary(mut self, dictionary: Dictionary) -> OcrEngine {
+        self.dictionary = dictionary;
+        self
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn dictionary(&self) -> &Dictionary {
+        &self.dictionary
+    }
+
+    /// A pipeline with the configured default for every processing option.
+    pub fn pipeline(&self) -> Pipeline<'_> {
+        Pipeline::new(self, ProcessingOptions::new(&self.config))
+    }
+}
@@ -0,0 +1,95 @@
+//! Serializable progress events for desktop frontends. Names and payloads are kept
+//! stable so a Tauri wrapper can forward them as-is with `app.emit(event.name(), &event)`.
+
+use crate::pipeline::{OcrResult, Progress};
+use serde::Serialize;
+
+pub const PROGRESS: &str = "ocr:progress";
+pub const PAGE_FINISHED: &str = "ocr:page-finished";
+pub const FILE_FINISHED: &str = "ocr:file-finished";
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(untagged)]
+pub enum Event {
+    Progress(ProgressEvent),
+    PageFinished(PageFinishedEvent),
+    FileFinished(FileFinishedEvent),
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ProgressEvent {
+    pub filename: String,
+    /// Kebab-case stage id: `converting-pdf`, `pdf-converted`, `converting-djvu`,
+    /// `djvu-converted`, `converting-zip`, `zip-converted` or `ocr-processing`.
+    pub stage: String,
+    pub current: usize,
+    /// Zero while the page count is still unknown.
+    pub total: usize,
+    pub message: String,
+}
+
+/// A page of a multi-page document with its text, as soon as it is read.
+#[derive(Clone, Debug, Serialize)]
+pub struct PageFinishedEvent {
+    pub filename: String,
+    pub page_number: usize,
+    pub source_page: usize,
+    pub text: String,
+    pub confidence: Option<f32>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct FileFinishedEvent {
+    pub filename: String,
+    pub success: bool,
+    pub error: Option<String>,
+    pub pages: usize,
+}
+
+impl Event {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::Progress(_) => PROGRESS,
+            Event::PageFinished(_) => PAGE_FINISHED,
+            Event::FileFinished(_) => FILE_FINISHED,
+        }
+    }
+
+    /// Updates that carry a page just read become [`Event::PageFinished`].
+    pub fn progress(filename: &str, progress: Progress) -> Event {
+        if let Some(page) = progress.page {
+            return Event::PageFinished(PageFinishedEvent {
+                filename: filename.to_string(),
+                page_number: page.page_number,
+                source_page: page.source_page,
+                text: page.text,
+                confidence: page.confidence,
+            });
+        }
+        Event::Progress(ProgressEvent {
+            filename: filename.to_string(),
+            stage: progress.stage.to_lowercase().replace(' ', "-"),
+            current: progress.current,
+            total: progress.total,
+            message: progress.message,
+        })
+    }
+
+    pub fn finished(result: &OcrResult) -> Event {
+        Event::FileFinished(FileFinishedEvent {
+            filename: result.filename.clone(),
+            success: result.success,
+            error: result.error.clone(),
+            pages: result.pages.len(),
+        })
+    }
+}
+
+/// Adapts an event sink into the progress callback taken by
+/// [`process_file`](crate::pipeline::process_file).
+pub fn progress_callback<'a>(
+    filename: &'a str,
+    emit: impl Fn(Event) + 'a,
+) -> impl Fn(Progress) + 'a {
+    move |progress| emit(Event::progress(filename, progress))
+}
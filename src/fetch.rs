@@ -0,0 +1,295 @@
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::header::CONTENT_TYPE;
+use reqwest::{Url, redirect};
+use sanskrit_ocr::convert;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::{AsyncWriteExt, BufWriter};
+use uuid::Uuid;
+
+/// Why a file could not be fetched from its URL.
+#[derive(Debug)]
+pub enum FetchError {
+    /// Not an `http://` or `https://` URL.
+    InvalidUrl(String),
+    /// The server could not be reached or answered with an error.
+    Failed(String),
+    /// Over `max_download_bytes`.
+    TooLarge(String),
+    /// Neither the content type nor the URL names a supported format.
+    Unsupported(String),
+}
+
+impl FetchError {
+    pub fn code(&self) -> Option<&'static str> {
+        match self {
+            FetchError::InvalidUrl(_) => None,
+            FetchError::Failed(_) => Some("DOWNLOAD_FAILED"),
+            FetchError::TooLarge(_) => Some("INPUT_TOO_LARGE"),
+            FetchError::Unsupported(_) => Some("UNSUPPORTED_FORMAT"),
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            FetchError::InvalidUrl(message)
+            | FetchError::Failed(message)
+            | FetchError::TooLarge(message)
+            | FetchError::Unsupported(message) => message,
+        }
+    }
+}
+
+/// How long a server a client named may take to accept the connection.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long it may go without sending anything.
+const READ_TIMEOUT: Duration = Duration::from_secs(60);
+/// How long a whole request to it may take, the download included.
+const TOTAL_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+/// Redirects followed before giving up.
+const MAX_REDIRECTS: usize = 10;
+
+/// Whether `ip` is reachable on the internet, rather than this machine, its
+/// network, or a reserved range such as the cloud metadata address.
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_v4(ip),
+            None => is_public_v6(ip),
+        },
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        || a == 0
+        // Shared address space of carrier-grade NAT
+        || (a == 100 && (64..128).contains(&b))
+        // IETF protocol assignments
+        || (a == 192 && b == 0 && c == 0)
+        // Benchmarking
+        || (a == 198 && (18..20).contains(&b))
+        // Reserved
+        || a >= 240)
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_multicast()
+        // Unique local
+        || (first & 0xfe00) == 0xfc00
+        // Link-local
+        || (first & 0xffc0) == 0xfe80
+        // Documentation
+        || (first == 0x2001 && ip.segments()[1] == 0x0db8))
+}
+
+/// Why the server would not fetch `url`, if it would not: only `http://` and
+/// `https://` URLs naming a host are fetched, and not at an address off the
+/// internet. Names are checked as they are resolved, by [`public_client`].
+pub fn refusal(url: &Url) -> Option<String> {
+    if !matches!(url.scheme(), "http" | "https") {
+        return Some(format!("'{}' is not an http:// or https:// URL", url));
+    }
+    let host = url.host_str()?;
+    let ip: IpAddr = host
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse()
+        .ok()?;
+    (!is_public(ip)).then(|| format!("'{}' is not a public address", ip))
+}
+
+/// Resolves names as the system does, dropping the addresses
+/// [`is_public`] refuses.
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let addrs: Vec<_> = tokio::net::lookup_host((host.as_str(), 0))
+                .await?
+                .filter(|addr| is_public(addr.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(format!("'{}' has no public address", host).into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// A client for the URLs clients send, for uploads and callbacks: it only
+/// connects to public addresses, checks where each redirect leads, and gives
+/// up on servers too slow to answer.
+pub fn public_client() -> reqwest::Result<reqwest::Client> {
+    let redirects = redirect::Policy::custom(|attempt| {
+        if attempt.previous().len() >= MAX_REDIRECTS {
+            return attempt.error("too many redirects");
+        }
+        match refusal(attempt.url()) {
+            Some(reason) => attempt.error(format!("redirected to {}", reason)),
+            None => attempt.follow(),
+        }
+    });
+    reqwest::Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .read_timeout(READ_TIMEOUT)
+        .timeout(TOTAL_TIMEOUT)
+        .redirect(redirects)
+        .dns_resolver(std::sync::Arc::new(PublicResolver))
+        // A proxy would resolve names where they are not checked
+        .no_proxy()
+        .build()
+}
+
+/// A file fetched into the work directory.
+pub struct Download {
+    pub path: PathBuf,
+    /// Last segment of the URL's path, with the extension of the format served.
+    pub filename: String,
+}
+
+/// Extension for a `Content-Type`, ignoring its parameters. `None` for types that
+/// say nothing about the format, such as `application/octet-stream`.
+fn extension_for(content_type: &str) -> Option<&'static str> {
+    let essence = content_type.split(';').next()?.trim().to_lowercase();
+    match essence.as_str() {
+        "application/pdf" => Some("pdf"),
+        "image/vnd.djvu" | "image/x-djvu" | "image/djvu" => Some("djvu"),
+        "application/zip" | "application/x-zip-compressed" => Some("zip"),
+        "image/png" => Some("png"),
+        "image/jpeg" => Some("jpg"),
+        "image/webp" => Some("webp"),
+        "image/heic" => Some("heic"),
+        "image/heif" => Some("heif"),
+        "image/avif" => Some("avif"),
+        _ => None,
+    }
+}
+
+/// `error` with the errors that caused it, such as the address a name was
+/// refused for, which reqwest leaves out of its message.
+fn describe(error: reqwest::Error) -> String {
+    let error = error.without_url();
+    let mut message = error.to_string();
+    let mut source = std::error::Error::source(&error);
+    while let Some(cause) = source {
+        message = format!("{}: {}", message, cause);
+        source = cause.source();
+    }
+    message
+}
+
+/// Downloads `url` with a [`public_client`] under `work_dir`, refusing it once
+/// it passes `max_bytes` and unless its `Content-Type`, or failing that its
+/// path, names a format `POST /upload` takes.
+pub async fn download(
+    client: &reqwest::Client,
+    url: &str,
+    max_bytes: u64,
+    work_dir: &Path,
+) -> Result<Download, FetchError> {
+    let parsed = Url::parse(url.trim()).map_err(|_| {
+        FetchError::InvalidUrl(format!("'{}' is not an http:// or https:// URL", url))
+    })?;
+    if let Some(reason) = refusal(&parsed) {
+        return Err(FetchError::InvalidUrl(reason));
+    }
+    let name = parsed
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .filter(|segment| !segment.is_empty())
+        .unwrap_or("download")
+        .to_string();
+
+    let mut response = client
+        .get(parsed)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| FetchError::Failed(format!("{}: {}", url, describe(e))))?;
+
+    let served = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    let filename = match extension_for(&served) {
+        Some(extension) if !name.to_lowercase().ends_with(&format!(".{}", extension)) => {
+            format!("{}.{}", name, extension)
+        }
+        Some(_) => name,
+        // Servers often send files as octet-stream; trust the URL then, but not
+        // when it is plainly a web page
+        None if convert::is_supported(&name) && !served.contains("text/") => name,
+        None => {
+            return Err(FetchError::Unsupported(format!(
+                "{}: unsupported content type '{}'; link one of {}",
+                url,
+                served,
+                convert::SUPPORTED_EXTENSIONS.join(", ")
+            )));
+        }
+    };
+
+    let too_large = || {
+        FetchError::TooLarge(format!(
+            "{}: larger than the {} bytes allowed",
+            url, max_bytes
+        ))
+    };
+    if response
+        .content_length()
+        .is_some_and(|length| length > max_bytes)
+    {
+        return Err(too_large());
+    }
+
+    let extension = filename.rsplit('.').next().unwrap_or("tmp");
+    let path = work_dir.join(format!("ocr_{}.{}", Uuid::new_v4(), extension));
+    let written = async {
+        let mut file = BufWriter::new(
+            tokio::fs::File::create(&path)
+                .await
+                .map_err(|e| FetchError::Failed(format!("Failed to save {}: {}", url, e)))?,
+        );
+        // The declared length may be missing or wrong, so count what arrives
+        let mut received = 0u64;
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|e| FetchError::Failed(format!("{}: {}", url, describe(e))))?
+        {
+            received += chunk.len() as u64;
+            if received > max_bytes {
+                return Err(too_large());
+            }
+            file.write_all(&chunk)
+                .await
+                .map_err(|e| FetchError::Failed(format!("Failed to save {}: {}", url, e)))?;
+        }
+        file.flush()
+            .await
+            .map_err(|e| FetchError::Failed(format!("Failed to save {}: {}", url, e)))
+    }
+    .await;
+    if let Err(e) = written {
+        let _ = tokio::fs::remove_file(&path).await;
+        return Err(e);
+    }
+    Ok(Download { path, filename })
+}
@@ -0,0 +1,352 @@
+use crate::{
+    FileProgress, MAX_SESSIONS_LISTED, ProgressStatus, ProgressTracker, SESSION_STATES, page_status,
+};
+use actix_web::{HttpResponse, get, post, web};
+use async_graphql::http::GraphiQLSource;
+use async_graphql::{Context, EmptyMutation, EmptySubscription, Object, Schema};
+use sanskrit_ocr::pipeline::{OcrResult, PageResult};
+
+pub type OcrSchema = Schema<Query, EmptyMutation, EmptySubscription>;
+
+/// Deepest query the schema answers; sessions, files and pages need four levels.
+const MAX_DEPTH: usize = 8;
+
+pub fn schema(tracker: ProgressTracker) -> OcrSchema {
+    Schema::build(Query, EmptyMutation, EmptySubscription)
+        .data(tracker)
+        .limit_depth(MAX_DEPTH)
+        .finish()
+}
+
+pub struct Query;
+
+#[Object]
+impl Query {
+    /// A session by the id `/upload` returned; null for unknown sessions.
+    async fn session(&self, ctx: &Context<'_>, id: String) -> Option<Session> {
+        let tracker = ctx.data_unchecked::<ProgressTracker>();
+        tracker
+            .read()
+            .contains_key(&id)
+            .then(|| Session::new(tracker, id))
+    }
+
+    /// Sessions newest first, optionally only those in `state`: queued, running,
+    /// held, complete, failed or cancelled, and those carrying `tag`.
+    async fn sessions(
+        &self,
+        ctx: &Context<'_>,
+        state: Option<String>,
+        tag: Option<String>,
+        #[graphql(default)] offset: usize,
+        limit: Option<usize>,
+    ) -> async_graphql::Result<Vec<Session>> {
+        if let Some(state) = &state
+            && !SESSION_STATES.contains(&state.as_str())
+        {
+            return Err(format!(
+                "Unknown state '{}'; expected one of {}",
+                state,
+                SESSION_STATES.join(", ")
+            )
+            .into());
+        }
+        let tracker = ctx.data_unchecked::<ProgressTracker>();
+        let mut sessions: Vec<(u64, String)> = tracker
+            .read()
+            .iter()
+            .filter(|(_, status)| {
+                state.as_ref().is_none_or(|state| status.state() == state)
+                    && tag
+                        .as_ref()
+                        .is_none_or(|tag| status.metadata.tags.contains(tag))
+            })
+            .map(|(id, status)| (status.created_at, id.clone()))
+            .collect();
+        sessions.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+        Ok(sessions
+            .into_iter()
+            .skip(offset)
+            .take(
+                limit
+                    .unwrap_or(MAX_SESSIONS_LISTED)
+                    .min(MAX_SESSIONS_LISTED),
+            )
+            .map(|(_, id)| Session::new(tracker, id))
+            .collect())
+    }
+}
+
+/// A session, read from the tracker as each field is asked for, so results are
+/// only copied for queries that select them.
+pub struct Session {
+    tracker: ProgressTracker,
+    id: String,
+}
+
+impl Session {
+    fn new(tracker: &ProgressTracker, id: String) -> Session {
+        Session {
+            tracker: tracker.clone(),
+            id,
+        }
+    }
+
+    /// `read` applied to the session's status; its default once the session is
+    /// deleted.
+    fn status<T: Default>(&self, read: impl FnOnce(&ProgressStatus) -> T) -> T {
+        self.tracker
+            .read()
+            .get(&self.id)
+            .map(read)
+            .unwrap_or_default()
+    }
+}
+
+#[Object]
+impl Session {
+    async fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// queued, running, held, complete, failed or cancelled.
+    async fn state(&self) -> &'static str {
+        self.status(|status| status.state())
+    }
+
+    async fn stage(&self) -> String {
+        self.status(|status| status.stage.clone())
+    }
+
+    async fn message(&self) -> String {
+        self.status(|status| status.message.clone())
+    }
+
+    /// Files done.
+    async fn current(&self) -> usize {
+        self.status(|status| status.current)
+    }
+
+    /// Files in the session.
+    async fn total(&self) -> usize {
+        self.status(|status| status.total)
+    }
+
+    /// Share of the session done, 0-100.
+    async fn percent(&self) -> f32 {
+        self.status(|status| status.percent)
+    }
+
+    /// Estimated seconds until the session is done, once a page rate is known.
+    async fn eta_seconds(&self) -> Option<f64> {
+        self.status(|status| status.eta_seconds)
+    }
+
+    /// Pages read per minute lately.
+    async fn pages_per_minute(&self) -> Option<f64> {
+        self.status(|status| status.pages_per_minute)
+    }
+
+    /// Place in the job queue while queued, 1 for the next session to start.
+    async fn queue_position(&self) -> Option<usize> {
+        self.status(|status| status.queue_position)
+    }
+
+    /// low, normal or high.
+    async fn priority(&self) -> &'static str {
+        self.status(|status| status.priority.name())
+    }
+
+    async fn complete(&self) -> bool {
+        self.status(|status| status.complete)
+    }
+
+    async fn title(&self) -> Option<String> {
+        self.status(|status| status.metadata.title.clone())
+    }
+
+    /// Library or archive the scanned volume belongs to.
+    async fn source_library(&self) -> Option<String> {
+        self.status(|status| status.metadata.source_library.clone())
+    }
+
+    /// The volume's call number in that library.
+    async fn shelfmark(&self) -> Option<String> {
+        self.status(|status| status.metadata.shelfmark.clone())
+    }
+
+    async fn tags(&self) -> Vec<String> {
+        self.status(|status| status.metadata.tags.clone())
+    }
+
+    /// Upload time, in seconds since the Unix epoch.
+    async fn created_at(&self) -> u64 {
+        self.status(|status| status.created_at)
+    }
+
+    async fn updated_at(&self) -> u64 {
+        self.status(|status| status.updated_at)
+    }
+
+    /// Progress of each file, in upload order.
+    async fn files(&self) -> Vec<FileStatus> {
+        self.status(|status| status.files.iter().cloned().map(FileStatus).collect())
+    }
+
+    /// Results of the files processed so far, in upload order.
+    async fn results(&self) -> Vec<FileResult> {
+        self.status(|status| status.results.iter().cloned().map(FileResult).collect())
+    }
+}
+
+pub struct FileStatus(FileProgress);
+
+#[Object]
+impl FileStatus {
+    async fn filename(&self) -> &str {
+        &self.0.filename
+    }
+
+    async fn stage(&self) -> &str {
+        &self.0.stage
+    }
+
+    async fn message(&self) -> &str {
+        &self.0.message
+    }
+
+    /// Pages done, once the page count is known.
+    async fn current(&self) -> usize {
+        self.0.current
+    }
+
+    async fn total(&self) -> usize {
+        self.0.total
+    }
+
+    async fn complete(&self) -> bool {
+        self.0.complete
+    }
+}
+
+pub struct FileResult(OcrResult);
+
+#[Object]
+impl FileResult {
+    async fn filename(&self) -> &str {
+        &self.0.filename
+    }
+
+    async fn success(&self) -> bool {
+        self.0.success
+    }
+
+    async fn error(&self) -> Option<&str> {
+        self.0.error.as_deref()
+    }
+
+    async fn cancelled(&self) -> bool {
+        self.0.cancelled
+    }
+
+    async fn text(&self) -> &str {
+        &self.0.text
+    }
+
+    async fn pages_processed(&self) -> Option<usize> {
+        self.0.pages_processed
+    }
+
+    async fn total_pages(&self) -> Option<usize> {
+        self.0.total_pages
+    }
+
+    /// Download path of the searchable PDF, when one was requested.
+    async fn searchable_pdf(&self) -> Option<&str> {
+        self.0.searchable_pdf.as_deref()
+    }
+
+    /// The file's pages, optionally only those with one of `statuses`: read,
+    /// empty, blank or failed.
+    async fn pages(&self, statuses: Option<Vec<String>>) -> Vec<Page<'_>> {
+        self.0
+            .pages
+            .iter()
+            .filter(|page| {
+                statuses
+                    .as_ref()
+                    .is_none_or(|statuses| statuses.iter().any(|s| s == page_status(page)))
+            })
+            .map(Page)
+            .collect()
+    }
+}
+
+pub struct Page<'a>(&'a PageResult);
+
+#[Object]
+impl Page<'_> {
+    /// Position of the page in the result, from 1.
+    async fn page_number(&self) -> usize {
+        self.0.page_number
+    }
+
+    /// Page number in the uploaded document.
+    async fn source_page(&self) -> usize {
+        self.0.source_page
+    }
+
+    /// read, empty, blank or failed.
+    async fn status(&self) -> &'static str {
+        page_status(self.0)
+    }
+
+    async fn text(&self) -> &str {
+        &self.0.text
+    }
+
+    /// Mean word confidence, 0-100.
+    async fn confidence(&self) -> Option<f32> {
+        self.0.confidence
+    }
+
+    /// Time spent on the page, in seconds.
+    async fn seconds(&self) -> Option<f64> {
+        self.0.seconds
+    }
+
+    async fn error(&self) -> Option<&str> {
+        self.0.error.as_deref()
+    }
+
+    /// Tesseract model the text was read with.
+    async fn model(&self) -> &str {
+        &self.0.model
+    }
+
+    async fn proofread_text(&self) -> Option<&str> {
+        self.0.proofread_text.as_deref()
+    }
+}
+
+/// Answers a GraphQL query over sessions and their results, for clients that
+/// want a few fields of many sessions or pages rather than whole statuses.
+#[post("/graphql")]
+pub async fn query(
+    schema: web::Data<OcrSchema>,
+    request: web::Json<async_graphql::Request>,
+) -> HttpResponse {
+    HttpResponse::Ok().json(schema.execute(request.into_inner()).await)
+}
+
+/// GraphiQL for exploring the schema, loaded from a CDN.
+#[get("/graphql")]
+pub async fn graphiql() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(
+            GraphiQLSource::build()
+                .endpoint(&format!("{}/graphql", crate::API_PREFIX))
+                .finish(),
+        )
+}
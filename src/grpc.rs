@@ -0,0 +1,411 @@
+use crate::{
+    Jobs, Priority, ProgressStatus, SessionEvent, SessionMetadata, UploadedFile, remove_uploads,
+    session_events, unrunnable,
+};
+use futures_util::{Stream, StreamExt};
+use sanskrit_ocr::convert;
+use sanskrit_ocr::events::{Event, PageFinishedEvent};
+use sanskrit_ocr::pdf::{self, PdfRejection};
+use sanskrit_ocr::pipeline::{OcrResult, ProcessingOptions};
+use std::collections::BTreeMap;
+use std::pin::Pin;
+use std::sync::Arc;
+use tonic::metadata::MetadataValue;
+use tonic::{Code, Request, Response};
+use uuid::Uuid;
+
+/// Messages of `proto/ocr.proto`, and the service build.rs generates from them.
+pub mod proto {
+    use std::collections::HashMap;
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct File {
+        #[prost(string, tag = "1")]
+        pub filename: String,
+        #[prost(bytes = "vec", tag = "2")]
+        pub content: Vec<u8>,
+        #[prost(string, tag = "3")]
+        pub password: String,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct UploadRequest {
+        #[prost(message, repeated, tag = "1")]
+        pub files: Vec<File>,
+        #[prost(map = "string, string", tag = "2")]
+        pub options: HashMap<String, String>,
+        #[prost(string, tag = "3")]
+        pub priority: String,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct UploadReply {
+        #[prost(string, tag = "1")]
+        pub session_id: String,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct SessionRequest {
+        #[prost(string, tag = "1")]
+        pub session_id: String,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct SessionUpdate {
+        #[prost(oneof = "session_update::Update", tags = "1, 2")]
+        pub update: Option<session_update::Update>,
+    }
+
+    pub mod session_update {
+        #[derive(Clone, PartialEq, prost::Oneof)]
+        pub enum Update {
+            #[prost(message, tag = "1")]
+            Status(super::Status),
+            #[prost(message, tag = "2")]
+            Page(super::Page),
+        }
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Status {
+        #[prost(string, tag = "1")]
+        pub state: String,
+        #[prost(string, tag = "2")]
+        pub stage: String,
+        #[prost(string, tag = "3")]
+        pub message: String,
+        #[prost(uint64, tag = "4")]
+        pub current: u64,
+        #[prost(uint64, tag = "5")]
+        pub total: u64,
+        #[prost(float, tag = "6")]
+        pub percent: f32,
+        #[prost(uint64, optional, tag = "7")]
+        pub queue_position: Option<u64>,
+        #[prost(bool, tag = "8")]
+        pub complete: bool,
+        #[prost(message, repeated, tag = "9")]
+        pub files: Vec<FileStatus>,
+        #[prost(double, optional, tag = "10")]
+        pub eta_seconds: Option<f64>,
+        #[prost(double, optional, tag = "11")]
+        pub pages_per_minute: Option<f64>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct FileStatus {
+        #[prost(string, tag = "1")]
+        pub filename: String,
+        #[prost(string, tag = "2")]
+        pub stage: String,
+        #[prost(uint64, tag = "3")]
+        pub current: u64,
+        #[prost(uint64, tag = "4")]
+        pub total: u64,
+        #[prost(string, tag = "5")]
+        pub message: String,
+        #[prost(bool, tag = "6")]
+        pub complete: bool,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Page {
+        #[prost(string, tag = "1")]
+        pub filename: String,
+        #[prost(uint64, tag = "2")]
+        pub page_number: u64,
+        #[prost(uint64, tag = "3")]
+        pub source_page: u64,
+        #[prost(string, tag = "4")]
+        pub text: String,
+        #[prost(float, optional, tag = "5")]
+        pub confidence: Option<f32>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ResultReply {
+        #[prost(string, tag = "1")]
+        pub session_id: String,
+        #[prost(string, tag = "2")]
+        pub state: String,
+        #[prost(message, repeated, tag = "3")]
+        pub files: Vec<FileResult>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct FileResult {
+        #[prost(string, tag = "1")]
+        pub filename: String,
+        #[prost(bool, tag = "2")]
+        pub success: bool,
+        #[prost(string, tag = "3")]
+        pub error: String,
+        #[prost(string, tag = "4")]
+        pub text: String,
+        #[prost(message, repeated, tag = "5")]
+        pub pages: Vec<Page>,
+        #[prost(string, tag = "6")]
+        pub result_json: String,
+    }
+
+    include!(concat!(env!("OUT_DIR"), "/sanskrit_ocr.v1.Ocr.rs"));
+}
+
+/// A failed call, with the machine-readable reason the HTTP API gives as `code`
+/// in the `error-code` metadata.
+fn error(code: Code, message: String, reason: Option<&str>) -> tonic::Status {
+    let mut status = tonic::Status::new(code, message);
+    if let Some(reason) = reason.and_then(|reason| MetadataValue::try_from(reason).ok()) {
+        status.metadata_mut().insert("error-code", reason);
+    }
+    status
+}
+
+fn rejection_code(rejection: &PdfRejection) -> Code {
+    match rejection {
+        PdfRejection::TooLarge(_) => Code::ResourceExhausted,
+        PdfRejection::Suspicious(_) => Code::InvalidArgument,
+        PdfRejection::PasswordRequired(_) | PdfRejection::WrongPassword(_) => {
+            Code::PermissionDenied
+        }
+    }
+}
+
+fn status_message(status: &ProgressStatus) -> proto::Status {
+    proto::Status {
+        state: status.state().to_string(),
+        stage: status.stage.clone(),
+        message: status.message.clone(),
+        current: status.current as u64,
+        total: status.total as u64,
+        percent: status.percent,
+        queue_position: status.queue_position.map(|position| position as u64),
+        complete: status.complete,
+        files: status
+            .files
+            .iter()
+            .map(|file| proto::FileStatus {
+                filename: file.filename.clone(),
+                stage: file.stage.clone(),
+                current: file.current as u64,
+                total: file.total as u64,
+                message: file.message.clone(),
+                complete: file.complete,
+            })
+            .collect(),
+        eta_seconds: status.eta_seconds,
+        pages_per_minute: status.pages_per_minute,
+    }
+}
+
+fn page_message(page: PageFinishedEvent) -> proto::Page {
+    proto::Page {
+        filename: page.filename,
+        page_number: page.page_number as u64,
+        source_page: page.source_page as u64,
+        text: page.text,
+        confidence: page.confidence,
+    }
+}
+
+fn file_result(result: &OcrResult) -> proto::FileResult {
+    proto::FileResult {
+        filename: result.filename.clone(),
+        success: result.success,
+        error: result.error.clone().unwrap_or_default(),
+        text: result.text.clone(),
+        pages: result
+            .pages
+            .iter()
+            .map(|page| proto::Page {
+                filename: result.filename.clone(),
+                page_number: page.page_number as u64,
+                source_page: page.source_page as u64,
+                text: page.text.clone(),
+                confidence: page.confidence,
+            })
+            .collect(),
+        result_json: serde_json::to_string(result).unwrap_or_default(),
+    }
+}
+
+/// The gRPC face of [`Jobs`]: sessions it starts are queued, tracked and
+/// served like those of `POST /upload`.
+pub struct OcrService {
+    jobs: Arc<Jobs>,
+}
+
+impl OcrService {
+    pub fn new(jobs: Arc<Jobs>, max_message_bytes: usize) -> proto::ocr_server::OcrServer<Self> {
+        proto::ocr_server::OcrServer::new(OcrService { jobs })
+            .max_decoding_message_size(max_message_bytes)
+            .max_encoding_message_size(max_message_bytes)
+    }
+}
+
+type UpdateStream = Pin<Box<dyn Stream<Item = Result<proto::SessionUpdate, tonic::Status>> + Send>>;
+
+#[tonic::async_trait]
+impl proto::ocr_server::Ocr for OcrService {
+    async fn upload(
+        &self,
+        request: Request<proto::UploadRequest>,
+    ) -> Result<Response<proto::UploadReply>, tonic::Status> {
+        let request = request.into_inner();
+        let config = &self.jobs.config;
+        if request.files.is_empty() {
+            return Err(error(
+                Code::InvalidArgument,
+                "No files given".to_string(),
+                None,
+            ));
+        }
+        if let Some(reason) = crate::capacity::refusal(&self.jobs) {
+            return Err(error(Code::Unavailable, reason, Some("OVER_CAPACITY")));
+        }
+
+        let mut options = ProcessingOptions::new(config);
+        let mut metadata = SessionMetadata::default();
+        for (name, value) in &request.options {
+            // Both need more than a gRPC call carries: a storage token, a
+            // later prepare call
+            if name == "storage_profile" || name == "prepare" {
+                return Err(error(
+                    Code::InvalidArgument,
+                    format!("'{}' is only available over HTTP", name),
+                    None,
+                ));
+            }
+            if SessionMetadata::FIELDS.contains(&name.as_str()) {
+                metadata
+                    .set(name, value)
+                    .map_err(|e| error(Code::InvalidArgument, e, None))?;
+                continue;
+            }
+            options
+                .set(config, name, value)
+                .map_err(|e| error(Code::InvalidArgument, e, None))?;
+        }
+        let priority = Priority::parse(&request.priority)
+            .map_err(|e| error(Code::InvalidArgument, e, None))?;
+        if let Some(e) = unrunnable(config, &options) {
+            return Err(error(Code::InvalidArgument, e, None));
+        }
+
+        let mut files = Vec::new();
+        for file in request.files {
+            if !convert::is_supported(&file.filename) {
+                remove_uploads(&files);
+                return Err(error(
+                    Code::InvalidArgument,
+                    format!(
+                        "{}: unsupported file type; upload one of {}",
+                        file.filename,
+                        convert::SUPPORTED_EXTENSIONS.join(", ")
+                    ),
+                    Some("UNSUPPORTED_FORMAT"),
+                ));
+            }
+            let extension = file.filename.rsplit('.').next().unwrap_or("tmp");
+            let path = options
+                .work_dir
+                .join(format!("ocr_{}.{}", Uuid::new_v4(), extension));
+            if let Err(e) = tokio::fs::write(&path, &file.content).await {
+                remove_uploads(&files);
+                return Err(error(
+                    Code::Internal,
+                    format!("Failed to save {}: {}", file.filename, e),
+                    None,
+                ));
+            }
+
+            let password = Some(file.password).filter(|password| !password.is_empty());
+            if extension.eq_ignore_ascii_case("pdf")
+                && let Err(rejection) = pdf::check(&path, config, password.as_deref())
+            {
+                println!("❌ Refusing '{}': {}", file.filename, rejection.message());
+                let _ = std::fs::remove_file(&path);
+                remove_uploads(&files);
+                return Err(error(
+                    rejection_code(&rejection),
+                    format!("{}: {}", file.filename, rejection.message()),
+                    Some(rejection.code()),
+                ));
+            }
+            files.push(UploadedFile {
+                path,
+                filename: file.filename,
+                password,
+                page_order: None,
+                rotations: BTreeMap::new(),
+            });
+        }
+
+        let session_id = Uuid::new_v4().to_string();
+        self.jobs
+            .update(&session_id, |status| status.metadata = metadata);
+        #[cfg(feature = "s3")]
+        self.jobs.archive_uploads(&session_id, &files).await;
+        self.jobs
+            .start(session_id.clone(), files, options, priority);
+        Ok(Response::new(proto::UploadReply { session_id }))
+    }
+
+    type WatchSessionStream = UpdateStream;
+
+    async fn watch_session(
+        &self,
+        request: Request<proto::SessionRequest>,
+    ) -> Result<Response<UpdateStream>, tonic::Status> {
+        let session_id = request.into_inner().session_id;
+        let Some(events) = session_events(self.jobs.clone(), session_id.clone()) else {
+            return Err(error(
+                Code::NotFound,
+                format!("No session '{}'", session_id),
+                None,
+            ));
+        };
+        let updates = events.filter_map(|event| async move {
+            let update = match event {
+                SessionEvent::Status(status) => {
+                    proto::session_update::Update::Status(status_message(&status))
+                }
+                SessionEvent::Page(Event::PageFinished(page)) => {
+                    proto::session_update::Update::Page(page_message(page))
+                }
+                SessionEvent::Page(_) => return None,
+            };
+            Some(Ok(proto::SessionUpdate {
+                update: Some(update),
+            }))
+        });
+        Ok(Response::new(Box::pin(updates)))
+    }
+
+    async fn get_result(
+        &self,
+        request: Request<proto::SessionRequest>,
+    ) -> Result<Response<proto::ResultReply>, tonic::Status> {
+        let session_id = request.into_inner().session_id;
+        let tracker = self.jobs.tracker.read();
+        let Some(status) = tracker.get(&session_id) else {
+            return Err(error(
+                Code::NotFound,
+                format!("No session '{}'", session_id),
+                None,
+            ));
+        };
+        if !status.complete {
+            return Err(error(
+                Code::FailedPrecondition,
+                "Session is still processing".to_string(),
+                None,
+            ));
+        }
+        Ok(Response::new(proto::ResultReply {
+            state: status.state().to_string(),
+            files: status.results.iter().map(file_result).collect(),
+            session_id,
+        }))
+    }
+}
@@ -0,0 +1,96 @@
+use crate::Jobs;
+use actix_web::{HttpResponse, get, web};
+use sanskrit_ocr::config::Config;
+use sanskrit_ocr::{ocr, pdf};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Outcome of one readiness check.
+#[derive(Serialize)]
+struct Check {
+    ok: bool,
+    message: String,
+}
+
+impl Check {
+    fn of(result: Result<String, String>) -> Check {
+        match result {
+            Ok(message) => Check { ok: true, message },
+            Err(message) => Check { ok: false, message },
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Liveness {
+    status: &'static str,
+}
+
+#[derive(Serialize)]
+struct Readiness {
+    ready: bool,
+    checks: BTreeMap<&'static str, Check>,
+}
+
+/// Whether tesseract runs and has the configured model installed; `+`-joined
+/// models need every part.
+fn tesseract(config: &Config) -> Result<String, String> {
+    let installed = ocr::installed_languages()?;
+    let missing: Vec<&str> = config
+        .language
+        .split('+')
+        .filter(|model| !installed.iter().any(|language| language == model))
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!(
+            "tesseract has no '{}' model installed",
+            missing.join("', '")
+        ));
+    }
+    Ok(format!("'{}' model installed", config.language))
+}
+
+fn queue(jobs: &Jobs) -> Result<String, String> {
+    let queued = jobs.queue.lock().len();
+    let limit = jobs.config.ready_max_queued_jobs;
+    if limit > 0 && queued > limit {
+        return Err(format!(
+            "{} sessions queued, over the {} allowed",
+            queued, limit
+        ));
+    }
+    Ok(format!("{} sessions queued", queued))
+}
+
+/// Liveness: answers as long as the server handles requests.
+#[get("/healthz")]
+pub async fn healthz() -> HttpResponse {
+    HttpResponse::Ok().json(Liveness { status: "ok" })
+}
+
+/// Readiness: whether tesseract and pdfium are usable and the job queue has
+/// room, with HTTP 503 and the failing checks when not.
+#[get("/readyz")]
+pub async fn readyz(jobs: web::Data<Jobs>) -> actix_web::Result<HttpResponse> {
+    let config = jobs.config.clone();
+    // Probing tesseract runs it, so keep that off the server's threads
+    let tesseract = web::block(move || tesseract(&config))
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    let checks = BTreeMap::from([
+        ("tesseract", Check::of(tesseract)),
+        (
+            "pdfium",
+            Check::of(pdf::pdfium(&jobs.config).map(|_| "library loaded".to_string())),
+        ),
+        ("queue", Check::of(queue(&jobs))),
+    ]);
+    let ready = checks.values().all(|check| check.ok);
+    let readiness = Readiness { ready, checks };
+    Ok(if ready {
+        HttpResponse::Ok().json(readiness)
+    } else {
+        HttpResponse::ServiceUnavailable().json(readiness)
+    })
+}
@@ -0,0 +1,266 @@
+use crate::{Jobs, unix_time};
+use sanskrit_ocr::config::CleanupConfig;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+/// How the names of files and directories the server and the pipeline leave
+/// in temp directories start; anything else there belongs to other programs.
+const TEMP_PREFIXES: [&str; 11] = [
+    "ocr_",
+    "inspect_",
+    "benchmark_",
+    "searchable_",
+    "zip_extract_",
+    "djvu_convert_",
+    "pdf_convert_",
+    "spread_",
+    "crop_",
+    "scale_",
+    "rotate_",
+];
+
+/// What a sweep removed.
+#[derive(Default)]
+struct Swept {
+    splits: usize,
+    sessions: usize,
+    temp_files: usize,
+    bytes: u64,
+}
+
+/// Sweeps every `cleanup.interval_seconds` for as long as the server runs.
+pub async fn run(jobs: Arc<Jobs>) {
+    let cleanup = jobs.config.cleanup.clone();
+    loop {
+        tokio::time::sleep(Duration::from_secs(cleanup.interval_seconds)).await;
+        sweep(&jobs, &cleanup).await;
+    }
+}
+
+/// Removes the `/split` chunks, finished sessions and temporary files past
+/// their retention, and logs the space reclaimed.
+async fn sweep(jobs: &Arc<Jobs>, cleanup: &CleanupConfig) {
+    let mut swept = Swept::default();
+
+    if cleanup.split_retention_seconds > 0 {
+        let retention = Duration::from_secs(cleanup.split_retention_seconds);
+        let dir = jobs.config.splits_dir();
+        match tokio::task::spawn_blocking(move || remove_splits(&dir, retention)).await {
+            Ok((splits, bytes)) => {
+                swept.splits = splits;
+                swept.bytes += bytes;
+            }
+            Err(e) => println!("  ⚠️  Warning: Failed to clean up splits: {}", e),
+        }
+    }
+
+    if cleanup.session_retention_seconds > 0 {
+        for session_id in expired_sessions(jobs, cleanup.session_retention_seconds) {
+            match jobs.clone().forget(&session_id).await {
+                Ok(Some(bytes)) => {
+                    swept.sessions += 1;
+                    swept.bytes += bytes;
+                }
+                Ok(None) => {}
+                Err(e) => println!(
+                    "  ⚠️  Warning: Failed to remove expired session {}: {}",
+                    session_id, e
+                ),
+            }
+        }
+    }
+
+    if cleanup.temp_retention_seconds > 0 {
+        let retention = Duration::from_secs(cleanup.temp_retention_seconds);
+        let dirs = temp_dirs(jobs);
+        let in_use = uploads_in_use(jobs);
+        match tokio::task::spawn_blocking(move || remove_temp_files(&dirs, &in_use, retention))
+            .await
+        {
+            Ok((temp_files, bytes)) => {
+                swept.temp_files = temp_files;
+                swept.bytes += bytes;
+            }
+            Err(e) => println!("  ⚠️  Warning: Failed to clean up temp files: {}", e),
+        }
+    }
+
+    if swept.splits + swept.sessions + swept.temp_files > 0 {
+        println!(
+            "🧹 Removed {} expired splits, {} sessions and {} temp files, reclaiming {:.1} MB",
+            swept.splits,
+            swept.sessions,
+            swept.temp_files,
+            swept.bytes as f64 / (1024.0 * 1024.0)
+        );
+    }
+}
+
+/// Sessions neither processed nor held whose status last changed over
+/// `retention_seconds` ago.
+fn expired_sessions(jobs: &Jobs, retention_seconds: u64) -> Vec<String> {
+    let cutoff = unix_time().saturating_sub(retention_seconds);
+    let running = jobs.running.read();
+    let held = jobs.held.read();
+    jobs.tracker
+        .read()
+        .iter()
+        .filter(|(id, status)| {
+            status.updated_at < cutoff && !running.contains_key(*id) && !held.contains_key(*id)
+        })
+        .map(|(id, _)| id.clone())
+        .collect()
+}
+
+/// The `temp_dir` and those of the storage profiles, where uploads are
+/// written and processed.
+fn temp_dirs(jobs: &Jobs) -> Vec<PathBuf> {
+    let mut dirs = vec![jobs.config.temp_dir()];
+    dirs.extend(
+        jobs.config
+            .storage_profiles
+            .values()
+            .map(|profile| profile.path.clone()),
+    );
+    dirs.sort();
+    dirs.dedup();
+    dirs
+}
+
+/// Uploads a job still needs: queued, being processed, held or kept for a
+/// retry.
+fn uploads_in_use(jobs: &Jobs) -> HashSet<PathBuf> {
+    let mut in_use: HashSet<PathBuf> = jobs
+        .queue
+        .lock()
+        .iter()
+        .flat_map(|job| job.files.iter().map(|file| file.path.clone()))
+        .collect();
+    in_use.extend(jobs.processing.read().values().flatten().cloned());
+    in_use.extend(
+        jobs.held
+            .read()
+            .values()
+            .flat_map(|job| job.files.iter().map(|file| file.path.clone())),
+    );
+    in_use.extend(
+        jobs.retained
+            .read()
+            .values()
+            .flat_map(|retained| retained.files.iter().map(|(_, file)| file.path.clone())),
+    );
+    in_use
+}
+
+/// Removes the splits in `dir` last written to over `retention` ago, returning
+/// how many there were and the bytes they took.
+fn remove_splits(dir: &Path, retention: Duration) -> (usize, u64) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return (0, 0);
+    };
+    let mut removed = (0, 0);
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() && expired(&path, retention) {
+            let bytes = disk_usage(&path);
+            match std::fs::remove_dir_all(&path) {
+                Ok(()) => {
+                    removed.0 += 1;
+                    removed.1 += bytes;
+                }
+                Err(e) => println!(
+                    "  ⚠️  Warning: Failed to remove split '{}': {}",
+                    path.display(),
+                    e
+                ),
+            }
+        }
+    }
+    removed
+}
+
+/// Removes the server's files and directories in `dirs` last written to over
+/// `retention` ago and not `in_use`, returning how many there were and the
+/// bytes they took.
+fn remove_temp_files(
+    dirs: &[PathBuf],
+    in_use: &HashSet<PathBuf>,
+    retention: Duration,
+) -> (usize, u64) {
+    let mut removed = (0, 0);
+    for dir in dirs {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let ours = entry
+                .file_name()
+                .to_str()
+                .is_some_and(|name| TEMP_PREFIXES.iter().any(|prefix| name.starts_with(prefix)));
+            if !ours || in_use.contains(&path) || !expired(&path, retention) {
+                continue;
+            }
+            let bytes = disk_usage(&path);
+            let result = if path.is_dir() {
+                std::fs::remove_dir_all(&path)
+            } else {
+                std::fs::remove_file(&path)
+            };
+            match result {
+                Ok(()) => {
+                    removed.0 += 1;
+                    removed.1 += bytes;
+                }
+                Err(e) => println!(
+                    "  ⚠️  Warning: Failed to remove temp file '{}': {}",
+                    path.display(),
+                    e
+                ),
+            }
+        }
+    }
+    removed
+}
+
+/// Whether nothing in `path` was written to for `retention`; a directory is
+/// as recent as the newest file in it.
+fn expired(path: &Path, retention: Duration) -> bool {
+    last_modified(path)
+        .is_some_and(|modified| modified.elapsed().is_ok_and(|elapsed| elapsed >= retention))
+}
+
+fn last_modified(path: &Path) -> Option<SystemTime> {
+    let metadata = std::fs::symlink_metadata(path).ok()?;
+    let mut modified = metadata.modified().ok()?;
+    if metadata.is_dir()
+        && let Ok(entries) = std::fs::read_dir(path)
+    {
+        for entry in entries.flatten() {
+            if let Some(inner) = last_modified(&entry.path()) {
+                modified = modified.max(inner);
+            }
+        }
+    }
+    Some(modified)
+}
+
+/// Bytes the file at `path` takes, or all files under it for a directory.
+pub fn disk_usage(path: &Path) -> u64 {
+    let Ok(metadata) = std::fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    std::fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| disk_usage(&entry.path()))
+                .sum()
+        })
+        .unwrap_or(0)
+}
@@ -0,0 +1,42 @@
+use actix_web::dev::ServiceRequest;
+use actix_web::http::Uri;
+
+/// First path segments of the endpoints served before the API moved under
+/// [`crate::API_PREFIX`].
+const LEGACY_ROUTES: [&str; 12] = [
+    "upload",
+    "upload-url",
+    "prepare",
+    "status",
+    "events",
+    "ws",
+    "cancel",
+    "sessions",
+    "result",
+    "suggest",
+    "split",
+    "inspect",
+];
+
+/// Routes a request for one of the old unversioned paths to its `/api/v1`
+/// endpoint, so clients written before the move keep working. Returns the path
+/// it now goes to, for telling the client.
+pub fn rewrite(req: &mut ServiceRequest) -> Option<String> {
+    let path = req.path();
+    let first = path.trim_start_matches('/').split('/').next()?;
+    if !LEGACY_ROUTES.contains(&first) {
+        return None;
+    }
+    let successor = format!("{}{}", crate::API_PREFIX, path);
+    let path_and_query = match req.query_string() {
+        "" => successor.clone(),
+        query => format!("{}?{}", successor, query),
+    };
+
+    let mut parts = req.head().uri.clone().into_parts();
+    parts.path_and_query = Some(path_and_query.parse().ok()?);
+    let uri = Uri::from_parts(parts).ok()?;
+    req.match_info_mut().get_mut().update(&uri);
+    req.head_mut().uri = uri;
+    Some(successor)
+}
@@ -0,0 +1,58 @@
+//! Sanskrit OCR pipeline. The `server` feature (on by default) adds the HTTP
+//! binary; build with `default-features = false, features = ["minimal"]` to
+//! link just the pipeline into a desktop frontend.
+//!
+//! The entry points are an [`OcrEngine`], set up once from a [`Config`], a
+//! [`Pipeline`] started from it with the job's processing options, and the
+//! [`Document`]s it reads:
+//!
+//! ```no_run
+//! use sanskrit_ocr::{Config, Document, OcrEngine};
+//!
+//! let engine = OcrEngine::load(Config::default())?;
+//! let pipeline = engine.pipeline().option("pages", "1-20")?;
+//! let result = pipeline.run(&Document::open("scan.pdf")?);
+//! println!("{}", result.text);
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! Tesseract must be installed, and PDFs need the pdfium library.
+
+pub mod annotate;
+pub mod archive;
+pub mod benchmark;
+pub mod cancel;
+pub mod chandas;
+pub mod config;
+pub mod convert;
+pub mod dehyphenate;
+pub mod dictionary;
+pub mod diff;
+pub mod djvu;
+pub mod document;
+pub mod docx;
+pub mod engine;
+pub mod events;
+pub mod lines;
+pub mod marks;
+pub mod matter;
+pub mod morphology;
+pub mod normalize;
+pub mod ocr;
+pub mod ocr_worker;
+pub mod page_images;
+pub mod pdf;
+pub mod pipeline;
+pub mod preprocess;
+pub mod processes;
+pub mod replace;
+pub mod sandhi;
+pub mod script;
+pub mod stats;
+pub mod transliterate;
+pub mod verse;
+
+pub use config::Config;
+pub use document::Document;
+pub use engine::OcrEngine;
+pub use pipeline::{OcrResult, Pipeline, ProcessingOptions, Progress};
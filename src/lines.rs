@@ -0,0 +1,214 @@
+use crate::cancel::Cancel;
+use crate::ocr::{self, PageText};
+use image::GrayImage;
+use std::ops::Range;
+use std::path::Path;
+use uuid::Uuid;
+
+/// Bands shorter than this share of the typical line height are stray marks (a
+/// hanging `ु`/`ृ`, a repha or an anusvāra riding above the headline) rather than
+/// lines of their own.
+const MIN_LINE_SHARE: f32 = 0.45;
+
+/// Bands taller than this multiple of the typical line height are lines glued
+/// together by descending marks and are split at their faintest row.
+const MAX_LINE_SHARE: f32 = 1.7;
+
+/// Otsu's threshold for a grayscale image: the gray level that best separates ink
+/// from paper.
+fn otsu_threshold(image: &GrayImage) -> u8 {
+    let mut histogram = [0u64; 256];
+    for pixel in image.pixels() {
+        histogram[pixel.0[0] as usize] += 1;
+    }
+    let total: u64 = histogram.iter().sum();
+    let weighted_total: f64 = (0..256).map(|v| v as f64 * histogram[v] as f64).sum();
+
+    let mut best = (0.0, 127u8);
+    let mut background = 0u64;
+    let mut weighted_background = 0.0;
+    for (level, &count) in histogram.iter().enumerate() {
+        background += count;
+        if background == 0 {
+            continue;
+        }
+        let foreground = total - background;
+        if foreground == 0 {
+            break;
+        }
+        weighted_background += level as f64 * count as f64;
+        let mean_background = weighted_background / background as f64;
+        let mean_foreground = (weighted_total - weighted_background) / foreground as f64;
+        let variance =
+            background as f64 * foreground as f64 * (mean_background - mean_foreground).powi(2);
+        if variance > best.0 {
+            best = (variance, level as u8);
+        }
+    }
+    best.1
+}
+
+/// Dark pixels per row, the horizontal projection profile of the page.
+fn projection_profile(image: &GrayImage) -> Vec<u32> {
+    let threshold = otsu_threshold(image);
+    image
+        .rows()
+        .map(|row| row.filter(|pixel| pixel.0[0] <= threshold).count() as u32)
+        .collect()
+}
+
+/// Height of the band at `quantile` (0.0-1.0) when sorted by height.
+fn band_height(bands: &[Range<u32>], quantile: f32) -> u32 {
+    let mut heights: Vec<u32> = bands.iter().map(|band| band.len() as u32).collect();
+    heights.sort_unstable();
+    let index = ((heights.len().saturating_sub(1)) as f32 * quantile).round() as usize;
+    heights.get(index).copied().unwrap_or(0)
+}
+
+/// Splits a band at its faintest row away from the edges, which is where two lines
+/// joined by a hanging mātrā touch.
+fn split_band(band: Range<u32>, profile: &[u32], line_height: u32, out: &mut Vec<Range<u32>>) {
+    if (band.len() as f32) <= line_height as f32 * MAX_LINE_SHARE {
+        out.push(band);
+        return;
+    }
+    let margin = (line_height / 2).max(1);
+    let cut = (band.start + margin..band.end - margin)
+        .min_by_key(|&row| profile[row as usize])
+        .unwrap_or(band.start + line_height);
+    split_band(band.start..cut, profile, line_height, out);
+    split_band(cut..band.end, profile, line_height, out);
+}
+
+/// Finds text lines on a page from its projection profile, returned top to bottom
+/// as row ranges padded to keep the marks above and below each line.
+pub fn find_lines(image: &GrayImage) -> Vec<Range<u32>> {
+    let profile = projection_profile(image);
+    // Ignore specks and scanner noise: a text row has ink over some of its width
+    let min_ink = (image.width() / 200).max(1);
+
+    let mut bands = Vec::new();
+    let mut start = None;
+    for (row, &ink) in profile.iter().enumerate() {
+        match (start, ink >= min_ink) {
+            (None, true) => start = Some(row as u32),
+            (Some(top), false) => {
+                bands.push(top..row as u32);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(top) = start {
+        bands.push(top..profile.len() as u32);
+    }
+
+    // Stray marks are short bands, so take the height from the upper half
+    let line_height = band_height(&bands, 0.75);
+    if line_height == 0 {
+        return bands;
+    }
+
+    // Attach stray marks to the nearer neighbouring line
+    let min_height = (line_height as f32 * MIN_LINE_SHARE) as u32;
+    let mut merged: Vec<Range<u32>> = Vec::new();
+    let mut pending = None;
+    for (i, band) in bands.iter().enumerate() {
+        let start = pending.take().unwrap_or(band.start);
+        if band.len() as u32 >= min_height {
+            merged.push(start..band.end);
+            continue;
+        }
+        let nearer_previous = match (merged.last(), bands.get(i + 1)) {
+            (Some(previous), Some(next)) => band.start - previous.end <= next.start - band.end,
+            (previous, _) => previous.is_some(),
+        };
+        match merged.last_mut() {
+            Some(previous) if nearer_previous => previous.end = band.end,
+            _ if i + 1 < bands.len() => pending = Some(start),
+            _ => merged.push(start..band.end),
+        }
+    }
+
+    // Glued lines are tall bands, so take the height from the lower half
+    let line_height = band_height(&merged, 0.4);
+    let mut lines = Vec::new();
+    for band in merged {
+        split_band(band, &profile, line_height, &mut lines);
+    }
+
+    let padding = line_height / 4;
+    lines
+        .into_iter()
+        .map(|line| line.start.saturating_sub(padding)..(line.end + padding).min(image.height()))
+        .collect()
+}
+
+/// Recognizes a page line by line: each line found by [`find_lines`] is cropped
+/// and read on its own as a single text line (`--psm 7`), then the page text is
+/// reassembled in order. Pages where fewer than two lines are found, or whose
+/// image cannot be decoded, are read whole.
+pub fn recognize_lines(
+    image: &Path,
+    language: &str,
+    worker: Option<&[String]>,
+    work_dir: &Path,
+    cancel: &Cancel,
+) -> Result<PageText, String> {
+    let page = match image::open(image) {
+        Ok(page) => page.to_luma8(),
+        Err(e) => {
+            println!("  ⚠️  Warning: Line segmentation skipped: {}", e);
+            return ocr::run_tesseract(image, language, None, false, worker, work_dir, cancel);
+        }
+    };
+    let lines = find_lines(&page);
+    if lines.len() < 2 {
+        return ocr::run_tesseract(image, language, None, false, worker, work_dir, cancel);
+    }
+    println!("  📏 Segmented page into {} lines", lines.len());
+
+    let mut text = String::new();
+    let mut words = Vec::new();
+    let mut commands: Vec<String> = Vec::new();
+    for line in lines {
+        let crop = image::imageops::crop_imm(&page, 0, line.start, page.width(), line.len() as u32)
+            .to_image();
+        let line_path = work_dir.join(format!("ocr_line_{}.png", Uuid::new_v4()));
+        crop.save(&line_path)
+            .map_err(|e| format!("Failed to write line image: {}", e))?;
+        let result = ocr::run_tesseract(
+            &line_path,
+            language,
+            Some(7),
+            false,
+            worker,
+            work_dir,
+            cancel,
+        );
+        let _ = std::fs::remove_file(&line_path);
+        let result = result?;
+
+        let line_text = result.text.trim();
+        if !line_text.is_empty() {
+            text.push_str(line_text);
+            text.push('\n');
+        }
+        words.extend(result.words);
+        // Every line runs the same command; record it once
+        for command in result.commands {
+            if !commands.contains(&command) {
+                commands.push(command);
+            }
+        }
+    }
+
+    Ok(PageText {
+        text,
+        confidence: ocr::mean_confidence(&words),
+        model: language.to_string(),
+        words,
+        commands,
+        hocr: None,
+    })
+}
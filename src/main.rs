@@ -1,598 +1,4399 @@
 use actix_files as fs;
 use actix_multipart::Multipart;
-use actix_web::{App, HttpResponse, HttpServer, Result, get, post, web};
+use actix_web::dev::Service;
+use actix_web::http::header::{self, HeaderName, HeaderValue};
+use actix_web::middleware::{Compress, from_fn};
+use actix_web::{
+    App, HttpRequest, HttpResponse, HttpServer, Result, delete, get, post, put, route, web,
+};
+use clap::Parser;
 use futures_util::StreamExt;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::io::Write;
-use std::process::Command;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::sync::Arc;
+use std::time::Instant;
+use tokio::io::{AsyncWriteExt, BufWriter};
+use tokio::sync::{Notify, broadcast};
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 
+mod bundle;
+mod capacity;
+mod cli;
+mod compression;
+mod fetch;
+#[cfg(feature = "graphql")]
+mod graphql;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod health;
+mod janitor;
+mod legacy;
+mod negotiate;
+mod notify;
+#[cfg(feature = "s3")]
+mod object_storage;
+mod openapi;
+#[cfg(feature = "postgres")]
+mod postgres_store;
+mod rate_limit;
+#[cfg(feature = "search")]
+mod search;
+#[cfg(feature = "redis")]
+mod shared_status;
+mod splits;
+mod sqlite_store;
+mod static_files;
+mod store;
+
+use notify::{JobSummary, Notifier};
+use sanskrit_ocr::benchmark;
+use sanskrit_ocr::cancel::Cancel;
+use sanskrit_ocr::config::Config;
+use sanskrit_ocr::convert;
+use sanskrit_ocr::dictionary::{self, Dictionary};
+use sanskrit_ocr::diff;
+use sanskrit_ocr::docx;
+use sanskrit_ocr::events::Event;
+use sanskrit_ocr::morphology;
+use sanskrit_ocr::page_images;
+use sanskrit_ocr::pdf::{self, PageRanges};
+use sanskrit_ocr::pipeline::{
+    self, DiskQuota, OcrResult, PageResult, ProcessingOptions, Progress, TextOrigin,
+};
+use sanskrit_ocr::preprocess::Rotation;
+use sanskrit_ocr::processes;
+use sanskrit_ocr::stats::TextStats;
+use store::{JobState, Store, StoredJob};
+
 type ProgressTracker = Arc<RwLock<HashMap<String, ProgressStatus>>>;
 
-#[derive(Clone, Serialize, Deserialize)]
+/// Progress of a session as a whole. `stage` and `message` are those of the
+/// latest update, `current` and `total` count files, and each file's own progress
+/// is under `files`.
+#[derive(Clone, Default, Serialize, Deserialize, ToSchema)]
 struct ProgressStatus {
     stage: String,
     current: usize,
     total: usize,
     message: String,
+    /// Share of the session done (0-100), each file counting the same.
+    percent: f32,
+    /// Estimated seconds until the session is done, from the rolling page rate;
+    /// files not yet opened are taken to have as many pages as the opened ones
+    /// average. Unset until a page of a multi-page document is read, and once
+    /// complete.
+    eta_seconds: Option<f64>,
+    /// Pages read per minute over the latest pages of a multi-page document.
+    pages_per_minute: Option<f64>,
+    /// Place in the job queue, 1 for the next session to start; unset unless
+    /// queued.
+    queue_position: Option<usize>,
+    priority: Priority,
     complete: bool,
+    /// The results of the files done so far, without their text; see
+    /// [`get_result`]. The session store keeps them apart.
+    #[serde(serialize_with = "summarize_results", skip_deserializing)]
+    #[schema(value_type = Vec<ResultSummary>)]
     results: Vec<OcrResult>,
+    /// The files uploaded in the session, in upload order.
+    files: Vec<FileProgress>,
+    metadata: SessionMetadata,
+    /// When the session was uploaded and last updated, in seconds since the Unix
+    /// epoch.
+    created_at: u64,
+    updated_at: u64,
 }
 
-#[derive(Clone, Serialize, Deserialize)]
-struct OcrResult {
+/// A file's result as `/status` and the progress streams give it: everything but
+/// the text, which a 1000-page book makes megabytes of and which
+/// `/result/{session_id}` serves a range of pages at a time.
+#[derive(Serialize, ToSchema)]
+struct ResultSummary {
     filename: String,
-    text: String,
     success: bool,
     error: Option<String>,
+    /// Machine-readable reason for `error`, e.g. `QUOTA_EXCEEDED`.
+    error_code: Option<String>,
+    cancelled: bool,
     pages_processed: Option<usize>,
     total_pages: Option<usize>,
     estimated_time_seconds: Option<f64>,
+    /// Pages in the result, to fetch from `/result/{session_id}`.
+    page_count: usize,
+    searchable_pdf: Option<String>,
+}
+
+fn summarize_results<S: serde::Serializer>(
+    results: &[OcrResult],
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    serializer.collect_seq(results.iter().map(|result| ResultSummary {
+        filename: result.filename.clone(),
+        success: result.success,
+        error: result.error.clone(),
+        error_code: result.error_code.clone(),
+        cancelled: result.cancelled,
+        pages_processed: result.pages_processed,
+        total_pages: result.total_pages,
+        estimated_time_seconds: result.estimated_time_seconds,
+        page_count: result.pages.len(),
+        searchable_pdf: result.searchable_pdf.clone(),
+    }))
+}
+
+/// What the uploader said about a session, for keeping many of them organized;
+/// none of it affects processing.
+#[derive(Clone, Default, Serialize, Deserialize, ToSchema)]
+struct SessionMetadata {
+    title: Option<String>,
+    /// Library or archive the scanned volume belongs to.
+    source_library: Option<String>,
+    /// The volume's call number in that library.
+    shelfmark: Option<String>,
+    tags: Vec<String>,
+}
+
+/// Longest metadata value accepted, in characters.
+const MAX_METADATA_LENGTH: usize = 500;
+
+/// Most tags a session may carry.
+const MAX_TAGS: usize = 50;
+
+impl SessionMetadata {
+    /// Upload fields that carry metadata rather than processing options.
+    const FIELDS: [&str; 4] = ["title", "source_library", "shelfmark", "tags"];
+
+    /// Sets the metadata field `name`. Tags are comma-separated and add to those
+    /// already given; blank values leave a field unset.
+    fn set(&mut self, name: &str, value: &str) -> std::result::Result<(), String> {
+        let value = value.trim();
+        if value.chars().count() > MAX_METADATA_LENGTH {
+            return Err(format!(
+                "'{}' is longer than {} characters",
+                name, MAX_METADATA_LENGTH
+            ));
+        }
+        let text = Some(value.to_string()).filter(|value| !value.is_empty());
+        match name {
+            "title" => self.title = text,
+            "source_library" => self.source_library = text,
+            "shelfmark" => self.shelfmark = text,
+            "tags" => {
+                for tag in value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|tag| !tag.is_empty())
+                {
+                    if !self.tags.iter().any(|known| known == tag) {
+                        self.tags.push(tag.to_string());
+                    }
+                }
+                if self.tags.len() > MAX_TAGS {
+                    return Err(format!("At most {} tags are allowed", MAX_TAGS));
+                }
+            }
+            _ => return Err(format!("Unknown metadata field '{}'", name)),
+        }
+        Ok(())
+    }
+}
+
+/// Progress of one file of a session, in pages once its page count is known.
+#[derive(Clone, Default, Serialize, Deserialize, ToSchema)]
+struct FileProgress {
+    filename: String,
+    stage: String,
+    current: usize,
+    total: usize,
+    message: String,
+    complete: bool,
+}
+
+impl FileProgress {
+    fn queued(filename: &str) -> FileProgress {
+        FileProgress {
+            filename: filename.to_string(),
+            stage: QUEUED.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Share of the file done, from 0 to 1.
+    fn done(&self) -> f32 {
+        if self.complete {
+            1.0
+        } else if self.total > 0 {
+            self.current as f32 / self.total as f32
+        } else {
+            0.0
+        }
+    }
+}
+
+impl ProgressStatus {
+    /// Starts tracking `filenames`, all queued.
+    fn queue<'a>(
+        &mut self,
+        stage: &str,
+        message: String,
+        filenames: impl Iterator<Item = &'a str>,
+    ) {
+        self.stage = stage.to_string();
+        self.message = message;
+        self.files = filenames.map(FileProgress::queued).collect();
+        self.total = self.files.len();
+    }
+
+    /// Queues the files at `indices` again, for a retry of some of their pages.
+    fn requeue(&mut self, indices: &[usize]) {
+        for &index in indices {
+            if let Some(file) = self.files.get_mut(index) {
+                *file = FileProgress::queued(&file.filename);
+            }
+        }
+        self.current = self.files.iter().filter(|file| file.complete).count();
+        self.stage = QUEUED.to_string();
+        self.message = "Waiting to retry failed and empty pages".to_string();
+        self.complete = false;
+    }
+
+    /// Records a progress update of the `index`-th file.
+    fn report(&mut self, index: usize, progress: Progress) {
+        if let Some(file) = self.files.get_mut(index) {
+            file.stage = progress.stage.clone();
+            file.current = progress.current;
+            file.total = progress.total;
+            file.message = progress.message.clone();
+        }
+        if let Some(seconds_per_page) = progress.seconds_per_page
+            && seconds_per_page > 0.0
+        {
+            self.pages_per_minute = Some(60.0 / seconds_per_page);
+        }
+        self.stage = progress.stage;
+        self.message = progress.message;
+    }
+
+    /// Seconds the pages left to read should take at the current page rate.
+    fn eta(&self) -> Option<f64> {
+        if self.complete {
+            return None;
+        }
+        let seconds_per_page = 60.0 / self.pages_per_minute?;
+        let opened: Vec<usize> = self
+            .files
+            .iter()
+            .map(|file| file.total)
+            .filter(|&total| total > 0)
+            .collect();
+        if opened.is_empty() {
+            return None;
+        }
+        let mean_pages = opened.iter().sum::<usize>() as f64 / opened.len() as f64;
+        let pages_left: f64 = self
+            .files
+            .iter()
+            .filter(|file| !file.complete)
+            .map(|file| match file.total {
+                0 => mean_pages,
+                total => total.saturating_sub(file.current) as f64,
+            })
+            .sum();
+        Some(pages_left * seconds_per_page)
+    }
+
+    /// Marks the `index`-th file as done.
+    fn finish_file(&mut self, index: usize, result: &OcrResult) {
+        if let Some(file) = self.files.get_mut(index) {
+            file.complete = true;
+            file.stage = if result.cancelled {
+                CANCELLED
+            } else if result.success {
+                "Complete"
+            } else {
+                "Failed"
+            }
+            .to_string();
+            file.message = result
+                .error
+                .clone()
+                .unwrap_or_else(|| format!("{} pages read", result.pages.len()));
+        }
+        self.current = self.files.iter().filter(|file| file.complete).count();
+    }
+
+    /// Coarse state of the session, as `GET /sessions` filters on it.
+    fn state(&self) -> &'static str {
+        if !self.complete {
+            match self.stage.as_str() {
+                AWAITING_PREPARATION => "held",
+                QUEUED => "queued",
+                _ => "running",
+            }
+        } else if self.stage == CANCELLED {
+            "cancelled"
+        } else if self.results.iter().any(|result| !result.success) {
+            "failed"
+        } else {
+            "complete"
+        }
+    }
+}
+
+/// How soon a queued session starts: higher priorities go ahead of lower ones,
+/// and sessions of the same priority start in the order they came.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema,
+)]
+#[serde(rename_all = "lowercase")]
+enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
 }
 
-#[derive(Serialize)]
+impl Priority {
+    fn parse(value: &str) -> std::result::Result<Priority, String> {
+        match value.trim().to_lowercase().as_str() {
+            "low" => Ok(Priority::Low),
+            "" | "normal" => Ok(Priority::Normal),
+            "high" => Ok(Priority::High),
+            _ => Err(format!(
+                "Unknown priority '{}'. Use low, normal or high.",
+                value
+            )),
+        }
+    }
+
+    #[cfg(feature = "graphql")]
+    fn name(self) -> &'static str {
+        match self {
+            Priority::Low => "low",
+            Priority::Normal => "normal",
+            Priority::High => "high",
+        }
+    }
+}
+
+const AWAITING_PREPARATION: &str = "Awaiting Preparation";
+const QUEUED: &str = "Queued";
+const CANCELLED: &str = "Cancelled";
+
+fn unix_time() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[derive(Serialize, ToSchema)]
+struct ErrorResponse {
+    error: String,
+    /// Machine-readable reason, e.g. `INPUT_TOO_LARGE` or `SUSPICIOUS_PDF`.
+    code: Option<String>,
+}
+
+fn rejection_status(rejection: &pdf::PdfRejection) -> actix_web::HttpResponseBuilder {
+    match rejection {
+        pdf::PdfRejection::TooLarge(_) => HttpResponse::PayloadTooLarge(),
+        pdf::PdfRejection::Suspicious(_) => HttpResponse::UnprocessableEntity(),
+        pdf::PdfRejection::PasswordRequired(_) | pdf::PdfRejection::WrongPassword(_) => {
+            HttpResponse::Forbidden()
+        }
+    }
+}
+
+/// [`pdf::check`] on a blocking thread, as it reads through the whole file.
+async fn check_pdf(
+    path: &std::path::Path,
+    config: &web::Data<Config>,
+    password: Option<&str>,
+) -> Result<Result<Option<pdf::PdfInfo>, pdf::PdfRejection>> {
+    let path = path.to_path_buf();
+    let config = config.clone();
+    let password = password.map(str::to_string);
+    Ok(web::block(move || pdf::check(&path, &config, password.as_deref())).await?)
+}
+
+/// A multipart request, or a file in it, over the configured upload limits or
+/// the session's disk quota.
+struct TooLarge {
+    error: String,
+    code: &'static str,
+}
+
+impl TooLarge {
+    fn new(error: String) -> TooLarge {
+        TooLarge {
+            error,
+            code: "INPUT_TOO_LARGE",
+        }
+    }
+
+    fn response(&self) -> HttpResponse {
+        HttpResponse::PayloadTooLarge().json(ErrorResponse {
+            error: self.error.clone(),
+            code: Some(self.code.to_string()),
+        })
+    }
+}
+
+/// Bytes of a multipart request read so far, checked against
+/// `max_upload_file_bytes` and `max_upload_bytes` as they arrive, so an
+/// oversized upload is cut off before it fills the disk. Requests starting a
+/// session are also held to `max_session_bytes`.
+struct UploadLimits {
+    file: u64,
+    request: u64,
+    /// Bytes the session may take; 0 for no limit.
+    session: u64,
+    read: u64,
+}
+
+impl UploadLimits {
+    /// Limits for `req`, refused outright when it announces a larger body.
+    fn new(req: &HttpRequest, config: &Config) -> std::result::Result<UploadLimits, TooLarge> {
+        let limits = UploadLimits {
+            file: config.max_upload_file_bytes,
+            request: config.max_upload_bytes,
+            session: 0,
+            read: 0,
+        };
+        let length = req
+            .headers()
+            .get(actix_web::http::header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok());
+        if length.is_some_and(|length| length > limits.request) {
+            return Err(limits.request_too_large());
+        }
+        Ok(limits)
+    }
+
+    /// Limits for `req` starting a session, whose uploads count against its
+    /// disk quota.
+    fn for_session(
+        req: &HttpRequest,
+        config: &Config,
+    ) -> std::result::Result<UploadLimits, TooLarge> {
+        let mut limits = UploadLimits::new(req, config)?;
+        limits.session = config.max_session_bytes;
+        Ok(limits)
+    }
+
+    fn request_too_large(&self) -> TooLarge {
+        TooLarge::new(format!(
+            "Upload larger than the {} bytes allowed per request",
+            self.request
+        ))
+    }
+
+    fn count(&mut self, bytes: usize) -> std::result::Result<(), TooLarge> {
+        self.read += bytes as u64;
+        if self.read > self.request {
+            return Err(self.request_too_large());
+        }
+        if self.session > 0 && self.read > self.session {
+            return Err(TooLarge {
+                error: format!(
+                    "Upload larger than the {} bytes of disk space a session is allowed",
+                    self.session
+                ),
+                code: pipeline::QUOTA_EXCEEDED,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Reads a plain form field whole.
+async fn read_value(
+    field: &mut actix_multipart::Field,
+    limits: &mut UploadLimits,
+) -> Result<std::result::Result<String, TooLarge>> {
+    let mut value = Vec::new();
+    while let Some(chunk) = field.next().await {
+        let chunk = chunk?;
+        if let Err(too_large) = limits.count(chunk.len()) {
+            return Ok(Err(too_large));
+        }
+        value.extend_from_slice(&chunk);
+    }
+    Ok(Ok(String::from_utf8_lossy(&value).into_owned()))
+}
+
+/// Streams the file `filename` of a multipart request to `path`, which is
+/// removed again when the file turns out to be over the limits.
+async fn save_file(
+    field: &mut actix_multipart::Field,
+    path: &std::path::Path,
+    filename: &str,
+    limits: &mut UploadLimits,
+) -> Result<std::result::Result<(), TooLarge>> {
+    // Written through tokio so a slow disk stalls this upload, not the other
+    // requests on the worker thread
+    let mut file = BufWriter::new(tokio::fs::File::create(path).await?);
+    let mut written = 0u64;
+    while let Some(chunk) = field.next().await {
+        let data = chunk?;
+        written += data.len() as u64;
+        let within = if written > limits.file {
+            Err(TooLarge::new(format!(
+                "{}: larger than the {} bytes allowed per file",
+                filename, limits.file
+            )))
+        } else {
+            limits.count(data.len())
+        };
+        if let Err(too_large) = within {
+            drop(file);
+            let _ = tokio::fs::remove_file(path).await;
+            return Ok(Err(too_large));
+        }
+        file.write_all(&data).await?;
+    }
+    file.flush().await?;
+    Ok(Ok(()))
+}
+
+#[derive(Serialize, ToSchema)]
 struct UploadResponse {
     session_id: String,
     results: Vec<OcrResult>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 struct ChunkInfo {
     filename: String,
     page_range: String,
     file_size: u64,
+    /// SHA-256 of the chunk, in hex.
+    sha256: String,
     download_path: String,
 }
 
-#[derive(Serialize)]
-struct SplitResponse {
-    success: bool,
-    original_filename: String,
-    total_pages: usize,
-    chunks: Vec<ChunkInfo>,
-    error: Option<String>,
+#[derive(Serialize, ToSchema)]
+struct SplitResponse {
+    success: bool,
+    /// Id the chunks are stored under, for `DELETE /sessions/{session_id}`.
+    session_id: Option<String>,
+    original_filename: String,
+    total_pages: usize,
+    chunks: Vec<ChunkInfo>,
+    /// The same PDF was split the same way before, and this is that split,
+    /// with the id and download paths it was given then.
+    reused: bool,
+    error: Option<String>,
+    error_code: Option<String>,
+}
+
+impl SplitResponse {
+    fn failed(original_filename: String, error: &str) -> SplitResponse {
+        SplitResponse {
+            success: false,
+            session_id: None,
+            original_filename,
+            total_pages: 0,
+            chunks: Vec::new(),
+            reused: false,
+            error: Some(error.to_string()),
+            error_code: None,
+        }
+    }
+}
+
+#[utoipa::path(
+    tag = "sessions",
+    params(("session_id" = String, Path, description = "Session id returned by /upload")),
+    responses((status = 200, description = "The session's status, null for unknown sessions", body = Option<ProgressStatus>))
+)]
+#[get("/status/{session_id}", wrap = "Compress::default()")]
+async fn get_status(path: web::Path<String>, jobs: web::Data<Jobs>) -> Result<HttpResponse> {
+    let session_id = path.into_inner();
+    let status = jobs.tracker.read().get(&session_id).cloned();
+
+    // A session another instance runs, when statuses are shared
+    #[cfg(feature = "redis")]
+    if status.is_none()
+        && let Some(shared) = jobs.shared.clone()
+    {
+        match web::block(move || shared.fetch(&session_id)).await? {
+            Ok(Some(body)) => {
+                return Ok(HttpResponse::Ok()
+                    .content_type("application/json")
+                    .body(body));
+            }
+            Ok(None) => {}
+            Err(e) => println!("  ⚠️  Warning: Failed to look up shared status: {}", e),
+        }
+    }
+    Ok(HttpResponse::Ok().json(status))
+}
+
+/// Status changes an `/events` stream may fall behind by before it skips to the
+/// latest status.
+const EVENT_BACKLOG: usize = 256;
+
+/// What `/events` and `/ws` streams send about a session.
+enum SessionEvent {
+    Status(ProgressStatus),
+    /// A page's text as soon as it is read.
+    Page(Event),
+}
+
+impl SessionEvent {
+    /// `progress` while the session runs, `page` for pages read and `complete`,
+    /// with the results, once it is done.
+    fn name(&self) -> &'static str {
+        match self {
+            SessionEvent::Status(status) if status.complete => "complete",
+            SessionEvent::Status(_) => "progress",
+            SessionEvent::Page(_) => "page",
+        }
+    }
+
+    fn data(&self) -> String {
+        match self {
+            SessionEvent::Status(status) => serde_json::to_string(status),
+            SessionEvent::Page(page) => serde_json::to_string(page),
+        }
+        .unwrap_or_default()
+    }
+}
+
+/// A session's events as they happen: its current status at once, then every
+/// change and every page read, until the session completes or is deleted. `None`
+/// for unknown sessions.
+fn session_events(
+    jobs: Arc<Jobs>,
+    session_id: String,
+) -> Option<impl futures_util::Stream<Item = SessionEvent>> {
+    // Subscribe before reading the status so no change falls in between
+    let updates = jobs.updates.subscribe();
+    let status = jobs.tracker.read().get(&session_id).cloned()?;
+
+    let first = SessionEvent::Status(status);
+    Some(futures_util::stream::unfold(
+        Some((updates, Some(first))),
+        move |state| {
+            let jobs = jobs.clone();
+            let session_id = session_id.clone();
+            async move {
+                let (mut updates, mut pending) = state?;
+                loop {
+                    if let Some(event) = pending.take() {
+                        let done =
+                            matches!(&event, SessionEvent::Status(status) if status.complete);
+                        return Some((event, (!done).then_some((updates, None))));
+                    }
+                    match updates.recv().await {
+                        Ok((updated, _)) if updated != session_id => {}
+                        Ok((_, Some(page))) => pending = Some(SessionEvent::Page(page)),
+                        // Missed changes are caught up on by reading the latest status
+                        Ok((_, None)) | Err(broadcast::error::RecvError::Lagged(_)) => {
+                            let status = jobs.tracker.read().get(&session_id).cloned()?;
+                            pending = Some(SessionEvent::Status(status));
+                        }
+                        Err(broadcast::error::RecvError::Closed) => return None,
+                    }
+                }
+            }
+        },
+    ))
+}
+
+fn unknown_session(session_id: &str) -> HttpResponse {
+    HttpResponse::NotFound().json(ErrorResponse {
+        error: format!("No session '{}'", session_id),
+        code: None,
+    })
+}
+
+/// Streams a session's [`session_events`] as server-sent events.
+#[utoipa::path(
+    tag = "sessions",
+    params(("session_id" = String, Path, description = "Session id returned by /upload")),
+    responses(
+        (status = 200, description = "Server-sent `progress`, `page` and `complete` events", content_type = "text/event-stream"),
+        (status = 404, body = ErrorResponse)
+    )
+)]
+#[get("/events/{session_id}")]
+async fn events(path: web::Path<String>, jobs: web::Data<Jobs>) -> Result<HttpResponse> {
+    let session_id = path.into_inner();
+    let Some(stream) = session_events(jobs.into_inner(), session_id.clone()) else {
+        return Ok(unknown_session(&session_id));
+    };
+    let stream = stream.map(|event| {
+        Ok::<_, actix_web::Error>(web::Bytes::from(format!(
+            "event: {}\ndata: {}\n\n",
+            event.name(),
+            event.data()
+        )))
+    });
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header((actix_web::http::header::CACHE_CONTROL, "no-cache"))
+        .streaming(stream))
+}
+
+/// Sends a session's [`session_events`] over a WebSocket, one text message of
+/// `{"event": ..., "data": ...}` each, and closes it after the last.
+#[utoipa::path(
+    tag = "sessions",
+    params(("session_id" = String, Path, description = "Session id returned by /upload")),
+    responses(
+        (status = 101, description = "WebSocket of `{\"event\", \"data\"}` messages, as on /events"),
+        (status = 404, body = ErrorResponse)
+    )
+)]
+#[get("/ws/{session_id}")]
+async fn websocket(
+    req: HttpRequest,
+    body: web::Payload,
+    path: web::Path<String>,
+    jobs: web::Data<Jobs>,
+) -> Result<HttpResponse> {
+    let session_id = path.into_inner();
+    let Some(stream) = session_events(jobs.into_inner(), session_id.clone()) else {
+        return Ok(unknown_session(&session_id));
+    };
+    let (response, mut socket, mut messages) = actix_ws::handle(&req, body)?;
+
+    actix_web::rt::spawn(async move {
+        let mut stream = std::pin::pin!(stream);
+        loop {
+            tokio::select! {
+                event = stream.next() => {
+                    let Some(event) = event else { break };
+                    let message = format!(
+                        "{{\"event\":\"{}\",\"data\":{}}}",
+                        event.name(),
+                        event.data()
+                    );
+                    if socket.text(message).await.is_err() {
+                        return;
+                    }
+                }
+                message = messages.next() => match message {
+                    Some(Ok(actix_ws::Message::Ping(bytes))) => {
+                        if socket.pong(&bytes).await.is_err() {
+                            return;
+                        }
+                    }
+                    Some(Ok(actix_ws::Message::Close(_))) | Some(Err(_)) | None => return,
+                    Some(Ok(_)) => {}
+                },
+            }
+        }
+        let _ = socket.close(None).await;
+    });
+
+    Ok(response)
+}
+
+/// States `GET /sessions` can filter on; see [`ProgressStatus::state`].
+const SESSION_STATES: [&str; 6] = [
+    "queued",
+    "running",
+    "held",
+    "complete",
+    "failed",
+    "cancelled",
+];
+
+/// Sessions `GET /sessions` lists per request unless asked for fewer.
+const MAX_SESSIONS_LISTED: usize = 100;
+
+#[derive(Deserialize, IntoParams)]
+struct SessionsQuery {
+    state: Option<String>,
+    /// Only sessions carrying this tag.
+    tag: Option<String>,
+    /// Sessions to skip, newest first.
+    #[serde(default)]
+    offset: usize,
+    limit: Option<usize>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct SessionSummary {
+    session_id: String,
+    state: &'static str,
+    stage: String,
+    current: usize,
+    total: usize,
+    message: String,
+    percent: f32,
+    files: Vec<String>,
+    metadata: SessionMetadata,
+    created_at: u64,
+    updated_at: u64,
+}
+
+#[derive(Serialize, ToSchema)]
+struct SessionsResponse {
+    /// Sessions matching the filter, across all pages.
+    total: usize,
+    offset: usize,
+    limit: usize,
+    sessions: Vec<SessionSummary>,
+}
+
+/// Lists the sessions the server knows of, newest first, without their results.
+#[utoipa::path(
+    tag = "sessions",
+    params(SessionsQuery),
+    responses(
+        (status = 200, body = SessionsResponse),
+        (status = 400, description = "Unknown state", body = ErrorResponse)
+    )
+)]
+#[get("/sessions", wrap = "Compress::default()")]
+async fn list_sessions(
+    query: web::Query<SessionsQuery>,
+    tracker: web::Data<ProgressTracker>,
+) -> Result<HttpResponse> {
+    if let Some(state) = &query.state
+        && !SESSION_STATES.contains(&state.as_str())
+    {
+        return Ok(HttpResponse::BadRequest().json(ErrorResponse {
+            error: format!(
+                "Unknown state '{}'; expected one of {}",
+                state,
+                SESSION_STATES.join(", ")
+            ),
+            code: None,
+        }));
+    }
+    let limit = query
+        .limit
+        .unwrap_or(MAX_SESSIONS_LISTED)
+        .min(MAX_SESSIONS_LISTED);
+
+    let mut sessions: Vec<SessionSummary> = tracker
+        .read()
+        .iter()
+        .filter(|(_, status)| {
+            query
+                .state
+                .as_ref()
+                .is_none_or(|state| status.state() == state)
+                && query
+                    .tag
+                    .as_ref()
+                    .is_none_or(|tag| status.metadata.tags.contains(tag))
+        })
+        .map(|(session_id, status)| SessionSummary {
+            session_id: session_id.clone(),
+            state: status.state(),
+            stage: status.stage.clone(),
+            current: status.current,
+            total: status.total,
+            message: status.message.clone(),
+            percent: status.percent,
+            files: status
+                .files
+                .iter()
+                .map(|file| file.filename.clone())
+                .collect(),
+            metadata: status.metadata.clone(),
+            created_at: status.created_at,
+            updated_at: status.updated_at,
+        })
+        .collect();
+    sessions.sort_by(|a, b| {
+        b.created_at
+            .cmp(&a.created_at)
+            .then_with(|| a.session_id.cmp(&b.session_id))
+    });
+    let total = sessions.len();
+    let sessions = sessions
+        .into_iter()
+        .skip(query.offset)
+        .take(limit)
+        .collect();
+
+    Ok(HttpResponse::Ok().json(SessionsResponse {
+        total,
+        offset: query.offset,
+        limit,
+        sessions,
+    }))
+}
+
+#[derive(Deserialize, IntoParams)]
+struct StatsQuery {
+    /// Number of word frequencies to list, 100 by default.
+    top: Option<usize>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct StatsResponse {
+    session_id: String,
+    files: Vec<String>,
+    #[serde(flatten)]
+    stats: TextStats,
+}
+
+#[utoipa::path(
+    tag = "results",
+    params(("session_id" = String, Path, description = "Session id returned by /upload"), StatsQuery),
+    responses(
+        (status = 200, body = StatsResponse),
+        (status = 404, body = ErrorResponse),
+        (status = 409, description = "Session still processing", body = ErrorResponse)
+    )
+)]
+#[get("/result/{session_id}/stats", wrap = "Compress::default()")]
+async fn get_stats(
+    path: web::Path<String>,
+    query: web::Query<StatsQuery>,
+    tracker: web::Data<ProgressTracker>,
+    dictionary: web::Data<Dictionary>,
+) -> Result<HttpResponse> {
+    let session_id = path.into_inner();
+    let Some(status) = tracker.read().get(&session_id).cloned() else {
+        return Ok(HttpResponse::NotFound().json(ErrorResponse {
+            error: format!("Unknown session '{}'", session_id),
+            code: None,
+        }));
+    };
+    if !status.complete {
+        return Ok(HttpResponse::Conflict().json(ErrorResponse {
+            error: "Session is still processing".to_string(),
+            code: None,
+        }));
+    }
+
+    let results: Vec<&OcrResult> = status.results.iter().filter(|r| r.success).collect();
+    let stats = TextStats::compute(
+        // The pages' own text, without the page markers of the file's text
+        results
+            .iter()
+            .flat_map(|result| result.pages.iter().map(|page| page.text.as_str())),
+        &dictionary,
+        query.top.unwrap_or(100),
+    );
+    Ok(HttpResponse::Ok().json(StatsResponse {
+        session_id,
+        files: results
+            .iter()
+            .map(|result| result.filename.clone())
+            .collect(),
+        stats,
+    }))
+}
+
+/// Pages `GET /result/{session_id}` returns per request unless asked for fewer.
+const MAX_RESULT_PAGES: usize = 100;
+
+#[derive(Deserialize, IntoParams)]
+struct ResultQuery {
+    /// Position of the file in the session's results, 0 (the first) by default.
+    #[serde(default)]
+    file: usize,
+    /// Pages to skip.
+    #[serde(default)]
+    offset: usize,
+    limit: Option<usize>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct ResultResponse {
+    session_id: String,
+    filename: String,
+    success: bool,
+    error: Option<String>,
+    /// Pages in the file's result, across all ranges.
+    total: usize,
+    offset: usize,
+    limit: usize,
+    /// Text of the pages returned, with the page markers of the full text.
+    text: String,
+    pages: Vec<PageResult>,
+}
+
+/// A range of pages of one file of a finished session, with their text joined;
+/// page through `offset` for results too large to fetch at once.
+#[utoipa::path(
+    tag = "results",
+    params(("session_id" = String, Path, description = "Session id returned by /upload"), ResultQuery),
+    responses(
+        (status = 200, description = "By the Accept header: JSON, plain text, or HTML with page anchors", content(
+            (ResultResponse = "application/json"),
+            (String = "text/plain"),
+            (String = "text/html")
+        )),
+        (status = 404, description = "Unknown session or file", body = ErrorResponse),
+        (status = 406, description = "None of the formats accepted", body = ErrorResponse),
+        (status = 409, description = "Session still processing", body = ErrorResponse)
+    )
+)]
+#[get("/result/{session_id}", wrap = "Compress::default()")]
+async fn get_result(
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<ResultQuery>,
+    tracker: web::Data<ProgressTracker>,
+) -> Result<HttpResponse> {
+    let format = match negotiate::format(&req) {
+        Ok(format) => format,
+        Err(response) => return Ok(response),
+    };
+    let session_id = path.into_inner();
+    let tracker = tracker.read();
+    let Some(status) = tracker.get(&session_id) else {
+        return Ok(HttpResponse::NotFound().json(ErrorResponse {
+            error: format!("Unknown session '{}'", session_id),
+            code: None,
+        }));
+    };
+    if !status.complete {
+        return Ok(HttpResponse::Conflict().json(ErrorResponse {
+            error: "Session is still processing".to_string(),
+            code: None,
+        }));
+    }
+    let Some(result) = status.results.get(query.file) else {
+        return Ok(HttpResponse::NotFound().json(ErrorResponse {
+            error: format!("Session '{}' has no file {}", session_id, query.file),
+            code: None,
+        }));
+    };
+
+    let limit = query
+        .limit
+        .unwrap_or(MAX_RESULT_PAGES)
+        .min(MAX_RESULT_PAGES);
+    let pages: Vec<PageResult> = result
+        .pages
+        .iter()
+        .skip(query.offset)
+        .take(limit)
+        .cloned()
+        .collect();
+    let response = ResultResponse {
+        filename: result.filename.clone(),
+        success: result.success,
+        error: result.error.clone(),
+        total: result.pages.len(),
+        offset: query.offset,
+        limit,
+        text: range_text(result, &pages),
+        pages,
+        session_id,
+    };
+    Ok(negotiate::respond(
+        format,
+        &response,
+        || response.text.clone(),
+        &response.filename,
+        || {
+            vec![negotiate::Section {
+                title: &response.filename,
+                pages: response
+                    .pages
+                    .iter()
+                    .map(|page| (page.page_number, page.text.as_str()))
+                    .collect(),
+            }]
+        },
+    ))
+}
+
+/// The text of `pages` of `result`, joined as in its full text.
+fn range_text(result: &OcrResult, pages: &[PageResult]) -> String {
+    // A single image's text goes without a page marker
+    if let [page] = pages
+        && page.text == result.text
+    {
+        return page.text.clone();
+    }
+    pipeline::join_pages(
+        pages
+            .iter()
+            .map(|page| (page.page_number, page.text.as_str())),
+    )
+}
+
+#[derive(Deserialize, IntoParams)]
+struct PageQuery {
+    /// Position of the file in the session's results, 0 (the first) by default.
+    #[serde(default)]
+    file: usize,
+}
+
+#[derive(Serialize, ToSchema)]
+struct PageResponse {
+    session_id: String,
+    filename: String,
+    /// `read`, `empty` (nothing recognized), `blank` (skipped as blank) or
+    /// `failed` (see `error`).
+    status: &'static str,
+    #[serde(flatten)]
+    page: PageResult,
+}
+
+/// One page of a finished session's result, numbered as in the file's `pages`.
+#[utoipa::path(
+    tag = "results",
+    params(
+        ("session_id" = String, Path, description = "Session id returned by /upload"),
+        ("page_number" = usize, Path, description = "Page number in the result, from 1"),
+        PageQuery
+    ),
+    responses(
+        (status = 200, description = "By the Accept header: JSON, plain text, or HTML with page anchors", content(
+            (PageResponse = "application/json"),
+            (String = "text/plain"),
+            (String = "text/html")
+        )),
+        (status = 404, description = "Unknown session, file or page", body = ErrorResponse),
+        (status = 406, description = "None of the formats accepted", body = ErrorResponse),
+        (status = 409, description = "Session still processing", body = ErrorResponse)
+    )
+)]
+#[get(
+    "/result/{session_id}/pages/{page_number}",
+    wrap = "Compress::default()"
+)]
+async fn get_page(
+    req: HttpRequest,
+    path: web::Path<(String, usize)>,
+    query: web::Query<PageQuery>,
+    tracker: web::Data<ProgressTracker>,
+) -> Result<HttpResponse> {
+    let format = match negotiate::format(&req) {
+        Ok(format) => format,
+        Err(response) => return Ok(response),
+    };
+    let (session_id, page_number) = path.into_inner();
+    let tracker = tracker.read();
+    let (file, page) = match locate_page(&tracker, &session_id, query.file, page_number) {
+        Ok(found) => found,
+        Err(response) => return Ok(response),
+    };
+    let result = &tracker[&session_id].results[file];
+    let response = page_response(session_id.clone(), result, &result.pages[page]);
+    Ok(negotiate::respond(
+        format,
+        &response,
+        || response.page.text.clone(),
+        &response.filename,
+        || {
+            vec![negotiate::Section {
+                title: &response.filename,
+                pages: vec![(response.page.page_number, response.page.text.as_str())],
+            }]
+        },
+    ))
+}
+
+/// The image page `n` of a finished session's file was read from, as kept with
+/// `keep_page_images`: rotated, cut from its spread and cropped as it was read.
+#[utoipa::path(
+    tag = "results",
+    params(
+        ("session_id" = String, Path, description = "Session id returned by /upload"),
+        ("page_number" = usize, Path, description = "Page number in the result, from 1"),
+        PageQuery
+    ),
+    responses(
+        (status = 200, description = "The page image", content(("image/png"), ("image/jpeg"), ("image/webp"))),
+        (status = 307, description = "Redirect to a presigned link to it in object storage"),
+        (status = 404, description = "Unknown session, file or page, or no image kept of it", body = ErrorResponse),
+        (status = 409, description = "Session still processing", body = ErrorResponse)
+    )
+)]
+#[get("/result/{session_id}/pages/{page_number}/image")]
+async fn get_page_image(
+    req: HttpRequest,
+    path: web::Path<(String, usize)>,
+    query: web::Query<PageQuery>,
+    jobs: web::Data<Jobs>,
+) -> Result<HttpResponse> {
+    page_image(&req, path.into_inner(), query.file, &jobs, false).await
+}
+
+/// A thumbnail of the image of page `n`, at most 256 pixels on its longer side.
+#[utoipa::path(
+    tag = "results",
+    params(
+        ("session_id" = String, Path, description = "Session id returned by /upload"),
+        ("page_number" = usize, Path, description = "Page number in the result, from 1"),
+        PageQuery
+    ),
+    responses(
+        (status = 200, description = "The thumbnail", content_type = "image/png"),
+        (status = 307, description = "Redirect to a presigned link to it in object storage"),
+        (status = 404, description = "Unknown session, file or page, or no image kept of it", body = ErrorResponse),
+        (status = 409, description = "Session still processing", body = ErrorResponse)
+    )
+)]
+#[get("/result/{session_id}/pages/{page_number}/thumbnail")]
+async fn get_page_thumbnail(
+    req: HttpRequest,
+    path: web::Path<(String, usize)>,
+    query: web::Query<PageQuery>,
+    jobs: web::Data<Jobs>,
+) -> Result<HttpResponse> {
+    page_image(&req, path.into_inner(), query.file, &jobs, true).await
+}
+
+/// A page's image, or its thumbnail, from the disk or else object storage.
+async fn page_image(
+    req: &HttpRequest,
+    (session_id, page_number): (String, usize),
+    file: usize,
+    jobs: &Jobs,
+    thumbnail: bool,
+) -> Result<HttpResponse> {
+    let (file, source_page, side) = {
+        let tracker = jobs.tracker.read();
+        let (file, page) = match locate_page(&tracker, &session_id, file, page_number) {
+            Ok(found) => found,
+            Err(response) => return Ok(response),
+        };
+        let page = &tracker[&session_id].results[file].pages[page];
+        (file, page.source_page, page.spread_side)
+    };
+    let kept = page_images::find(
+        &page_images_dir(&jobs.config, &session_id, file),
+        source_page,
+        side,
+        thumbnail,
+    );
+    #[cfg(feature = "s3")]
+    if kept.is_none()
+        && let Some(storage) = &jobs.storage
+    {
+        let names: Vec<String> = page_images::names(source_page, side, thumbnail)
+            .into_iter()
+            .map(|name| page_object_name(file, &name))
+            .collect();
+        if let Some(name) = storage.first_of(&session_id, &names).await {
+            return Ok(redirect_to_object(storage, &session_id, &name).await);
+        }
+    }
+    let Some(kept) = kept else {
+        // Pages taken from a text layer have no image
+        return Ok(HttpResponse::NotFound().json(ErrorResponse {
+            error: format!("No image was kept of page {}", page_number),
+            code: None,
+        }));
+    };
+    Ok(fs::NamedFile::open(kept)?.into_response(req))
+}
+
+/// Position of the file and of the page numbered `page_number` within it in a
+/// finished session's results, or the response for why there is none.
+fn locate_page(
+    tracker: &HashMap<String, ProgressStatus>,
+    session_id: &str,
+    file: usize,
+    page_number: usize,
+) -> std::result::Result<(usize, usize), HttpResponse> {
+    let not_found =
+        |error: String| HttpResponse::NotFound().json(ErrorResponse { error, code: None });
+    let Some(status) = tracker.get(session_id) else {
+        return Err(not_found(format!("Unknown session '{}'", session_id)));
+    };
+    if !status.complete {
+        return Err(HttpResponse::Conflict().json(ErrorResponse {
+            error: "Session is still processing".to_string(),
+            code: None,
+        }));
+    }
+    let Some(result) = status.results.get(file) else {
+        return Err(not_found(format!(
+            "Session '{}' has no file {}",
+            session_id, file
+        )));
+    };
+    let Some(page) = result
+        .pages
+        .iter()
+        .position(|page| page.page_number == page_number)
+    else {
+        return Err(not_found(format!(
+            "'{}' has no page {}",
+            result.filename, page_number
+        )));
+    };
+    Ok((file, page))
+}
+
+/// The `status` of a page in a [`PageResponse`].
+fn page_status(page: &PageResult) -> &'static str {
+    if page.error.is_some() {
+        "failed"
+    } else if page.origin == TextOrigin::Blank {
+        "blank"
+    } else if page.text.trim().is_empty() {
+        "empty"
+    } else {
+        "read"
+    }
+}
+
+fn page_response(session_id: String, result: &OcrResult, page: &PageResult) -> PageResponse {
+    PageResponse {
+        session_id,
+        filename: result.filename.clone(),
+        status: page_status(page),
+        page: page.clone(),
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+struct CorrectionRequest {
+    /// The page's full text as proofread.
+    text: String,
+}
+
+/// Saves a proofreader's text for one page of a finished session. The recognized
+/// text is kept beside it.
+#[utoipa::path(
+    tag = "results",
+    params(
+        ("session_id" = String, Path, description = "Session id returned by /upload"),
+        ("page_number" = usize, Path, description = "Page number in the result, from 1"),
+        PageQuery
+    ),
+    request_body = CorrectionRequest,
+    responses(
+        (status = 200, description = "The page with its proofread text", body = PageResponse),
+        (status = 404, description = "Unknown session, file or page", body = ErrorResponse),
+        (status = 409, description = "Session still processing", body = ErrorResponse)
+    )
+)]
+#[put("/result/{session_id}/pages/{page_number}/correction")]
+async fn put_correction(
+    path: web::Path<(String, usize)>,
+    query: web::Query<PageQuery>,
+    request: web::Json<CorrectionRequest>,
+    jobs: web::Data<Jobs>,
+) -> Result<HttpResponse> {
+    let (session_id, page_number) = path.into_inner();
+    let located = locate_page(&jobs.tracker.read(), &session_id, query.file, page_number);
+    let (file, page) = match located {
+        Ok(found) => found,
+        Err(response) => return Ok(response),
+    };
+
+    // The session may have been deleted since it was looked up
+    let corrected = jobs.update_existing(&session_id, |status| {
+        let result = status.results.get_mut(file)?;
+        let proofread = result.pages.get_mut(page)?;
+        proofread.proofread_text = Some(request.into_inner().text);
+        let response = page_response(session_id.clone(), result, &result.pages[page]);
+        Some((result.pages[page].clone(), response))
+    });
+    let Some(Some((saved, response))) = corrected else {
+        return Ok(HttpResponse::NotFound().json(ErrorResponse {
+            error: format!("Unknown session '{}'", session_id),
+            code: None,
+        }));
+    };
+    jobs.persist(&session_id, move |store, id| {
+        store.save_page(id, file, page, &saved)
+    });
+    #[cfg(feature = "search")]
+    jobs.index_for_search(&session_id).await;
+    Ok(HttpResponse::Ok().json(response))
+}
+
+#[derive(Serialize, ToSchema)]
+struct ProofreadFile {
+    filename: String,
+    /// The file's text with every proofread page in place of its recognized text.
+    text: String,
+    /// Numbers of the pages with proofread text.
+    proofread_pages: Vec<usize>,
+    total_pages: usize,
+}
+
+#[derive(Serialize, ToSchema)]
+struct ProofreadResponse {
+    session_id: String,
+    files: Vec<ProofreadFile>,
+}
+
+/// A finished session's text as proofread so far, file by file.
+#[utoipa::path(
+    tag = "results",
+    params(("session_id" = String, Path, description = "Session id returned by /upload")),
+    responses(
+        (status = 200, description = "By the Accept header: JSON, plain text, or HTML with page anchors", content(
+            (ProofreadResponse = "application/json"),
+            (String = "text/plain"),
+            (String = "text/html")
+        )),
+        (status = 404, body = ErrorResponse),
+        (status = 406, description = "None of the formats accepted", body = ErrorResponse),
+        (status = 409, description = "Session still processing", body = ErrorResponse)
+    )
+)]
+#[get("/result/{session_id}/corrected", wrap = "Compress::default()")]
+async fn get_corrected(
+    req: HttpRequest,
+    path: web::Path<String>,
+    tracker: web::Data<ProgressTracker>,
+) -> Result<HttpResponse> {
+    let format = match negotiate::format(&req) {
+        Ok(format) => format,
+        Err(response) => return Ok(response),
+    };
+    let session_id = path.into_inner();
+    let Some(status) = tracker.read().get(&session_id).cloned() else {
+        return Ok(unknown_session(&session_id));
+    };
+    if !status.complete {
+        return Ok(HttpResponse::Conflict().json(ErrorResponse {
+            error: "Session is still processing".to_string(),
+            code: None,
+        }));
+    }
+
+    let successful: Vec<&OcrResult> = status.results.iter().filter(|r| r.success).collect();
+    let files: Vec<ProofreadFile> = successful
+        .iter()
+        .map(|result| ProofreadFile {
+            filename: result.filename.clone(),
+            text: result.proofread_text(),
+            proofread_pages: result
+                .pages
+                .iter()
+                .filter(|page| page.proofread_text.is_some())
+                .map(|page| page.page_number)
+                .collect(),
+            total_pages: result.pages.len(),
+        })
+        .collect();
+    let response = ProofreadResponse { session_id, files };
+    Ok(negotiate::respond(
+        format,
+        &response,
+        || match response.files.as_slice() {
+            [file] => file.text.clone(),
+            files => files
+                .iter()
+                .map(|file| format!("═══ {} ═══\n{}", file.filename, file.text))
+                .collect::<Vec<_>>()
+                .join("\n\n"),
+        },
+        &format!("Session {}", response.session_id),
+        || {
+            successful
+                .iter()
+                .map(|result| negotiate::Section {
+                    title: &result.filename,
+                    pages: result
+                        .pages
+                        .iter()
+                        .map(|page| {
+                            let text = page.proofread_text.as_deref().unwrap_or(&page.text);
+                            (page.page_number, text)
+                        })
+                        .collect(),
+                })
+                .collect()
+        },
+    ))
+}
+
+#[derive(Serialize, ToSchema)]
+struct PageDiff {
+    page_number: usize,
+    changes: Vec<diff::Change>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct FileDiff {
+    filename: String,
+    /// The proofread pages, each diffed against its recognized text.
+    pages: Vec<PageDiff>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct DiffResponse {
+    session_id: String,
+    files: Vec<FileDiff>,
+    /// Replacements over all files, most frequent first.
+    substitutions: Vec<diff::Substitution>,
+}
+
+/// Word-level diff between the recognized and the proofread text of every
+/// proofread page of a finished session.
+#[utoipa::path(
+    tag = "results",
+    params(("session_id" = String, Path, description = "Session id returned by /upload")),
+    responses(
+        (status = 200, body = DiffResponse),
+        (status = 404, body = ErrorResponse),
+        (status = 409, description = "Session still processing", body = ErrorResponse)
+    )
+)]
+#[get("/result/{session_id}/diff", wrap = "Compress::default()")]
+async fn get_diff(
+    path: web::Path<String>,
+    tracker: web::Data<ProgressTracker>,
+) -> Result<HttpResponse> {
+    let session_id = path.into_inner();
+    let Some(status) = tracker.read().get(&session_id).cloned() else {
+        return Ok(unknown_session(&session_id));
+    };
+    if !status.complete {
+        return Ok(HttpResponse::Conflict().json(ErrorResponse {
+            error: "Session is still processing".to_string(),
+            code: None,
+        }));
+    }
+
+    let files: Vec<FileDiff> = status
+        .results
+        .iter()
+        .map(|result| FileDiff {
+            filename: result.filename.clone(),
+            pages: result
+                .pages
+                .iter()
+                .filter_map(|page| {
+                    let proofread = page.proofread_text.as_deref()?;
+                    Some(PageDiff {
+                        page_number: page.page_number,
+                        changes: diff::words(&page.text, proofread),
+                    })
+                })
+                .collect(),
+        })
+        .filter(|file| !file.pages.is_empty())
+        .collect();
+    let substitutions = diff::substitutions(
+        files
+            .iter()
+            .flat_map(|file| &file.pages)
+            .flat_map(|page| &page.changes),
+    );
+    Ok(HttpResponse::Ok().json(DiffResponse {
+        session_id,
+        files,
+        substitutions,
+    }))
+}
+
+/// Where searchable PDFs are kept for download under `conversions_dir`, one
+/// directory per session.
+const SEARCHABLE_DIR: &str = "searchable";
+
+/// Where the images pages were read from are kept under `conversions_dir`, one
+/// directory per session and within it one per file.
+const PAGE_IMAGES_DIR: &str = "pages";
+
+fn page_images_dir(config: &Config, session_id: &str, index: usize) -> std::path::PathBuf {
+    config
+        .conversions_dir
+        .join(PAGE_IMAGES_DIR)
+        .join(session_id)
+        .join(index.to_string())
+}
+
+/// Where the searchable PDF of a session's `index`-th file is kept for download.
+fn searchable_pdf_path(config: &Config, session_id: &str, index: usize) -> std::path::PathBuf {
+    config
+        .conversions_dir
+        .join(SEARCHABLE_DIR)
+        .join(session_id)
+        .join(format!("{}.pdf", index))
+}
+
+/// Name of the searchable PDF of a session's `index`-th file in object storage.
+#[cfg(feature = "s3")]
+fn searchable_pdf_object(index: usize) -> String {
+    format!("searchable/{}.pdf", index)
+}
+
+/// Name in object storage of the page image a session's `index`-th file keeps
+/// as `name`.
+#[cfg(feature = "s3")]
+fn page_object_name(index: usize, name: &str) -> String {
+    format!("pages/{}/{}", index, name)
+}
+
+/// Name of an upload's copy in object storage.
+#[cfg(feature = "s3")]
+fn upload_object_name(file: &UploadedFile) -> String {
+    let name = file.path.file_name().unwrap_or_default().to_string_lossy();
+    format!("uploads/{}", name)
+}
+
+/// Sends the client to download the session's `name` from object storage
+/// through a presigned link.
+#[cfg(feature = "s3")]
+async fn redirect_to_object(
+    storage: &object_storage::ObjectStorage,
+    session_id: &str,
+    name: &str,
+) -> HttpResponse {
+    match storage.presigned(session_id, name).await {
+        Ok(url) => HttpResponse::TemporaryRedirect()
+            .insert_header((header::LOCATION, url))
+            .finish(),
+        Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
+            error: format!("Failed to sign a download link: {}", e),
+            code: None,
+        }),
+    }
+}
+
+/// Moves a file's searchable PDF out of the work directory and points the result
+/// at its download path.
+fn publish_searchable_pdf(config: &Config, session_id: &str, index: usize, result: &mut OcrResult) {
+    let Some(written) = result.searchable_pdf.take() else {
+        return;
+    };
+    let target = searchable_pdf_path(config, session_id, index);
+    let moved = target
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|()| {
+            // The work directory may be on another device (storage profiles)
+            std::fs::rename(&written, &target)
+                .or_else(|_| std::fs::copy(&written, &target).map(|_| ()))
+        });
+    let _ = std::fs::remove_file(&written);
+    match moved {
+        Ok(()) => {
+            result.searchable_pdf = Some(format!(
+                "{}/result/{}/pdf/{}",
+                API_PREFIX, session_id, index
+            ));
+        }
+        Err(e) => println!(
+            "  ⚠️  Warning: Failed to store searchable PDF for '{}': {}",
+            result.filename, e
+        ),
+    }
+}
+
+#[utoipa::path(
+    tag = "results",
+    params(
+        ("session_id" = String, Path, description = "Session id returned by /upload"),
+        ("index" = usize, Path, description = "Position of the file in the session's results")
+    ),
+    responses(
+        (status = 200, description = "The searchable PDF", content_type = "application/pdf"),
+        (status = 307, description = "Redirect to a presigned link to it in object storage"),
+        (status = 404, body = ErrorResponse)
+    )
+)]
+#[get("/result/{session_id}/pdf/{index}")]
+async fn get_searchable_pdf(
+    req: HttpRequest,
+    path: web::Path<(String, usize)>,
+    jobs: web::Data<Jobs>,
+) -> Result<HttpResponse> {
+    let (session_id, index) = path.into_inner();
+    let filename = jobs
+        .tracker
+        .read()
+        .get(&session_id)
+        .and_then(|status| status.results.get(index))
+        .filter(|result| result.searchable_pdf.is_some())
+        .map(|result| result.filename.clone());
+    let Some(filename) = filename else {
+        return Ok(HttpResponse::NotFound().json(ErrorResponse {
+            error: format!(
+                "No searchable PDF for file {} of session '{}'",
+                index, session_id
+            ),
+            code: None,
+        }));
+    };
+
+    let kept = searchable_pdf_path(&jobs.config, &session_id, index);
+    #[cfg(feature = "s3")]
+    if !kept.is_file()
+        && let Some(storage) = &jobs.storage
+    {
+        let name = searchable_pdf_object(index);
+        return Ok(redirect_to_object(storage, &session_id, &name).await);
+    }
+    let file = fs::NamedFile::open(kept)?.set_content_disposition(
+        actix_web::http::header::ContentDisposition::attachment(searchable_pdf_filename(&filename)),
+    );
+    Ok(file.into_response(&req))
+}
+
+/// Name a file's searchable PDF is downloaded as.
+fn searchable_pdf_filename(filename: &str) -> String {
+    let stem = filename.rsplit_once('.').map_or(filename, |(stem, _)| stem);
+    format!("{}-searchable.pdf", stem)
+}
+
+#[derive(Deserialize, IntoParams)]
+struct DownloadQuery {
+    /// `txt` (the default), `json` or `docx`.
+    format: Option<String>,
+}
+
+/// Name of the file a finished session is downloaded as, without extension: the
+/// uploaded file's for single-file sessions.
+fn download_stem(session_id: &str, results: &[OcrResult]) -> String {
+    match results {
+        [result] => result
+            .filename
+            .rsplit_once('.')
+            .map_or(result.filename.as_str(), |(stem, _)| stem)
+            .to_string(),
+        _ => format!("session-{}", session_id),
+    }
+}
+
+/// Downloads a finished session's output as one file: the text of its successful
+/// files as plain text or a Word document, each file under its name when there
+/// are several, or every result as JSON.
+#[utoipa::path(
+    tag = "results",
+    params(("session_id" = String, Path, description = "Session id returned by /upload"), DownloadQuery),
+    responses(
+        (status = 200, description = "The session's output as an attachment", content(
+            ("text/plain"),
+            ("application/json"),
+            ("application/vnd.openxmlformats-officedocument.wordprocessingml.document")
+        )),
+        (status = 206, description = "The part of the output a `Range` header asked for"),
+        (status = 307, description = "Redirect to a presigned link to it in object storage"),
+        (status = 304, description = "Unchanged since the `If-None-Match` ETag"),
+        (status = 400, description = "Unknown format", body = ErrorResponse),
+        (status = 404, body = ErrorResponse),
+        (status = 409, description = "Session still processing", body = ErrorResponse)
+    )
+)]
+#[route(
+    "/result/{session_id}/download",
+    method = "GET",
+    method = "HEAD",
+    wrap = "Compress::default()",
+    wrap = "from_fn(compression::weaken_validators)"
+)]
+async fn download_result(
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<DownloadQuery>,
+    jobs: web::Data<Jobs>,
+) -> Result<HttpResponse> {
+    let session_id = path.into_inner();
+    let Some(status) = jobs.tracker.read().get(&session_id).cloned() else {
+        return Ok(HttpResponse::NotFound().json(ErrorResponse {
+            error: format!("Unknown session '{}'", session_id),
+            code: None,
+        }));
+    };
+    if !status.complete {
+        return Ok(HttpResponse::Conflict().json(ErrorResponse {
+            error: "Session is still processing".to_string(),
+            code: None,
+        }));
+    }
+
+    let format = query.format.as_deref().unwrap_or("txt");
+    let (body, content_type) = match format {
+        "txt" => (
+            session_text(&status.results).into_bytes(),
+            "text/plain; charset=utf-8",
+        ),
+        "json" => (
+            serde_json::to_vec_pretty(&status.results)?,
+            "application/json",
+        ),
+        "docx" => match docx::write(&session_texts(&status.results)) {
+            Ok(document) => (
+                document,
+                "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+            ),
+            Err(error) => {
+                return Ok(
+                    HttpResponse::InternalServerError().json(ErrorResponse { error, code: None })
+                );
+            }
+        },
+        _ => {
+            return Ok(HttpResponse::BadRequest().json(ErrorResponse {
+                error: format!("Unknown format '{}'; expected txt, json or docx", format),
+                code: None,
+            }));
+        }
+    };
+
+    let filename = format!("{}.{}", download_stem(&session_id, &status.results), format);
+    serve_download(
+        &req,
+        &jobs,
+        &session_id,
+        &format!("download.{}", format),
+        filename,
+        body,
+        content_type,
+    )
+    .await
+}
+
+/// The text of a session's successful files, each under its name when there
+/// are several.
+fn session_texts(results: &[OcrResult]) -> Vec<(Option<&str>, &str)> {
+    results
+        .iter()
+        .filter(|result| result.success)
+        .map(|result| {
+            let heading = (results.len() > 1).then_some(result.filename.as_str());
+            (heading, result.text.as_str())
+        })
+        .collect()
+}
+
+/// [`session_texts`] as one plain text, the files' headings set off by `═══`.
+fn session_text(results: &[OcrResult]) -> String {
+    session_texts(results)
+        .iter()
+        .map(|(heading, text)| match heading {
+            Some(heading) => format!("═══ {} ═══\n{}", heading, text),
+            None => text.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Serves `body`, a download of the session made on request, as the attachment
+/// `filename`: from object storage under `downloads/{name}` when there is one,
+/// else kept on disk as `name` in the session's [`downloads_dir`].
+async fn serve_download(
+    req: &HttpRequest,
+    jobs: &Jobs,
+    session_id: &str,
+    name: &str,
+    filename: String,
+    body: Vec<u8>,
+    content_type: &str,
+) -> Result<HttpResponse> {
+    #[cfg(feature = "s3")]
+    if let Some(storage) = &jobs.storage {
+        let name = format!("downloads/{}", name);
+        let served = object_storage::Served {
+            content_type: Some(content_type),
+            disposition: Some(
+                actix_web::http::header::ContentDisposition::attachment(filename.clone())
+                    .to_string(),
+            ),
+        };
+        match storage.put(session_id, &name, body.clone(), served).await {
+            Ok(()) => return Ok(redirect_to_object(storage, session_id, &name).await),
+            Err(e) => println!(
+                "  ⚠️  Warning: Failed to store download of session {}, serving it from disk: {}",
+                session_id, e
+            ),
+        }
+    }
+    let stored = downloads_dir(&jobs.config, session_id).join(name);
+    store_download(&stored, &body)?;
+    let file = fs::NamedFile::open(stored)?
+        .set_content_type(
+            content_type
+                .parse()
+                .unwrap_or(actix_web::mime::APPLICATION_OCTET_STREAM),
+        )
+        .set_content_disposition(actix_web::http::header::ContentDisposition::attachment(
+            filename,
+        ));
+    let response = file.into_response(req);
+    if response.status() != actix_web::http::StatusCode::PARTIAL_CONTENT
+        || if_range_holds(req, &response)
+    {
+        return Ok(response);
+    }
+
+    // actix-files ignores If-Range; a download resumed after the results changed
+    // would otherwise splice two versions together
+    let mut full = HttpResponse::Ok();
+    for name in [
+        header::CONTENT_TYPE,
+        header::CONTENT_DISPOSITION,
+        header::ETAG,
+        header::LAST_MODIFIED,
+        header::ACCEPT_RANGES,
+    ] {
+        if let Some(value) = response.headers().get(&name) {
+            full.insert_header((name, value.clone()));
+        }
+    }
+    Ok(full.body(body))
+}
+
+#[derive(Deserialize, IntoParams)]
+struct TextQuery {
+    /// `none`, `formfeed`, or a line put before each page with `{page}`,
+    /// `{source_page}` and `{file}` replaced by its numbers and file name;
+    /// `page_separator` by default.
+    separator: Option<String>,
+    /// Use the proofread text of the pages that have it.
+    #[serde(default)]
+    proofread: bool,
+}
+
+/// A finished session's text as plain text, its pages set apart by a
+/// separator of the caller's choosing rather than the `━━━ Page N ━━━`
+/// markers of `text`. Every page of the successful files is included, each
+/// file under its name when there are several.
+#[utoipa::path(
+    tag = "results",
+    params(("session_id" = String, Path, description = "Session id returned by /upload"), TextQuery),
+    responses(
+        (status = 200, description = "The session's text", body = String, content_type = "text/plain"),
+        (status = 404, body = ErrorResponse),
+        (status = 409, description = "Session still processing", body = ErrorResponse)
+    )
+)]
+#[get("/result/{session_id}/text", wrap = "Compress::default()")]
+async fn export_text(
+    path: web::Path<String>,
+    query: web::Query<TextQuery>,
+    jobs: web::Data<Jobs>,
+) -> Result<HttpResponse> {
+    let session_id = path.into_inner();
+    let Some(status) = jobs.tracker.read().get(&session_id).cloned() else {
+        return Ok(unknown_session(&session_id));
+    };
+    if !status.complete {
+        return Ok(HttpResponse::Conflict().json(ErrorResponse {
+            error: "Session is still processing".to_string(),
+            code: None,
+        }));
+    }
+
+    let separator = pipeline::PageSeparator::parse(
+        query
+            .separator
+            .as_deref()
+            .unwrap_or(&jobs.config.page_separator),
+    );
+    let successful: Vec<&OcrResult> = status.results.iter().filter(|r| r.success).collect();
+    let text = successful
+        .iter()
+        .map(|result| {
+            let text = separator.join(result.pages.iter().map(|page| {
+                let text = match query.proofread {
+                    true => page.proofread_text.as_deref().unwrap_or(&page.text),
+                    false => &page.text,
+                };
+                (result.filename.as_str(), page, text)
+            }));
+            match successful.len() {
+                1 => text,
+                _ => format!("═══ {} ═══\n{}", result.filename, text),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain; charset=utf-8")
+        .body(text))
+}
+
+#[derive(Deserialize, IntoParams)]
+struct BundleQuery {
+    /// `zip` (the default) or `tar.gz`.
+    format: Option<String>,
+}
+
+/// Packs a finished session into one archive to keep or read offline: its
+/// combined text, each page's text (and proofread text and hOCR, when it has
+/// them) in a directory per file, and `metadata.json` describing the session
+/// and its pages.
+#[utoipa::path(
+    tag = "results",
+    params(("session_id" = String, Path, description = "Session id returned by /upload"), BundleQuery),
+    responses(
+        (status = 200, description = "The session bundle as an attachment", content(
+            ("application/zip"),
+            ("application/gzip")
+        )),
+        (status = 206, description = "The part of the bundle a `Range` header asked for"),
+        (status = 307, description = "Redirect to a presigned link to it in object storage"),
+        (status = 304, description = "Unchanged since the `If-None-Match` ETag"),
+        (status = 400, description = "Unknown format", body = ErrorResponse),
+        (status = 404, body = ErrorResponse),
+        (status = 409, description = "Session still processing", body = ErrorResponse)
+    )
+)]
+#[route("/result/{session_id}/bundle", method = "GET", method = "HEAD")]
+async fn download_bundle(
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<BundleQuery>,
+    jobs: web::Data<Jobs>,
+) -> Result<HttpResponse> {
+    let session_id = path.into_inner();
+    let Some(status) = jobs.tracker.read().get(&session_id).cloned() else {
+        return Ok(HttpResponse::NotFound().json(ErrorResponse {
+            error: format!("Unknown session '{}'", session_id),
+            code: None,
+        }));
+    };
+    if !status.complete {
+        return Ok(HttpResponse::Conflict().json(ErrorResponse {
+            error: "Session is still processing".to_string(),
+            code: None,
+        }));
+    }
+    let requested = query.format.as_deref().unwrap_or("zip");
+    let Some(format) = bundle::Format::parse(requested) else {
+        return Ok(HttpResponse::BadRequest().json(ErrorResponse {
+            error: format!("Unknown format '{}'; expected zip or tar.gz", requested),
+            code: None,
+        }));
+    };
+
+    let text = session_text(&status.results);
+    let body = match bundle::write(format, &session_id, &status, &text) {
+        Ok(body) => body,
+        Err(error) => {
+            return Ok(
+                HttpResponse::InternalServerError().json(ErrorResponse { error, code: None })
+            );
+        }
+    };
+    let filename = format!(
+        "{}-bundle.{}",
+        download_stem(&session_id, &status.results),
+        format.extension()
+    );
+    serve_download(
+        &req,
+        &jobs,
+        &session_id,
+        &format!("bundle.{}", format.extension()),
+        filename,
+        body,
+        format.content_type(),
+    )
+    .await
+}
+
+/// Whether a range request's `If-Range` validator, if it has one, names the
+/// version `response` is of: its ETag, or its Last-Modified date.
+fn if_range_holds(req: &HttpRequest, response: &HttpResponse) -> bool {
+    let Some(validator) = req.headers().get(header::IF_RANGE) else {
+        return true;
+    };
+    [header::ETAG, header::LAST_MODIFIED]
+        .iter()
+        .filter_map(|name| response.headers().get(name))
+        .any(|value| value == validator && !value.as_bytes().starts_with(b"W/"))
+}
+
+/// Where generated session downloads are kept under `conversions_dir`, one
+/// directory per session.
+const DOWNLOADS_DIR: &str = "downloads";
+
+fn downloads_dir(config: &Config, session_id: &str) -> std::path::PathBuf {
+    config.conversions_dir.join(DOWNLOADS_DIR).join(session_id)
+}
+
+/// The directories a session's output is kept in: searchable PDFs, page
+/// images, `/split` chunks and downloads.
+fn session_dirs(config: &Config, session_id: &str) -> [std::path::PathBuf; 4] {
+    let conversions = &config.conversions_dir;
+    [
+        conversions.join(SEARCHABLE_DIR).join(session_id),
+        conversions.join(PAGE_IMAGES_DIR).join(session_id),
+        config.splits_dir().join(session_id),
+        downloads_dir(config, session_id),
+    ]
+}
+
+/// Keeps `body` at `path` to be served from disk. The file is only replaced
+/// when its content changed, so its ETag holds between requests and a download
+/// cut off part way can be resumed with a range request; a new file is moved
+/// into place, leaving downloads of the old one to finish.
+fn store_download(path: &std::path::Path, body: &[u8]) -> std::io::Result<()> {
+    let unchanged = std::fs::metadata(path).is_ok_and(|m| m.len() == body.len() as u64)
+        && std::fs::read(path).is_ok_and(|kept| kept == body);
+    if unchanged {
+        return Ok(());
+    }
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let partial = path.with_extension(format!("{}.part", Uuid::new_v4()));
+    std::fs::write(&partial, body)
+        .and_then(|()| std::fs::rename(&partial, path))
+        .inspect_err(|_| {
+            let _ = std::fs::remove_file(&partial);
+        })
+}
+
+/// Resolves a `storage_profile` form field to its directory, checking the
+/// profile's token against the `X-Storage-Token` header.
+fn storage_profile_dir(
+    req: &HttpRequest,
+    config: &Config,
+    name: &str,
+) -> std::result::Result<std::path::PathBuf, HttpResponse> {
+    let Some(profile) = config.storage_profiles.get(name.trim()) else {
+        return Err(HttpResponse::BadRequest().json(ErrorResponse {
+            error: format!("Unknown storage profile '{}'", name.trim()),
+            code: None,
+        }));
+    };
+    if let Some(token) = &profile.token {
+        let supplied = req
+            .headers()
+            .get("x-storage-token")
+            .and_then(|value| value.to_str().ok());
+        if supplied != Some(token.as_str()) {
+            return Err(HttpResponse::Forbidden().json(ErrorResponse {
+                error: format!("Storage profile '{}' requires a valid token", name.trim()),
+                code: None,
+            }));
+        }
+    }
+    std::fs::create_dir_all(&profile.path).map_err(|e| {
+        HttpResponse::InternalServerError().json(ErrorResponse {
+            error: format!("Storage profile '{}' is unavailable: {}", name.trim(), e),
+            code: None,
+        })
+    })?;
+    Ok(profile.path.clone())
+}
+
+/// An uploaded file waiting to be processed, with the settings for it alone.
+#[derive(Clone, Serialize, Deserialize)]
+struct UploadedFile {
+    path: std::path::PathBuf,
+    filename: String,
+    password: Option<String>,
+    page_order: Option<PageRanges>,
+    rotations: BTreeMap<usize, Rotation>,
+}
+
+fn remove_uploads(files: &[UploadedFile]) {
+    for file in files {
+        let _ = std::fs::remove_file(&file.path);
+    }
+}
+
+/// A job waiting in [`Jobs::queue`].
+struct QueuedJob {
+    session_id: String,
+    files: Vec<UploadedFile>,
+    options: ProcessingOptions,
+    priority: Priority,
+    /// Whether every file is a single image, which the fast lane takes.
+    images_only: bool,
+    /// For a retry of some pages, the position of each file among the session's
+    /// results.
+    retried: Option<Vec<usize>>,
+}
+
+/// Uploads kept after their session because some of their pages failed or came
+/// out empty, for `POST /sessions/{session_id}/retry`.
+struct RetainedUploads {
+    /// Each file with its position among the session's results.
+    files: Vec<(usize, UploadedFile)>,
+    /// The settings the files were last processed with.
+    options: ProcessingOptions,
+}
+
+/// An upload held with `prepare=true` until its pages are arranged.
+struct HeldJob {
+    files: Vec<UploadedFile>,
+    options: ProcessingOptions,
+    priority: Priority,
+}
+
+/// What processing jobs need from the server, shared by the handlers that start
+/// them.
+struct Jobs {
+    tracker: ProgressTracker,
+    config: Arc<Config>,
+    notifier: Arc<Notifier>,
+    dictionary: Arc<Dictionary>,
+    http: Arc<reqwest::Client>,
+    /// For the URLs `POST /upload-url` is sent, kept to public addresses.
+    public_http: reqwest::Client,
+    /// Uploads held with `prepare=true`, by session id.
+    held: RwLock<HashMap<String, HeldJob>>,
+    /// Jobs waiting for a worker, by priority and then in the order they came.
+    queue: Mutex<VecDeque<QueuedJob>>,
+    /// Wakes an idle worker when a job is queued.
+    queued: Notify,
+    /// Wakes an idle fast-lane worker when a job of images only is queued.
+    queued_images: Notify,
+    /// Uploads of finished sessions with pages to retry, by session id.
+    retained: RwLock<HashMap<String, RetainedUploads>>,
+    /// Cancellation flags of the jobs queued or being processed, by session id.
+    running: RwLock<HashMap<String, Cancel>>,
+    /// Uploads of the jobs being processed, by session id.
+    processing: RwLock<HashMap<String, Vec<std::path::PathBuf>>>,
+    /// Pages read of files whose jobs a restart interrupted, by session id and
+    /// file position, for picking the files up after them.
+    read_pages: Mutex<HashMap<(String, usize), Vec<PageResult>>>,
+    /// Ids of sessions whose status changed, with the page just read when that
+    /// was the change, for `/events` and `/ws` streams.
+    updates: broadcast::Sender<(String, Option<Event>)>,
+    /// Full-text index of the pages of finished sessions.
+    #[cfg(feature = "search")]
+    search: search::SearchIndex,
+    /// Where sessions are saved to survive a restart, unless
+    /// `state_store` is unset.
+    store: Option<Arc<dyn Store>>,
+    /// Runs the writes to `store` and `shared` in order, off the async workers.
+    writer: store::Writer,
+    /// Where statuses are published for other server instances, unless
+    /// `redis_url` is unset.
+    #[cfg(feature = "redis")]
+    shared: Option<Arc<shared_status::SharedStatuses>>,
+    /// Where uploads, kept page images and result files go, unless
+    /// `object_storage` is unset.
+    #[cfg(feature = "s3")]
+    storage: Option<object_storage::ObjectStorage>,
+    /// Sessions whose status changed since it was last saved.
+    unsaved: Mutex<HashSet<String>>,
+}
+
+impl Jobs {
+    /// Applies `change` to the session's status, creating it if need be, and
+    /// stamps the update time.
+    fn update(&self, session_id: &str, change: impl FnOnce(&mut ProgressStatus)) {
+        let now = unix_time();
+        let mut tracker = self.tracker.write();
+        let status = tracker
+            .entry(session_id.to_string())
+            .or_insert_with(|| ProgressStatus {
+                created_at: now,
+                ..Default::default()
+            });
+        change(status);
+        Self::settle(status, now);
+        drop(tracker);
+        self.changed(session_id);
+    }
+
+    /// Like [`Jobs::update`], for a session that must still be there: returns
+    /// what `change` returns, or `None`, creating nothing, when it is gone.
+    fn update_existing<T>(
+        &self,
+        session_id: &str,
+        change: impl FnOnce(&mut ProgressStatus) -> T,
+    ) -> Option<T> {
+        let mut tracker = self.tracker.write();
+        let status = tracker.get_mut(session_id)?;
+        let changed = change(status);
+        Self::settle(status, unix_time());
+        drop(tracker);
+        self.changed(session_id);
+        Some(changed)
+    }
+
+    /// Recomputes the progress figures of a status that was just changed.
+    fn settle(status: &mut ProgressStatus, now: u64) {
+        status.percent = if status.complete {
+            100.0
+        } else if status.files.is_empty() {
+            0.0
+        } else {
+            status.files.iter().map(FileProgress::done).sum::<f32>() / status.files.len() as f32
+                * 100.0
+        };
+        status.eta_seconds = status.eta();
+        status.updated_at = now;
+    }
+
+    /// Marks the session for saving and tells its listeners it changed.
+    fn changed(&self, session_id: &str) {
+        if self.keeps_statuses() {
+            self.unsaved.lock().insert(session_id.to_string());
+        }
+        // Nobody may be listening
+        let _ = self.updates.send((session_id.to_string(), None));
+    }
+
+    /// Queues `save` of the session for the session store, when sessions are
+    /// stored, with a warning when it fails. It runs on the writer thread after
+    /// the writes queued before it.
+    fn persist(
+        &self,
+        session_id: &str,
+        save: impl FnOnce(&dyn Store, &str) -> Result<(), String> + Send + 'static,
+    ) {
+        let Some(store) = self.store.clone() else {
+            return;
+        };
+        let session_id = session_id.to_string();
+        self.writer.queue(move || {
+            if let Err(e) = save(store.as_ref(), &session_id) {
+                println!(
+                    "  ⚠️  Warning: Failed to save session {}: {}",
+                    session_id, e
+                );
+            }
+        });
+    }
+
+    /// Whether status changes are written out, to the session store or for
+    /// other server instances.
+    fn keeps_statuses(&self) -> bool {
+        #[cfg(feature = "redis")]
+        if self.shared.is_some() {
+            return true;
+        }
+        self.store.is_some()
+    }
+
+    /// Queues the statuses changed since the last time for the session store
+    /// and for other server instances. They are copied and queued with the
+    /// tracker locked, so a session deleted meanwhile is either left out or
+    /// deleted after its status is written.
+    fn flush(&self) {
+        let unsaved: Vec<String> = self.unsaved.lock().drain().collect();
+        if unsaved.is_empty() {
+            return;
+        }
+        let tracker = self.tracker.read();
+        let statuses: Vec<(String, ProgressStatus)> = unsaved
+            .into_iter()
+            .filter_map(|id| tracker.get(&id).cloned().map(|status| (id, status)))
+            .collect();
+        let store = self.store.clone();
+        #[cfg(feature = "redis")]
+        let shared = self.shared.clone();
+        self.writer.queue(move || {
+            let statuses: Vec<_> = statuses.iter().map(|(id, status)| (id, status)).collect();
+            if let Some(store) = &store
+                && let Err(e) = store.save_statuses(&statuses)
+            {
+                println!("  ⚠️  Warning: Failed to save session statuses: {}", e);
+            }
+            #[cfg(feature = "redis")]
+            if let Some(shared) = &shared {
+                Self::publish(shared, &statuses);
+            }
+        });
+        drop(tracker);
+    }
+
+    /// Publishes `statuses` for other server instances.
+    #[cfg(feature = "redis")]
+    fn publish(shared: &shared_status::SharedStatuses, statuses: &[(&String, &ProgressStatus)]) {
+        if let Err(e) = shared.publish(statuses) {
+            println!("  ⚠️  Warning: Failed to share session statuses: {}", e);
+        }
+    }
+
+    /// Removes a session that is not being processed: its status, saved state,
+    /// uploads held or kept for a retry, result files and search entries.
+    /// Returns the bytes freed on the local disk, or `None` when there was no
+    /// such session.
+    async fn forget(self: Arc<Self>, session_id: &str) -> std::io::Result<Option<u64>> {
+        let mut found = self.tracker.write().remove(session_id).is_some();
+        self.persist(session_id, |store, id| store.delete(id));
+        #[cfg(feature = "s3")]
+        if let Some(storage) = &self.storage
+            && let Err(e) = storage.remove_session(session_id).await
+        {
+            println!(
+                "  ⚠️  Warning: Failed to remove stored files of session {}: {}",
+                session_id, e
+            );
+        }
+        #[cfg(feature = "redis")]
+        if let Some(shared) = self.shared.clone() {
+            let session_id = session_id.to_string();
+            self.writer.queue(move || {
+                if let Err(e) = shared.remove(&session_id) {
+                    println!(
+                        "  ⚠️  Warning: Failed to withdraw shared status of session {}: {}",
+                        session_id, e
+                    );
+                }
+            });
+        }
+        // Ends any event streams of the session
+        let _ = self.updates.send((session_id.to_string(), None));
+        let mut freed = 0;
+        if let Some(job) = self.held.write().remove(session_id) {
+            freed += job
+                .files
+                .iter()
+                .map(|file| janitor::disk_usage(&file.path))
+                .sum::<u64>();
+            remove_uploads(&job.files);
+            found = true;
+        }
+        if let Some(retained) = self.retained.write().remove(session_id) {
+            for (_, file) in &retained.files {
+                freed += janitor::disk_usage(&file.path);
+                let _ = std::fs::remove_file(&file.path);
+            }
+        }
+        let split_dir = self.config.splits_dir().join(session_id);
+        for dir in session_dirs(&self.config, session_id) {
+            if dir.exists() {
+                // A split handed out to several requests stays for the others
+                if dir == split_dir && !splits::release(&dir) {
+                    found = true;
+                    continue;
+                }
+                freed += janitor::disk_usage(&dir);
+                std::fs::remove_dir_all(&dir)?;
+                found = true;
+            }
+        }
+
+        if !found {
+            return Ok(None);
+        }
+        #[cfg(feature = "search")]
+        {
+            let jobs = self.clone();
+            let id = session_id.to_string();
+            tokio::task::spawn_blocking(move || jobs.search.remove_session(&id))
+                .await
+                .map_err(std::io::Error::other)?
+                .map_err(std::io::Error::other)?;
+        }
+        Ok(Some(freed))
+    }
+
+    /// Writes out changed statuses every [`store::FLUSH_INTERVAL`], and marks this
+    /// instance's jobs as still its own every [`store::HEARTBEAT_INTERVAL`],
+    /// for as long as the server runs.
+    async fn save_statuses(self: Arc<Self>) {
+        let mut last_heartbeat = Instant::now();
+        loop {
+            tokio::time::sleep(store::FLUSH_INTERVAL).await;
+            let heartbeat = last_heartbeat.elapsed() >= store::HEARTBEAT_INTERVAL;
+            if heartbeat {
+                last_heartbeat = Instant::now();
+            }
+            self.flush();
+            if heartbeat && let Some(store) = self.store.clone() {
+                self.writer.queue(move || {
+                    if let Err(e) = store.heartbeat() {
+                        println!("  ⚠️  Warning: Failed to renew session jobs: {}", e);
+                    }
+                });
+            }
+        }
+    }
+
+    /// Takes back the sessions saved before a restart: their statuses and
+    /// results, uploads held or kept for a retry, and the files of jobs that
+    /// were queued or being processed, queued again behind the files already
+    /// read. Sessions that were processing with no work saved are marked
+    /// interrupted. Returns the ids of the finished sessions.
+    fn restore(&self) -> Vec<String> {
+        let Some(store) = &self.store else {
+            return Vec::new();
+        };
+        let sessions = match store.load() {
+            Ok(sessions) => sessions,
+            Err(e) => {
+                println!("  ⚠️  Warning: Failed to load saved sessions: {}", e);
+                return Vec::new();
+            }
+        };
+
+        let mut finished = Vec::new();
+        let mut resumed = 0;
+        for stored in sessions {
+            let (id, mut status) = (stored.id, stored.status);
+            status.queue_position = None;
+            match stored.job {
+                Some(job) if job.state == JobState::Queued => {
+                    let mut read_pages = self.read_pages.lock();
+                    for (index, pages) in stored.read_pages {
+                        read_pages.insert((id.clone(), index), pages);
+                    }
+                    drop(read_pages);
+                    self.tracker.write().insert(id.clone(), status);
+                    self.retry(id, job.files, job.options, job.priority);
+                    resumed += 1;
+                    continue;
+                }
+                Some(job) if job.state == JobState::Held => {
+                    let files = job.files.into_iter().map(|(_, file)| file).collect();
+                    self.held.write().insert(
+                        id.clone(),
+                        HeldJob {
+                            files,
+                            options: job.options,
+                            priority: job.priority,
+                        },
+                    );
+                }
+                Some(job) => {
+                    self.retained.write().insert(
+                        id.clone(),
+                        RetainedUploads {
+                            files: job.files,
+                            options: job.options,
+                        },
+                    );
+                }
+                None if !status.complete => {
+                    status.stage = CANCELLED.to_string();
+                    status.message = "Interrupted by a server restart".to_string();
+                    status.complete = true;
+                    self.unsaved.lock().insert(id.clone());
+                }
+                None => {}
+            }
+            if status.complete {
+                finished.push(id.clone());
+            }
+            self.tracker.write().insert(id, status);
+        }
+        #[cfg(feature = "redis")]
+        if let Some(shared) = self.shared.clone() {
+            let tracker = self.tracker.read();
+            let statuses: Vec<(String, ProgressStatus)> = tracker
+                .iter()
+                .map(|(id, status)| (id.clone(), status.clone()))
+                .collect();
+            self.writer.queue(move || {
+                let statuses: Vec<_> = statuses.iter().map(|(id, status)| (id, status)).collect();
+                Self::publish(&shared, &statuses);
+            });
+        }
+        println!(
+            "💾 Restored {} sessions, {} of them resumed",
+            self.tracker.read().len(),
+            resumed
+        );
+        finished
+    }
+
+    /// Copies the session's uploads to object storage, so its job can still be
+    /// run once they are gone from the temp directory, such as by another
+    /// server instance after a restart.
+    #[cfg(feature = "s3")]
+    async fn archive_uploads(&self, session_id: &str, files: &[UploadedFile]) {
+        let Some(storage) = &self.storage else {
+            return;
+        };
+        for file in files {
+            let name = upload_object_name(file);
+            let served = object_storage::Served::default();
+            if let Err(e) = storage
+                .put_file(session_id, &name, &file.path, served)
+                .await
+            {
+                println!(
+                    "  ⚠️  Warning: Failed to store upload '{}' of session {}: {}",
+                    file.filename, session_id, e
+                );
+            }
+        }
+    }
+
+    /// Fetches an upload back from object storage if it is gone from the temp
+    /// directory.
+    #[cfg(feature = "s3")]
+    async fn restore_upload(&self, session_id: &str, file: &UploadedFile) {
+        let Some(storage) = &self.storage else {
+            return;
+        };
+        if file.path.exists() {
+            return;
+        }
+        if let Err(e) = storage
+            .fetch(session_id, &upload_object_name(file), &file.path)
+            .await
+        {
+            println!(
+                "  ⚠️  Warning: Failed to fetch upload '{}' of session {}: {}",
+                file.filename, session_id, e
+            );
+        }
+    }
+
+    /// Removes the copy in object storage of an upload the session is done
+    /// with.
+    #[cfg(feature = "s3")]
+    async fn remove_archived_upload(&self, session_id: &str, file: &UploadedFile) {
+        if let Some(storage) = &self.storage
+            && let Err(e) = storage.remove(session_id, &upload_object_name(file)).await
+        {
+            println!(
+                "  ⚠️  Warning: Failed to remove stored upload '{}' of session {}: {}",
+                file.filename, session_id, e
+            );
+        }
+    }
+
+    /// Moves the searchable PDF and the kept page images of the session's
+    /// `index`-th file to object storage, where they are downloaded from.
+    /// Whatever fails to move stays on disk and is served from there.
+    #[cfg(feature = "s3")]
+    async fn store_outputs(&self, session_id: &str, index: usize, result: &OcrResult) {
+        let Some(storage) = &self.storage else {
+            return;
+        };
+        let pdf = searchable_pdf_path(&self.config, session_id, index);
+        if pdf.is_file() {
+            let served = object_storage::Served {
+                content_type: Some("application/pdf"),
+                disposition: Some(
+                    actix_web::http::header::ContentDisposition::attachment(
+                        searchable_pdf_filename(&result.filename),
+                    )
+                    .to_string(),
+                ),
+            };
+            let name = searchable_pdf_object(index);
+            match storage.put_file(session_id, &name, &pdf, served).await {
+                Ok(()) => {
+                    let _ = std::fs::remove_file(&pdf);
+                }
+                Err(e) => println!(
+                    "  ⚠️  Warning: Failed to store searchable PDF for '{}': {}",
+                    result.filename, e
+                ),
+            }
+        }
+
+        let dir = page_images_dir(&self.config, session_id, index);
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let served = object_storage::Served {
+                content_type: Some(page_images::content_type(&name)),
+                disposition: None,
+            };
+            let object = page_object_name(index, &name);
+            if let Err(e) = storage
+                .put_file(session_id, &object, &entry.path(), served)
+                .await
+            {
+                println!(
+                    "  ⚠️  Warning: Failed to store page images of '{}': {}",
+                    result.filename, e
+                );
+                return;
+            }
+            let _ = std::fs::remove_file(entry.path());
+        }
+        let _ = std::fs::remove_dir(&dir);
+    }
+
+    /// Indexes the session's current results for `/search`, off the server's
+    /// threads.
+    #[cfg(feature = "search")]
+    async fn index_for_search(self: &Arc<Self>, session_id: &str) {
+        let jobs = self.clone();
+        let session_id = session_id.to_string();
+        let indexed = tokio::task::spawn_blocking(move || {
+            let results = jobs
+                .tracker
+                .read()
+                .get(&session_id)
+                .map(|status| status.results.clone())
+                .unwrap_or_default();
+            jobs.search
+                .index_session(&session_id, &results)
+                .map_err(|e| format!("{}: {}", session_id, e))
+        })
+        .await;
+        match indexed {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => println!("  ⚠️  Warning: Failed to index session {}", e),
+            Err(e) => println!("  ⚠️  Warning: Failed to index session: {}", e),
+        }
+    }
+
+    /// Queues `files` for processing in the background, recording progress and
+    /// results under `session_id` and notifying when the job is done.
+    fn start(
+        &self,
+        session_id: String,
+        files: Vec<UploadedFile>,
+        options: ProcessingOptions,
+        priority: Priority,
+    ) {
+        self.update(&session_id, |status| {
+            status.queue(
+                QUEUED,
+                "Waiting to start OCR".to_string(),
+                files.iter().map(|file| file.filename.as_str()),
+            );
+            status.priority = priority;
+        });
+        self.enqueue(session_id, files, options, priority, None);
+    }
+
+    /// Queues the retained `files` of a finished session to be read again, their
+    /// page selections already narrowed to the pages to retry. Their new pages
+    /// are merged into the session's results.
+    fn retry(
+        &self,
+        session_id: String,
+        files: Vec<(usize, UploadedFile)>,
+        options: ProcessingOptions,
+        priority: Priority,
+    ) {
+        let (indices, files): (Vec<usize>, Vec<UploadedFile>) = files.into_iter().unzip();
+        self.update(&session_id, |status| {
+            status.requeue(&indices);
+            status.priority = priority;
+        });
+        self.enqueue(session_id, files, options, priority, Some(indices));
+    }
+
+    fn enqueue(
+        &self,
+        session_id: String,
+        files: Vec<UploadedFile>,
+        mut options: ProcessingOptions,
+        priority: Priority,
+        retried: Option<Vec<usize>>,
+    ) {
+        options.cancel = Cancel::default();
+        self.running
+            .write()
+            .insert(session_id.clone(), options.cancel.clone());
+        let indices = retried
+            .clone()
+            .unwrap_or_else(|| (0..files.len()).collect());
+        let job = StoredJob {
+            state: JobState::Queued,
+            files: indices.into_iter().zip(files.iter().cloned()).collect(),
+            options: options.clone(),
+            priority,
+        };
+        self.persist(&session_id, move |store, id| store.save_job(id, &job));
+        let images_only = files.iter().all(|file| !pipeline::is_paged(&file.path));
+        let mut queue = self.queue.lock();
+        // Behind every job of the same priority or higher
+        let place = queue
+            .iter()
+            .position(|job| job.priority < priority)
+            .unwrap_or(queue.len());
+        queue.insert(
+            place,
+            QueuedJob {
+                session_id,
+                files,
+                options,
+                priority,
+                images_only,
+                retried,
+            },
+        );
+        self.number_queue(&queue);
+        drop(queue);
+        self.queued.notify_one();
+        if images_only {
+            self.queued_images.notify_one();
+        }
+    }
+
+    /// Records every queued job's place in the queue.
+    fn number_queue(&self, queue: &VecDeque<QueuedJob>) {
+        for (index, job) in queue.iter().enumerate() {
+            self.update(&job.session_id, |status| {
+                status.queue_position = Some(index + 1);
+                status.message = format!("Waiting to start OCR, number {} in line", index + 1);
+            });
+        }
+    }
+
+    /// Takes a queued job off the queue before it starts, for cancelling it.
+    fn dequeue(&self, session_id: &str) -> Option<QueuedJob> {
+        let mut queue = self.queue.lock();
+        let index = queue.iter().position(|job| job.session_id == session_id)?;
+        let job = queue.remove(index);
+        self.number_queue(&queue);
+        job
+    }
+
+    /// Processes queued jobs one after another, for as long as the server runs.
+    /// `max_concurrent_jobs` of these run side by side, plus `fast_lane_workers`
+    /// in the `fast_lane` that only take jobs of single images, so a photo is
+    /// not stuck behind a book.
+    async fn work(self: Arc<Self>, fast_lane: bool) {
+        loop {
+            let next = {
+                let mut queue = self.queue.lock();
+                let next = queue
+                    .iter()
+                    .position(|job| !fast_lane || job.images_only)
+                    .and_then(|index| queue.remove(index));
+                if next.is_some() {
+                    self.number_queue(&queue);
+                }
+                next
+            };
+            match next {
+                Some(job) => self.clone().run(job).await,
+                None if fast_lane => self.queued_images.notified().await,
+                None => self.queued.notified().await,
+            }
+        }
+    }
+
+    /// Reads the session's `index`-th file through the pipeline, saving its
+    /// pages as they are read on a `first_reading`.
+    async fn read(
+        self: &Arc<Self>,
+        session_id: &str,
+        index: usize,
+        file: &UploadedFile,
+        options: &ProcessingOptions,
+        first_reading: bool,
+    ) -> OcrResult {
+        #[cfg(feature = "s3")]
+        self.restore_upload(session_id, file).await;
+        // Recognition blocks, so keep it off the server's threads
+        let jobs = self.clone();
+        let (session, path, filename, file_options) = (
+            session_id.to_string(),
+            file.path.clone(),
+            file.filename.clone(),
+            options.clone(),
+        );
+        let processed = tokio::task::spawn_blocking(move || {
+            let report = |progress: Progress| {
+                if first_reading {
+                    for page in &progress.read_pages {
+                        let page = page.clone();
+                        jobs.persist(&session, move |store, id| {
+                            store.save_page(id, index, page.page_number - 1, &page)
+                        });
+                    }
+                }
+                if progress.page.is_some() {
+                    let page = Event::progress(&filename, progress.clone());
+                    let _ = jobs.updates.send((session.clone(), Some(page)));
+                }
+                jobs.update(&session, |status| status.report(index, progress));
+            };
+            pipeline::process_file(
+                &path,
+                &filename,
+                &jobs.config,
+                &file_options,
+                &jobs.dictionary,
+                &report,
+            )
+        })
+        .await;
+        match processed {
+            Ok(result) => result,
+            Err(e) => OcrResult::failed(&file.filename, format!("Processing failed: {}", e)),
+        }
+    }
+
+    /// The session's disk quota, with what its `uploads` and kept output take
+    /// now; none without `max_session_bytes`.
+    fn disk_quota<'a>(
+        &self,
+        session_id: &str,
+        uploads: impl Iterator<Item = &'a std::path::PathBuf>,
+    ) -> Option<DiskQuota> {
+        let limit = self.config.max_session_bytes;
+        if limit == 0 {
+            return None;
+        }
+        let outputs = session_dirs(&self.config, session_id)
+            .iter()
+            .map(|dir| janitor::disk_usage(dir))
+            .sum::<u64>();
+        let uploads = uploads.map(|path| janitor::disk_usage(path)).sum::<u64>();
+        Some(DiskQuota {
+            limit,
+            used: outputs + uploads,
+        })
+    }
+
+    /// Processes a job's files. A cancelled job keeps the results of the files it
+    /// got to and drops the rest.
+    async fn run(self: Arc<Self>, job: QueuedJob) {
+        let QueuedJob {
+            session_id,
+            files,
+            mut options,
+            priority,
+            retried,
+            ..
+        } = job;
+        self.processing.write().insert(
+            session_id.clone(),
+            files.iter().map(|file| file.path.clone()).collect(),
+        );
+        self.update(&session_id, |status| {
+            status.queue_position = None;
+            status.stage = "Starting".to_string();
+            status.message = "Starting OCR".to_string();
+        });
+
+        let indices = retried
+            .clone()
+            .unwrap_or_else(|| (0..files.len()).collect());
+        let mut results = match retried {
+            Some(_) => self
+                .tracker
+                .read()
+                .get(&session_id)
+                .map(|status| status.results.clone())
+                .unwrap_or_default(),
+            None => Vec::new(),
+        };
+        let mut retained = Vec::new();
+        // The files still to read, saved as each one is done
+        let mut pending: Vec<(usize, UploadedFile)> =
+            indices.iter().copied().zip(files.iter().cloned()).collect();
+        // Set once a file took the session over its disk quota; the files
+        // after it are failed unread
+        let mut over_quota = false;
+
+        for (index, file) in indices.into_iter().zip(files) {
+            if options.cancel.is_cancelled() {
+                // Pages still to retry stay retryable
+                if index < results.len() {
+                    retained.push((index, file));
+                } else {
+                    let _ = std::fs::remove_file(&file.path);
+                    #[cfg(feature = "s3")]
+                    self.remove_archived_upload(&session_id, &file).await;
+                }
+                continue;
+            }
+            options.password = file.password.clone();
+            options.page_order = file.page_order.clone();
+            options.rotations = file.rotations.clone();
+            options.page_images = self
+                .config
+                .keep_page_images
+                .then(|| page_images_dir(&self.config, &session_id, index));
+            // A file read before has its result; only a first reading is
+            // saved page by page to be picked up after an interruption
+            let first_reading = index >= results.len();
+            options.read_pages = self
+                .read_pages
+                .lock()
+                .remove(&(session_id.clone(), index))
+                .filter(|_| first_reading)
+                .unwrap_or_default();
+            let uploads = pending.iter().chain(&retained).map(|(_, file)| &file.path);
+            options.disk_quota = self.disk_quota(&session_id, uploads);
+            let mut ocr_result = match options.disk_quota {
+                Some(quota) if over_quota || quota.used > quota.limit => {
+                    println!(
+                        "  🛑 Session {} is over its disk quota; failing '{}'",
+                        session_id, file.filename
+                    );
+                    OcrResult {
+                        error_code: Some(pipeline::QUOTA_EXCEEDED.to_string()),
+                        ..OcrResult::failed(&file.filename, quota.exceeded())
+                    }
+                }
+                _ => {
+                    self.read(&session_id, index, &file, &options, first_reading)
+                        .await
+                }
+            };
+            over_quota |= ocr_result.error_code.as_deref() == Some(pipeline::QUOTA_EXCEEDED);
+            if options.morphology
+                && self.config.morphology.command.is_none()
+                && let Some(url) = &self.config.morphology.url
+            {
+                analyze_morphology(&self.http, url, &mut ocr_result).await;
+            }
+            publish_searchable_pdf(&self.config, &session_id, index, &mut ocr_result);
+            #[cfg(feature = "s3")]
+            self.store_outputs(&session_id, index, &ocr_result).await;
+            match results.get_mut(index) {
+                // Single images and failed files are read again whole
+                Some(earlier) if earlier.success && pipeline::is_paged(&file.path) => {
+                    earlier.merge_pages(ocr_result);
+                    earlier.postprocess(&options);
+                }
+                Some(earlier) => {
+                    ocr_result.postprocess(&options);
+                    *earlier = ocr_result;
+                }
+                None => {
+                    ocr_result.postprocess(&options);
+                    results.push(ocr_result);
+                }
+            }
+            let result = &results[index];
+            self.update(&session_id, |status| status.finish_file(index, result));
+            pending.retain(|(pending, _)| *pending != index);
+            let job = StoredJob {
+                state: JobState::Queued,
+                files: pending.clone(),
+                options: options.clone(),
+                priority,
+            };
+            let saved = result.clone();
+            self.persist(&session_id, move |store, id| {
+                store.save_result(id, index, &saved)?;
+                store.save_job(id, &job)
+            });
+            // With the status, so a restart finds the file done
+            self.flush();
+            if !result.cancelled
+                && (!result.success || result.pages.iter().any(PageResult::needs_retry))
+            {
+                retained.push((index, file));
+            } else {
+                let _ = std::fs::remove_file(&file.path);
+                #[cfg(feature = "s3")]
+                self.remove_archived_upload(&session_id, &file).await;
+            }
+        }
+
+        // Saved as the job once the session is saved complete
+        let kept = retained.clone();
+        if !retained.is_empty() {
+            self.retained.write().insert(
+                session_id.clone(),
+                RetainedUploads {
+                    files: retained,
+                    options: options.clone(),
+                },
+            );
+        }
+        // Mark as complete with results
+        self.running.write().remove(&session_id);
+        self.processing.write().remove(&session_id);
+        let (stage, message) = if options.cancel.is_cancelled() {
+            (CANCELLED, "Processing cancelled")
+        } else {
+            ("Complete", "Processing complete")
+        };
+        self.update(&session_id, |status| {
+            status.stage = stage.to_string();
+            status.message = message.to_string();
+            status.complete = true;
+            status.results = results.clone();
+        });
+        self.flush();
+        let job = (!kept.is_empty()).then(|| StoredJob {
+            state: JobState::Retained,
+            files: kept,
+            options: options.clone(),
+            priority,
+        });
+        self.persist(&session_id, move |store, id| match job {
+            None => store.remove_job(id),
+            Some(job) => store.save_job(id, &job),
+        });
+        #[cfg(feature = "search")]
+        self.index_for_search(&session_id).await;
+
+        let summary = JobSummary::new(&session_id, options.notes, &results);
+        self.notifier.job_finished(&summary, &results).await;
+        if let Some(url) = &options.callback_url {
+            self.notifier.callback(url, &summary, &results).await;
+        }
+    }
+}
+
+#[utoipa::path(
+    tag = "ocr",
+    request_body(content = openapi::UploadForm, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, description = "Processing started", body = UploadResponse),
+        (status = 400, description = "Invalid option", body = ErrorResponse),
+        (status = 403, description = "Encrypted PDF without its password", body = ErrorResponse),
+        (status = 413, description = "Upload over the configured limits or the session disk quota", body = ErrorResponse),
+        (status = 415, description = "Unsupported file type", body = ErrorResponse),
+        (status = 422, description = "Suspicious PDF", body = ErrorResponse),
+        (status = 429, description = "Too many requests; see Retry-After", body = ErrorResponse),
+        (status = 503, description = "Too little disk space or too long a queue", body = ErrorResponse)
+    )
+)]
+#[post("/upload", wrap = "from_fn(rate_limit::limit)")]
+async fn upload(
+    req: HttpRequest,
+    mut payload: Multipart,
+    config: web::Data<Config>,
+    jobs: web::Data<Jobs>,
+) -> Result<HttpResponse> {
+    // Collect files first, each with the password it was sent with
+    let mut files_to_process = Vec::new();
+    let mut options = ProcessingOptions::new(&config);
+    let mut hold = false;
+    let mut priority = Priority::default();
+    let mut metadata = SessionMetadata::default();
+    if let Some(reason) = capacity::refusal(&jobs) {
+        return Ok(capacity::unavailable(&jobs, reason));
+    }
+    let mut limits = match UploadLimits::for_session(&req, &config) {
+        Ok(limits) => limits,
+        Err(too_large) => return Ok(too_large.response()),
+    };
+
+    while let Some(item) = payload.next().await {
+        let mut field = item?;
+
+        // Plain form fields (no filename) carry processing options
+        let option_name = field
+            .content_disposition()
+            .filter(|cd| cd.get_filename().is_none())
+            .and_then(|cd| cd.get_name())
+            .map(|name| name.to_string());
+        if let Some(name) = option_name {
+            let value = match read_value(&mut field, &mut limits).await? {
+                Ok(value) => value,
+                Err(too_large) => {
+                    remove_uploads(&files_to_process);
+                    return Ok(too_large.response());
+                }
+            };
+            if name == "storage_profile" {
+                match storage_profile_dir(&req, &config, &value) {
+                    Ok(dir) => options.work_dir = dir,
+                    Err(response) => {
+                        remove_uploads(&files_to_process);
+                        return Ok(response);
+                    }
+                }
+            } else if name == "prepare" {
+                match pipeline::parse_flag(&name, &value) {
+                    Ok(flag) => hold = flag,
+                    Err(error) => {
+                        remove_uploads(&files_to_process);
+                        return Ok(
+                            HttpResponse::BadRequest().json(ErrorResponse { error, code: None })
+                        );
+                    }
+                }
+            } else if name == "priority" {
+                match Priority::parse(&value) {
+                    Ok(level) => priority = level,
+                    Err(error) => {
+                        remove_uploads(&files_to_process);
+                        return Ok(
+                            HttpResponse::BadRequest().json(ErrorResponse { error, code: None })
+                        );
+                    }
+                }
+            } else if SessionMetadata::FIELDS.contains(&name.as_str()) {
+                if let Err(error) = metadata.set(&name, &value) {
+                    remove_uploads(&files_to_process);
+                    return Ok(HttpResponse::BadRequest().json(ErrorResponse { error, code: None }));
+                }
+            } else if let Err(error) = options.set(&config, &name, &value) {
+                remove_uploads(&files_to_process);
+                return Ok(HttpResponse::BadRequest().json(ErrorResponse { error, code: None }));
+            }
+            continue;
+        }
+
+        let filename = field
+            .content_disposition()
+            .and_then(|cd| cd.get_filename())
+            .unwrap_or("unnamed")
+            .to_string();
+
+        if !convert::is_supported(&filename) {
+            remove_uploads(&files_to_process);
+            return Ok(HttpResponse::UnsupportedMediaType().json(ErrorResponse {
+                error: format!(
+                    "{}: unsupported file type; upload one of {}",
+                    filename,
+                    convert::SUPPORTED_EXTENSIONS.join(", ")
+                ),
+                code: Some("UNSUPPORTED_FORMAT".to_string()),
+            }));
+        }
+
+        // Generate unique filename and save
+        let file_id = Uuid::new_v4();
+        let extension = filename.rsplit('.').next().unwrap_or("tmp");
+        let temp_path = options
+            .work_dir
+            .join(format!("ocr_{}.{}", file_id, extension));
+        if let Err(too_large) = save_file(&mut field, &temp_path, &filename, &mut limits).await? {
+            println!("❌ Refusing '{}': {}", filename, too_large.error);
+            remove_uploads(&files_to_process);
+            return Ok(too_large.response());
+        }
+
+        // A password field applies to the file that follows it
+        let password = options.password.take();
+        if extension.eq_ignore_ascii_case("pdf")
+            && let Err(rejection) = check_pdf(&temp_path, &config, password.as_deref()).await?
+        {
+            println!("❌ Refusing '{}': {}", filename, rejection.message());
+            let _ = std::fs::remove_file(&temp_path);
+            remove_uploads(&files_to_process);
+            return Ok(rejection_status(&rejection).json(ErrorResponse {
+                error: format!("{}: {}", filename, rejection.message()),
+                code: Some(rejection.code().to_string()),
+            }));
+        }
+
+        files_to_process.push(UploadedFile {
+            path: temp_path,
+            filename,
+            password,
+            page_order: None,
+            rotations: BTreeMap::new(),
+        });
+    }
+
+    Ok(submit(
+        jobs,
+        &config,
+        files_to_process,
+        options,
+        hold,
+        priority,
+        metadata,
+    )
+    .await)
+}
+
+#[derive(Deserialize, ToSchema)]
+struct UrlUploadRequest {
+    /// HTTP(S) links to the files, processed in this order.
+    urls: Vec<String>,
+    /// Processing options by form field name, as sent to `/upload`, e.g.
+    /// `{"pages": "1-20", "normalize": false}`.
+    #[serde(default)]
+    #[schema(value_type = Object)]
+    options: BTreeMap<String, serde_json::Value>,
+}
+
+/// A JSON option as the text a form field would carry; lists, such as `tags`,
+/// comma-separated.
+fn option_value(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(value) => value.clone(),
+        serde_json::Value::Array(values) => values
+            .iter()
+            .map(option_value)
+            .collect::<Vec<_>>()
+            .join(","),
+        value => value.to_string(),
+    }
+}
+
+fn fetch_status(error: &fetch::FetchError) -> actix_web::HttpResponseBuilder {
+    match error {
+        fetch::FetchError::InvalidUrl(_) => HttpResponse::BadRequest(),
+        fetch::FetchError::Failed(_) => HttpResponse::BadGateway(),
+        fetch::FetchError::TooLarge(_) => HttpResponse::PayloadTooLarge(),
+        fetch::FetchError::Unsupported(_) => HttpResponse::UnsupportedMediaType(),
+    }
+}
+
+/// Downloads the linked files, e.g. from archive.org, and processes them like an
+/// upload.
+#[utoipa::path(
+    tag = "ocr",
+    request_body = UrlUploadRequest,
+    responses(
+        (status = 200, description = "Processing started", body = UploadResponse),
+        (status = 400, description = "Invalid URL or option", body = ErrorResponse),
+        (status = 403, description = "Encrypted PDF without its password", body = ErrorResponse),
+        (status = 413, description = "File over the configured limits", body = ErrorResponse),
+        (status = 415, description = "Unsupported file type", body = ErrorResponse),
+        (status = 422, description = "Suspicious PDF", body = ErrorResponse),
+        (status = 429, description = "Too many requests; see Retry-After", body = ErrorResponse),
+        (status = 502, description = "A file could not be downloaded", body = ErrorResponse),
+        (status = 503, description = "Too little disk space or too long a queue", body = ErrorResponse)
+    )
+)]
+#[post("/upload-url", wrap = "from_fn(rate_limit::limit)")]
+async fn upload_url(
+    req: HttpRequest,
+    request: web::Json<UrlUploadRequest>,
+    config: web::Data<Config>,
+    jobs: web::Data<Jobs>,
+) -> Result<HttpResponse> {
+    let request = request.into_inner();
+    if request.urls.is_empty() {
+        return Ok(HttpResponse::BadRequest().json(ErrorResponse {
+            error: "No URLs given".to_string(),
+            code: None,
+        }));
+    }
+    if let Some(reason) = capacity::refusal(&jobs) {
+        return Ok(capacity::unavailable(&jobs, reason));
+    }
+
+    let mut options = ProcessingOptions::new(&config);
+    let mut hold = false;
+    let mut priority = Priority::default();
+    let mut metadata = SessionMetadata::default();
+    for (name, value) in &request.options {
+        let value = option_value(value);
+        if name == "storage_profile" {
+            match storage_profile_dir(&req, &config, &value) {
+                Ok(dir) => options.work_dir = dir,
+                Err(response) => return Ok(response),
+            }
+        } else if name == "prepare" {
+            match pipeline::parse_flag(name, &value) {
+                Ok(flag) => hold = flag,
+                Err(error) => {
+                    return Ok(HttpResponse::BadRequest().json(ErrorResponse { error, code: None }));
+                }
+            }
+        } else if name == "priority" {
+            match Priority::parse(&value) {
+                Ok(level) => priority = level,
+                Err(error) => {
+                    return Ok(HttpResponse::BadRequest().json(ErrorResponse { error, code: None }));
+                }
+            }
+        } else if SessionMetadata::FIELDS.contains(&name.as_str()) {
+            if let Err(error) = metadata.set(name, &value) {
+                return Ok(HttpResponse::BadRequest().json(ErrorResponse { error, code: None }));
+            }
+        } else if let Err(error) = options.set(&config, name, &value) {
+            return Ok(HttpResponse::BadRequest().json(ErrorResponse { error, code: None }));
+        }
+    }
+
+    // One password serves every linked PDF
+    let password = options.password.take();
+    let mut files_to_process = Vec::new();
+    for url in &request.urls {
+        println!("🌐 Downloading {}", url);
+        let download = match fetch::download(
+            &jobs.public_http,
+            url,
+            config.max_download_bytes,
+            &options.work_dir,
+        )
+        .await
+        {
+            Ok(download) => download,
+            Err(error) => {
+                println!("❌ Refusing '{}': {}", url, error.message());
+                remove_uploads(&files_to_process);
+                return Ok(fetch_status(&error).json(ErrorResponse {
+                    error: error.message().to_string(),
+                    code: error.code().map(str::to_string),
+                }));
+            }
+        };
+
+        if download.filename.to_lowercase().ends_with(".pdf")
+            && let Err(rejection) = check_pdf(&download.path, &config, password.as_deref()).await?
+        {
+            println!("❌ Refusing '{}': {}", url, rejection.message());
+            let _ = std::fs::remove_file(&download.path);
+            remove_uploads(&files_to_process);
+            return Ok(rejection_status(&rejection).json(ErrorResponse {
+                error: format!("{}: {}", download.filename, rejection.message()),
+                code: Some(rejection.code().to_string()),
+            }));
+        }
+
+        files_to_process.push(UploadedFile {
+            path: download.path,
+            filename: download.filename,
+            password: password.clone(),
+            page_order: None,
+            rotations: BTreeMap::new(),
+        });
+    }
+
+    Ok(submit(
+        jobs,
+        &config,
+        files_to_process,
+        options,
+        hold,
+        priority,
+        metadata,
+    )
+    .await)
+}
+
+/// Why a job with `options` cannot run on this server, if it cannot.
+fn unrunnable(config: &Config, options: &ProcessingOptions) -> Option<String> {
+    if options.morphology && config.morphology.command.is_none() && config.morphology.url.is_none()
+    {
+        return Some("No morphological analyzer is configured".to_string());
+    }
+    if config.script_model(options.script).is_none() {
+        return Some(format!(
+            "No tesseract model configured for {} script; add one under script_models",
+            options.script.name()
+        ));
+    }
+    None
+}
+
+/// Checks the options of a new session and starts it, or holds it for
+/// `POST /prepare` when `hold` is set.
+async fn submit(
+    jobs: web::Data<Jobs>,
+    config: &Config,
+    files_to_process: Vec<UploadedFile>,
+    options: ProcessingOptions,
+    hold: bool,
+    priority: Priority,
+    metadata: SessionMetadata,
+) -> HttpResponse {
+    if let Some(error) = unrunnable(config, &options) {
+        remove_uploads(&files_to_process);
+        return HttpResponse::BadRequest().json(ErrorResponse { error, code: None });
+    }
+
+    let session_id = Uuid::new_v4().to_string();
+    jobs.update(&session_id, |status| status.metadata = metadata);
+    #[cfg(feature = "s3")]
+    jobs.archive_uploads(&session_id, &files_to_process).await;
+
+    if hold {
+        jobs.update(&session_id, |status| {
+            status.queue(
+                AWAITING_PREPARATION,
+                format!(
+                    "Waiting for page order and rotations at {}/prepare/{}",
+                    API_PREFIX, session_id
+                ),
+                files_to_process.iter().map(|file| file.filename.as_str()),
+            )
+        });
+        let job = StoredJob {
+            state: JobState::Held,
+            files: files_to_process.iter().cloned().enumerate().collect(),
+            options: options.clone(),
+            priority,
+        };
+        jobs.persist(&session_id, move |store, id| store.save_job(id, &job));
+        jobs.held.write().insert(
+            session_id.clone(),
+            HeldJob {
+                files: files_to_process,
+                options,
+                priority,
+            },
+        );
+    } else {
+        jobs.start(session_id.clone(), files_to_process, options, priority);
+    }
+
+    // Return immediately with session_id
+    HttpResponse::Ok().json(UploadResponse {
+        session_id,
+        results: vec![], // Results will be available via status endpoint
+    })
+}
+
+#[derive(Deserialize, ToSchema)]
+struct PrepareRequest {
+    /// Page order and rotations per file, in upload order; files without an entry
+    /// are processed as uploaded.
+    #[serde(default)]
+    files: Vec<FilePreparation>,
+}
+
+#[derive(Deserialize, ToSchema)]
+struct FilePreparation {
+    /// Pages in the order to process them, e.g. `1-40,49-56,41-48,57-`.
+    page_order: Option<String>,
+    /// Degrees to turn pages clockwise, by page number.
+    #[serde(default)]
+    rotations: HashMap<usize, i32>,
+}
+
+impl FilePreparation {
+    fn parse(
+        &self,
+    ) -> std::result::Result<(Option<PageRanges>, BTreeMap<usize, Rotation>), String> {
+        let page_order = self
+            .page_order
+            .as_deref()
+            .filter(|order| !order.trim().is_empty())
+            .map(PageRanges::parse_order)
+            .transpose()?;
+        let mut rotations = BTreeMap::new();
+        for (&page, &degrees) in &self.rotations {
+            if degrees % 360 == 0 {
+                continue;
+            }
+            let rotation = Rotation::from_degrees(degrees).ok_or_else(|| {
+                format!(
+                    "Cannot turn page {} by {} degrees; use 90, 180 or 270",
+                    page, degrees
+                )
+            })?;
+            rotations.insert(page, rotation);
+        }
+        Ok((page_order, rotations))
+    }
+}
+
+/// Starts a session uploaded with `prepare=true`, first reordering and turning
+/// the pages of its files as requested.
+#[utoipa::path(
+    tag = "ocr",
+    params(("session_id" = String, Path, description = "Session id returned by /upload")),
+    request_body = PrepareRequest,
+    responses(
+        (status = 200, description = "Processing started", body = UploadResponse),
+        (status = 400, body = ErrorResponse),
+        (status = 404, description = "Session not held for preparation", body = ErrorResponse)
+    )
+)]
+#[post("/prepare/{session_id}")]
+async fn prepare(
+    path: web::Path<String>,
+    request: web::Json<PrepareRequest>,
+    jobs: web::Data<Jobs>,
+) -> Result<HttpResponse> {
+    let session_id = path.into_inner();
+    let mut job = match jobs.held.write().remove(&session_id) {
+        Some(job) => job,
+        None => {
+            return Ok(HttpResponse::NotFound().json(ErrorResponse {
+                error: format!("Session '{}' is not waiting for preparation", session_id),
+                code: None,
+            }));
+        }
+    };
+
+    let prepared = if request.files.len() > job.files.len() {
+        Err(format!(
+            "{} files described but only {} uploaded",
+            request.files.len(),
+            job.files.len()
+        ))
+    } else {
+        request
+            .files
+            .iter()
+            .zip(&job.files)
+            .map(|(preparation, file)| {
+                preparation
+                    .parse()
+                    .map_err(|e| format!("{}: {}", file.filename, e))
+            })
+            .collect::<std::result::Result<Vec<_>, String>>()
+    };
+    let prepared = match prepared {
+        Ok(prepared) => prepared,
+        Err(error) => {
+            // Keep the upload so the request can be corrected
+            jobs.held.write().insert(session_id, job);
+            return Ok(HttpResponse::BadRequest().json(ErrorResponse { error, code: None }));
+        }
+    };
+
+    for (file, (page_order, rotations)) in job.files.iter_mut().zip(prepared) {
+        file.page_order = page_order;
+        file.rotations = rotations;
+    }
+    jobs.start(session_id.clone(), job.files, job.options, job.priority);
+
+    Ok(HttpResponse::Ok().json(UploadResponse {
+        session_id,
+        results: vec![],
+    }))
+}
+
+/// Stops a job: a running one after the page it is on, killing any tool still
+/// working on it, and keeping the pages read so far; a held one at once, dropping
+/// its uploads. Answers with the session's status.
+#[utoipa::path(
+    tag = "sessions",
+    params(("session_id" = String, Path, description = "Session id returned by /upload")),
+    responses(
+        (status = 200, description = "Held or queued session dropped", body = ProgressStatus),
+        (status = 202, description = "Running job stopping after its current page", body = ProgressStatus),
+        (status = 404, body = ErrorResponse)
+    )
+)]
+#[post("/cancel/{session_id}")]
+async fn cancel(path: web::Path<String>, jobs: web::Data<Jobs>) -> Result<HttpResponse> {
+    let session_id = path.into_inner();
+
+    if let Some(job) = jobs.held.write().remove(&session_id) {
+        remove_uploads(&job.files);
+        jobs.persist(&session_id, |store, id| store.remove_job(id));
+        jobs.update(&session_id, |status| {
+            status.stage = CANCELLED.to_string();
+            status.message = "Processing cancelled".to_string();
+            status.complete = true;
+        });
+        let status = jobs.tracker.read().get(&session_id).cloned();
+        return Ok(HttpResponse::Ok().json(status));
+    }
+
+    if let Some(job) = jobs.dequeue(&session_id) {
+        remove_uploads(&job.files);
+        jobs.running.write().remove(&session_id);
+        jobs.persist(&session_id, |store, id| store.remove_job(id));
+        jobs.update(&session_id, |status| {
+            status.queue_position = None;
+            status.stage = CANCELLED.to_string();
+            status.message = "Processing cancelled".to_string();
+            status.complete = true;
+        });
+        println!("🛑 Cancelled queued session {}", session_id);
+        let status = jobs.tracker.read().get(&session_id).cloned();
+        return Ok(HttpResponse::Ok().json(status));
+    }
+
+    match jobs.running.read().get(&session_id) {
+        Some(flag) => flag.cancel(),
+        None => {
+            return Ok(HttpResponse::NotFound().json(ErrorResponse {
+                error: format!("Session '{}' is not being processed", session_id),
+                code: None,
+            }));
+        }
+    }
+    println!("🛑 Cancelling session {}", session_id);
+    let status = jobs.tracker.read().get(&session_id).cloned();
+    Ok(HttpResponse::Accepted().json(status))
+}
+
+#[derive(Deserialize, Default, ToSchema)]
+struct RetryRequest {
+    /// Processing options to change for the retry, by form field name, e.g.
+    /// `{"segment_lines": true, "skip_blank_pages": false}`.
+    #[serde(default)]
+    #[schema(value_type = Object)]
+    options: BTreeMap<String, serde_json::Value>,
+}
+
+/// Reads the pages of a finished session that failed or came out empty again,
+/// optionally with other settings, and merges the new pages into its results.
+#[utoipa::path(
+    tag = "sessions",
+    params(("session_id" = String, Path, description = "Session id returned by /upload")),
+    request_body(content = Option<RetryRequest>),
+    responses(
+        (status = 200, description = "Retry queued", body = ProgressStatus),
+        (status = 400, description = "Invalid option", body = ErrorResponse),
+        (status = 404, body = ErrorResponse),
+        (status = 409, description = "Session still processing, or nothing to retry", body = ErrorResponse)
+    )
+)]
+#[post("/sessions/{session_id}/retry")]
+async fn retry(
+    path: web::Path<String>,
+    request: Option<web::Json<RetryRequest>>,
+    config: web::Data<Config>,
+    jobs: web::Data<Jobs>,
+) -> Result<HttpResponse> {
+    let session_id = path.into_inner();
+    let conflict =
+        |error: String| HttpResponse::Conflict().json(ErrorResponse { error, code: None });
+    let Some(results) = jobs
+        .tracker
+        .read()
+        .get(&session_id)
+        .map(|status| status.results.clone())
+    else {
+        return Ok(unknown_session(&session_id));
+    };
+    if jobs.running.read().contains_key(&session_id) || jobs.held.read().contains_key(&session_id) {
+        return Ok(conflict(format!(
+            "Session '{}' has not finished processing",
+            session_id
+        )));
+    }
+    let Some(retained) = jobs.retained.write().remove(&session_id) else {
+        return Ok(conflict(format!(
+            "Session '{}' has no failed or empty pages to retry",
+            session_id
+        )));
+    };
+
+    let mut options = retained.options.clone();
+    let mut priority = Priority::default();
+    let request = request.map(web::Json::into_inner).unwrap_or_default();
+    for (name, value) in &request.options {
+        let value = option_value(value);
+        let set = if name == "priority" {
+            Priority::parse(&value).map(|level| priority = level)
+        } else {
+            options.set(&config, name, &value)
+        };
+        if let Err(error) = set {
+            jobs.retained.write().insert(session_id, retained);
+            return Ok(HttpResponse::BadRequest().json(ErrorResponse { error, code: None }));
+        }
+    }
+    // The searchable PDF stays the one of the first run
+    options.searchable_pdf = false;
+
+    let mut files = retained.files;
+    for (index, file) in &mut files {
+        let Some(result) = results.get(*index).filter(|result| result.success) else {
+            continue;
+        };
+        let mut pages: Vec<usize> = result
+            .pages
+            .iter()
+            .filter(|page| page.needs_retry())
+            .map(|page| page.source_page)
+            .collect();
+        pages.dedup();
+        let spec = pages
+            .iter()
+            .map(usize::to_string)
+            .collect::<Vec<_>>()
+            .join(",");
+        if pipeline::is_paged(&file.path)
+            && let Ok(selection) = PageRanges::parse_order(&spec)
+        {
+            file.page_order = Some(selection);
+        }
+    }
+    println!(
+        "🔁 Retrying {} files of session {}",
+        files.len(),
+        session_id
+    );
+    jobs.retry(session_id.clone(), files, options, priority);
+
+    let status = jobs.tracker.read().get(&session_id).cloned();
+    Ok(HttpResponse::Ok().json(status))
+}
+
+/// Forgets a session and removes what it left on disk: its status and results,
+/// held uploads, searchable PDFs and downloads, and the chunks of a `/split`
+/// with that id.
+#[utoipa::path(
+    tag = "sessions",
+    params(("session_id" = String, Path, description = "Session id from /upload or /split")),
+    responses(
+        (status = 204, description = "Session and its files removed"),
+        (status = 404, body = ErrorResponse),
+        (status = 409, description = "Session still processing", body = ErrorResponse)
+    )
+)]
+#[delete("/sessions/{session_id}")]
+async fn delete_session(path: web::Path<String>, jobs: web::Data<Jobs>) -> Result<HttpResponse> {
+    let session_id = path.into_inner();
+    let not_found = || {
+        HttpResponse::NotFound().json(ErrorResponse {
+            error: format!("No session '{}'", session_id),
+            code: None,
+        })
+    };
+    // Session ids name directories; anything but a UUID is not one of ours
+    if Uuid::parse_str(&session_id).is_err() {
+        return Ok(not_found());
+    }
+    if jobs.running.read().contains_key(&session_id) {
+        return Ok(HttpResponse::Conflict().json(ErrorResponse {
+            error: format!(
+                "Session '{}' is still being processed; cancel it first",
+                session_id
+            ),
+            code: None,
+        }));
+    }
+
+    if jobs
+        .clone()
+        .into_inner()
+        .forget(&session_id)
+        .await?
+        .is_none()
+    {
+        return Ok(not_found());
+    }
+    println!("🗑  Deleted session {}", session_id);
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Queries the configured HTTP morphological analyzer for every page of a file.
+async fn analyze_morphology(client: &reqwest::Client, url: &str, result: &mut OcrResult) {
+    for page in &mut result.pages {
+        let words = morphology::words(page.morphology_source());
+        if words.is_empty() {
+            continue;
+        }
+        match query_analyzer(client, url, words).await {
+            Ok(analyses) => page.morphology = Some(analyses),
+            Err(e) => println!(
+                "  ⚠️  Warning: Morphological analysis of page {} failed: {}",
+                page.page_number, e
+            ),
+        }
+    }
+}
+
+async fn query_analyzer(
+    client: &reqwest::Client,
+    url: &str,
+    words: Vec<String>,
+) -> std::result::Result<Vec<morphology::WordAnalysis>, String> {
+    let body = serde_json::to_string(&morphology::ServiceRequest { words: &words })
+        .map_err(|e| e.to_string())?;
+    let response = client
+        .post(url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("analyzer answered {}", response.status()));
+    }
+    let text = response.text().await.map_err(|e| e.to_string())?;
+    let parsed: morphology::ServiceResponse =
+        serde_json::from_str(&text).map_err(|e| format!("invalid response: {}", e))?;
+    morphology::combine(words, parsed.analyses)
+}
+
+#[derive(Deserialize, ToSchema)]
+struct SuggestRequest {
+    text: String,
+    /// Defaults to `correction_max_distance`; capped at `MAX_SUGGEST_DISTANCE`.
+    max_distance: Option<usize>,
+    /// Candidates per word, 5 by default.
+    limit: Option<usize>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct SuggestResponse {
+    words: Vec<dictionary::Suggestion>,
 }
 
-#[get("/status/{session_id}")]
-async fn get_status(
-    path: web::Path<String>,
-    tracker: web::Data<ProgressTracker>,
+/// Dictionary lookups grow quickly with the edit distance.
+const MAX_SUGGEST_DISTANCE: usize = 3;
+
+#[utoipa::path(
+    tag = "text",
+    request_body = SuggestRequest,
+    responses(
+        (status = 200, body = SuggestResponse),
+        (status = 503, description = "No dictionary configured", body = ErrorResponse)
+    )
+)]
+#[post("/suggest")]
+async fn suggest(
+    body: web::Json<SuggestRequest>,
+    config: web::Data<Config>,
+    dictionary: web::Data<Dictionary>,
 ) -> Result<HttpResponse> {
-    let session_id = path.into_inner();
-    let status = tracker.read().get(&session_id).cloned();
+    if dictionary.is_empty() {
+        return Ok(HttpResponse::ServiceUnavailable().json(ErrorResponse {
+            error: "No dictionary is configured; set dictionary_path".to_string(),
+            code: None,
+        }));
+    }
 
-    Ok(HttpResponse::Ok().json(status))
+    let max_distance = body
+        .max_distance
+        .unwrap_or(config.correction_max_distance)
+        .min(MAX_SUGGEST_DISTANCE);
+    let words = dictionary::suggest(
+        &body.text,
+        &dictionary,
+        max_distance,
+        body.limit.unwrap_or(5),
+    );
+    Ok(HttpResponse::Ok().json(SuggestResponse { words }))
 }
 
-#[post("/upload")]
-async fn upload(
+#[derive(Serialize, ToSchema)]
+struct InspectResponse {
+    filename: String,
+    #[serde(flatten)]
+    details: pdf::PdfDetails,
+}
+
+/// Reports a PDF's metadata, page sizes and which pages carry text, without
+/// recognizing anything. Takes the file and, for encrypted PDFs, a `password`.
+#[utoipa::path(
+    tag = "pdf",
+    request_body(content = openapi::PdfForm, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, body = InspectResponse),
+        (status = 400, body = ErrorResponse),
+        (status = 403, description = "Encrypted PDF without its password", body = ErrorResponse),
+        (status = 413, description = "Upload over the configured limits", body = ErrorResponse)
+    )
+)]
+#[post("/inspect")]
+async fn inspect_pdf(
+    req: HttpRequest,
     mut payload: Multipart,
-    tracker: web::Data<ProgressTracker>,
+    config: web::Data<Config>,
 ) -> Result<HttpResponse> {
-    let session_id = Uuid::new_v4().to_string();
-    let temp_dir = std::env::temp_dir();
-
-    // Collect files first
-    let mut files_to_process = Vec::new();
+    let mut password = None;
+    let mut saved = None;
+    let mut limits = match UploadLimits::new(&req, &config) {
+        Ok(limits) => limits,
+        Err(too_large) => return Ok(too_large.response()),
+    };
 
     while let Some(item) = payload.next().await {
         let mut field = item?;
-
         let filename = field
             .content_disposition()
             .and_then(|cd| cd.get_filename())
-            .unwrap_or("unnamed")
-            .to_string();
-
-        // Validate file extension
-        let is_valid = filename.to_lowercase().ends_with(".pdf")
-            || filename.to_lowercase().ends_with(".png")
-            || filename.to_lowercase().ends_with(".jpg")
-            || filename.to_lowercase().ends_with(".jpeg");
-
-        if !is_valid {
+            .map(|name| name.to_string());
+        let Some(filename) = filename else {
+            let is_password = field
+                .content_disposition()
+                .and_then(|cd| cd.get_name())
+                .is_some_and(|name| name == "password");
+            let value = match read_value(&mut field, &mut limits).await? {
+                Ok(value) => value,
+                Err(too_large) => {
+                    if let Some((path, _)) = &saved {
+                        let _ = std::fs::remove_file(path);
+                    }
+                    return Ok(too_large.response());
+                }
+            };
+            if is_password {
+                password = Some(value).filter(|v| !v.is_empty());
+            }
+            continue;
+        };
+        if saved.is_some() {
+            // Only the first file is inspected
             continue;
         }
-
-        // Generate unique filename and save
-        let file_id = Uuid::new_v4();
-        let extension = filename.split('.').last().unwrap_or("tmp");
-        let temp_path = temp_dir.join(format!("ocr_{}.{}", file_id, extension));
-
-        let mut file = std::fs::File::create(&temp_path)?;
-        while let Some(chunk) = field.next().await {
-            let data = chunk?;
-            file.write_all(&data)?;
+        if !filename.to_lowercase().ends_with(".pdf") {
+            return Ok(HttpResponse::BadRequest().json(ErrorResponse {
+                error: format!("{}: only PDF files can be inspected", filename),
+                code: None,
+            }));
         }
-        file.flush()?;
-
-        files_to_process.push((temp_path, filename));
-    }
 
-    // Spawn background task to process files
-    let session_id_clone = session_id.clone();
-    let tracker_clone = tracker.get_ref().clone();
-
-    tokio::spawn(async move {
-        let mut results = Vec::new();
-
-        for (temp_path, filename) in files_to_process {
-            let ocr_result =
-                process_with_tesseract(&temp_path, &filename, &session_id_clone, &tracker_clone)
-                    .await;
-            results.push(ocr_result);
-            let _ = std::fs::remove_file(&temp_path);
+        let path = config
+            .temp_dir()
+            .join(format!("inspect_{}.pdf", Uuid::new_v4()));
+        if let Err(too_large) = save_file(&mut field, &path, &filename, &mut limits).await? {
+            return Ok(too_large.response());
         }
+        saved = Some((path, filename));
+    }
 
-        // Mark as complete with results
-        tracker_clone.write().insert(
-            session_id_clone.clone(),
-            ProgressStatus {
-                stage: "Complete".to_string(),
-                current: results.len(),
-                total: results.len(),
-                message: "Processing complete".to_string(),
-                complete: true,
-                results: results.clone(),
-            },
-        );
-    });
+    let Some((path, filename)) = saved else {
+        return Ok(HttpResponse::BadRequest().json(ErrorResponse {
+            error: "No file uploaded".to_string(),
+            code: None,
+        }));
+    };
 
-    // Return immediately with session_id
-    Ok(HttpResponse::Ok().json(UploadResponse {
-        session_id,
-        results: vec![], // Results will be available via status endpoint
-    }))
+    let response = match check_pdf(&path, &config, password.as_deref()).await? {
+        Err(rejection) => rejection_status(&rejection).json(ErrorResponse {
+            error: format!("{}: {}", filename, rejection.message()),
+            code: Some(rejection.code().to_string()),
+        }),
+        Ok(_) => match pdf::pdfium(&config)
+            .and_then(|pdfium| pdf::details(&path, password.as_deref(), pdfium))
+        {
+            Ok(details) => HttpResponse::Ok().json(InspectResponse { filename, details }),
+            Err(e) => HttpResponse::UnprocessableEntity().json(ErrorResponse {
+                error: format!("{}: {}", filename, e),
+                code: None,
+            }),
+        },
+    };
+    let _ = std::fs::remove_file(&path);
+    Ok(response)
 }
 
-async fn process_with_tesseract(
-    file_path: &std::path::Path,
-    original_filename: &str,
-    session_id: &str,
-    tracker: &ProgressTracker,
-) -> OcrResult {
-    // Check if the file is a PDF
-    let is_pdf = file_path
-        .extension()
-        .and_then(|s| s.to_str())
-        .map(|s| s.to_lowercase() == "pdf")
-        .unwrap_or(false);
-
-    // If it's a PDF, convert to images first (ALL pages)
-    let image_paths = if is_pdf {
-        // Initial status - we don't know page count yet
-        tracker.write().insert(
-            session_id.to_string(),
-            ProgressStatus {
-                stage: "Converting PDF".to_string(),
-                current: 0,
-                total: 0,
-                message: format!("Converting PDF '{}'...", original_filename),
-                complete: false,
-                results: vec![],
-            },
-        );
-
-        let temp_dir = std::env::temp_dir();
-        let output_base = temp_dir.join(format!("pdf_convert_{}", Uuid::new_v4()));
-        let output_prefix = output_base.to_str().unwrap();
-
-        println!("Converting PDF '{}' to images...", original_filename);
-
-        let convert_result = Command::new("pdftoppm")
-            .arg("-png")
-            .arg(file_path)
-            .arg(output_prefix)
-            .output();
-
-        match convert_result {
-            Ok(result) => {
-                if result.status.success() {
-                    // pdftoppm has inconsistent padding - check both formats
-                    let mut pages = Vec::new();
-                    let mut page_num = 1;
-                    loop {
-                        // Try 3-digit format first (001, 002, etc)
-                        let mut png_path = format!("{}-{:03}.png", output_prefix, page_num);
-                        if !std::path::Path::new(&png_path).exists() {
-                            // Try 2-digit format (01, 02, etc)
-                            png_path = format!("{}-{:02}.png", output_prefix, page_num);
-                            if !std::path::Path::new(&png_path).exists() {
-                                break;
-                            }
-                        }
-                        pages.push(png_path);
-                        page_num += 1;
-                    }
-
-                    if pages.is_empty() {
-                        return OcrResult {
-                            filename: original_filename.to_string(),
-                            text: String::new(),
-                            success: false,
-                            error: Some(
-                                "PDF conversion failed: no output files created".to_string(),
-                            ),
-                            pages_processed: None,
-                            total_pages: None,
-                            estimated_time_seconds: None,
-                        };
-                    }
-
-                    println!("Converted {} pages from PDF", pages.len());
-
-                    // Update progress with actual page count
-                    tracker.write().insert(
-                        session_id.to_string(),
-                        ProgressStatus {
-                            stage: "PDF Converted".to_string(),
-                            current: pages.len(),
-                            total: pages.len(),
-                            message: format!("Converted {} pages, starting OCR...", pages.len()),
-                            complete: false,
-                            results: vec![],
-                        },
-                    );
+/// Reads a few pages of an uploaded document under every combination of the
+/// resolutions (`dpi`, for PDFs), page segmentation modes (`psm`) and
+/// `preprocessing` listed, and reports the time and confidence of each, to pick
+/// settings before a long run. Takes one file of any supported type; `sample`
+/// sets how many pages are read.
+#[utoipa::path(
+    tag = "ocr",
+    request_body(content = openapi::BenchmarkForm, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, body = benchmark::Report),
+        (status = 400, body = ErrorResponse),
+        (status = 403, description = "Encrypted PDF without its password", body = ErrorResponse),
+        (status = 413, description = "Upload over the configured limits", body = ErrorResponse),
+        (status = 415, description = "Unsupported file type", body = ErrorResponse),
+        (status = 422, description = "The document could not be read", body = ErrorResponse),
+        (status = 429, description = "Too many requests; see Retry-After", body = ErrorResponse)
+    )
+)]
+#[post("/benchmark", wrap = "from_fn(rate_limit::limit)")]
+async fn run_benchmark(
+    req: HttpRequest,
+    mut payload: Multipart,
+    config: web::Data<Config>,
+) -> Result<HttpResponse> {
+    let mut plan = benchmark::Plan::default();
+    let mut saved = None;
+    let mut limits = match UploadLimits::new(&req, &config) {
+        Ok(limits) => limits,
+        Err(too_large) => return Ok(too_large.response()),
+    };
+    let remove_saved = |saved: &Option<(std::path::PathBuf, String)>| {
+        if let Some((path, _)) = saved {
+            let _ = std::fs::remove_file(path);
+        }
+    };
 
-                    Some(pages)
-                } else {
-                    let stderr = String::from_utf8_lossy(&result.stderr);
-                    return OcrResult {
-                        filename: original_filename.to_string(),
-                        text: String::new(),
-                        success: false,
-                        error: Some(format!(
-                            "PDF conversion error: {}. Make sure poppler-utils is installed.",
-                            stderr
-                        )),
-                        pages_processed: None,
-                        total_pages: None,
-                        estimated_time_seconds: None,
-                    };
+    while let Some(item) = payload.next().await {
+        let mut field = item?;
+        let filename = field
+            .content_disposition()
+            .and_then(|cd| cd.get_filename())
+            .map(|name| name.to_string());
+        let Some(filename) = filename else {
+            let name = field
+                .content_disposition()
+                .and_then(|cd| cd.get_name())
+                .unwrap_or_default()
+                .to_string();
+            let value = match read_value(&mut field, &mut limits).await? {
+                Ok(value) => value,
+                Err(too_large) => {
+                    remove_saved(&saved);
+                    return Ok(too_large.response());
                 }
+            };
+            if let Err(error) = plan.set(&name, &value) {
+                remove_saved(&saved);
+                return Ok(HttpResponse::BadRequest().json(ErrorResponse { error, code: None }));
             }
-            Err(e) => {
-                return OcrResult {
-                    filename: original_filename.to_string(),
-                    text: String::new(),
-                    success: false,
-                    error: Some(format!(
-                        "Failed to execute pdftoppm: {}. Install poppler-utils package.",
-                        e
-                    )),
-                    pages_processed: None,
-                    total_pages: None,
-                    estimated_time_seconds: None,
-                };
-            }
+            continue;
+        };
+        if saved.is_some() {
+            // Only the first file is benchmarked
+            continue;
+        }
+        if !convert::is_supported(&filename) {
+            return Ok(HttpResponse::UnsupportedMediaType().json(ErrorResponse {
+                error: format!(
+                    "{}: unsupported file type; upload one of {}",
+                    filename,
+                    convert::SUPPORTED_EXTENSIONS.join(", ")
+                ),
+                code: Some("UNSUPPORTED_FORMAT".to_string()),
+            }));
         }
-    } else {
-        None
-    };
 
-    // Process pages or single image
-    let mut all_text = String::new();
+        let extension = filename.rsplit('.').next().unwrap_or("tmp");
+        let path = config
+            .temp_dir()
+            .join(format!("benchmark_{}.{}", Uuid::new_v4(), extension));
+        if let Err(too_large) = save_file(&mut field, &path, &filename, &mut limits).await? {
+            return Ok(too_large.response());
+        }
+        saved = Some((path, filename));
+    }
 
-    if let Some(ref pages) = image_paths {
-        // Process multiple pages from PDF with time estimation
-        let total_pages = pages.len();
-        println!(
-            "Processing {} pages with Tesseract OCR (Sanskrit)...",
-            total_pages
-        );
+    let Some((path, filename)) = saved else {
+        return Ok(HttpResponse::BadRequest().json(ErrorResponse {
+            error: "No file uploaded".to_string(),
+            code: None,
+        }));
+    };
+    let is_pdf = filename.to_lowercase().ends_with(".pdf");
+    if let Err(error) = plan.settings(is_pdf) {
+        let _ = std::fs::remove_file(&path);
+        return Ok(HttpResponse::BadRequest().json(ErrorResponse { error, code: None }));
+    }
+    if is_pdf && let Err(rejection) = check_pdf(&path, &config, plan.password.as_deref()).await? {
+        let _ = std::fs::remove_file(&path);
+        return Ok(rejection_status(&rejection).json(ErrorResponse {
+            error: format!("{}: {}", filename, rejection.message()),
+            code: Some(rejection.code().to_string()),
+        }));
+    }
 
-        let mut estimated_time: Option<f64> = None;
-        let start_time = std::time::Instant::now();
-
-        for (idx, page_path) in pages.iter().enumerate() {
-            let _page_start = std::time::Instant::now();
-
-            // Update progress
-            tracker.write().insert(
-                session_id.to_string(),
-                ProgressStatus {
-                    stage: "OCR Processing".to_string(),
-                    current: idx + 1,
-                    total: total_pages,
-                    message: format!("Processing page {}/{}", idx + 1, total_pages),
-                    complete: false,
-                    results: vec![],
-                },
-            );
+    println!("📊 Benchmarking '{}'", filename);
+    let measured = {
+        let path = path.clone();
+        let config = config.clone();
+        web::block(move || {
+            benchmark::run(
+                &path,
+                &plan,
+                &config,
+                &config.temp_dir(),
+                &Cancel::default(),
+            )
+        })
+        .await
+    };
+    let _ = std::fs::remove_file(&path);
+    Ok(match measured? {
+        Ok(report) => HttpResponse::Ok().json(report),
+        Err(e) => HttpResponse::UnprocessableEntity().json(ErrorResponse {
+            error: format!("{}: {}", filename, e),
+            code: None,
+        }),
+    })
+}
 
-            // After first page, calculate estimated remaining time
-            if idx == 1 && estimated_time.is_none() {
-                let first_page_time = start_time.elapsed().as_secs_f64();
-                let remaining_pages = total_pages - 1;
-                let estimated_total = first_page_time * total_pages as f64;
-                estimated_time = Some(estimated_total);
+/// Size `/split` aims for per chunk when the request sets none.
+const DEFAULT_CHUNK_KB: f64 = 500.0;
 
-                println!("  ⏱  First page took {:.1}s", first_page_time);
-                println!(
-                    "  📊 Estimated total time: {:.1}s ({:.1} minutes)",
-                    estimated_total,
-                    estimated_total / 60.0
-                );
-                println!(
-                    "  📈 Estimated completion: ~{} remaining pages",
-                    remaining_pages
-                );
-            }
+#[utoipa::path(
+    tag = "pdf",
+    request_body(content = openapi::SplitForm, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, body = SplitResponse),
+        (status = 400, body = SplitResponse),
+        (status = 413, description = "Upload over the configured limits, or the upload and chunks over the session's disk quota", body = SplitResponse),
+        (status = 429, description = "Too many requests; see Retry-After", body = ErrorResponse),
+        (status = 503, description = "Too little disk space or too long a queue; see Retry-After", body = SplitResponse)
+    )
+)]
+#[post("/split", wrap = "from_fn(rate_limit::limit)")]
+async fn split_pdf(
+    req: HttpRequest,
+    mut payload: Multipart,
+    config: web::Data<Config>,
+    jobs: web::Data<Jobs>,
+) -> Result<HttpResponse> {
+    // Splitting takes the disk and CPU the queued sessions are waiting for
+    if let Some(reason) = capacity::refusal(&jobs) {
+        let mut response = SplitResponse::failed(String::new(), &reason);
+        response.error_code = Some("OVER_CAPACITY".to_string());
+        return Ok(HttpResponse::ServiceUnavailable()
+            .insert_header((
+                header::RETRY_AFTER,
+                capacity::retry_after(&jobs).to_string(),
+            ))
+            .json(response));
+    }
+    let too_large = |filename: Option<String>, too_large: TooLarge| {
+        let mut response = SplitResponse::failed(filename.unwrap_or_default(), &too_large.error);
+        response.error_code = Some(too_large.code.to_string());
+        HttpResponse::PayloadTooLarge().json(response)
+    };
+    let mut limits = match UploadLimits::for_session(&req, &config) {
+        Ok(limits) => limits,
+        Err(error) => return Ok(too_large(None, error)),
+    };
+    let splits_dir = config.splits_dir();
+    std::fs::create_dir_all(&splits_dir)?;
 
-            let progress_percent = (idx + 1) as f64 / total_pages as f64 * 100.0;
-            println!(
-                "  [{:.1}%] Processing page {}/{}...",
-                progress_percent,
-                idx + 1,
-                total_pages
-            );
+    let file_id = Uuid::new_v4();
+    let split_session_dir = splits_dir.join(file_id.to_string());
+    let input_path = split_session_dir.join("original.pdf");
+    let mut filename = None;
+    let mut pages_per_chunk = None;
+    let mut max_chunk_mb = None;
 
-            let temp_dir = std::env::temp_dir();
-            let output_base = temp_dir.join(format!("ocr_output_{}", Uuid::new_v4()));
-            let output_path = format!("{}", output_base.display());
-
-            let output = Command::new("tesseract")
-                .arg(page_path)
-                .arg(&output_path)
-                .arg("-l")
-                .arg("san")
-                .output();
-
-            match output {
-                Ok(result) => {
-                    if result.status.success() {
-                        let txt_file = format!("{}.txt", output_path);
-                        if let Ok(text) = std::fs::read_to_string(&txt_file) {
-                            if !text.trim().is_empty() {
-                                all_text.push_str(&format!("\n━━━ Page {} ━━━\n", idx + 1));
-                                all_text.push_str(&text);
-                            }
-                            let _ = std::fs::remove_file(&txt_file);
-                        }
-                    }
+    while let Some(item) = payload.next().await {
+        let mut field = item?;
+
+        // Plain form fields (no filename) carry the chunk size
+        let option_name = field
+            .content_disposition()
+            .filter(|cd| cd.get_filename().is_none())
+            .and_then(|cd| cd.get_name())
+            .map(|name| name.to_string());
+        if let Some(name) = option_name {
+            let value = match read_value(&mut field, &mut limits).await? {
+                Ok(value) => value,
+                Err(error) => {
+                    let _ = std::fs::remove_dir_all(&split_session_dir);
+                    return Ok(too_large(filename, error));
                 }
-                Err(_) => {
-                    println!("  ⚠️  Warning: Failed to OCR page {}", idx + 1);
+            };
+            let valid = match name.as_str() {
+                "pages_per_chunk" => {
+                    pages_per_chunk = value.trim().parse::<usize>().ok().filter(|&p| p > 0);
+                    pages_per_chunk.is_some()
                 }
+                "max_chunk_mb" => {
+                    max_chunk_mb = value
+                        .trim()
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|&mb| mb > 0.0 && mb.is_finite());
+                    max_chunk_mb.is_some()
+                }
+                _ => true,
+            };
+            if !valid {
+                let _ = std::fs::remove_dir_all(&split_session_dir);
+                return Ok(HttpResponse::BadRequest().json(SplitResponse::failed(
+                    filename.unwrap_or_default(),
+                    &format!(
+                        "Invalid value '{}' for '{}': expected a positive number",
+                        value, name
+                    ),
+                )));
             }
-
-            if idx > 0 && idx % 10 == 0 {
-                let elapsed = start_time.elapsed().as_secs_f64();
-                let avg_time_per_page = elapsed / (idx + 1) as f64;
-                let remaining = (total_pages - idx - 1) as f64 * avg_time_per_page;
-                println!(
-                    "  ⏰ Avg: {:.1}s/page | Remaining: ~{:.1}s ({:.1} min)",
-                    avg_time_per_page,
-                    remaining,
-                    remaining / 60.0
-                );
-            }
+            continue;
         }
 
-        // Clean up all converted images
-        for page_path in pages {
-            let _ = std::fs::remove_file(page_path);
+        if filename.is_some() {
+            // Only the first file is split
+            continue;
         }
+        let name = field
+            .content_disposition()
+            .and_then(|cd| cd.get_filename())
+            .unwrap_or("unnamed.pdf")
+            .to_string();
 
-        let total_time = start_time.elapsed().as_secs_f64();
-        println!(
-            "✅ OCR completed for '{}': {} total characters in {:.1}s ({:.1} min)",
-            original_filename,
-            all_text.len(),
-            total_time,
-            total_time / 60.0
-        );
-
-        OcrResult {
-            filename: original_filename.to_string(),
-            text: all_text.trim().to_string(),
-            success: true,
-            error: None,
-            pages_processed: Some(total_pages),
-            total_pages: Some(total_pages),
-            estimated_time_seconds: Some(total_time),
+        // Validate PDF
+        if !name.to_lowercase().ends_with(".pdf") {
+            let _ = std::fs::remove_dir_all(&split_session_dir);
+            return Ok(HttpResponse::BadRequest().json(SplitResponse::failed(
+                name,
+                "Only PDF files are supported for splitting",
+            )));
         }
-    } else {
-        // Process single image file
-        let assets_dir = std::path::PathBuf::from("./assets/conversions");
-        let output_base = assets_dir.join(format!("ocr_output_{}", Uuid::new_v4()));
-        let output_path = format!("{}", output_base.display());
-
-        let start_time = std::time::Instant::now();
-
-        let output = Command::new("tesseract")
-            .arg(file_path.to_str().unwrap())
-            .arg(&output_path)
-            .arg("-l")
-            .arg("san")
-            .output();
-
-        match output {
-            Ok(result) => {
-                if result.status.success() {
-                    let txt_file = format!("{}.txt", output_path);
-                    match std::fs::read_to_string(&txt_file) {
-                        Ok(text) => {
-                            let _ = std::fs::remove_file(&txt_file);
-                            let processing_time = start_time.elapsed().as_secs_f64();
-                            println!(
-                                "OCR Success for '{}': {} chars extracted in {:.1}s",
-                                original_filename,
-                                text.len(),
-                                processing_time
-                            );
-                            if text.is_empty() {
-                                println!("  WARNING: Empty text extracted!");
-                            }
-
-                            OcrResult {
-                                filename: original_filename.to_string(),
-                                text: text.trim().to_string(),
-                                success: true,
-                                error: None,
-                                pages_processed: Some(1),
-                                total_pages: Some(1),
-                                estimated_time_seconds: Some(processing_time),
-                            }
-                        }
-                        Err(e) => OcrResult {
-                            filename: original_filename.to_string(),
-                            text: String::new(),
-                            success: false,
-                            error: Some(format!("Failed to read OCR output: {}", e)),
-                            pages_processed: None,
-                            total_pages: None,
-                            estimated_time_seconds: None,
-                        },
-                    }
-                } else {
-                    let stderr = String::from_utf8_lossy(&result.stderr);
-                    OcrResult {
-                        filename: original_filename.to_string(),
-                        text: String::new(),
-                        success: false,
-                        error: Some(format!("Tesseract error: {}", stderr)),
-                        pages_processed: None,
-                        total_pages: None,
-                        estimated_time_seconds: None,
-                    }
-                }
-            }
-            Err(e) => OcrResult {
-                filename: original_filename.to_string(),
-                text: String::new(),
-                success: false,
-                error: Some(format!(
-                    "Failed to execute tesseract: {}. Make sure tesseract is installed.",
-                    e
-                )),
-                pages_processed: None,
-                total_pages: None,
-                estimated_time_seconds: None,
-            },
+
+        // Save uploaded PDF
+        std::fs::create_dir_all(&split_session_dir)?;
+        if let Err(error) = save_file(&mut field, &input_path, &name, &mut limits).await? {
+            let _ = std::fs::remove_dir_all(&split_session_dir);
+            return Ok(too_large(Some(name), error));
         }
+        filename = Some(name);
     }
-}
-
-#[post("/split")]
-async fn split_pdf(mut payload: Multipart) -> Result<HttpResponse> {
-    let splits_dir = std::path::PathBuf::from("./assets/conversions/splits");
-    std::fs::create_dir_all(&splits_dir)?;
 
-    let mut field = match payload.next().await {
-        Some(item) => item?,
-        None => {
-            return Ok(HttpResponse::BadRequest().json(SplitResponse {
-                success: false,
-                original_filename: String::new(),
-                total_pages: 0,
-                chunks: Vec::new(),
-                error: Some("No file uploaded".to_string()),
-            }));
-        }
+    let Some(filename) = filename else {
+        return Ok(HttpResponse::BadRequest()
+            .json(SplitResponse::failed(String::new(), "No file uploaded")));
     };
 
-    let filename = field
-        .content_disposition()
-        .and_then(|cd| cd.get_filename())
-        .unwrap_or("unnamed.pdf")
-        .to_string();
-
-    // Validate PDF
-    if !filename.to_lowercase().ends_with(".pdf") {
-        return Ok(HttpResponse::BadRequest().json(SplitResponse {
-            success: false,
-            original_filename: filename,
-            total_pages: 0,
-            chunks: Vec::new(),
-            error: Some("Only PDF files are supported for splitting".to_string()),
-        }));
+    if pages_per_chunk.is_some() && max_chunk_mb.is_some() {
+        let _ = std::fs::remove_dir_all(&split_session_dir);
+        return Ok(HttpResponse::BadRequest().json(SplitResponse::failed(
+            filename,
+            "Send either pages_per_chunk or max_chunk_mb, not both",
+        )));
     }
 
-    // Save uploaded PDF
-    let file_id = Uuid::new_v4();
-    let split_session_dir = splits_dir.join(file_id.to_string());
-    std::fs::create_dir_all(&split_session_dir)?;
+    if let Err(rejection) = check_pdf(&input_path, &config, None).await? {
+        println!(
+            "❌ Refusing to split '{}': {}",
+            filename,
+            rejection.message()
+        );
+        let _ = std::fs::remove_dir_all(&split_session_dir);
+        let mut response = SplitResponse::failed(filename, rejection.message());
+        response.error_code = Some(rejection.code().to_string());
+        return Ok(rejection_status(&rejection).json(response));
+    }
 
-    let input_path = split_session_dir.join("original.pdf");
-    let mut file = std::fs::File::create(&input_path)?;
-    while let Some(chunk) = field.next().await {
-        let data = chunk?;
-        file.write_all(&data)?;
+    // The same PDF split the same way gets the chunks it got before
+    let parameters = splits::Parameters {
+        pages_per_chunk,
+        max_chunk_mb,
+    };
+    let hashed = {
+        let input_path = input_path.clone();
+        let splits_dir = splits_dir.clone();
+        web::block(move || {
+            let sha256 = splits::sha256(&input_path)?;
+            let reused = splits::reuse(&splits_dir, &sha256, parameters);
+            Ok::<_, std::io::Error>((sha256, reused))
+        })
+        .await
+    };
+    let hashed = hashed
+        .map_err(|e| e.to_string())
+        .and_then(|hashed| hashed.map_err(|e| e.to_string()));
+    let (sha256, reused) = match hashed {
+        Ok(hashed) => hashed,
+        Err(e) => {
+            let _ = std::fs::remove_dir_all(&split_session_dir);
+            return Ok(
+                HttpResponse::InternalServerError().json(SplitResponse::failed(
+                    filename,
+                    &format!("Failed to read the upload: {}", e),
+                )),
+            );
+        }
+    };
+    if let Some((split_id, manifest)) = reused {
+        let _ = std::fs::remove_dir_all(&split_session_dir);
+        return Ok(HttpResponse::Ok().json(SplitResponse {
+            success: true,
+            session_id: Some(split_id),
+            original_filename: filename,
+            total_pages: manifest.total_pages,
+            chunks: manifest.chunks,
+            reused: true,
+            error: None,
+            error_code: None,
+        }));
     }
-    file.flush()?;
 
-    // Get PDF info using pdftk
     println!("Analyzing PDF '{}'...", filename);
-    let dump_output = Command::new("pdftk")
-        .arg(&input_path)
-        .arg("dump_data")
-        .output();
-
-    let total_pages = match dump_output {
-        Ok(result) => {
-            if result.status.success() {
-                let output_str = String::from_utf8_lossy(&result.stdout);
-                // Parse "NumberOfPages: N"
-                output_str
-                    .lines()
-                    .find(|line| line.starts_with("NumberOfPages:"))
-                    .and_then(|line| line.split(':').nth(1))
-                    .and_then(|s| s.trim().parse::<usize>().ok())
-                    .unwrap_or(0)
-            } else {
-                return Ok(HttpResponse::InternalServerError().json(SplitResponse {
-                    success: false,
-                    original_filename: filename,
-                    total_pages: 0,
-                    chunks: Vec::new(),
-                    error: Some(
-                        "Failed to analyze PDF with pdftk. Make sure pdftk is installed."
-                            .to_string(),
-                    ),
-                }));
-            }
-        }
-        Err(_) => {
-            return Ok(HttpResponse::InternalServerError().json(SplitResponse {
-                success: false,
-                original_filename: filename,
-                total_pages: 0,
-                chunks: Vec::new(),
-                error: Some("pdftk not found. Please install: sudo apt install pdftk".to_string()),
-            }));
+    let document = match pdf::load(&input_path, None) {
+        Ok(document) => document,
+        Err(e) => {
+            let _ = std::fs::remove_dir_all(&split_session_dir);
+            return Ok(HttpResponse::BadRequest().json(SplitResponse::failed(filename, &e)));
         }
     };
+    let total_pages = document.get_pages().len();
 
     if total_pages == 0 {
-        return Ok(HttpResponse::InternalServerError().json(SplitResponse {
-            success: false,
-            original_filename: filename,
-            total_pages: 0,
-            chunks: Vec::new(),
-            error: Some("Could not determine PDF page count".to_string()),
-        }));
+        return Ok(
+            HttpResponse::InternalServerError().json(SplitResponse::failed(
+                filename,
+                "Could not determine PDF page count",
+            )),
+        );
     }
 
-    // Calculate pages per chunk (~500KB target, estimate 10KB per page)
-    let file_size_kb = std::fs::metadata(&input_path)?.len() / 1024;
-    let estimated_kb_per_page = (file_size_kb as f64 / total_pages as f64).max(1.0);
-    let pages_per_chunk = ((500.0 / estimated_kb_per_page).floor() as usize)
-        .max(1)
-        .min(total_pages);
+    if total_pages > config.max_pdf_pages {
+        let rejection = pdf::PdfRejection::TooLarge(format!(
+            "PDF has {} pages; the limit is {}",
+            total_pages, config.max_pdf_pages
+        ));
+        let _ = std::fs::remove_dir_all(&split_session_dir);
+        let mut response = SplitResponse::failed(filename, rejection.message());
+        response.error_code = Some(rejection.code().to_string());
+        return Ok(rejection_status(&rejection).json(response));
+    }
+
+    // Pages per chunk as requested, or enough to stay near the size target
+    // (~500KB by default) given the document's average page size
+    let pages_per_chunk = match pages_per_chunk {
+        Some(pages) => pages,
+        None => {
+            let target_kb = max_chunk_mb.map_or(DEFAULT_CHUNK_KB, |mb| mb * 1024.0);
+            let file_size_kb = std::fs::metadata(&input_path)?.len() / 1024;
+            let estimated_kb_per_page = (file_size_kb as f64 / total_pages as f64).max(1.0);
+            (target_kb / estimated_kb_per_page).floor() as usize
+        }
+    }
+    .max(1)
+    .min(total_pages);
 
     println!(
         "Splitting {} pages into chunks of ~{} pages each...",
         total_pages, pages_per_chunk
     );
 
-    // Split PDF into chunks
+    // Split PDF into chunks, the upload and the chunks held to the session's
+    // disk quota
     let mut chunks = Vec::new();
     let mut current_page = 1;
     let mut chunk_num = 1;
+    let mut written = std::fs::metadata(&input_path)?.len();
 
     while current_page <= total_pages {
         let end_page = (current_page + pages_per_chunk - 1).min(total_pages);
@@ -607,65 +4408,334 @@ async fn split_pdf(mut payload: Multipart) -> Result<HttpResponse> {
             chunk_num, current_page, end_page
         );
 
-        let split_output = Command::new("pdftk")
-            .arg(&input_path)
-            .arg("cat")
-            .arg(format!("{}-{}", current_page, end_page))
-            .arg("output")
-            .arg(&chunk_path)
-            .output();
+        let extracted =
+            pdf::extract_pages(&document, current_page as u32, end_page as u32, &chunk_path);
 
-        match split_output {
-            Ok(result) if result.status.success() => {
-                if let Ok(metadata) = std::fs::metadata(&chunk_path) {
-                    let download_path = format!("/downloads/{}/{}", file_id, chunk_filename);
-                    chunks.push(ChunkInfo {
-                        filename: chunk_filename,
-                        page_range: format!("{}-{}", current_page, end_page),
-                        file_size: metadata.len(),
-                        download_path,
-                    });
-                }
+        let hashed = extracted.and_then(|()| {
+            std::fs::metadata(&chunk_path)
+                .and_then(|metadata| Ok((metadata.len(), splits::sha256(&chunk_path)?)))
+                .map_err(|e| e.to_string())
+        });
+        match hashed {
+            Ok((file_size, sha256)) => {
+                written += file_size;
+                let download_path = format!("/downloads/{}/{}", file_id, chunk_filename);
+                chunks.push(ChunkInfo {
+                    filename: chunk_filename,
+                    page_range: format!("{}-{}", current_page, end_page),
+                    file_size,
+                    sha256,
+                    download_path,
+                });
             }
-            _ => {
-                println!("  Warning: Failed to create chunk {}", chunk_num);
+            Err(e) => {
+                println!("  Warning: Failed to create chunk {}: {}", chunk_num, e);
             }
         }
 
+        if config.max_session_bytes > 0 && written > config.max_session_bytes {
+            println!("  🛑 Split went over its disk quota at chunk {}", chunk_num);
+            let _ = std::fs::remove_dir_all(&split_session_dir);
+            let error = format!(
+                "The split took more than the {} bytes of disk space a session is allowed; stopped at pages {}-{}",
+                config.max_session_bytes, current_page, end_page
+            );
+            let mut response = SplitResponse::failed(filename, &error);
+            response.error_code = Some(pipeline::QUOTA_EXCEEDED.to_string());
+            return Ok(HttpResponse::PayloadTooLarge().json(response));
+        }
+
         current_page = end_page + 1;
         chunk_num += 1;
     }
 
     println!("✅ Split complete: {} chunks created", chunks.len());
 
+    let manifest = splits::Manifest {
+        sha256,
+        parameters,
+        total_pages,
+        chunks,
+        holders: 1,
+    };
+    if let Err(e) = manifest.save(&split_session_dir) {
+        println!("  ⚠️  Warning: {}", e);
+    }
     Ok(HttpResponse::Ok().json(SplitResponse {
         success: true,
+        session_id: Some(file_id.to_string()),
         original_filename: filename,
         total_pages,
-        chunks,
+        chunks: manifest.chunks,
+        reused: false,
         error: None,
+        error_code: None,
     }))
 }
 
-#[actix_web::main]
-async fn main() -> std::io::Result<()> {
+/// Downloads a chunk of a `/split`, or the PDF it was cut from, as an
+/// attachment. Served from disk in pieces with an ETag and `Range` support, so
+/// a large chunk whose download broke off can be resumed.
+#[route("/downloads/{split_id}/{filename}", method = "GET", method = "HEAD")]
+async fn download_chunk(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse> {
+    let (split_id, filename) = path.into_inner();
+    // Splits are named by UUID and hold plain PDF files; nothing else is served
+    let chunk = (Uuid::parse_str(&split_id).is_ok()
+        && filename.ends_with(".pdf")
+        && !filename.contains(['/', '\\'])
+        && !filename.starts_with('.'))
+    .then(|| config.splits_dir().join(&split_id).join(&filename))
+    .filter(|path| path.is_file());
+    let Some(chunk) = chunk else {
+        return Ok(HttpResponse::NotFound().json(ErrorResponse {
+            error: format!("No file '{}' in split '{}'", filename, split_id),
+            code: None,
+        }));
+    };
+    let file = fs::NamedFile::open(chunk)?.set_content_disposition(
+        actix_web::http::header::ContentDisposition::attachment(filename),
+    );
+    Ok(file.into_response(&req))
+}
+
+/// Versioned prefix of the JSON API; the old unversioned paths still work, see
+/// [`legacy::rewrite`].
+const API_PREFIX: &str = "/api/v1";
+
+/// Registers the JSON API, served under [`API_PREFIX`].
+fn api(config: &mut web::ServiceConfig) {
+    config
+        .service(get_status)
+        .service(list_sessions)
+        .service(events)
+        .service(websocket)
+        .service(get_result)
+        .service(get_stats)
+        .service(get_page)
+        .service(get_page_image)
+        .service(get_page_thumbnail)
+        .service(put_correction)
+        .service(get_corrected)
+        .service(get_diff)
+        .service(get_searchable_pdf)
+        .service(download_result)
+        .service(download_bundle)
+        .service(export_text)
+        .service(upload)
+        .service(upload_url)
+        .service(prepare)
+        .service(cancel)
+        .service(delete_session)
+        .service(retry)
+        .service(suggest)
+        .service(split_pdf)
+        .service(inspect_pdf)
+        .service(run_benchmark)
+        .service(capacity::capacity);
+    #[cfg(feature = "graphql")]
+    config.service(graphql::query).service(graphql::graphiql);
+    #[cfg(feature = "search")]
+    config.service(search::search);
+}
+
+fn main() -> std::io::Result<()> {
+    let cli = cli::Cli::parse();
+    match cli.command {
+        Some(cli::Command::Ocr(args)) => {
+            if let Err(e) = cli::ocr(args) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+        Some(cli::Command::Serve) | None => actix_web::rt::System::new().block_on(serve()),
+    }
+}
+
+async fn serve() -> std::io::Result<()> {
     println!("Starting Sanskrit OCR server at http://127.0.0.1:8080");
 
+    let config = web::Data::new(Config::load()?);
+    config.create_dirs()?;
+    if !config.public_dir.is_dir() {
+        println!(
+            "  ⚠️  Warning: Frontend directory '{}' does not exist; only the API is served",
+            config.public_dir.display()
+        );
+    }
+    processes::set_limit(config.external_processes());
+    processes::set_priority(config.process_priority());
+    let notifier = web::Data::new(Notifier::new(&config.notifications)?);
+    let http = web::Data::new(reqwest::Client::new());
+    let rate_limiter = web::Data::new(rate_limit::RateLimiter::new(&config.rate_limit));
+    let dictionary = web::Data::new(match &config.dictionary_path {
+        Some(path) => Dictionary::load(path)?,
+        None => {
+            println!(
+                "  ⚠️  Warning: No dictionary_path is set; dictionary correction, mark repair and /suggest need a wordlist"
+            );
+            Dictionary::default()
+        }
+    });
+
+    let store = match &config.state_store {
+        Some(state_store) => {
+            let store = store::open(state_store, &config.instance_name());
+            Some(Arc::from(store.map_err(std::io::Error::other)?))
+        }
+        None => None,
+    };
+    #[cfg(feature = "redis")]
+    let shared = match &config.redis_url {
+        Some(url) => Some(Arc::new(
+            shared_status::SharedStatuses::connect(url)
+                .map_err(|e| std::io::Error::other(format!("Failed to connect to Redis: {}", e)))?,
+        )),
+        None => None,
+    };
+    #[cfg(not(feature = "redis"))]
+    if config.redis_url.is_some() {
+        return Err(std::io::Error::other(
+            "This build has no Redis support; rebuild with the redis feature",
+        ));
+    }
+    #[cfg(feature = "s3")]
+    let storage = match &config.object_storage {
+        Some(bucket) => Some(object_storage::ObjectStorage::new(bucket).map_err(|e| {
+            std::io::Error::other(format!("Invalid object storage '{}': {}", bucket.bucket, e))
+        })?),
+        None => None,
+    };
+    #[cfg(not(feature = "s3"))]
+    if config.object_storage.is_some() {
+        return Err(std::io::Error::other(
+            "This build has no object storage support; rebuild with the s3 feature",
+        ));
+    }
+
     // Create progress tracker
     let progress_tracker: ProgressTracker = Arc::new(RwLock::new(HashMap::new()));
+    let jobs = web::Data::new(Jobs {
+        tracker: progress_tracker.clone(),
+        config: config.clone().into_inner(),
+        notifier: notifier.clone().into_inner(),
+        dictionary: dictionary.clone().into_inner(),
+        http: http.clone().into_inner(),
+        public_http: fetch::public_client().map_err(std::io::Error::other)?,
+        held: RwLock::new(HashMap::new()),
+        queue: Mutex::new(VecDeque::new()),
+        queued: Notify::new(),
+        queued_images: Notify::new(),
+        retained: RwLock::new(HashMap::new()),
+        running: RwLock::new(HashMap::new()),
+        processing: RwLock::new(HashMap::new()),
+        read_pages: Mutex::new(HashMap::new()),
+        updates: broadcast::channel(EVENT_BACKLOG).0,
+        #[cfg(feature = "search")]
+        search: search::SearchIndex::new().map_err(std::io::Error::other)?,
+        store,
+        writer: store::Writer::start()?,
+        #[cfg(feature = "redis")]
+        shared,
+        #[cfg(feature = "s3")]
+        storage,
+        unsaved: Mutex::new(HashSet::new()),
+    });
+    let finished = jobs.restore();
+    #[cfg(feature = "search")]
+    for session_id in finished {
+        let jobs = jobs.clone().into_inner();
+        actix_web::rt::spawn(async move { jobs.index_for_search(&session_id).await });
+    }
+    #[cfg(not(feature = "search"))]
+    let _ = finished;
+    if jobs.keeps_statuses() {
+        actix_web::rt::spawn(jobs.clone().into_inner().save_statuses());
+    }
+    if config.cleanup.interval_seconds > 0 {
+        actix_web::rt::spawn(janitor::run(jobs.clone().into_inner()));
+    }
+    let saved = jobs.clone();
+    for _ in 0..config.max_concurrent_jobs.max(1) {
+        actix_web::rt::spawn(jobs.clone().into_inner().work(false));
+    }
+    for _ in 0..config.fast_lane_workers {
+        actix_web::rt::spawn(jobs.clone().into_inner().work(true));
+    }
+
+    #[cfg(feature = "grpc")]
+    if let Some(port) = config.grpc_port {
+        let service =
+            grpc::OcrService::new(jobs.clone().into_inner(), config.grpc_max_message_bytes);
+        let incoming = tonic::transport::server::TcpIncoming::bind(([0, 0, 0, 0], port).into())?;
+        println!("Starting gRPC service at 0.0.0.0:{}", port);
+        actix_web::rt::spawn(async move {
+            if let Err(e) = tonic::transport::Server::builder()
+                .add_service(service)
+                .serve_with_incoming(incoming)
+                .await
+            {
+                println!("❌ gRPC service stopped: {}", e);
+            }
+        });
+    }
+
+    #[cfg(feature = "graphql")]
+    let schema = web::Data::new(graphql::schema(progress_tracker.clone()));
 
     HttpServer::new(move || {
-        App::new()
-            .app_data(web::Data::new(progress_tracker.clone()))
-            .service(get_status)
-            .service(upload)
-            .service(split_pdf)
-            .service(
-                fs::Files::new("/downloads", "./assets/conversions/splits").show_files_listing(),
-            )
-            .service(fs::Files::new("/", "./public").index_file("index.html"))
+        let app = App::new();
+        #[cfg(feature = "graphql")]
+        let app = app.app_data(schema.clone());
+        app.app_data(web::Data::new(progress_tracker.clone()))
+            .app_data(config.clone())
+            .app_data(notifier.clone())
+            .app_data(dictionary.clone())
+            .app_data(http.clone())
+            .app_data(rate_limiter.clone())
+            .app_data(jobs.clone())
+            .service(web::scope(API_PREFIX).configure(api))
+            .service(health::healthz)
+            .service(health::readyz)
+            .service(openapi::document)
+            .service(openapi::swagger_ui)
+            .service(download_chunk)
+            .default_service(web::to(static_files::serve))
+            .wrap_fn(|mut req, service| {
+                let successor = legacy::rewrite(&mut req);
+                let response = service.call(req);
+                async move {
+                    let mut response = response.await?;
+                    if let Some(successor) = successor {
+                        let headers = response.headers_mut();
+                        headers.insert(
+                            HeaderName::from_static("deprecation"),
+                            HeaderValue::from_static("true"),
+                        );
+                        if let Ok(link) = HeaderValue::from_str(&format!(
+                            "<{}>; rel=\"successor-version\"",
+                            successor
+                        )) {
+                            headers.insert(actix_web::http::header::LINK, link);
+                        }
+                    }
+                    Ok(response)
+                }
+            })
     })
     .bind(("0.0.0.0", 8080))?
     .run()
+    .await?;
+    // Writes what changed last before exiting
+    web::block(move || {
+        saved.flush();
+        saved.writer.wait();
+    })
     .await
+    .map_err(std::io::Error::other)?;
+    Ok(())
 }
@@ -0,0 +1,104 @@
+use crate::dictionary::{Dictionary, is_word_char};
+
+const ANUSVARA: char = 'ं';
+const CANDRABINDU: char = 'ँ';
+const VISARGA: char = 'ः';
+const VIRAMA: char = '्';
+
+fn is_consonant(c: char) -> bool {
+    ('\u{0915}'..='\u{0939}').contains(&c)
+}
+
+/// Letters that can end a syllable with an anusvāra: consonants (with their
+/// inherent vowel), independent vowels and vowel signs.
+fn can_carry_anusvara(c: char) -> bool {
+    is_consonant(c)
+        || ('\u{0905}'..='\u{0914}').contains(&c)
+        || ('\u{093E}'..='\u{094C}').contains(&c)
+}
+
+/// Rewrites the marks of one whitespace-free token by context: a colon after a
+/// Devanagari letter is a visarga and a visarga after anything else is a colon;
+/// an anusvāra before `ल्ल` is a candrabindu (`ताँल्लोकान्`), and a candrabindu
+/// before any other consonant than a semivowel is a misread anusvāra.
+fn fix_context(token: &str) -> (String, usize) {
+    let chars: Vec<char> = token.chars().collect();
+    let mut fixed = String::with_capacity(token.len());
+    let mut count = 0;
+
+    for (i, &c) in chars.iter().enumerate() {
+        let prev = i.checked_sub(1).map(|p| chars[p]);
+        let next = chars.get(i + 1).copied();
+        let replacement = match c {
+            ':' if prev.is_some_and(|p| is_word_char(p) && p != VISARGA) => VISARGA,
+            VISARGA if !prev.is_some_and(is_word_char) => ':',
+            ANUSVARA if chars[i + 1..].starts_with(&['ल', VIRAMA, 'ल']) => CANDRABINDU,
+            CANDRABINDU
+                if next.is_some_and(|n| is_consonant(n) && !matches!(n, 'ल' | 'य' | 'व')) =>
+            {
+                ANUSVARA
+            }
+            _ => c,
+        };
+        if replacement != c {
+            count += 1;
+        }
+        fixed.push(replacement);
+    }
+    (fixed, count)
+}
+
+/// Restores a dropped anusvāra in a word the dictionary does not know, when
+/// exactly one position yields a known word (`सस्कृत` becomes `संस्कृत`).
+fn restore_anusvara(word: &str, dictionary: &Dictionary) -> Option<String> {
+    if dictionary.is_empty() || dictionary.contains(word) {
+        return None;
+    }
+    let chars: Vec<char> = word.chars().collect();
+    let mut found = None;
+    for i in 0..chars.len().saturating_sub(1) {
+        // An anusvāra closes a syllable and comes before a consonant
+        if can_carry_anusvara(chars[i]) && is_consonant(chars[i + 1]) {
+            let candidate: String = chars[..=i]
+                .iter()
+                .chain(std::iter::once(&ANUSVARA))
+                .chain(&chars[i + 1..])
+                .collect();
+            if dictionary.contains(&candidate) {
+                if found.is_some() {
+                    return None;
+                }
+                found = Some(candidate);
+            }
+        }
+    }
+    found
+}
+
+/// Sanskrit-aware repair of the marks tesseract most often confuses: visarga and
+/// colon, anusvāra and candrabindu, and dropped anusvāras (the latter only with a
+/// wordlist). Returns the repaired text and the number of changes made.
+pub fn fix_marks(text: &str, dictionary: &Dictionary) -> (String, usize) {
+    let mut fixed = String::with_capacity(text.len());
+    let mut count = 0;
+
+    for token in crate::dictionary::tokens(text) {
+        if token.starts_with(char::is_whitespace) {
+            fixed.push_str(token);
+            continue;
+        }
+        let (mut token, changes) = fix_context(token);
+        count += changes;
+
+        let core = token.trim_matches(|c: char| !is_word_char(c)).to_string();
+        if core.chars().count() > 2
+            && let Some(restored) = restore_anusvara(&core, dictionary)
+        {
+            token = token.replacen(&core, &restored, 1);
+            count += 1;
+        }
+        fixed.push_str(&token);
+    }
+
+    (fixed, count)
+}
@@ -0,0 +1,89 @@
+use serde::{Deserialize, Serialize};
+
+/// Where a page sits in the book, as far as the corpus is concerned.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum Section {
+    Front,
+    Body,
+    Back,
+}
+
+/// What to do with detected front/back matter when assembling the output text.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatterHandling {
+    /// Keep every page in the main text (pages are still labeled).
+    Keep,
+    /// Drop front/back matter pages from the main text.
+    Exclude,
+    /// Move front/back matter pages into their own text buckets.
+    Separate,
+}
+
+impl MatterHandling {
+    pub fn parse(name: &str) -> Option<MatterHandling> {
+        match name.trim().to_lowercase().as_str() {
+            "keep" => Some(MatterHandling::Keep),
+            "exclude" => Some(MatterHandling::Exclude),
+            "separate" => Some(MatterHandling::Separate),
+            _ => None,
+        }
+    }
+}
+
+/// Share of letters that must be Devanagari for a page to count as Sanskrit text.
+const MIN_DEVANAGARI_SHARE: f64 = 0.5;
+/// Front/back matter may only span this fraction of the book at either end.
+const MAX_MATTER_SHARE: f64 = 0.2;
+/// Books shorter than this are never split into sections.
+const MIN_PAGES: usize = 5;
+
+/// Fraction of alphabetic characters in `text` that belong to the Devanagari block,
+/// or `None` when the page has no letters at all.
+pub fn devanagari_share(text: &str) -> Option<f64> {
+    let mut letters = 0usize;
+    let mut devanagari = 0usize;
+    for c in text.chars().filter(|c| c.is_alphabetic()) {
+        letters += 1;
+        if ('\u{0900}'..='\u{097F}').contains(&c) {
+            devanagari += 1;
+        }
+    }
+    (letters > 0).then(|| devanagari as f64 / letters as f64)
+}
+
+/// Labels each page as front matter, body, or back matter.
+///
+/// Pages that are mostly non-Devanagari (English title pages, prefaces, indexes) or
+/// blank are front matter when they form an unbroken run from the first page, and back
+/// matter when they form an unbroken run to the last page; either run is capped at
+/// a fifth of the book so a mostly-English document is never discarded wholesale.
+pub fn classify(pages: &[&str]) -> Vec<Section> {
+    let mut sections = vec![Section::Body; pages.len()];
+    if pages.len() < MIN_PAGES {
+        return sections;
+    }
+
+    let limit = ((pages.len() as f64 * MAX_MATTER_SHARE).ceil() as usize).max(1);
+    let is_matter =
+        |text: &&&str| devanagari_share(text).is_none_or(|share| share < MIN_DEVANAGARI_SHARE);
+
+    let front = pages.iter().take(limit).take_while(is_matter).count();
+    let back = pages[front..]
+        .iter()
+        .rev()
+        .take(limit)
+        .take_while(is_matter)
+        .count();
+
+    for section in sections.iter_mut().take(front) {
+        *section = Section::Front;
+    }
+    for section in sections.iter_mut().rev().take(back) {
+        *section = Section::Back;
+    }
+
+    sections
+}
@@ -0,0 +1,126 @@
+use crate::cancel::Cancel;
+use crate::dictionary::is_word_char;
+use crate::processes;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// One reading of a word as reported by the analyzer.
+#[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct Analysis {
+    pub lemma: String,
+    #[serde(default)]
+    pub stem: Option<String>,
+    /// Free-form grammatical description, e.g. `noun, locative singular, neuter`.
+    #[serde(default)]
+    pub grammar: Option<String>,
+}
+
+/// The analyses found for one distinct word of a page; empty when the analyzer
+/// does not know the word.
+#[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct WordAnalysis {
+    pub word: String,
+    pub analyses: Vec<Analysis>,
+}
+
+/// Request body for HTTP analyzers: `{"words": [...]}`.
+#[derive(Serialize)]
+pub struct ServiceRequest<'a> {
+    pub words: &'a [String],
+}
+
+/// Response of HTTP analyzers: one list of analyses per requested word, in order.
+#[derive(Deserialize)]
+pub struct ServiceResponse {
+    pub analyses: Vec<Vec<Analysis>>,
+}
+
+/// Distinct words of `text` in order of first appearance, without punctuation.
+pub fn words(text: &str) -> Vec<String> {
+    let mut words: Vec<String> = Vec::new();
+    for token in text.split_whitespace() {
+        let word = token.trim_matches(|c: char| !is_word_char(c));
+        if !word.is_empty() && !words.iter().any(|known| known == word) {
+            words.push(word.to_string());
+        }
+    }
+    words
+}
+
+/// Pairs each word with the analyses returned for it, checking the counts match.
+pub fn combine(
+    words: Vec<String>,
+    analyses: Vec<Vec<Analysis>>,
+) -> Result<Vec<WordAnalysis>, String> {
+    if analyses.len() != words.len() {
+        return Err(format!(
+            "Morphological analyzer returned {} results for {} words",
+            analyses.len(),
+            words.len()
+        ));
+    }
+    Ok(words
+        .into_iter()
+        .zip(analyses)
+        .map(|(word, analyses)| WordAnalysis { word, analyses })
+        .collect())
+}
+
+/// Runs a local analyzer (`command[0]` with the remaining arguments) that reads one
+/// word per line and prints, per word, one line holding a JSON array of analyses.
+pub fn analyze_with_command(
+    words: Vec<String>,
+    command: &[String],
+) -> Result<Vec<WordAnalysis>, String> {
+    let Some((program, args)) = command.split_first() else {
+        return Err("Morphological analyzer command is empty".to_string());
+    };
+
+    let _slot = processes::acquire(&Cancel::default()).map_err(|e| e.to_string())?;
+    let mut child = processes::prioritized(Command::new(program).args(args))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| {
+            format!(
+                "Failed to execute morphological analyzer '{}': {}",
+                program, e
+            )
+        })?;
+
+    // Feed the words from another thread so a chatty analyzer cannot fill its
+    // output pipe while we are still writing
+    let input = words.join("\n") + "\n";
+    let stdin = child.stdin.take();
+    let writer = std::thread::spawn(move || match stdin {
+        Some(mut stdin) => stdin.write_all(input.as_bytes()),
+        None => Ok(()),
+    });
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("Morphological analyzer failed: {}", e))?;
+    writer
+        .join()
+        .map_err(|_| "Morphological analyzer input thread panicked".to_string())?
+        .map_err(|e| format!("Failed to write to morphological analyzer: {}", e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "Morphological analyzer error: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    let analyses = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| {
+            serde_json::from_str(line)
+                .map_err(|e| format!("Invalid morphological analyzer output '{}': {}", line, e))
+        })
+        .collect::<Result<Vec<Vec<Analysis>>, String>>()?;
+    combine(words, analyses)
+}
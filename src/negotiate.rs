@@ -0,0 +1,138 @@
+use crate::ErrorResponse;
+use actix_web::http::header::{self, Accept, Header, Quality};
+use actix_web::{HttpRequest, HttpResponse};
+use serde::Serialize;
+
+/// Representations of a result the `Accept` header can ask for.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Text,
+    Html,
+}
+
+/// Formats in the order a request without a preference gets them.
+const FORMATS: [Format; 3] = [Format::Json, Format::Text, Format::Html];
+
+impl Format {
+    fn media_type(self) -> (&'static str, &'static str) {
+        match self {
+            Format::Json => ("application", "json"),
+            Format::Text => ("text", "plain"),
+            Format::Html => ("text", "html"),
+        }
+    }
+
+    /// Quality the client gives this format: that of the most specific media
+    /// range matching it, zero when none does.
+    fn quality(self, accept: &Accept) -> Quality {
+        let (type_, subtype) = self.media_type();
+        accept
+            .iter()
+            .filter_map(|range| {
+                let specificity = match (range.item.type_().as_str(), range.item.subtype().as_str())
+                {
+                    (t, s) if t == type_ && s == subtype => 2,
+                    (t, "*") if t == type_ => 1,
+                    ("*", "*") => 0,
+                    _ => return None,
+                };
+                Some((specificity, range.quality))
+            })
+            .max_by_key(|(specificity, _)| *specificity)
+            .map_or(Quality::ZERO, |(_, quality)| quality)
+    }
+}
+
+/// The format the request's `Accept` header prefers, JSON without one, or the
+/// `406` response when it accepts none of them.
+pub fn format(req: &HttpRequest) -> Result<Format, HttpResponse> {
+    let Ok(accept) = Accept::parse(req) else {
+        return Ok(Format::Json);
+    };
+    if accept.is_empty() {
+        return Ok(Format::Json);
+    }
+    let mut best: Option<(Format, Quality)> = None;
+    for format in FORMATS {
+        let quality = format.quality(&accept);
+        // Ties go to the earlier format
+        if quality > Quality::ZERO && best.is_none_or(|(_, best)| quality > best) {
+            best = Some((format, quality));
+        }
+    }
+    best.map(|(format, _)| format).ok_or_else(|| {
+        HttpResponse::NotAcceptable().json(ErrorResponse {
+            error: "Results are available as application/json, text/plain or text/html".to_string(),
+            code: None,
+        })
+    })
+}
+
+/// A titled run of pages, rendered under one heading in the HTML view.
+pub struct Section<'a> {
+    pub title: &'a str,
+    pub pages: Vec<(usize, &'a str)>,
+}
+
+/// A simple page showing `sections`, each page under a heading with an anchor:
+/// `#page-N`, or `#file-I-page-N` when there are several sections.
+fn html(title: &str, sections: &[Section]) -> String {
+    let anchor = |index: usize, page_number: usize| match sections.len() {
+        1 => format!("page-{}", page_number),
+        _ => format!("file-{}-page-{}", index, page_number),
+    };
+    let mut body = String::new();
+    for (index, section) in sections.iter().enumerate() {
+        body.push_str(&format!("<h1>{}</h1>\n<nav>", escape(section.title)));
+        for (page_number, _) in &section.pages {
+            let id = anchor(index, *page_number);
+            body.push_str(&format!(" <a href=\"#{}\">{}</a>", id, page_number));
+        }
+        body.push_str("</nav>\n");
+        for (page_number, text) in &section.pages {
+            let id = anchor(index, *page_number);
+            body.push_str(&format!(
+                "<section id=\"{id}\">\n<h2><a href=\"#{id}\">Page {}</a></h2>\n<pre>{}</pre>\n</section>\n",
+                page_number,
+                escape(text)
+            ));
+        }
+    }
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"sa\">\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+         <style>body {{ font-family: sans-serif; max-width: 50rem; margin: 2rem auto; }} \
+         pre {{ white-space: pre-wrap; font-size: 1.1rem; }}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape(title),
+        body
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// The response in `format`: `json` as JSON, `text` as plain text, or `sections`
+/// as an HTML page titled `title`.
+pub fn respond<'a>(
+    format: Format,
+    json: &impl Serialize,
+    text: impl FnOnce() -> String,
+    title: &str,
+    sections: impl FnOnce() -> Vec<Section<'a>>,
+) -> HttpResponse {
+    let mut response = HttpResponse::Ok();
+    response.insert_header((header::VARY, "Accept"));
+    match format {
+        Format::Json => response.json(json),
+        Format::Text => response
+            .content_type("text/plain; charset=utf-8")
+            .body(text()),
+        Format::Html => response
+            .content_type("text/html; charset=utf-8")
+            .body(html(title, &sections())),
+    }
+}
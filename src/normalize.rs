@@ -0,0 +1,159 @@
+use serde::{Deserialize, Serialize};
+use unicode_normalization::UnicodeNormalization;
+
+const NUKTA: char = '\u{093C}';
+const VIRAMA: char = '\u{094D}';
+const SIGN_I: char = '\u{093F}';
+
+fn is_consonant(c: char) -> bool {
+    matches!(c, '\u{0915}'..='\u{0939}' | '\u{0958}'..='\u{095F}' | '\u{0978}'..='\u{097F}')
+}
+
+fn is_vowel_sign(c: char) -> bool {
+    matches!(c, '\u{093A}' | '\u{093B}' | '\u{093E}'..='\u{094C}' | '\u{094E}' | '\u{094F}' | '\u{0955}'..='\u{0957}' | '\u{0962}' | '\u{0963}')
+}
+
+/// Candrabindu, anusvāra and visarga: these always follow the vowel sign.
+fn is_modifier(c: char) -> bool {
+    matches!(c, '\u{0900}'..='\u{0903}')
+}
+
+/// Returns the index just past the consonant cluster starting at `start`
+/// (consonants joined by virama, each optionally carrying a nukta).
+fn cluster_end(chars: &[char], start: usize) -> usize {
+    let mut end = start + 1;
+    loop {
+        if chars.get(end) == Some(&NUKTA) {
+            end += 1;
+        }
+        if chars.get(end) == Some(&VIRAMA) && chars.get(end + 1).is_some_and(|&c| is_consonant(c)) {
+            end += 2;
+        } else {
+            return end;
+        }
+    }
+}
+
+/// Cleans up Devanagari combining-mark sequences as emitted by tesseract.
+///
+/// The text is NFC-normalized, then marks that came out in the wrong logical order
+/// are moved back into place: nukta directly after its consonant, vowel signs before
+/// anusvāra/visarga/candrabindu, and a short-i sign emitted in visual order (before
+/// its consonant) after it.
+pub fn normalize_devanagari(text: &str) -> String {
+    let mut chars: Vec<char> = text.nfc().collect();
+
+    let mut i = 0;
+    while i + 1 < chars.len() {
+        let (current, next) = (chars[i], chars[i + 1]);
+        let misordered = (is_modifier(current) && is_vowel_sign(next))
+            || ((is_vowel_sign(current) || current == VIRAMA) && next == NUKTA);
+
+        if misordered {
+            chars.swap(i, i + 1);
+            // the moved mark may now be out of order with its new predecessor
+            i = i.saturating_sub(1);
+            continue;
+        }
+
+        let attached = i > 0 && (is_consonant(chars[i - 1]) || chars[i - 1] == NUKTA);
+        if current == SIGN_I && !attached && is_consonant(next) {
+            let end = cluster_end(&chars, i + 1);
+            chars[i..end].rotate_left(1);
+            i = end;
+            continue;
+        }
+
+        i += 1;
+    }
+
+    chars.into_iter().nfc().collect()
+}
+
+/// Which digits to emit.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DigitStyle {
+    #[default]
+    Keep,
+    Ascii,
+    Devanagari,
+}
+
+/// How sentence and verse ends are written.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DandaStyle {
+    #[default]
+    Keep,
+    /// `।` and `॥`, also repairing `|`, `||` and doubled single daṇḍas.
+    Danda,
+    /// ASCII `|` and `||`.
+    Pipe,
+}
+
+impl DigitStyle {
+    pub fn parse(name: &str) -> Option<DigitStyle> {
+        match name.trim().to_lowercase().as_str() {
+            "keep" => Some(DigitStyle::Keep),
+            "ascii" => Some(DigitStyle::Ascii),
+            "devanagari" => Some(DigitStyle::Devanagari),
+            _ => None,
+        }
+    }
+}
+
+impl DandaStyle {
+    pub fn parse(name: &str) -> Option<DandaStyle> {
+        match name.trim().to_lowercase().as_str() {
+            "keep" => Some(DandaStyle::Keep),
+            "danda" => Some(DandaStyle::Danda),
+            "pipe" => Some(DandaStyle::Pipe),
+            _ => None,
+        }
+    }
+}
+
+/// Digit and punctuation conventions applied to every page, so a text comes out
+/// consistent even when tesseract varies from page to page.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Punctuation {
+    pub digits: DigitStyle,
+    pub dandas: DandaStyle,
+    /// Replace runs of avagrahas (`ऽऽ`) with a single one.
+    pub collapse_avagraha: bool,
+}
+
+impl Punctuation {
+    pub fn apply(&self, text: &str) -> String {
+        let mut out = String::with_capacity(text.len());
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '0'..='9' if self.digits == DigitStyle::Devanagari => {
+                    out.push(char::from_u32(c as u32 - '0' as u32 + '०' as u32).unwrap_or(c));
+                }
+                '०'..='९' if self.digits == DigitStyle::Ascii => {
+                    out.push(char::from_u32(c as u32 - '०' as u32 + '0' as u32).unwrap_or(c));
+                }
+                'ऽ' if self.collapse_avagraha => {
+                    out.push(c);
+                    while chars.next_if_eq(&'ऽ').is_some() {}
+                }
+                '|' | '।' if self.dandas == DandaStyle::Danda => {
+                    // A doubled stroke, in either form, is a double daṇḍa
+                    if chars.next_if(|&next| next == '|' || next == '।').is_some() {
+                        out.push('॥');
+                    } else {
+                        out.push('।');
+                    }
+                }
+                '।' if self.dandas == DandaStyle::Pipe => out.push('|'),
+                '॥' if self.dandas == DandaStyle::Pipe => out.push_str("||"),
+                _ => out.push(c),
+            }
+        }
+        out
+    }
+}
@@ -0,0 +1,401 @@
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use sanskrit_ocr::config::{NotificationConfig, ReviewConfig};
+use sanskrit_ocr::pipeline::OcrResult;
+use serde::Serialize;
+use tera::{Context, Tera};
+
+const DEFAULT_SUBJECT: &str = "Sanskrit OCR job {{ session_id }} finished";
+
+const DEFAULT_EMAIL_BODY: &str = "\
+Session {{ session_id }} finished: {{ succeeded }}/{{ file_count }} files succeeded.
+{% if notes %}
+Notes: {{ notes }}
+{% endif %}
+{% for file in files %}- {{ file.filename }}: {% if file.success %}{{ file.pages }} pages{% if file.mean_confidence %}, mean confidence {{ file.mean_confidence | round(precision=1) }}{% endif %}{% else %}failed ({{ file.error }}){% endif %}
+{% endfor %}";
+
+/// Confidence under which a page is counted as low quality in the summary.
+const LOW_CONFIDENCE: f32 = 60.0;
+
+/// Tries per callback when the configuration sets none.
+const DEFAULT_CALLBACK_ATTEMPTS: u32 = 5;
+
+/// Wait before the first retry of a callback, doubled for each one after.
+const CALLBACK_BACKOFF: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// Everything a notification template can reference.
+#[derive(Serialize)]
+pub struct JobSummary {
+    pub session_id: String,
+    pub notes: Option<String>,
+    pub completed_at: u64,
+    pub file_count: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub mean_confidence: Option<f32>,
+    pub files: Vec<FileSummary>,
+}
+
+#[derive(Serialize)]
+pub struct FileSummary {
+    pub filename: String,
+    pub success: bool,
+    pub error: Option<String>,
+    pub pages: usize,
+    pub mean_confidence: Option<f32>,
+    pub low_confidence_pages: usize,
+}
+
+fn mean(values: impl Iterator<Item = f32>) -> Option<f32> {
+    let (sum, count) = values.fold((0.0, 0), |(sum, count), v| (sum + v, count + 1));
+    (count > 0).then(|| sum / count as f32)
+}
+
+impl JobSummary {
+    pub fn new(session_id: &str, notes: Option<String>, results: &[OcrResult]) -> JobSummary {
+        let files: Vec<FileSummary> = results
+            .iter()
+            .map(|result| FileSummary {
+                filename: result.filename.clone(),
+                success: result.success,
+                error: result.error.clone(),
+                pages: result.pages.len(),
+                mean_confidence: mean(result.pages.iter().filter_map(|page| page.confidence)),
+                low_confidence_pages: result
+                    .pages
+                    .iter()
+                    .filter(|page| page.confidence.is_some_and(|conf| conf < LOW_CONFIDENCE))
+                    .count(),
+            })
+            .collect();
+
+        JobSummary {
+            session_id: session_id.to_string(),
+            notes,
+            completed_at: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            file_count: files.len(),
+            succeeded: files.iter().filter(|file| file.success).count(),
+            failed: files.iter().filter(|file| !file.success).count(),
+            mean_confidence: mean(
+                results
+                    .iter()
+                    .flat_map(|result| &result.pages)
+                    .filter_map(|page| page.confidence),
+            ),
+            files,
+        }
+    }
+}
+
+/// Template context for review submissions: the summary plus the full results.
+#[derive(Serialize)]
+struct ReviewPackage<'a> {
+    #[serde(flatten)]
+    summary: &'a JobSummary,
+    results: &'a [OcrResult],
+}
+
+/// Body of a per-upload callback: the summary, where to fetch the results, and
+/// the results themselves unless the configuration leaves them out.
+#[derive(Serialize)]
+struct CallbackPayload<'a> {
+    #[serde(flatten)]
+    summary: &'a JobSummary,
+    status_url: String,
+    download_url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    results: Option<&'a [OcrResult]>,
+}
+
+/// Sends completion notifications with templates compiled once at startup.
+pub struct Notifier {
+    config: NotificationConfig,
+    templates: Tera,
+    client: reqwest::Client,
+    /// For the `callback_url` of uploads, which clients choose.
+    callback_client: reqwest::Client,
+}
+
+impl Notifier {
+    pub fn new(config: &NotificationConfig) -> std::io::Result<Notifier> {
+        let mut templates = Tera::default();
+        let invalid = |e: tera::Error| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Invalid notification template: {:?}", e),
+            )
+        };
+
+        if let Some(path) = &config.webhook_template {
+            templates
+                .add_template_file(path, Some("webhook"))
+                .map_err(invalid)?;
+        }
+        if let Some(email) = &config.email {
+            templates
+                .add_raw_template(
+                    "email_subject",
+                    email.subject_template.as_deref().unwrap_or(DEFAULT_SUBJECT),
+                )
+                .map_err(invalid)?;
+            match &email.body_template {
+                Some(path) => templates.add_template_file(path, Some("email_body")),
+                None => templates.add_raw_template("email_body", DEFAULT_EMAIL_BODY),
+            }
+            .map_err(invalid)?;
+        }
+        if let Some(path) = config
+            .review
+            .as_ref()
+            .and_then(|review| review.template.as_ref())
+        {
+            templates
+                .add_template_file(path, Some("review"))
+                .map_err(invalid)?;
+        }
+
+        Ok(Notifier {
+            config: config.clone(),
+            templates,
+            client: reqwest::Client::new(),
+            callback_client: crate::fetch::public_client().map_err(std::io::Error::other)?,
+        })
+    }
+
+    fn render(&self, name: &str, summary: &impl Serialize) -> Result<String, String> {
+        let context = Context::from_serialize(summary).map_err(|e| e.to_string())?;
+        self.templates
+            .render(name, &context)
+            .map_err(|e| format!("{:?}", e))
+    }
+
+    /// Delivers all configured notifications for a finished job, logging failures.
+    pub async fn job_finished(&self, summary: &JobSummary, results: &[OcrResult]) {
+        if let Some(url) = &self.config.webhook_url
+            && let Err(e) = self.send_webhook(url, summary).await
+        {
+            println!("⚠️  Webhook notification to {} failed: {}", url, e);
+        }
+
+        if self.config.email.is_some()
+            && let Err(e) = self.send_email(summary).await
+        {
+            println!("⚠️  Email notification failed: {}", e);
+        }
+
+        if summary.succeeded > 0
+            && let Some(review) = &self.config.review
+            && let Err(e) = self.submit_for_review(review, summary, results).await
+        {
+            println!("⚠️  Review submission to {} failed: {}", review.url, e);
+        }
+    }
+
+    /// Posts a finished job to the `callback_url` sent with its upload, retrying
+    /// with growing pauses while the endpoint cannot be reached or answers with a
+    /// server error or 429; logs the outcome.
+    pub async fn callback(&self, url: &str, summary: &JobSummary, results: &[OcrResult]) {
+        let base = self
+            .config
+            .public_url
+            .as_deref()
+            .unwrap_or("")
+            .trim_end_matches('/');
+        let payload = CallbackPayload {
+            summary,
+            status_url: format!(
+                "{}{}/status/{}",
+                base,
+                crate::API_PREFIX,
+                summary.session_id
+            ),
+            download_url: format!(
+                "{}{}/result/{}/download",
+                base,
+                crate::API_PREFIX,
+                summary.session_id
+            ),
+            results: self
+                .config
+                .callback_results
+                .unwrap_or(true)
+                .then_some(results),
+        };
+        let body = match serde_json::to_string(&payload) {
+            Ok(body) => body,
+            Err(e) => {
+                println!("⚠️  Callback to {} failed: {}", url, e);
+                return;
+            }
+        };
+        if let Some(reason) = reqwest::Url::parse(url)
+            .map_err(|e| e.to_string())
+            .map(|parsed| crate::fetch::refusal(&parsed))
+            .unwrap_or_else(Some)
+        {
+            println!("⚠️  Callback to {} refused: {}", url, reason);
+            return;
+        }
+
+        let attempts = self
+            .config
+            .callback_attempts
+            .unwrap_or(DEFAULT_CALLBACK_ATTEMPTS)
+            .max(1);
+        let mut pause = CALLBACK_BACKOFF;
+        for attempt in 1..=attempts {
+            let sent = self
+                .callback_client
+                .post(url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body.clone())
+                .send()
+                .await;
+            let (error, retry) = match sent {
+                Ok(response) if response.status().is_success() => {
+                    println!(
+                        "📨 Callback sent for session {} to {}",
+                        summary.session_id, url
+                    );
+                    return;
+                }
+                Ok(response) => {
+                    let status = response.status();
+                    let retry = status.is_server_error()
+                        || status == reqwest::StatusCode::TOO_MANY_REQUESTS;
+                    (format!("endpoint answered {}", status), retry)
+                }
+                Err(e) => (e.to_string(), true),
+            };
+            if !retry || attempt == attempts {
+                println!(
+                    "⚠️  Callback to {} failed after {} attempts: {}",
+                    url, attempt, error
+                );
+                return;
+            }
+            println!(
+                "  ⚠️  Warning: Callback to {} failed ({}), retrying in {}s",
+                url,
+                error,
+                pause.as_secs()
+            );
+            tokio::time::sleep(pause).await;
+            pause *= 2;
+        }
+    }
+
+    async fn submit_for_review(
+        &self,
+        review: &ReviewConfig,
+        summary: &JobSummary,
+        results: &[OcrResult],
+    ) -> Result<(), String> {
+        let package = ReviewPackage { summary, results };
+        let body = if review.template.is_some() {
+            self.render("review", &package)?
+        } else {
+            serde_json::to_string(&package).map_err(|e| e.to_string())?
+        };
+
+        let mut request = self
+            .client
+            .post(&review.url)
+            .header(
+                reqwest::header::CONTENT_TYPE,
+                review.content_type.as_deref().unwrap_or("application/json"),
+            )
+            .body(body);
+        if let Some(token) = &review.auth_token {
+            let header = review.auth_header.as_deref().unwrap_or("Authorization");
+            request = request.header(header, token);
+        }
+
+        let response = request.send().await.map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("platform answered {}", response.status()));
+        }
+        println!(
+            "📨 Results for session {} submitted for review",
+            summary.session_id
+        );
+        Ok(())
+    }
+
+    async fn send_webhook(&self, url: &str, summary: &JobSummary) -> Result<(), String> {
+        let body = if self.config.webhook_template.is_some() {
+            self.render("webhook", summary)?
+        } else {
+            serde_json::to_string(summary).map_err(|e| e.to_string())?
+        };
+        let content_type = self
+            .config
+            .webhook_content_type
+            .as_deref()
+            .unwrap_or("application/json");
+
+        let response = self
+            .client
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, content_type)
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        if !response.status().is_success() {
+            return Err(format!("endpoint answered {}", response.status()));
+        }
+        println!(
+            "📨 Webhook notification sent for session {}",
+            summary.session_id
+        );
+        Ok(())
+    }
+
+    async fn send_email(&self, summary: &JobSummary) -> Result<(), String> {
+        let Some(email) = &self.config.email else {
+            return Ok(());
+        };
+
+        let from: Mailbox = email.from.parse().map_err(|e| format!("{}", e))?;
+        let mut builder = Message::builder()
+            .from(from)
+            .subject(self.render("email_subject", summary)?.trim());
+        for to in &email.to {
+            builder = builder.to(to.parse().map_err(|e| format!("{}", e))?);
+        }
+        let message = builder
+            .body(self.render("email_body", summary)?)
+            .map_err(|e| e.to_string())?;
+
+        let mut transport = if email.starttls {
+            AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&email.smtp_host)
+        } else {
+            AsyncSmtpTransport::<Tokio1Executor>::relay(&email.smtp_host)
+        }
+        .map_err(|e| e.to_string())?;
+        if let Some(port) = email.smtp_port {
+            transport = transport.port(port);
+        }
+        if let (Some(username), Some(password)) = (&email.username, &email.password) {
+            transport = transport.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        transport
+            .build()
+            .send(message)
+            .await
+            .map_err(|e| e.to_string())?;
+        println!(
+            "📧 Email notification sent for session {}",
+            summary.session_id
+        );
+        Ok(())
+    }
+}
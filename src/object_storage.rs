@@ -0,0 +1,202 @@
+use futures_util::StreamExt;
+use object_store::aws::{AmazonS3, AmazonS3Builder};
+use object_store::buffered::BufWriter;
+use object_store::path::Path;
+use object_store::signer::Signer;
+use object_store::{Attribute, Attributes, ObjectStore};
+use sanskrit_ocr::config::ObjectStorageConfig;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+
+/// How long presigned links stay valid when the config leaves it unset.
+const DEFAULT_PRESIGNED_SECONDS: u64 = 3600;
+
+/// An S3-compatible bucket, such as AWS S3 or MinIO, that uploads, kept page
+/// images and result files are written to instead of the local disk. Each
+/// session's objects are kept under `sessions/{session_id}/`, so deleting a
+/// session removes them in one go. Clients download them from the bucket
+/// itself, through presigned links.
+pub struct ObjectStorage {
+    bucket: Arc<AmazonS3>,
+    prefix: String,
+    presigned_for: Duration,
+}
+
+/// How an object is served when it is downloaded.
+#[derive(Default)]
+pub struct Served<'a> {
+    pub content_type: Option<&'a str>,
+    /// `Content-Disposition` header, e.g. to name the file it is saved as.
+    pub disposition: Option<String>,
+}
+
+impl ObjectStorage {
+    /// Connects to the bucket `config` names. Credentials it leaves unset are
+    /// taken from the usual `AWS_*` environment variables.
+    pub fn new(config: &ObjectStorageConfig) -> Result<ObjectStorage, String> {
+        let mut builder = AmazonS3Builder::from_env()
+            .with_bucket_name(&config.bucket)
+            .with_region(config.region.as_deref().unwrap_or("us-east-1"));
+        if let Some(endpoint) = &config.endpoint {
+            builder = builder
+                .with_endpoint(endpoint)
+                .with_allow_http(endpoint.starts_with("http://"));
+        }
+        if let Some(key) = &config.access_key_id {
+            builder = builder.with_access_key_id(key);
+        }
+        if let Some(secret) = &config.secret_access_key {
+            builder = builder.with_secret_access_key(secret);
+        }
+        let bucket = builder.build().map_err(|e| e.to_string())?;
+        Ok(ObjectStorage {
+            bucket: Arc::new(bucket),
+            prefix: config.prefix.clone(),
+            presigned_for: Duration::from_secs(
+                config
+                    .presigned_url_seconds
+                    .unwrap_or(DEFAULT_PRESIGNED_SECONDS),
+            ),
+        })
+    }
+
+    /// Where `name`, e.g. `searchable/0.pdf`, is kept for the session.
+    fn path(&self, session_id: &str, name: &str) -> Path {
+        Path::from(format!("{}sessions/{}/{}", self.prefix, session_id, name))
+    }
+
+    /// Writes the file at `local` as the session's `name`, in parts when it is
+    /// large, so it is never read into memory whole.
+    pub async fn put_file(
+        &self,
+        session_id: &str,
+        name: &str,
+        local: &std::path::Path,
+        served: Served<'_>,
+    ) -> Result<(), String> {
+        let mut file = tokio::fs::File::open(local)
+            .await
+            .map_err(|e| e.to_string())?;
+        let store: Arc<dyn ObjectStore> = self.bucket.clone();
+        let mut writer =
+            BufWriter::new(store, self.path(session_id, name)).with_attributes(attributes(served));
+        let written = tokio::io::copy(&mut file, &mut writer).await;
+        match written {
+            Ok(_) => writer.shutdown().await.map_err(|e| e.to_string()),
+            Err(e) => {
+                let _ = writer.abort().await;
+                Err(e.to_string())
+            }
+        }
+    }
+
+    /// Writes `body` as the session's `name`.
+    pub async fn put(
+        &self,
+        session_id: &str,
+        name: &str,
+        body: Vec<u8>,
+        served: Served<'_>,
+    ) -> Result<(), String> {
+        let options = object_store::PutOptions {
+            attributes: attributes(served),
+            ..Default::default()
+        };
+        self.bucket
+            .put_opts(&self.path(session_id, name), body.into(), options)
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    /// Copies the session's `name` to the file at `local`, creating its
+    /// directory if need be.
+    pub async fn fetch(
+        &self,
+        session_id: &str,
+        name: &str,
+        local: &std::path::Path,
+    ) -> Result<(), String> {
+        let object = self
+            .bucket
+            .get(&self.path(session_id, name))
+            .await
+            .map_err(|e| e.to_string())?;
+        if let Some(dir) = local.parent() {
+            tokio::fs::create_dir_all(dir)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        let mut file = tokio::fs::File::create(local)
+            .await
+            .map_err(|e| e.to_string())?;
+        let mut chunks = object.into_stream();
+        while let Some(chunk) = chunks.next().await {
+            let chunk = chunk.map_err(|e| e.to_string())?;
+            file.write_all(&chunk).await.map_err(|e| e.to_string())?;
+        }
+        file.flush().await.map_err(|e| e.to_string())
+    }
+
+    /// The first of `names` the session has an object for.
+    pub async fn first_of(&self, session_id: &str, names: &[String]) -> Option<String> {
+        for name in names {
+            if self.bucket.head(&self.path(session_id, name)).await.is_ok() {
+                return Some(name.clone());
+            }
+        }
+        None
+    }
+
+    /// A link downloading the session's `name` straight from the bucket, valid
+    /// for the configured time.
+    pub async fn presigned(&self, session_id: &str, name: &str) -> Result<String, String> {
+        self.bucket
+            .signed_url(
+                reqwest::Method::GET,
+                &self.path(session_id, name),
+                self.presigned_for,
+            )
+            .await
+            .map(String::from)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Removes the session's `name`.
+    pub async fn remove(&self, session_id: &str, name: &str) -> Result<(), String> {
+        match self.bucket.delete(&self.path(session_id, name)).await {
+            Ok(()) | Err(object_store::Error::NotFound { .. }) => Ok(()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    /// Removes every object of the session.
+    pub async fn remove_session(&self, session_id: &str) -> Result<(), String> {
+        let prefix = Path::from(format!("{}sessions/{}", self.prefix, session_id));
+        let locations = self
+            .bucket
+            .list(Some(&prefix))
+            .map(|meta| meta.map(|meta| meta.location))
+            .boxed();
+        let mut deleted = self.bucket.delete_stream(locations);
+        while let Some(outcome) = deleted.next().await {
+            match outcome {
+                Ok(_) | Err(object_store::Error::NotFound { .. }) => {}
+                Err(e) => return Err(e.to_string()),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn attributes(served: Served) -> Attributes {
+    let mut attributes = Attributes::new();
+    if let Some(content_type) = served.content_type {
+        attributes.insert(Attribute::ContentType, content_type.to_string().into());
+    }
+    if let Some(disposition) = served.disposition {
+        attributes.insert(Attribute::ContentDisposition, disposition.into());
+    }
+    attributes
+}
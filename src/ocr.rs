@@ -0,0 +1,373 @@
+use crate::cancel::Cancel;
+use crate::config::Config;
+use crate::lines;
+use crate::ocr_worker;
+use crate::script::Script;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+use uuid::Uuid;
+
+/// Text recognized on a single page image, along with the model that produced it.
+pub struct PageText {
+    pub text: String,
+    /// Mean word confidence reported by tesseract (0-100), if any words were found.
+    pub confidence: Option<f32>,
+    pub model: String,
+    /// Word-level boxes and confidences, in reading order.
+    pub words: Vec<Word>,
+    /// Every tesseract invocation made for this page, including rejected fallbacks.
+    pub commands: Vec<String>,
+    /// Tesseract's hOCR report of the page, with the layout and a box for every
+    /// word, when it was asked for.
+    pub hocr: Option<String>,
+}
+
+/// One word from tesseract's TSV report.
+#[derive(Clone, Default)]
+pub struct Word {
+    pub text: String,
+    pub confidence: f32,
+}
+
+/// Renders a command line for the result provenance, with the temporary input
+/// and output paths replaced by `{input}` and `{output}`.
+pub fn command_line(command: &Command, input: &Path, output: &str) -> String {
+    std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(|arg| {
+            if arg == input.as_os_str() {
+                "{input}".to_string()
+            } else if arg == output {
+                "{output}".to_string()
+            } else {
+                let arg = arg.to_string_lossy();
+                if arg.contains(char::is_whitespace) {
+                    format!("'{}'", arg)
+                } else {
+                    arg.into_owned()
+                }
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// First line of `<tool> <flag>` output, which is where tesseract prints its
+/// version (on stdout or stderr depending on the build).
+fn probe_version(tool: &str, flag: &str) -> Option<String> {
+    let output = Command::new(tool).arg(flag).output().ok()?;
+    let text = if output.stdout.is_empty() {
+        output.stderr
+    } else {
+        output.stdout
+    };
+    String::from_utf8_lossy(&text)
+        .lines()
+        .next()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+}
+
+/// Versions of the external tools, probed once per process.
+pub fn tool_versions() -> &'static BTreeMap<String, String> {
+    static VERSIONS: OnceLock<BTreeMap<String, String>> = OnceLock::new();
+    VERSIONS.get_or_init(|| {
+        [("tesseract", "--version")]
+            .into_iter()
+            .filter_map(|(tool, flag)| Some((tool.to_string(), probe_version(tool, flag)?)))
+            .collect()
+    })
+}
+
+/// Language models tesseract has installed, as `tesseract --list-langs` lists
+/// them, e.g. `san` or `script/Devanagari`.
+pub fn installed_languages() -> Result<Vec<String>, String> {
+    let output = Command::new("tesseract")
+        .arg("--list-langs")
+        .output()
+        .map_err(|e| format!("Failed to run tesseract: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "tesseract --list-langs failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    // The first line names the tessdata directory
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .skip(1)
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect())
+}
+
+/// Runs tesseract once on `image` with the given language model and, if set, page
+/// segmentation mode, writing its output files under `work_dir`.
+///
+/// Both the plain text and the TSV output are requested so the page confidence can
+/// be computed without a second pass, and with `hocr` the hOCR output as well.
+/// With a `worker` command, the page goes to a running OCR helper instead (see
+/// [`ocr_worker`]), which reports no hOCR.
+pub fn run_tesseract(
+    image: &Path,
+    language: &str,
+    psm: Option<u8>,
+    hocr: bool,
+    worker: Option<&[String]>,
+    work_dir: &Path,
+    cancel: &Cancel,
+) -> Result<PageText, String> {
+    if let Some(worker) = worker {
+        let read = ocr_worker::recognize(worker, image, language, psm, cancel)?;
+        let words = parse_tsv(&read.tsv);
+        return Ok(PageText {
+            text: read.text,
+            confidence: mean_confidence(&words),
+            model: language.to_string(),
+            words,
+            commands: vec![read.command],
+            hocr: None,
+        });
+    }
+
+    let output_base = work_dir.join(format!("ocr_output_{}", Uuid::new_v4()));
+    let output_path = format!("{}", output_base.display());
+
+    let mut command = Command::new("tesseract");
+    command.arg(image).arg(&output_path).arg("-l").arg(language);
+    if let Some(psm) = psm {
+        command.arg("--psm").arg(psm.to_string());
+    }
+    command.arg("txt").arg("tsv");
+    if hocr {
+        command.arg("hocr");
+    }
+    let command_text = command_line(&command, image, &output_path);
+
+    let output = execute(&mut command, cancel)?;
+
+    let txt_file = format!("{}.txt", output_path);
+    let tsv_file = format!("{}.tsv", output_path);
+    let hocr_file = format!("{}.hocr", output_path);
+
+    if !output.status.success() {
+        let _ = std::fs::remove_file(&txt_file);
+        let _ = std::fs::remove_file(&tsv_file);
+        let _ = std::fs::remove_file(&hocr_file);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Tesseract error: {}", stderr));
+    }
+
+    let text = std::fs::read_to_string(&txt_file);
+    let words = std::fs::read_to_string(&tsv_file)
+        .map(|tsv| parse_tsv(&tsv))
+        .unwrap_or_default();
+    let hocr = hocr
+        .then(|| std::fs::read_to_string(&hocr_file).ok())
+        .flatten();
+    let _ = std::fs::remove_file(&txt_file);
+    let _ = std::fs::remove_file(&tsv_file);
+    let _ = std::fs::remove_file(&hocr_file);
+
+    Ok(PageText {
+        text: text.map_err(|e| format!("Failed to read OCR output: {}", e))?,
+        confidence: mean_confidence(&words),
+        model: language.to_string(),
+        words,
+        commands: vec![command_text],
+        hocr,
+    })
+}
+
+/// Makes a one-page PDF of `image` with the text tesseract recognizes in it as an
+/// invisible layer over the picture, written under `work_dir`. `dpi` tells
+/// tesseract the image resolution when the file does not record it. Returns the
+/// PDF path and the command line used.
+pub fn searchable_page(
+    image: &Path,
+    language: &str,
+    dpi: Option<u32>,
+    work_dir: &Path,
+    cancel: &Cancel,
+) -> Result<(PathBuf, String), String> {
+    let output_base = work_dir.join(format!("ocr_pdf_{}", Uuid::new_v4()));
+    let output_path = format!("{}", output_base.display());
+
+    let mut command = Command::new("tesseract");
+    command.arg(image).arg(&output_path).arg("-l").arg(language);
+    if let Some(dpi) = dpi {
+        command.arg("--dpi").arg(dpi.to_string());
+    }
+    command.arg("pdf");
+    let command_text = command_line(&command, image, &output_path);
+
+    let output = execute(&mut command, cancel)?;
+    let pdf_path = PathBuf::from(format!("{}.pdf", output_path));
+    if !output.status.success() {
+        let _ = std::fs::remove_file(&pdf_path);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Tesseract error: {}", stderr));
+    }
+
+    Ok((pdf_path, command_text))
+}
+
+/// Runs a tesseract command, stopping it when `cancel` is set.
+fn execute(command: &mut Command, cancel: &Cancel) -> Result<std::process::Output, String> {
+    cancel.output(command).map_err(|e| {
+        if e.kind() == std::io::ErrorKind::Interrupted {
+            e.to_string()
+        } else {
+            format!(
+                "Failed to execute tesseract: {}. Make sure tesseract is installed.",
+                e
+            )
+        }
+    })
+}
+
+/// Extracts the word rows of a tesseract TSV report.
+fn parse_tsv(tsv: &str) -> Vec<Word> {
+    let mut words = Vec::new();
+
+    for line in tsv.lines().skip(1) {
+        let columns: Vec<&str> = line.split('\t').collect();
+        // level 5 rows are words; non-word rows carry a confidence of -1
+        if columns.len() < 12 || columns[0] != "5" || columns[11].trim().is_empty() {
+            continue;
+        }
+        let Ok(confidence) = columns[10].parse::<f32>() else {
+            continue;
+        };
+        if confidence < 0.0 {
+            continue;
+        }
+
+        words.push(Word {
+            text: columns[11].trim().to_string(),
+            confidence,
+        });
+    }
+
+    words
+}
+
+/// Averages the word-level confidences of a page.
+pub fn mean_confidence(words: &[Word]) -> Option<f32> {
+    (!words.is_empty())
+        .then(|| words.iter().map(|word| word.confidence).sum::<f32>() / words.len() as f32)
+}
+
+/// Script confidence under which tesseract's orientation and script detection is
+/// ignored; its scores are small positive numbers and near-zero ones are guesses.
+const MIN_SCRIPT_CONFIDENCE: f32 = 1.0;
+
+/// Detects the dominant script of a page with tesseract's OSD mode (`--psm 0`,
+/// needs `osd.traineddata`). Returns the script, if it is one we have a model
+/// for and the detection is confident, along with the command line used.
+pub fn detect_script(image: &Path, cancel: &Cancel) -> (Option<Script>, String) {
+    let mut command = Command::new("tesseract");
+    command.arg(image).arg("stdout").arg("--psm").arg("0");
+    let command_text = command_line(&command, image, "stdout");
+
+    let output = match cancel.output(&mut command) {
+        Ok(output) if output.status.success() => output,
+        Ok(output) => {
+            println!(
+                "  ⚠️  Warning: Script detection failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+            return (None, command_text);
+        }
+        Err(e) => {
+            println!("  ⚠️  Warning: Failed to execute tesseract: {}", e);
+            return (None, command_text);
+        }
+    };
+
+    let report = String::from_utf8_lossy(&output.stdout);
+    let field = |name: &str| {
+        report
+            .lines()
+            .find_map(|line| line.strip_prefix(name)?.trim().strip_prefix(':'))
+            .map(str::trim)
+    };
+    let confidence = field("Script confidence")
+        .and_then(|value| value.parse::<f32>().ok())
+        .unwrap_or(0.0);
+    let script = field("Script")
+        .and_then(Script::parse)
+        .filter(|_| confidence >= MIN_SCRIPT_CONFIDENCE);
+    (script, command_text)
+}
+
+/// Recognizes a page with the model for `script`, walking the configured fallback
+/// chain while the confidence stays below the threshold. The fallback models are
+/// Devanagari ones, so other scripts only use their own model.
+///
+/// The candidate with the highest confidence wins; its `model` records which entry
+/// of the chain produced the accepted text. With `segment_lines`, every candidate
+/// is read line by line (see [`crate::lines`]), which leaves it without hOCR;
+/// otherwise `hocr` keeps tesseract's hOCR report of it.
+pub fn recognize_page(
+    image: &Path,
+    config: &Config,
+    script: Script,
+    segment_lines: bool,
+    hocr: bool,
+    work_dir: &Path,
+    cancel: &Cancel,
+) -> Result<PageText, String> {
+    let language = config
+        .script_model(script)
+        .ok_or_else(|| format!("No tesseract model configured for {} script", script.name()))?;
+    let fallbacks: &[String] = match script {
+        Script::Devanagari => &config.fallback_languages,
+        _ => &[],
+    };
+
+    let worker = config.ocr_worker_command.as_deref();
+    let recognize = |language: &str| {
+        if segment_lines {
+            lines::recognize_lines(image, language, worker, work_dir, cancel)
+        } else {
+            run_tesseract(image, language, None, hocr, worker, work_dir, cancel)
+        }
+    };
+
+    let mut best = recognize(&language)?;
+    let mut commands = std::mem::take(&mut best.commands);
+
+    for language in fallbacks {
+        if best
+            .confidence
+            .is_some_and(|conf| conf >= config.fallback_min_confidence)
+        {
+            break;
+        }
+
+        match recognize(language) {
+            Ok(mut candidate) => {
+                commands.append(&mut candidate.commands);
+                println!(
+                    "  ↪ Fallback model '{}' scored {:.1} (best so far: '{}' at {:.1})",
+                    language,
+                    candidate.confidence.unwrap_or(0.0),
+                    best.model,
+                    best.confidence.unwrap_or(0.0)
+                );
+                if candidate.confidence.unwrap_or(0.0) > best.confidence.unwrap_or(0.0) {
+                    best = candidate;
+                }
+            }
+            Err(e) => {
+                println!("  ⚠️  Warning: Fallback model '{}' failed: {}", language, e);
+            }
+        }
+    }
+
+    best.commands = commands;
+    Ok(best)
+}
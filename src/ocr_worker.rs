@@ -0,0 +1,181 @@
+use crate::cancel::Cancel;
+use crate::processes;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+/// How often a worker reading a page is checked for cancellation.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A page for a worker, sent as one JSON line on its stdin.
+#[derive(Serialize)]
+struct Request<'a> {
+    image: &'a Path,
+    language: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    psm: Option<u8>,
+}
+
+/// A worker's answer, one JSON line on its stdout: the page's plain text and
+/// TSV report as tesseract writes them, or why it could not be read.
+#[derive(Deserialize)]
+struct Response {
+    #[serde(default)]
+    text: String,
+    #[serde(default)]
+    tsv: String,
+    error: Option<String>,
+}
+
+/// A page read by a worker, with the command line recorded for it.
+pub struct Recognized {
+    pub text: String,
+    pub tsv: String,
+    pub command: String,
+}
+
+/// A running OCR helper. Its answers are read on a thread of their own so a
+/// page can be given up on when the job is cancelled; the helper is killed
+/// when the worker is dropped.
+struct Worker {
+    command: Vec<String>,
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+}
+
+impl Worker {
+    fn start(command: &[String]) -> Result<Worker, String> {
+        let (program, args) = command
+            .split_first()
+            .ok_or_else(|| "The OCR worker command is empty".to_string())?;
+        let mut child = processes::prioritized(Command::new(program).args(args))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(|e| format!("Failed to start OCR worker '{}': {}", program, e))?;
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err("Failed to connect to the OCR worker".to_string());
+        };
+
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(Worker {
+            command: command.to_vec(),
+            child,
+            stdin,
+            lines,
+        })
+    }
+
+    /// Sends one page and waits for the answer. An error leaves the helper in
+    /// an unknown state, so the worker is dropped rather than reused.
+    fn read(&mut self, request: &Request, cancel: &Cancel) -> Result<Response, String> {
+        let mut line = serde_json::to_string(request).map_err(|e| e.to_string())?;
+        line.push('\n');
+        self.stdin
+            .write_all(line.as_bytes())
+            .and_then(|_| self.stdin.flush())
+            .map_err(|e| format!("Failed to send page to OCR worker: {}", e))?;
+        loop {
+            match self.lines.recv_timeout(POLL_INTERVAL) {
+                Ok(line) => {
+                    return serde_json::from_str(&line)
+                        .map_err(|e| format!("Invalid OCR worker response: {}", e));
+                }
+                Err(RecvTimeoutError::Timeout) if cancel.is_cancelled() => {
+                    return Err("Cancelled".to_string());
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err("The OCR worker exited".to_string());
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Workers between pages. There are as many as pages were read at once, and
+/// they live as long as the process.
+fn idle() -> &'static Mutex<Vec<Worker>> {
+    static IDLE: OnceLock<Mutex<Vec<Worker>>> = OnceLock::new();
+    IDLE.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+/// Reads `image` with a running `command` helper, started when none is idle,
+/// so the language models are loaded once per helper instead of once per page.
+pub fn recognize(
+    command: &[String],
+    image: &Path,
+    language: &str,
+    psm: Option<u8>,
+    cancel: &Cancel,
+) -> Result<Recognized, String> {
+    let slot = processes::acquire(cancel).map_err(|e| e.to_string())?;
+    let waiting = match idle().lock() {
+        Ok(mut idle) => idle
+            .iter()
+            .position(|worker| worker.command == command)
+            .map(|position| idle.swap_remove(position)),
+        Err(_) => None,
+    };
+    let mut worker = match waiting {
+        Some(worker) => worker,
+        None => Worker::start(command)?,
+    };
+
+    let response = worker.read(
+        &Request {
+            image,
+            language,
+            psm,
+        },
+        cancel,
+    )?;
+    drop(slot);
+    if let Ok(mut idle) = idle().lock() {
+        idle.push(worker);
+    }
+    if let Some(error) = response.error {
+        return Err(format!("OCR worker error: {}", error));
+    }
+
+    let sent = Request {
+        image: Path::new("{input}"),
+        language,
+        psm,
+    };
+    Ok(Recognized {
+        text: response.text,
+        tsv: response.tsv,
+        command: format!(
+            "{} <<< {}",
+            command.join(" "),
+            serde_json::to_string(&sent).unwrap_or_default()
+        ),
+    })
+}
@@ -0,0 +1,159 @@
+use actix_web::{HttpResponse, get};
+use utoipa::{OpenApi, ToSchema};
+
+/// Form of `POST /upload`. Besides the files, any processing option (`normalize`,
+/// `pages`, `script`, `transliteration`, ...) may be sent as a plain field; each
+/// overrides its configured default for this upload only.
+#[derive(ToSchema)]
+#[allow(dead_code)]
+pub struct UploadForm {
+    /// One or more PDFs, DjVu documents, ZIP archives of page images or images.
+    #[schema(value_type = Vec<String>, format = Binary)]
+    files: Vec<Vec<u8>>,
+    /// Hold the session for `POST /prepare/{session_id}` instead of starting it.
+    prepare: Option<bool>,
+    /// `low`, `normal` (the default) or `high`.
+    priority: Option<String>,
+    /// Password for encrypted PDFs.
+    password: Option<String>,
+    /// Pages to process, e.g. `1-10,15`.
+    pages: Option<String>,
+    /// Named storage directory for the uploads, when configured.
+    storage_profile: Option<String>,
+    /// URL to post the results to once the job ends.
+    callback_url: Option<String>,
+    /// Title of the session, kept with it and shown in listings.
+    title: Option<String>,
+    /// Library or archive the scanned volume belongs to.
+    source_library: Option<String>,
+    /// The volume's call number in that library.
+    shelfmark: Option<String>,
+    /// Comma-separated tags; `GET /sessions?tag=...` lists the sessions carrying one.
+    tags: Option<String>,
+}
+
+/// Form of `POST /inspect`.
+#[derive(ToSchema)]
+#[allow(dead_code)]
+pub struct PdfForm {
+    #[schema(value_type = String, format = Binary)]
+    file: Vec<u8>,
+    /// Password for an encrypted PDF.
+    password: Option<String>,
+}
+
+/// Form of `POST /benchmark`. Every combination of the listed settings is
+/// compared.
+#[derive(ToSchema)]
+#[allow(dead_code)]
+pub struct BenchmarkForm {
+    /// A PDF, DjVu document, ZIP archive of page images or image.
+    #[schema(value_type = String, format = Binary)]
+    file: Vec<u8>,
+    /// Pages to read, spread evenly through the document; 3 by default, at most 10.
+    sample: Option<usize>,
+    /// Comma-separated resolutions to render PDF pages at; `150,300` by default.
+    dpi: Option<String>,
+    /// Comma-separated tesseract page segmentation modes; `3,6` by default.
+    psm: Option<String>,
+    /// Comma-separated `none`, `crop_margins` or `segment_lines`; `none,crop_margins`
+    /// by default.
+    preprocessing: Option<String>,
+    /// Script the pages are written in; `devanagari` by default.
+    script: Option<String>,
+    /// Password for an encrypted PDF.
+    password: Option<String>,
+}
+
+/// Form of `POST /split`; set one of the chunk sizes.
+#[derive(ToSchema)]
+#[allow(dead_code)]
+pub struct SplitForm {
+    #[schema(value_type = String, format = Binary)]
+    file: Vec<u8>,
+    pages_per_chunk: Option<usize>,
+    max_chunk_mb: Option<f64>,
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Sanskrit OCR"),
+    servers((url = "/api/v1")),
+    paths(
+        crate::upload,
+        crate::upload_url,
+        crate::prepare,
+        crate::get_status,
+        crate::events,
+        crate::websocket,
+        crate::cancel,
+        crate::list_sessions,
+        crate::delete_session,
+        crate::retry,
+        crate::get_result,
+        crate::get_stats,
+        crate::get_page,
+        crate::get_page_image,
+        crate::get_page_thumbnail,
+        crate::put_correction,
+        crate::get_corrected,
+        crate::get_diff,
+        crate::get_searchable_pdf,
+        crate::download_result,
+        crate::download_bundle,
+        crate::export_text,
+        crate::suggest,
+        crate::inspect_pdf,
+        crate::run_benchmark,
+        crate::split_pdf,
+        crate::capacity::capacity,
+    ),
+    tags(
+        (name = "ocr", description = "Uploading documents for recognition"),
+        (name = "sessions", description = "Following and managing jobs"),
+        (name = "results", description = "Results of finished jobs"),
+        (name = "text", description = "Working with recognized text"),
+        (name = "pdf", description = "PDF tools"),
+    )
+)]
+struct ApiDoc;
+
+/// Endpoints of the optional `search` feature, merged into [`ApiDoc`] when built.
+#[cfg(feature = "search")]
+#[derive(OpenApi)]
+#[openapi(paths(crate::search::search))]
+struct SearchDoc;
+
+/// The OpenAPI 3 document describing the HTTP API.
+#[get("/openapi.json")]
+pub async fn document() -> HttpResponse {
+    let doc = ApiDoc::openapi();
+    #[cfg(feature = "search")]
+    let doc = doc.merge_from(SearchDoc::openapi());
+    HttpResponse::Ok().json(doc)
+}
+
+const SWAGGER_UI: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="utf-8">
+    <title>Sanskrit OCR API</title>
+    <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css">
+</head>
+<body>
+    <div id="swagger-ui"></div>
+    <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+    <script>
+        SwaggerUIBundle({ url: '/openapi.json', dom_id: '#swagger-ui' });
+    </script>
+</body>
+</html>
+"#;
+
+/// Swagger UI for [`document`], loaded from a CDN.
+#[get("/docs")]
+pub async fn swagger_ui() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(SWAGGER_UI)
+}
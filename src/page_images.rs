@@ -0,0 +1,90 @@
+use crate::preprocess::Side;
+use std::path::{Path, PathBuf};
+
+/// Longest side of a page thumbnail, in pixels.
+pub const THUMBNAIL_SIZE: u32 = 256;
+
+/// Formats browsers show, which page images are kept in as they are.
+const WEB_FORMATS: [&str; 4] = ["png", "jpg", "jpeg", "webp"];
+
+/// File name of a page's image without extension: the page number in the
+/// uploaded document, and the half of a spread it was cut from.
+fn stem(source_page: usize, side: Option<Side>) -> String {
+    match side {
+        None => source_page.to_string(),
+        Some(Side::Left) => format!("{}-left", source_page),
+        Some(Side::Right) => format!("{}-right", source_page),
+    }
+}
+
+/// Copies the page image `image`, as it was read, into `dir` with a thumbnail
+/// beside it; images in other formats than [`WEB_FORMATS`] are kept as PNG. A
+/// page read again replaces its earlier image.
+pub fn keep(
+    image: &Path,
+    dir: &Path,
+    source_page: usize,
+    side: Option<Side>,
+) -> Result<(), String> {
+    let decoded = image::open(image).map_err(|e| format!("Failed to read page image: {}", e))?;
+    std::fs::create_dir_all(dir)
+        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let stem = stem(source_page, side);
+    for earlier in WEB_FORMATS {
+        let _ = std::fs::remove_file(dir.join(format!("{}.{}", stem, earlier)));
+    }
+
+    let extension = image
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_lowercase)
+        .filter(|extension| WEB_FORMATS.contains(&extension.as_str()));
+    match extension {
+        Some(extension) => std::fs::copy(image, dir.join(format!("{}.{}", stem, extension)))
+            .map(|_| ())
+            .map_err(|e| e.to_string()),
+        None => decoded
+            .save(dir.join(format!("{}.png", stem)))
+            .map_err(|e| e.to_string()),
+    }
+    .map_err(|e| format!("Failed to keep page image: {}", e))?;
+
+    decoded
+        .thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+        .save(dir.join(format!("{}.thumbnail.png", stem)))
+        .map_err(|e| format!("Failed to write thumbnail: {}", e))
+}
+
+/// The names [`keep`] may have given a page's image, or its thumbnail.
+pub fn names(source_page: usize, side: Option<Side>, thumbnail: bool) -> Vec<String> {
+    let stem = stem(source_page, side);
+    if thumbnail {
+        return vec![format!("{}.thumbnail.png", stem)];
+    }
+    WEB_FORMATS
+        .iter()
+        .map(|extension| format!("{}.{}", stem, extension))
+        .collect()
+}
+
+/// The image [`keep`] left in `dir` for a page, or its thumbnail.
+pub fn find(
+    dir: &Path,
+    source_page: usize,
+    side: Option<Side>,
+    thumbnail: bool,
+) -> Option<PathBuf> {
+    names(source_page, side, thumbnail)
+        .into_iter()
+        .map(|name| dir.join(name))
+        .find(|path| path.is_file())
+}
+
+/// The type a page image named `name` by [`keep`] is served as.
+pub fn content_type(name: &str) -> &'static str {
+    match name.rsplit_once('.').map(|(_, extension)| extension) {
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("webp") => "image/webp",
+        _ => "image/png",
+    }
+}
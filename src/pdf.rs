@@ -0,0 +1,872 @@
+use crate::config::Config;
+use pdfium_render::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use uuid::Uuid;
+
+/// Resolution pages are rendered at for OCR.
+pub const RENDER_DPI: f64 = 150.0;
+/// Real documents average far more than this per page; 100k pages in a few
+/// kilobytes means every page references the same content stream.
+const MIN_BYTES_PER_PAGE: u64 = 200;
+/// The bytes-per-page check only applies above this page count.
+const SUSPICIOUS_PAGE_COUNT: usize = 1000;
+
+/// Why a PDF was refused before conversion.
+pub enum PdfRejection {
+    /// Over a configured page or byte limit.
+    TooLarge(String),
+    /// Looks like a decompression/render bomb.
+    Suspicious(String),
+    /// Encrypted and no password was given.
+    PasswordRequired(String),
+    /// Encrypted and the given password does not open it.
+    WrongPassword(String),
+}
+
+impl PdfRejection {
+    pub fn code(&self) -> &'static str {
+        match self {
+            PdfRejection::TooLarge(_) => "INPUT_TOO_LARGE",
+            PdfRejection::Suspicious(_) => "SUSPICIOUS_PDF",
+            PdfRejection::PasswordRequired(_) => "PASSWORD_REQUIRED",
+            PdfRejection::WrongPassword(_) => "WRONG_PASSWORD",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            PdfRejection::TooLarge(message)
+            | PdfRejection::Suspicious(message)
+            | PdfRejection::PasswordRequired(message)
+            | PdfRejection::WrongPassword(message) => message,
+        }
+    }
+}
+
+/// Binds the pdfium library once per process: `pdfium_library` from the config if
+/// set, otherwise `libpdfium` next to the executable, otherwise the system one.
+pub fn pdfium(config: &Config) -> Result<&'static Pdfium, String> {
+    static PDFIUM: OnceLock<Result<Pdfium, String>> = OnceLock::new();
+    PDFIUM
+        .get_or_init(|| {
+            let bindings = match library_path(config) {
+                Some(path) => Pdfium::bind_to_library(path),
+                None => Pdfium::bind_to_system_library(),
+            };
+            bindings.map(Pdfium::new).map_err(|e| {
+                format!(
+                    "Failed to load the pdfium library: {}. Install libpdfium or set pdfium_library.",
+                    e
+                )
+            })
+        })
+        .as_ref()
+        .map_err(Clone::clone)
+}
+
+/// The pdfium library [`pdfium`] binds when it is not the system one.
+fn library_path(config: &Config) -> Option<PathBuf> {
+    config.pdfium_library.clone().or_else(|| {
+        std::env::current_exe()
+            .ok()
+            .and_then(|exe| {
+                exe.parent()
+                    .map(Pdfium::pdfium_platform_library_name_at_path)
+            })
+            .filter(|path| path.exists())
+    })
+}
+
+/// Version of the pdfium library, as the `VERSION` file of a
+/// [pdfium-binaries](https://github.com/bblanchon/pdfium-binaries) build gives
+/// it next to the library or in the directory above, e.g. `140.0.7543.0`.
+/// `None` for the system library or a build without the file.
+pub fn version(config: &Config) -> Option<&'static str> {
+    static VERSION: OnceLock<Option<String>> = OnceLock::new();
+    VERSION
+        .get_or_init(|| {
+            let library = library_path(config)?;
+            let dir = library.parent()?;
+            let file = [dir.join("VERSION"), dir.parent()?.join("VERSION")]
+                .into_iter()
+                .find_map(|path| std::fs::read_to_string(path).ok())?;
+            let field = |name: &str| {
+                file.lines()
+                    .find_map(|line| line.trim().strip_prefix(name)?.strip_prefix('='))
+                    .map(str::trim)
+            };
+            Some(format!(
+                "{}.{}.{}.{}",
+                field("MAJOR")?,
+                field("MINOR")?,
+                field("BUILD")?,
+                field("PATCH")?
+            ))
+        })
+        .as_deref()
+}
+
+/// The rejection for a document pdfium could not open for want of the right
+/// password, if that is why.
+fn password_rejection(error: &PdfiumError, password: Option<&str>) -> Option<PdfRejection> {
+    if !matches!(
+        error,
+        PdfiumError::PdfiumLibraryInternalError(PdfiumInternalError::PasswordError)
+    ) {
+        return None;
+    }
+    Some(match password {
+        None => PdfRejection::PasswordRequired(
+            "PDF is encrypted; send its password with the upload".to_string(),
+        ),
+        Some(_) => PdfRejection::WrongPassword("The password does not open this PDF".to_string()),
+    })
+}
+
+/// Opens a PDF with pdfium, decrypting it with `password` if it is encrypted.
+fn open<'a>(
+    pdfium: &'a Pdfium,
+    path: &Path,
+    password: Option<&'a str>,
+) -> Result<PdfDocument<'a>, String> {
+    pdfium.load_pdf_from_file(path, password).map_err(|e| {
+        password_rejection(&e, password).map_or_else(
+            || format!("Failed to open PDF: {}", e),
+            |rejection| rejection.message().to_string(),
+        )
+    })
+}
+
+/// Page count and sizes of a document.
+pub struct PdfInfo {
+    pub pages: usize,
+    /// Page sizes in points, for the pages that were inspected.
+    pub page_sizes: Vec<(f64, f64)>,
+}
+
+/// Reads the page count and the sizes of at most `max_pages` pages.
+pub fn inspect(document: &PdfDocument, max_pages: usize) -> PdfInfo {
+    let pages = document.pages();
+    let inspected = (pages.len() as usize).min(max_pages.max(1));
+    let page_sizes = (0..inspected)
+        .filter_map(|index| pages.page_size(index as PdfPageIndex).ok())
+        .map(|rect| (rect.width().value as f64, rect.height().value as f64))
+        .collect();
+
+    PdfInfo {
+        pages: pages.len() as usize,
+        page_sizes,
+    }
+}
+
+/// Pages picked for processing with an upload, e.g. `12-58,103` or `200-` for
+/// page 200 to the end. A page order, e.g. `1-40,49-56,41-48,57-` for a misbound
+/// signature, also keeps the pages in the order listed.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(try_from = "PageRangesSpec", into = "PageRangesSpec")]
+pub struct PageRanges {
+    spec: String,
+    ranges: Vec<RangeInclusive<usize>>,
+    in_order: bool,
+}
+
+/// A [`PageRanges`] as it is stored: the ranges as given, and whether they
+/// are a page order.
+#[derive(Serialize, Deserialize)]
+struct PageRangesSpec {
+    spec: String,
+    in_order: bool,
+}
+
+impl TryFrom<PageRangesSpec> for PageRanges {
+    type Error = String;
+
+    fn try_from(stored: PageRangesSpec) -> Result<PageRanges, String> {
+        match stored.in_order {
+            true => PageRanges::parse_order(&stored.spec),
+            false => PageRanges::parse(&stored.spec),
+        }
+    }
+}
+
+impl From<PageRanges> for PageRangesSpec {
+    fn from(ranges: PageRanges) -> PageRangesSpec {
+        PageRangesSpec {
+            spec: ranges.spec,
+            in_order: ranges.in_order,
+        }
+    }
+}
+
+impl PageRanges {
+    pub fn parse(spec: &str) -> Result<PageRanges, String> {
+        let invalid = |part: &str| {
+            format!(
+                "Invalid page range '{}' in '{}': use page numbers like 12-58,103",
+                part, spec
+            )
+        };
+        let page = |number: &str, part: &str| match number.trim().parse::<usize>() {
+            Ok(page) if page > 0 => Ok(page),
+            _ => Err(invalid(part)),
+        };
+
+        let mut ranges = Vec::new();
+        for part in spec
+            .split(',')
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+        {
+            let range = match part.split_once('-') {
+                Some((first, "")) => page(first, part)?..=usize::MAX,
+                Some((first, last)) => page(first, part)?..=page(last, part)?,
+                None => page(part, part)?..=page(part, part)?,
+            };
+            if range.is_empty() {
+                return Err(invalid(part));
+            }
+            ranges.push(range);
+        }
+        if ranges.is_empty() {
+            return Err(invalid(spec));
+        }
+
+        Ok(PageRanges {
+            spec: spec.trim().to_string(),
+            ranges,
+            in_order: false,
+        })
+    }
+
+    /// Parses a page order: the pages listed, in the order listed.
+    pub fn parse_order(spec: &str) -> Result<PageRanges, String> {
+        Ok(PageRanges {
+            in_order: true,
+            ..PageRanges::parse(spec)?
+        })
+    }
+
+    pub fn contains(&self, page: usize) -> bool {
+        self.ranges.iter().any(|range| range.contains(&page))
+    }
+}
+
+impl std::fmt::Display for PageRanges {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.spec)
+    }
+}
+
+/// Numbers of the pages of a `page_count`-page document to process: those in
+/// `selection`, in page order or, for a page order, as listed (each page once);
+/// or all of them.
+pub fn selected_pages(
+    selection: Option<&PageRanges>,
+    page_count: usize,
+) -> Result<Vec<usize>, String> {
+    let selected: Vec<usize> = match selection {
+        Some(order) if order.in_order => {
+            let mut pages = Vec::new();
+            for range in &order.ranges {
+                for page in *range.start()..=(*range.end()).min(page_count) {
+                    if !pages.contains(&page) {
+                        pages.push(page);
+                    }
+                }
+            }
+            pages
+        }
+        _ => (1..=page_count)
+            .filter(|&page| selection.is_none_or(|selection| selection.contains(page)))
+            .collect(),
+    };
+    match selection {
+        Some(selection) if selected.is_empty() => Err(format!(
+            "Page range '{}' selects none of the document's {} pages",
+            selection, page_count
+        )),
+        _ => Ok(selected),
+    }
+}
+
+/// Letters (or digits) a page's text layer needs before it is trusted over OCR;
+/// scans often carry a few stray characters from stamps or page numbers.
+const MIN_TEXT_LAYER_CHARS: usize = 20;
+
+/// What a PDF page yields for the pipeline.
+pub enum PageContent {
+    /// Rendered image, to be recognized.
+    Image(PathBuf),
+    /// Text taken from the page's embedded text layer.
+    Text(String),
+}
+
+/// Text of a page's embedded text layer, if it holds enough letters to use.
+fn text_layer(page: &PdfPage) -> Option<String> {
+    let text = page.text().ok()?.all();
+    let letters = text
+        .chars()
+        .filter(|&c| c.is_alphanumeric() || crate::dictionary::is_word_char(c))
+        .count();
+    (letters >= MIN_TEXT_LAYER_CHARS).then_some(text)
+}
+
+/// What an uploaded PDF holds, for deciding whether and how to OCR it.
+#[derive(Serialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct PdfDetails {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub subject: Option<String>,
+    /// Application the document was made with.
+    pub creator: Option<String>,
+    /// Library that wrote the PDF.
+    pub producer: Option<String>,
+    pub page_count: usize,
+    /// `image` for a scan, `text` for a born-digital document, `mixed` when
+    /// some pages are one and some the other (or carry both).
+    pub kind: PageKind,
+    pub pages: Vec<PageDetails>,
+}
+
+/// What a page is made of, to tell scans from born-digital pages.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum PageKind {
+    /// A scanned image without usable text: needs OCR.
+    Image,
+    /// Text without a scanned image: its text layer can be used as is.
+    Text,
+    /// A scanned image with a text layer, usually an earlier OCR.
+    Mixed,
+    /// Neither text nor images.
+    Empty,
+}
+
+/// Images covering at least this share of a page make it a scan.
+const MIN_SCAN_COVERAGE: f32 = 0.5;
+
+/// Area in square points covered by the images among `objects`, looking into form
+/// XObjects, which some scanners wrap page images in.
+fn image_area(objects: PdfPageObjectsIterator<'_>) -> f32 {
+    objects
+        .map(|object| match object.as_x_object_form_object() {
+            Some(form) => image_area(form.iter()),
+            None if object.object_type() == PdfPageObjectType::Image => object
+                .bounds()
+                .map_or(0.0, |bounds| bounds.width().value * bounds.height().value),
+            None => 0.0,
+        })
+        .sum()
+}
+
+/// Classifies a page by whether a scanned image covers it and whether it has a
+/// usable text layer.
+fn page_kind(page: &PdfPage) -> PageKind {
+    let page_area = page.width().value * page.height().value;
+    let scanned =
+        page_area > 0.0 && image_area(page.objects().iter()) / page_area >= MIN_SCAN_COVERAGE;
+    match (scanned, text_layer(page).is_some()) {
+        (true, true) => PageKind::Mixed,
+        (true, false) => PageKind::Image,
+        (false, true) => PageKind::Text,
+        (false, false) => PageKind::Empty,
+    }
+}
+
+#[derive(Serialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct PageDetails {
+    /// Page number, counting from 1.
+    pub page: usize,
+    /// Page size in points.
+    pub width: f64,
+    pub height: f64,
+    /// Whether the page's text layer holds enough text to be used instead of OCR.
+    pub has_text: bool,
+    pub kind: PageKind,
+}
+
+/// Reads a PDF's document information and, for every page, its size, whether it
+/// carries a usable text layer and whether it is a scan. Opens the document with
+/// `password` if it is encrypted.
+pub fn details(path: &Path, password: Option<&str>, pdfium: &Pdfium) -> Result<PdfDetails, String> {
+    let document = open(pdfium, path, password)?;
+    let metadata = document.metadata();
+    let tag = |tag| {
+        metadata
+            .get(tag)
+            .map(|tag| tag.value().trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+
+    let pages = document
+        .pages()
+        .iter()
+        .enumerate()
+        .map(|(index, page)| {
+            let kind = page_kind(&page);
+            PageDetails {
+                page: index + 1,
+                width: page.width().value as f64,
+                height: page.height().value as f64,
+                has_text: matches!(kind, PageKind::Text | PageKind::Mixed),
+                kind,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // Empty pages go either way
+    let mut kinds = pages
+        .iter()
+        .map(|page| page.kind)
+        .filter(|&kind| kind != PageKind::Empty);
+    let kind = match kinds.next() {
+        None => PageKind::Empty,
+        Some(first) if kinds.all(|kind| kind == first) => first,
+        Some(_) => PageKind::Mixed,
+    };
+
+    Ok(PdfDetails {
+        title: tag(PdfDocumentMetadataTagType::Title),
+        author: tag(PdfDocumentMetadataTagType::Author),
+        subject: tag(PdfDocumentMetadataTagType::Subject),
+        creator: tag(PdfDocumentMetadataTagType::Creator),
+        producer: tag(PdfDocumentMetadataTagType::Producer),
+        page_count: pages.len(),
+        kind,
+        pages,
+    })
+}
+
+/// How a PDF's pages were rendered. pdfium is called as a library rather than
+/// run as a program, so results record its settings in place of a command line.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct RenderParameters {
+    pub engine: String,
+    /// The library's version, when its build says (see [`version`]).
+    pub version: Option<String>,
+    pub dpi: f64,
+    /// Whether the document was opened with a password.
+    pub password: bool,
+    /// Whether pages with embedded text took it instead of being rendered.
+    pub text_layer: bool,
+}
+
+/// Pages of a PDF prepared one at a time as they are iterated, so a long
+/// document never has more than the page being recognized on disk: with
+/// `use_text_layer`, pages with embedded text yield that text, and every other
+/// page is rendered to a PNG under the work directory. Yields the original page
+/// numbers with their content, in page order.
+pub struct RenderedPages<'a> {
+    document: PdfDocument<'a>,
+    render_config: PdfRenderConfig,
+    dpi: f64,
+    /// Pixels a rendered page may have, for pages rendered at another
+    /// resolution than [`check`] allowed for.
+    max_pixels: Option<(f64, u64)>,
+    /// Whether the document was opened with a password given.
+    with_password: bool,
+    prefix: String,
+    work_dir: PathBuf,
+    use_text_layer: bool,
+    selected: std::vec::IntoIter<usize>,
+}
+
+impl RenderedPages<'_> {
+    /// Renders pages at `dpi` instead of [`RENDER_DPI`], refusing those that
+    /// would come out larger than `max_pixels`.
+    pub fn with_dpi(mut self, dpi: f64, max_pixels: u64) -> Self {
+        self.render_config = PdfRenderConfig::new().scale_page_by_factor((dpi / 72.0) as f32);
+        self.dpi = dpi;
+        self.max_pixels = Some((dpi, max_pixels));
+        self
+    }
+
+    /// How pages are rendered, for the result provenance.
+    pub fn parameters(&self, config: &Config) -> RenderParameters {
+        RenderParameters {
+            engine: "pdfium".to_string(),
+            version: version(config).map(str::to_string),
+            dpi: self.dpi,
+            password: self.with_password,
+            text_layer: self.use_text_layer,
+        }
+    }
+
+    fn prepare(&self, page_number: usize) -> Result<PageContent, String> {
+        let page = self
+            .document
+            .pages()
+            .get((page_number - 1) as PdfPageIndex)
+            .map_err(|e| format!("Failed to read page {}: {}", page_number, e))?;
+        if let Some((dpi, max_pixels)) = self.max_pixels {
+            let pixels = (page.width().value as f64 / 72.0 * dpi)
+                * (page.height().value as f64 / 72.0 * dpi);
+            if pixels > max_pixels as f64 {
+                return Err(format!(
+                    "Page {} would render to {:.0} megapixels at {} dpi",
+                    page_number,
+                    pixels / 1_000_000.0,
+                    dpi
+                ));
+            }
+        }
+        if self.use_text_layer
+            && let Some(text) = text_layer(&page)
+        {
+            return Ok(PageContent::Text(text));
+        }
+        let image_path = self
+            .work_dir
+            .join(format!("{}-{:04}.png", self.prefix, page_number));
+        page.render_with_config(&self.render_config)
+            .map_err(|e| format!("Failed to render page {}: {}", page_number, e))?
+            .as_image()
+            .into_rgb8()
+            .save(&image_path)
+            .map_err(|e| format!("Failed to write page {}: {}", page_number, e))?;
+        Ok(PageContent::Image(image_path))
+    }
+}
+
+impl Iterator for RenderedPages<'_> {
+    /// A page's number with its content, or with why it could not be prepared.
+    type Item = Result<(usize, PageContent), (usize, String)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let page_number = self.selected.next()?;
+        Some(
+            self.prepare(page_number)
+                .map(|content| (page_number, content))
+                .map_err(|e| (page_number, e)),
+        )
+    }
+
+    /// Passes over `n` pages without rendering them, for picking a document up
+    /// where it was left.
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        if n > 0 {
+            self.selected.nth(n - 1)?;
+        }
+        self.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.selected.size_hint()
+    }
+}
+
+impl ExactSizeIterator for RenderedPages<'_> {}
+
+/// Opens a PDF, with `password` if it is encrypted, for rendering all its pages or
+/// those in `selection` one at a time.
+pub fn render<'a>(
+    path: &Path,
+    work_dir: &Path,
+    pdfium: &'a Pdfium,
+    password: Option<&'a str>,
+    selection: Option<&PageRanges>,
+    use_text_layer: bool,
+) -> Result<RenderedPages<'a>, String> {
+    let document = open(pdfium, path, password)?;
+    let selected = selected_pages(selection, document.pages().len() as usize)?;
+    Ok(RenderedPages {
+        document,
+        render_config: PdfRenderConfig::new().scale_page_by_factor((RENDER_DPI / 72.0) as f32),
+        dpi: RENDER_DPI,
+        max_pixels: None,
+        with_password: password.is_some(),
+        prefix: format!("pdf_convert_{}", Uuid::new_v4()),
+        work_dir: work_dir.to_path_buf(),
+        use_text_layer,
+        selected: selected.into_iter(),
+    })
+}
+
+/// Loads a PDF for splitting, decrypting it with `password` if it is encrypted.
+pub fn load(path: &Path, password: Option<&str>) -> Result<lopdf::Document, String> {
+    match password {
+        Some(password) => lopdf::Document::load_with_password(path, password),
+        None => lopdf::Document::load(path),
+    }
+    .map_err(|e| format!("Failed to read PDF: {}", e))
+}
+
+/// Writes pages `first..=last` (1-based) of `document` to `output` as a new PDF,
+/// dropping every object only the other pages used.
+pub fn extract_pages(
+    document: &lopdf::Document,
+    first: u32,
+    last: u32,
+    output: &Path,
+) -> Result<(), String> {
+    let mut chunk = document.clone();
+    let others: Vec<u32> = chunk
+        .get_pages()
+        .into_keys()
+        .filter(|page| !(first..=last).contains(page))
+        .collect();
+    chunk.delete_pages(&others);
+    chunk.prune_objects();
+    chunk
+        .save(output)
+        .map(|_| ())
+        .map_err(|e| format!("Failed to write pages {}-{}: {}", first, last, e))
+}
+
+/// Page attributes a page may inherit from the page tree above it.
+const INHERITED_KEYS: [&[u8]; 4] = [b"Resources", b"MediaBox", b"CropBox", b"Rotate"];
+
+/// Concatenates the pages of `inputs`, in order, into one PDF at `output`.
+pub fn merge(inputs: &[PathBuf], output: &Path) -> Result<(), String> {
+    let mut merged = lopdf::Document::with_version("1.5");
+    let mut page_ids = Vec::new();
+    let mut max_id = 1;
+
+    for input in inputs {
+        let mut document = lopdf::Document::load(input)
+            .map_err(|e| format!("Failed to read {}: {}", input.display(), e))?;
+        document.renumber_objects_with(max_id);
+        max_id = document.max_id + 1;
+
+        // The page trees are replaced below, so copy what pages inherit from them
+        for page_id in document.get_pages().into_values() {
+            let mut inherited = Vec::new();
+            let mut node = document.get_dictionary(page_id).ok();
+            while let Some(dictionary) = node {
+                for key in INHERITED_KEYS {
+                    if let Ok(value) = dictionary.get(key)
+                        && !inherited.iter().any(|(known, _)| *known == key)
+                    {
+                        inherited.push((key, value.clone()));
+                    }
+                }
+                node = dictionary
+                    .get(b"Parent")
+                    .and_then(lopdf::Object::as_reference)
+                    .and_then(|parent| document.get_dictionary(parent))
+                    .ok();
+            }
+            if let Ok(page) = document.get_dictionary_mut(page_id) {
+                for (key, value) in inherited {
+                    page.set(key, value);
+                }
+            }
+            page_ids.push(page_id);
+        }
+
+        for (id, object) in document.objects {
+            if !matches!(
+                object.type_name(),
+                Ok(b"Catalog" | b"Pages" | b"Outlines" | b"Outline")
+            ) {
+                merged.objects.insert(id, object);
+            }
+        }
+    }
+
+    merged.max_id = max_id;
+    let pages_id = merged.new_object_id();
+    for &page_id in &page_ids {
+        if let Ok(page) = merged.get_dictionary_mut(page_id) {
+            page.set("Parent", pages_id);
+        }
+    }
+    merged.objects.insert(
+        pages_id,
+        lopdf::dictionary! {
+            "Type" => "Pages",
+            "Kids" => page_ids.iter().map(|&id| id.into()).collect::<Vec<lopdf::Object>>(),
+            "Count" => page_ids.len() as i64
+        }
+        .into(),
+    );
+    let catalog_id = merged.add_object(lopdf::dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id
+    });
+    merged.trailer.set("Root", catalog_id);
+    merged.renumber_objects();
+    merged.compress();
+    merged
+        .save(output)
+        .map(|_| ())
+        .map_err(|e| format!("Failed to write {}: {}", output.display(), e))
+}
+
+/// Largest `/Width × /Height` pair declared in an uncompressed image dictionary.
+fn largest_declared_image(bytes: &[u8]) -> u64 {
+    let text = String::from_utf8_lossy(bytes);
+    let number_after = |dict: &str, key: &str| -> Option<u64> {
+        let rest = &dict[dict.find(key)? + key.len()..];
+        rest.split(|c: char| !c.is_ascii_digit())
+            .find(|token| !token.is_empty())
+            .and_then(|token| token.parse().ok())
+    };
+
+    text.split("<<")
+        .filter(|dict| dict.contains("/Image"))
+        .filter_map(|dict| {
+            let width = number_after(dict, "/Width")?;
+            let height = number_after(dict, "/Height")?;
+            Some(width.saturating_mul(height))
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Streams the file through `largest_declared_image` in blocks, overlapping them
+/// so a dictionary split across a block boundary is still seen whole.
+fn scan_declared_images(path: &Path) -> u64 {
+    const BLOCK: usize = 4 * 1024 * 1024;
+    const OVERLAP: usize = 512;
+
+    let Ok(mut file) = std::fs::File::open(path) else {
+        return 0;
+    };
+    let mut buffer = vec![0u8; BLOCK + OVERLAP];
+    let mut carried = 0;
+    let mut largest = 0;
+
+    loop {
+        let read = match file.read(&mut buffer[carried..]) {
+            Ok(0) | Err(_) => break,
+            Ok(read) => read,
+        };
+        let filled = carried + read;
+        largest = largest.max(largest_declared_image(&buffer[..filled]));
+
+        carried = OVERLAP.min(filled);
+        buffer.copy_within(filled - carried..filled, 0);
+    }
+
+    largest
+}
+
+/// Enforces the configured size limits and bomb heuristics on an uploaded PDF,
+/// opening it with `password` if it is encrypted.
+///
+/// Returns the page count and sizes when the document is acceptable. `Ok(None)`
+/// means the document could not be inspected (e.g. pdfium missing or the file is
+/// damaged); the renderer will then report its own error.
+pub fn check(
+    path: &Path,
+    config: &Config,
+    password: Option<&str>,
+) -> Result<Option<PdfInfo>, PdfRejection> {
+    let bytes = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    if bytes > config.max_pdf_bytes {
+        return Err(PdfRejection::TooLarge(format!(
+            "PDF is {} MB; the limit is {} MB",
+            bytes / (1024 * 1024),
+            config.max_pdf_bytes / (1024 * 1024)
+        )));
+    }
+
+    let pixels = scan_declared_images(path);
+    if pixels > config.max_page_pixels.saturating_mul(4) {
+        return Err(PdfRejection::Suspicious(format!(
+            "PDF declares an embedded image of {} megapixels",
+            pixels / 1_000_000
+        )));
+    }
+
+    let pdfium = match pdfium(config) {
+        Ok(pdfium) => pdfium,
+        Err(e) => {
+            println!("  ⚠️  Warning: Could not analyze PDF: {}", e);
+            return Ok(None);
+        }
+    };
+    let info = match pdfium.load_pdf_from_file(path, password) {
+        Ok(document) => inspect(&document, config.max_pdf_pages),
+        Err(e) => {
+            if let Some(rejection) = password_rejection(&e, password) {
+                return Err(rejection);
+            }
+            println!("  ⚠️  Warning: Could not analyze PDF: {}", e);
+            return Ok(None);
+        }
+    };
+
+    if info.pages > config.max_pdf_pages {
+        return Err(PdfRejection::TooLarge(format!(
+            "PDF has {} pages; the limit is {}",
+            info.pages, config.max_pdf_pages
+        )));
+    }
+
+    if info.pages > SUSPICIOUS_PAGE_COUNT && bytes / (info.pages as u64) < MIN_BYTES_PER_PAGE {
+        return Err(PdfRejection::Suspicious(format!(
+            "PDF declares {} pages in only {} bytes",
+            info.pages, bytes
+        )));
+    }
+
+    for (index, (width, height)) in info.page_sizes.iter().enumerate() {
+        let pixels = (width / 72.0 * RENDER_DPI) * (height / 72.0 * RENDER_DPI);
+        if pixels > config.max_page_pixels as f64 {
+            return Err(PdfRejection::Suspicious(format!(
+                "Page {} would render to {:.0} megapixels ({:.0} x {:.0} pt)",
+                index + 1,
+                pixels / 1_000_000.0,
+                width,
+                height
+            )));
+        }
+    }
+
+    Ok(Some(info))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refuses_invalid_ranges() {
+        for spec in ["0", "0-4", "5-3", "", " , ", "a-b", "-4"] {
+            assert!(PageRanges::parse(spec).is_err(), "{:?}", spec);
+        }
+    }
+
+    #[test]
+    fn selects_pages_in_range() {
+        let ranges = PageRanges::parse("2-3, 7, 9-").unwrap();
+        assert_eq!(selected_pages(Some(&ranges), 10), Ok(vec![2, 3, 7, 9, 10]));
+        assert_eq!(selected_pages(None, 3), Ok(vec![1, 2, 3]));
+    }
+
+    #[test]
+    fn refuses_ranges_past_the_last_page() {
+        let ranges = PageRanges::parse("12-58").unwrap();
+        assert!(selected_pages(Some(&ranges), 10).is_err());
+        let order = PageRanges::parse_order("11-").unwrap();
+        assert!(selected_pages(Some(&order), 10).is_err());
+    }
+
+    #[test]
+    fn keeps_a_page_order_as_listed() {
+        let order = PageRanges::parse_order("1-2,5-6,3-4,2,7-").unwrap();
+        assert_eq!(
+            selected_pages(Some(&order), 8),
+            Ok(vec![1, 2, 5, 6, 3, 4, 7, 8])
+        );
+    }
+
+    #[test]
+    fn stores_ranges_as_given() {
+        let order = PageRanges::parse_order("3-4,1").unwrap();
+        let json = serde_json::to_string(&order).unwrap();
+        let stored: PageRanges = serde_json::from_str(&json).unwrap();
+        assert_eq!(stored.to_string(), "3-4,1");
+        assert_eq!(selected_pages(Some(&stored), 4), Ok(vec![3, 4, 1]));
+    }
+}
@@ -0,0 +1,2107 @@
+//! The OCR pipeline without any HTTP layer: page recognition, the per-page and
+//! document-level text stages, and the result types the server returns as JSON.
+
+use crate::annotate::{self, HighlightStyle};
+use crate::cancel::Cancel;
+use crate::config::Config;
+use crate::dictionary::{self, Correction, Dictionary};
+use crate::document::Document;
+use crate::engine::OcrEngine;
+use crate::marks;
+use crate::matter::{self, MatterHandling, Section};
+use crate::morphology::{self, WordAnalysis};
+use crate::normalize::{DandaStyle, DigitStyle, Punctuation};
+use crate::pdf::{PageContent, PageRanges};
+use crate::preprocess::{Rotation, Side};
+use crate::replace::{self, Rule};
+use crate::script::{self, Script, ScriptOutput};
+use crate::transliterate::{self, Scheme};
+use crate::{
+    archive, chandas, convert, dehyphenate, djvu, normalize, ocr, page_images, pdf, preprocess,
+    sandhi, verse,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::{Mutex, mpsc};
+use std::time::Instant;
+use uuid::Uuid;
+
+/// A progress update reported while a file is being processed.
+#[derive(Clone)]
+pub struct Progress {
+    pub stage: String,
+    pub current: usize,
+    pub total: usize,
+    pub message: String,
+    /// The page of a multi-page document that was just read, on the update
+    /// reporting it.
+    pub page: Option<FinishedPage>,
+    /// Mean time the latest pages of a multi-page document took, in seconds,
+    /// once one is done.
+    pub seconds_per_page: Option<f64>,
+    /// The pages a rendered page of a multi-page document made, read or
+    /// failed, on the update after they were put in place. Saved, they let an
+    /// interrupted job pick the document up after them through
+    /// [`ProcessingOptions::read_pages`].
+    pub read_pages: Vec<PageResult>,
+}
+
+/// A page's text as soon as it is read, before the document-level stages.
+#[derive(Clone, Debug)]
+pub struct FinishedPage {
+    pub page_number: usize,
+    pub source_page: usize,
+    pub text: String,
+    pub confidence: Option<f32>,
+}
+
+impl Progress {
+    fn new(stage: &str, current: usize, total: usize, message: String) -> Progress {
+        Progress {
+            stage: stage.to_string(),
+            current,
+            total,
+            message,
+            page: None,
+            seconds_per_page: None,
+            read_pages: Vec::new(),
+        }
+    }
+}
+
+/// Source pages the rolling page time averages over, so the estimate follows
+/// a document whose pages get denser or sparser.
+const PAGE_TIME_WINDOW: usize = 10;
+
+/// How long the latest pages of a document took.
+struct PageTimes {
+    recent: VecDeque<f64>,
+    last: Instant,
+}
+
+impl PageTimes {
+    fn start() -> PageTimes {
+        PageTimes {
+            recent: VecDeque::with_capacity(PAGE_TIME_WINDOW),
+            last: Instant::now(),
+        }
+    }
+
+    /// Records that a page is done, counting the time since the previous one.
+    fn lap(&mut self) {
+        if self.recent.len() == PAGE_TIME_WINDOW {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(self.last.elapsed().as_secs_f64());
+        self.last = Instant::now();
+    }
+
+    /// Mean time of the latest pages, in seconds.
+    fn average(&self) -> Option<f64> {
+        (!self.recent.is_empty())
+            .then(|| self.recent.iter().sum::<f64>() / self.recent.len() as f64)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct OcrResult {
+    pub filename: String,
+    pub text: String,
+    pub success: bool,
+    pub error: Option<String>,
+    pub pages_processed: Option<usize>,
+    pub total_pages: Option<usize>,
+    pub estimated_time_seconds: Option<f64>,
+    pub pages: Vec<PageResult>,
+    pub front_matter_text: Option<String>,
+    pub back_matter_text: Option<String>,
+    /// Verse structure of the text, when verse detection was requested.
+    pub verses: Option<Vec<verse::Verse>>,
+    /// Script requested for the file; with detection on, each page records the
+    /// script it was read in. The text is Devanagari unless `script_output` asked
+    /// to keep the source script.
+    pub script: Script,
+    pub transliteration_scheme: Option<Scheme>,
+    pub transliterated_text: Option<String>,
+    /// `text` with low-confidence words marked for proofreading.
+    pub annotated_text: Option<String>,
+    /// Paragraph variant of `text` with line breaks and broken words rejoined.
+    pub joined_text: Option<String>,
+    /// The text with every line prefixed by `[page:line]`, when requested.
+    pub provenance_text: Option<String>,
+    /// External tool invocations used for this file, with `{input}`/`{output}`
+    /// standing in for temporary paths.
+    pub commands: Vec<String>,
+    /// How a PDF's pages were rendered.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pdf_rendering: Option<pdf::RenderParameters>,
+    pub tool_versions: BTreeMap<String, String>,
+    /// Searchable PDF of the file, when requested: the page images with the
+    /// recognized text as an invisible layer. [`process_file`] leaves the path it
+    /// was written to; the server replaces it with the download path.
+    pub searchable_pdf: Option<String>,
+    /// The job was cancelled while this file was processed; the pages read
+    /// before that are kept.
+    #[serde(default)]
+    pub cancelled: bool,
+    /// Machine-readable reason for `error`, e.g. [`QUOTA_EXCEEDED`].
+    #[serde(default)]
+    pub error_code: Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct PageResult {
+    /// Position of the page in this result, counting from 1.
+    pub page_number: usize,
+    /// Page number in the uploaded document, which differs from `page_number`
+    /// when only some pages were selected.
+    pub source_page: usize,
+    /// Whether the text was recognized or taken from the PDF's text layer.
+    pub origin: TextOrigin,
+    /// Half of a two-page spread the page was cut from, when it was.
+    pub spread_side: Option<Side>,
+    /// Factor the page image's sides were scaled by before OCR, when it was
+    /// larger than `max_ocr_pixels` or `max_ocr_dimension` allow.
+    #[serde(default)]
+    pub scale: Option<f32>,
+    pub text: String,
+    pub confidence: Option<f32>,
+    /// Time spent reading the page and running its text stages, in seconds.
+    pub seconds: Option<f64>,
+    pub model: String,
+    pub section: Section,
+    pub script: Script,
+    /// Number of visarga, anusvāra and candrabindu repairs, when that pass ran.
+    pub mark_corrections: Option<usize>,
+    /// Low-confidence words replaced by their closest dictionary form.
+    pub corrections: Vec<Correction>,
+    /// The recognized text before dictionary corrections, when any were applied.
+    pub uncorrected_text: Option<String>,
+    /// The page's text as corrected by a proofreader, once submitted.
+    pub proofread_text: Option<String>,
+    /// The text with low-confidence words marked, when highlighting was requested.
+    pub annotated_text: Option<String>,
+    /// The text as paragraphs, with lines rejoined and broken words mended.
+    pub joined_text: Option<String>,
+    /// Line-by-line word segmentation produced by the sandhi splitter.
+    pub segmented_text: Option<String>,
+    /// Lemma and grammatical analyses for each distinct word of the page.
+    pub morphology: Option<Vec<WordAnalysis>>,
+    pub transliterated_text: Option<String>,
+    /// Devanagari and romanized text side by side, line by line.
+    pub parallel_lines: Option<Vec<ParallelLine>>,
+    /// Why the page could not be read; its text is empty then.
+    #[serde(default)]
+    pub error: Option<String>,
+    /// Tesseract's hOCR report of the page, when `hocr` was requested.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hocr: Option<String>,
+    #[serde(skip)]
+    pub words: Vec<ocr::Word>,
+}
+
+/// Where a page's text came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum TextOrigin {
+    /// Read from the page image by tesseract.
+    Recognized,
+    /// Taken from the PDF's embedded text layer.
+    Extracted,
+    /// Not read: the page is blank.
+    Blank,
+}
+
+/// `error_code` of files stopped because their session went over its disk
+/// quota.
+pub const QUOTA_EXCEEDED: &str = "QUOTA_EXCEEDED";
+
+/// A session's disk quota: the bytes it may take, and those it takes outside
+/// the work directory of the file being processed, such as its uploads and
+/// the output of files done before.
+#[derive(Clone, Copy)]
+pub struct DiskQuota {
+    pub limit: u64,
+    pub used: u64,
+}
+
+impl DiskQuota {
+    /// Why a session over its quota was stopped.
+    pub fn exceeded(&self) -> String {
+        format!(
+            "The session took more than the {} bytes of disk space it is allowed",
+            self.limit
+        )
+    }
+}
+
+/// `model` reported for pages whose text came from the PDF's text layer.
+pub const TEXT_LAYER_MODEL: &str = "text-layer";
+
+impl PageResult {
+    pub fn new(page_number: usize, page: ocr::PageText, script: Script) -> PageResult {
+        PageResult {
+            page_number,
+            source_page: page_number,
+            origin: TextOrigin::Recognized,
+            spread_side: None,
+            scale: None,
+            text: page.text.trim().to_string(),
+            confidence: page.confidence,
+            seconds: None,
+            model: page.model,
+            section: Section::Body,
+            script,
+            mark_corrections: None,
+            corrections: Vec::new(),
+            uncorrected_text: None,
+            proofread_text: None,
+            annotated_text: None,
+            joined_text: None,
+            segmented_text: None,
+            morphology: None,
+            transliterated_text: None,
+            parallel_lines: None,
+            error: None,
+            hocr: page.hocr,
+            words: page.words,
+        }
+    }
+
+    /// A page that could not be rendered or recognized, kept so it can be retried.
+    pub fn failed(
+        page_number: usize,
+        source_page: usize,
+        spread_side: Option<Side>,
+        error: String,
+        options: &ProcessingOptions,
+    ) -> PageResult {
+        let text = ocr::PageText {
+            text: String::new(),
+            confidence: None,
+            model: String::new(),
+            words: Vec::new(),
+            commands: Vec::new(),
+            hocr: None,
+        };
+        PageResult {
+            source_page,
+            spread_side,
+            error: Some(error),
+            ..PageResult::new(page_number, text, options.script)
+        }
+    }
+
+    /// Whether the page is worth reading again: it failed or came out empty, and
+    /// no proofreader has supplied its text since.
+    pub fn needs_retry(&self) -> bool {
+        self.proofread_text.is_none() && (self.error.is_some() || self.text.trim().is_empty())
+    }
+
+    /// Text whose words go to the morphological analyzer: the sandhi-split
+    /// version when there is one, since analyzers expect separate words.
+    pub fn morphology_source(&self) -> &str {
+        self.segmented_text.as_deref().unwrap_or(&self.text)
+    }
+
+    /// Runs the per-page text stages requested for this job, in order: conversion
+    /// to Devanagari, normalization, digit/punctuation conventions, find/replace
+    /// rules, visarga/anusvāra repair, dictionary correction, highlighting, line joining, sandhi splitting.
+    pub fn process(
+        &mut self,
+        options: &ProcessingOptions,
+        config: &Config,
+        dictionary: &Dictionary,
+    ) {
+        if options.script_output == ScriptOutput::Devanagari {
+            self.text = script::to_devanagari(&self.text, self.script);
+            for word in &mut self.words {
+                word.text = script::to_devanagari(&word.text, self.script);
+            }
+        }
+
+        if options.normalize {
+            self.text = normalize::normalize_devanagari(&self.text);
+            for word in &mut self.words {
+                word.text = normalize::normalize_devanagari(&word.text);
+            }
+        }
+
+        if options.punctuation != Punctuation::default() {
+            self.text = options.punctuation.apply(&self.text);
+            for word in &mut self.words {
+                word.text = options.punctuation.apply(&word.text);
+            }
+        }
+
+        for rules in [&options.replacements, &options.extra_replacements] {
+            if !rules.is_empty() {
+                self.text = replace::apply(&self.text, rules);
+                for word in &mut self.words {
+                    word.text = replace::apply(&word.text, rules);
+                }
+            }
+        }
+
+        if options.fix_marks {
+            let (fixed, count) = marks::fix_marks(&self.text, dictionary);
+            self.text = fixed;
+            self.mark_corrections = Some(count);
+            for word in &mut self.words {
+                word.text = marks::fix_marks(&word.text, dictionary).0;
+            }
+        }
+
+        if options.correct && !dictionary.is_empty() {
+            let (corrected, corrections) = dictionary::correct_page(
+                &self.text,
+                &self.words,
+                dictionary,
+                config.correction_max_confidence,
+                config.correction_max_distance,
+            );
+            if !corrections.is_empty() {
+                self.uncorrected_text = Some(std::mem::replace(&mut self.text, corrected));
+                self.corrections = corrections;
+            }
+        }
+
+        if let Some(style) = options.highlight {
+            self.annotated_text = Some(annotate::highlight(
+                &self.text,
+                &self.words,
+                config.highlight_max_confidence,
+                style,
+            ));
+        }
+
+        if options.join_lines {
+            self.joined_text = Some(dehyphenate::join_lines(&self.text, dictionary));
+        }
+
+        if options.sandhi {
+            let segmented = sandhi::split_text(&self.text, config.sandhi_command.as_deref())
+                .unwrap_or_else(|e| {
+                    println!("  ⚠️  Warning: {}; using built-in sandhi rules", e);
+                    sandhi::split_text(&self.text, None).unwrap_or_default()
+                });
+            self.segmented_text = Some(segmented);
+        }
+
+        if options.morphology
+            && let Some(command) = &config.morphology.command
+        {
+            let words = morphology::words(self.morphology_source());
+            if !words.is_empty() {
+                match morphology::analyze_with_command(words, command) {
+                    Ok(analyses) => self.morphology = Some(analyses),
+                    Err(e) => println!("  ⚠️  Warning: {}", e),
+                }
+            }
+        }
+    }
+}
+
+/// Processing options for a job; the server fills these from form fields.
+/// They serialize without the cancellation flag, for keeping a job to run
+/// later.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ProcessingOptions {
+    pub normalize: bool,
+    pub punctuation: Punctuation,
+    /// Find/replace rules from the server defaults or a named profile.
+    pub replacements: Vec<Rule>,
+    /// Rules sent with the request, applied after `replacements`.
+    pub extra_replacements: Vec<Rule>,
+    pub fix_marks: bool,
+    pub correct: bool,
+    pub matter: MatterHandling,
+    pub sandhi: bool,
+    pub morphology: bool,
+    pub verses: bool,
+    pub meter: bool,
+    /// Also return the text with low-confidence words marked in this style.
+    pub highlight: Option<HighlightStyle>,
+    /// Also return paragraph text with words broken across lines rejoined.
+    pub join_lines: bool,
+    /// Also return the text with page and line markers on every line.
+    pub provenance: bool,
+    pub transliteration: Option<Scheme>,
+    /// Return each page's lines in Devanagari and romanized form side by side,
+    /// using `transliteration` or IAST.
+    pub parallel: bool,
+    /// Script the pages are written in, which picks the tesseract model.
+    pub script: Script,
+    /// Detect each page's script, falling back to `script` when unsure.
+    pub detect_script: bool,
+    pub script_output: ScriptOutput,
+    /// Pages of a PDF to process; all of them when unset.
+    pub pages: Option<PageRanges>,
+    /// Password of the PDF being processed, when it is encrypted.
+    pub password: Option<String>,
+    /// Order to process the pages of the file being processed in, overriding
+    /// `pages`; pages left out are not processed.
+    pub page_order: Option<PageRanges>,
+    /// Turns applied to page images of the file being processed before OCR, by
+    /// page number in the uploaded document.
+    pub rotations: BTreeMap<usize, Rotation>,
+    /// Use a PDF page's embedded text instead of OCR when it has enough of it.
+    pub use_text_layer: bool,
+    /// Also produce a searchable PDF of each file.
+    pub searchable_pdf: bool,
+    /// Find the text lines of each page and OCR them one by one.
+    pub segment_lines: bool,
+    /// Keep tesseract's hOCR report of each page read whole.
+    #[serde(default)]
+    pub hocr: bool,
+    /// Leave blank pages of multi-page documents unread.
+    pub skip_blank_pages: bool,
+    /// Cut pages that are two-page spreads into their left and right pages.
+    pub split_spreads: bool,
+    /// Crop dark scanner borders and wide margins off pages before OCR.
+    pub crop_margins: bool,
+    /// Pages of a multi-page document read at once.
+    pub page_workers: usize,
+    /// Where rendered pages and tesseract output are written.
+    pub work_dir: PathBuf,
+    /// Disk quota of the session the file being processed belongs to, which
+    /// its temporary files count against; none when unset.
+    #[serde(skip)]
+    pub disk_quota: Option<DiskQuota>,
+    /// Where to keep the image each page of the file being processed was read
+    /// from, for showing next to its text; see [`page_images`].
+    pub page_images: Option<PathBuf>,
+    /// Free-form job notes passed through to completion notifications.
+    pub notes: Option<String>,
+    /// HTTP(S) URL that receives a POST with the results when the job finishes.
+    pub callback_url: Option<String>,
+    /// Stops the job, and any tool it is running, once set; keep a clone to set
+    /// it from elsewhere.
+    #[serde(skip)]
+    pub cancel: Cancel,
+    /// Pages of the multi-page document being processed that were read before
+    /// its job was interrupted, in order. They are taken as they are and the
+    /// document is picked up after them, unless a searchable PDF is wanted,
+    /// which needs every page's image.
+    #[serde(skip)]
+    pub read_pages: Vec<PageResult>,
+}
+
+/// Parses a true/false form field such as `1`, `yes` or `off`.
+pub fn parse_flag(name: &str, value: &str) -> Result<bool, String> {
+    match value.trim().to_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Ok(true),
+        "0" | "false" | "no" | "off" => Ok(false),
+        _ => Err(format!(
+            "Invalid value '{}' for '{}': expected true or false",
+            value, name
+        )),
+    }
+}
+
+impl ProcessingOptions {
+    pub fn new(config: &Config) -> ProcessingOptions {
+        ProcessingOptions {
+            normalize: config.normalize_unicode,
+            punctuation: config.punctuation,
+            replacements: config.replacements.clone(),
+            extra_replacements: Vec::new(),
+            fix_marks: config.fix_marks,
+            correct: config.dictionary_correction,
+            matter: config.matter_handling,
+            sandhi: config.sandhi_split,
+            morphology: config.analyze_morphology,
+            verses: config.detect_verses,
+            meter: config.identify_meter,
+            highlight: config.highlight,
+            join_lines: config.join_lines,
+            provenance: config.provenance_markers,
+            transliteration: None,
+            parallel: config.parallel_lines,
+            script: Script::Devanagari,
+            detect_script: config.detect_script,
+            script_output: config.script_output,
+            pages: None,
+            password: None,
+            page_order: None,
+            rotations: BTreeMap::new(),
+            use_text_layer: config.use_text_layer,
+            searchable_pdf: false,
+            segment_lines: config.segment_lines,
+            hocr: config.hocr,
+            skip_blank_pages: config.skip_blank_pages,
+            split_spreads: config.split_spreads,
+            crop_margins: config.crop_margins,
+            page_workers: config.page_workers(),
+            work_dir: config.temp_dir(),
+            disk_quota: None,
+            page_images: None,
+            notes: None,
+            callback_url: None,
+            cancel: Cancel::default(),
+            read_pages: Vec::new(),
+        }
+    }
+
+    /// Pages to process and their order: `page_order` when set, else `pages`.
+    pub fn selection(&self) -> Option<&PageRanges> {
+        self.page_order.as_ref().or(self.pages.as_ref())
+    }
+
+    pub fn set(&mut self, config: &Config, name: &str, value: &str) -> Result<(), String> {
+        match name {
+            "normalize" => self.normalize = parse_flag(name, value)?,
+            "correct" => self.correct = parse_flag(name, value)?,
+            "fix_marks" => self.fix_marks = parse_flag(name, value)?,
+            "replacement_profile" => {
+                self.replacements = config
+                    .replacement_profiles
+                    .get(value.trim())
+                    .cloned()
+                    .ok_or_else(|| format!("Unknown replacement profile '{}'", value.trim()))?;
+            }
+            "replacements" => self.extra_replacements = replace::parse_rules(value)?,
+            "collapse_avagraha" => {
+                self.punctuation.collapse_avagraha = parse_flag(name, value)?;
+            }
+            "digits" => {
+                self.punctuation.digits = DigitStyle::parse(value).ok_or_else(|| {
+                    format!(
+                        "Unknown digit style '{}'. Use keep, ascii or devanagari.",
+                        value
+                    )
+                })?;
+            }
+            "dandas" => {
+                self.punctuation.dandas = DandaStyle::parse(value).ok_or_else(|| {
+                    format!("Unknown daṇḍa style '{}'. Use keep, danda or pipe.", value)
+                })?;
+            }
+            "sandhi" => self.sandhi = parse_flag(name, value)?,
+            "morphology" => self.morphology = parse_flag(name, value)?,
+            "verses" => self.verses = parse_flag(name, value)?,
+            "meter" => self.meter = parse_flag(name, value)?,
+            "highlight" if value.trim().is_empty() || value == "none" => self.highlight = None,
+            "highlight" => {
+                self.highlight = Some(HighlightStyle::parse(value).ok_or_else(|| {
+                    format!(
+                        "Unknown highlight style '{}'. Use brackets, html or none.",
+                        value
+                    )
+                })?);
+            }
+            "join_lines" => self.join_lines = parse_flag(name, value)?,
+            "provenance" => self.provenance = parse_flag(name, value)?,
+            "notes" => self.notes = Some(value.to_string()).filter(|v| !v.trim().is_empty()),
+            "callback_url" => {
+                let url = value.trim();
+                if !url.starts_with("http://") && !url.starts_with("https://") {
+                    return Err(format!(
+                        "Invalid callback_url '{}': expected an http:// or https:// URL",
+                        value
+                    ));
+                }
+                self.callback_url = Some(url.to_string());
+            }
+            "matter" => {
+                self.matter = MatterHandling::parse(value).ok_or_else(|| {
+                    format!(
+                        "Unknown matter handling '{}'. Use keep, exclude or separate.",
+                        value
+                    )
+                })?;
+            }
+            "script" if value.trim().eq_ignore_ascii_case("auto") => self.detect_script = true,
+            "script" => {
+                self.detect_script = false;
+                self.script = Script::parse(value).ok_or_else(|| {
+                    format!(
+                        "Unknown script '{}'. Use auto, devanagari, grantha, sharada, telugu, malayalam or latin.",
+                        value
+                    )
+                })?;
+            }
+            "script_output" => {
+                self.script_output = ScriptOutput::parse(value).ok_or_else(|| {
+                    format!(
+                        "Unknown script output '{}'. Use devanagari or source.",
+                        value
+                    )
+                })?;
+            }
+            "parallel" => self.parallel = parse_flag(name, value)?,
+            "segment_lines" => self.segment_lines = parse_flag(name, value)?,
+            "hocr" => self.hocr = parse_flag(name, value)?,
+            "skip_blank_pages" => self.skip_blank_pages = parse_flag(name, value)?,
+            "split_spreads" => self.split_spreads = parse_flag(name, value)?,
+            "crop_margins" => self.crop_margins = parse_flag(name, value)?,
+            "use_text_layer" => self.use_text_layer = parse_flag(name, value)?,
+            "searchable_pdf" => self.searchable_pdf = parse_flag(name, value)?,
+            "pages" if value.trim().is_empty() => self.pages = None,
+            "pages" => self.pages = Some(PageRanges::parse(value)?),
+            // Not trimmed: spaces may be part of the password
+            "password" => self.password = Some(value.to_string()).filter(|v| !v.is_empty()),
+            "transliteration" if value.trim().is_empty() || value == "none" => {
+                self.transliteration = None;
+            }
+            "transliteration" => {
+                self.transliteration = Some(Scheme::parse(value).ok_or_else(|| {
+                    format!(
+                        "Unknown transliteration scheme '{}'. Use iast, slp1, hk, itrans or velthuis.",
+                        value
+                    )
+                })?);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// Joins page texts line by line, prefixing each non-blank line with its page and
+/// line number on that page, e.g. `[12:3] धर्मक्षेत्रे कुरुक्षेत्रे`.
+pub fn provenance_text<'a>(pages: impl IntoIterator<Item = &'a PageResult>) -> String {
+    let mut text = String::new();
+    for page in pages {
+        let lines = page.text.lines().filter(|line| !line.trim().is_empty());
+        for (index, line) in lines.enumerate() {
+            text.push_str(&format!(
+                "[{}:{}] {}\n",
+                page.page_number,
+                index + 1,
+                line.trim_end()
+            ));
+        }
+    }
+    text.trim_end().to_string()
+}
+
+/// How pages are set apart in exported plain text.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PageSeparator {
+    /// Nothing but a blank line between pages.
+    None,
+    /// A form feed starting each page after the first, as `pdftotext` writes.
+    FormFeed,
+    /// A line before each page, `{page}` in it replaced by the page's number,
+    /// `{source_page}` by its number in the uploaded document and `{file}` by
+    /// the name of the file it is from.
+    Template(String),
+}
+
+impl PageSeparator {
+    /// `none`, `formfeed` (or `ff`), or any other text as a template.
+    pub fn parse(value: &str) -> PageSeparator {
+        match value.trim().to_lowercase().as_str() {
+            "none" => PageSeparator::None,
+            "formfeed" | "form_feed" | "ff" => PageSeparator::FormFeed,
+            _ => PageSeparator::Template(value.to_string()),
+        }
+    }
+
+    /// Joins the text of every page, each given with the name of its file,
+    /// empty pages included so that but for [`PageSeparator::None`] pages can
+    /// be counted off the separators.
+    pub fn join<'a>(
+        &self,
+        pages: impl IntoIterator<Item = (&'a str, &'a PageResult, &'a str)>,
+    ) -> String {
+        let pages = pages.into_iter();
+        let texts: Vec<String> = match self {
+            PageSeparator::None | PageSeparator::FormFeed => {
+                pages.map(|(_, _, text)| text.to_string()).collect()
+            }
+            PageSeparator::Template(template) => pages
+                .map(|(filename, page, text)| {
+                    let marker = template
+                        .replace("{page}", &page.page_number.to_string())
+                        .replace("{source_page}", &page.source_page.to_string())
+                        .replace("{file}", filename);
+                    format!("{}\n{}", marker, text)
+                })
+                .collect(),
+        };
+        let between = match self {
+            PageSeparator::None => "\n\n",
+            PageSeparator::FormFeed => "\n\u{c}",
+            PageSeparator::Template(_) => "\n",
+        };
+        texts.join(between)
+    }
+}
+
+/// Joins page texts with the `━━━ Page N ━━━` markers used in combined output.
+pub fn join_pages<'a>(pages: impl IntoIterator<Item = (usize, &'a str)>) -> String {
+    let mut text = String::new();
+    for (page_number, page_text) in pages {
+        if !page_text.is_empty() {
+            text.push_str(&format!("\n━━━ Page {} ━━━\n", page_number));
+            text.push_str(page_text);
+        }
+    }
+    text.trim().to_string()
+}
+
+impl OcrResult {
+    pub fn completed(
+        filename: &str,
+        text: String,
+        pages: Vec<PageResult>,
+        processing_time: f64,
+    ) -> OcrResult {
+        OcrResult {
+            filename: filename.to_string(),
+            text,
+            success: true,
+            error: None,
+            pages_processed: Some(pages.len()),
+            total_pages: Some(pages.len()),
+            estimated_time_seconds: Some(processing_time),
+            pages,
+            front_matter_text: None,
+            back_matter_text: None,
+            verses: None,
+            script: Script::Devanagari,
+            transliteration_scheme: None,
+            transliterated_text: None,
+            annotated_text: None,
+            joined_text: None,
+            provenance_text: None,
+            commands: Vec::new(),
+            pdf_rendering: None,
+            tool_versions: ocr::tool_versions().clone(),
+            searchable_pdf: None,
+            cancelled: false,
+            error_code: None,
+        }
+    }
+
+    pub fn failed(filename: &str, error: String) -> OcrResult {
+        OcrResult {
+            filename: filename.to_string(),
+            text: String::new(),
+            success: false,
+            error: Some(error),
+            pages_processed: None,
+            total_pages: None,
+            estimated_time_seconds: None,
+            pages: vec![],
+            front_matter_text: None,
+            back_matter_text: None,
+            verses: None,
+            script: Script::Devanagari,
+            transliteration_scheme: None,
+            transliterated_text: None,
+            annotated_text: None,
+            joined_text: None,
+            provenance_text: None,
+            commands: Vec::new(),
+            pdf_rendering: None,
+            tool_versions: ocr::tool_versions().clone(),
+            searchable_pdf: None,
+            cancelled: false,
+            error_code: None,
+        }
+    }
+
+    /// The text of every page with the proofread text in place of the recognized
+    /// one where a proofreader supplied it, joined like `text`.
+    pub fn proofread_text(&self) -> String {
+        // A single image's text goes without a page marker
+        if let [page] = self.pages.as_slice()
+            && page.text == self.text
+        {
+            return page
+                .proofread_text
+                .clone()
+                .unwrap_or_else(|| page.text.clone());
+        }
+        join_pages(self.pages.iter().map(|page| {
+            let text = page.proofread_text.as_deref().unwrap_or(&page.text);
+            (page.page_number, text)
+        }))
+    }
+
+    /// Takes in the pages of `retried`, a run over some of this document's pages
+    /// again, in place of the pages read from the same source pages before, and
+    /// rebuilds the text. The document-level results are cleared for
+    /// [`OcrResult::postprocess`] to redo. A failed retry leaves the result as it was.
+    pub fn merge_pages(&mut self, retried: OcrResult) {
+        if !retried.success {
+            return;
+        }
+        for page in retried.pages {
+            let earlier = self.pages.iter_mut().find(|earlier| {
+                earlier.source_page == page.source_page && earlier.spread_side == page.spread_side
+            });
+            if let Some(earlier) = earlier {
+                *earlier = PageResult {
+                    page_number: earlier.page_number,
+                    ..page
+                };
+            }
+        }
+        self.text = join_pages(
+            self.pages
+                .iter()
+                .map(|page| (page.page_number, page.text.as_str())),
+        );
+        for command in retried.commands {
+            if !self.commands.contains(&command) {
+                self.commands.push(command);
+            }
+        }
+        self.front_matter_text = None;
+        self.back_matter_text = None;
+        self.verses = None;
+        self.transliterated_text = None;
+        self.annotated_text = None;
+        self.joined_text = None;
+        self.provenance_text = None;
+        self.cancelled = retried.cancelled;
+    }
+
+    /// Runs the document-level stages requested for this job once all pages are in.
+    pub fn postprocess(&mut self, options: &ProcessingOptions) {
+        self.script = options.script;
+
+        let texts: Vec<&str> = self.pages.iter().map(|page| page.text.as_str()).collect();
+        let sections = matter::classify(&texts);
+        for (page, section) in self.pages.iter_mut().zip(sections) {
+            page.section = section;
+        }
+        if options.matter != MatterHandling::Keep
+            && self.pages.iter().any(|page| page.section != Section::Body)
+        {
+            let in_section = |section| {
+                join_pages(
+                    self.pages
+                        .iter()
+                        .filter(|page| page.section == section)
+                        .map(|page| (page.page_number, page.text.as_str())),
+                )
+            };
+            if options.matter == MatterHandling::Separate {
+                self.front_matter_text = Some(in_section(Section::Front));
+                self.back_matter_text = Some(in_section(Section::Back));
+            }
+            self.text = in_section(Section::Body);
+        }
+
+        let included = |page: &&PageResult| {
+            options.matter == MatterHandling::Keep || page.section == Section::Body
+        };
+        if options.verses || options.meter {
+            let mut verses = verse::detect(
+                self.pages
+                    .iter()
+                    .filter(included)
+                    .map(|page| (page.page_number, page.text.as_str())),
+            );
+            if options.meter {
+                for verse in &mut verses {
+                    verse.meter = Some(chandas::analyze(&verse.lines));
+                }
+            }
+            self.verses = Some(verses);
+        }
+
+        if options.highlight.is_some() {
+            self.annotated_text =
+                Some(join_pages(self.pages.iter().filter(included).map(|page| {
+                    let text = page.annotated_text.as_deref().unwrap_or(&page.text);
+                    (page.page_number, text)
+                })));
+        }
+
+        if options.join_lines {
+            self.joined_text = Some(join_pages(self.pages.iter().filter(included).map(|page| {
+                let text = page.joined_text.as_deref().unwrap_or(&page.text);
+                (page.page_number, text)
+            })));
+        }
+
+        if options.provenance {
+            self.provenance_text = Some(provenance_text(self.pages.iter().filter(included)));
+        }
+
+        if let Some(scheme) = options.transliteration {
+            self.transliteration_scheme = Some(scheme);
+            // Romanization works from Devanagari, whichever script is kept for output
+            let mut scripts: Vec<Script> = self.pages.iter().map(|page| page.script).collect();
+            scripts.dedup();
+            let text = scripts.iter().fold(self.text.clone(), |text, &from| {
+                script::to_devanagari(&text, from)
+            });
+            self.transliterated_text = Some(transliterate::transliterate(&text, scheme));
+            for page in &mut self.pages {
+                let text = script::to_devanagari(&page.text, page.script);
+                page.transliterated_text = Some(transliterate::transliterate(&text, scheme));
+            }
+        }
+
+        if options.parallel {
+            let scheme = options.transliteration.unwrap_or(Scheme::Iast);
+            self.transliteration_scheme = Some(scheme);
+            for page in &mut self.pages {
+                let text = script::to_devanagari(&page.text, page.script);
+                page.parallel_lines = Some(parallel_lines(&text, scheme));
+            }
+        }
+    }
+}
+
+/// One line of a page in Devanagari and romanized form, for diffing against
+/// romanized editions.
+#[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct ParallelLine {
+    /// Line number on the page, counting non-blank lines as in `provenance_text`.
+    pub line: usize,
+    pub devanagari: String,
+    pub romanized: String,
+}
+
+fn parallel_lines(text: &str, scheme: Scheme) -> Vec<ParallelLine> {
+    text.lines()
+        .map(str::trim_end)
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(index, line)| ParallelLine {
+            line: index + 1,
+            devanagari: line.to_string(),
+            romanized: transliterate::transliterate(line, scheme),
+        })
+        .collect()
+}
+
+/// Adds a page's tool invocations to the file-level list, skipping duplicates.
+fn record_commands(commands: &mut Vec<String>, page_commands: &mut Vec<String>) {
+    for command in page_commands.drain(..) {
+        if !commands.contains(&command) {
+            commands.push(command);
+        }
+    }
+}
+
+/// Makes the searchable PDF page for one page of an uploaded document: tesseract's
+/// text-over-image page for recognized pages, given the images' resolution when
+/// they do not record it, and the original page for pages whose text came from a
+/// PDF's text layer (loading `source` on first use). Returns the page PDF and the
+/// commands used.
+fn searchable_pdf_page(
+    content: &PageContent,
+    source_page: usize,
+    model: &str,
+    dpi: Option<u32>,
+    file_path: &std::path::Path,
+    source: &mut Option<lopdf::Document>,
+    options: &ProcessingOptions,
+) -> Result<(PathBuf, Vec<String>), String> {
+    match content {
+        PageContent::Image(image) => {
+            let (path, command) =
+                ocr::searchable_page(image, model, dpi, &options.work_dir, &options.cancel)?;
+            Ok((path, vec![command]))
+        }
+        PageContent::Text(_) => {
+            let document = match source {
+                Some(document) => document,
+                None => source.insert(pdf::load(file_path, options.password.as_deref())?),
+            };
+            let path = options
+                .work_dir
+                .join(format!("ocr_pdf_{}.pdf", Uuid::new_v4()));
+            pdf::extract_pages(document, source_page as u32, source_page as u32, &path)?;
+            Ok((path, Vec::new()))
+        }
+    }
+}
+
+/// Recognizes a page in the requested script, or in the one tesseract detects when
+/// detection is on and a model exists for it.
+fn recognize(
+    image: &std::path::Path,
+    config: &Config,
+    options: &ProcessingOptions,
+    commands: &mut Vec<String>,
+) -> Result<(ocr::PageText, Script), String> {
+    let mut script = options.script;
+    if options.detect_script {
+        let (detected, command) = ocr::detect_script(image, &options.cancel);
+        record_commands(commands, &mut vec![command]);
+        if let Some(detected) = detected.filter(|&d| config.script_model(d).is_some()) {
+            script = detected;
+        }
+    }
+    ocr::recognize_page(
+        image,
+        config,
+        script,
+        options.segment_lines,
+        options.hocr,
+        &options.work_dir,
+        &options.cancel,
+    )
+    .map(|page| (page, script))
+}
+
+/// Scales a page image down to the size OCR is allowed, replacing the image;
+/// keeps it as it is when it fits or scaling fails. Returns the image with the
+/// factor it was scaled by.
+fn shrink_page(
+    image: PathBuf,
+    page: usize,
+    config: &Config,
+    work_dir: &std::path::Path,
+) -> (PathBuf, Option<f32>) {
+    match preprocess::downscale(
+        &image,
+        config.max_ocr_pixels,
+        config.max_ocr_dimension,
+        work_dir,
+    ) {
+        Ok(Some((scaled, scale))) => {
+            println!("  🔍 Scaled page {} down to {:.0}%", page, scale * 100.0);
+            let _ = std::fs::remove_file(&image);
+            (scaled, Some(scale))
+        }
+        Ok(None) => (image, None),
+        Err(e) => {
+            println!("  ⚠️  Warning: Page {} left at full size: {}", page, e);
+            (image, None)
+        }
+    }
+}
+
+/// Crops a page image's dark borders and wide margins off, replacing the image;
+/// keeps it as it is when there is nothing to crop or cropping fails.
+fn crop_page(image: PathBuf, page: usize, work_dir: &std::path::Path) -> PathBuf {
+    match preprocess::crop_margins(&image, work_dir) {
+        Ok(Some(cropped)) => {
+            let _ = std::fs::remove_file(&image);
+            cropped
+        }
+        Ok(None) => image,
+        Err(e) => {
+            println!("  ⚠️  Warning: Page {} left uncropped: {}", page, e);
+            image
+        }
+    }
+}
+
+/// A multi-page document's pages as they become available, with their count:
+/// the original page numbers and content, in page order.
+type PageSource<'a> = (usize, Pages<'a>);
+
+/// Pages in the order they are made: each page's number and content, or its
+/// number and why it could not be made.
+type Pages<'a> = Box<dyn Iterator<Item = Result<(usize, PageContent), (usize, String)>> + 'a>;
+
+/// The page images of a DjVu document or ZIP archive as the pages of a
+/// [`PageSource`].
+fn page_images<'a>(pages: impl Iterator<Item = convert::PageImage> + 'a) -> Pages<'a> {
+    Box::new(pages.map(|page| page.map(|(number, image)| (number, PageContent::Image(image)))))
+}
+
+/// Prepares a rendered page for OCR as requested: a page too large for OCR is
+/// scaled down, the page is turned, a two-page spread is cut into its left and
+/// right page, then dark borders and wide margins are cropped off, replacing the
+/// rendered image. A page that cannot be prepared is kept as it is.
+/// Returns the factor the page was scaled by and the resulting pages with the
+/// side of the spread they came from.
+fn prepare_page(
+    page: usize,
+    content: PageContent,
+    config: &Config,
+    options: &ProcessingOptions,
+) -> (Option<f32>, Vec<(Option<Side>, PageContent)>) {
+    let PageContent::Image(image) = content else {
+        return (None, vec![(None, content)]);
+    };
+    let (mut image, scale) = shrink_page(image, page, config, &options.work_dir);
+    if let Some(&rotation) = options.rotations.get(&page) {
+        match preprocess::rotate(&image, rotation, &options.work_dir) {
+            Ok(rotated) => {
+                let _ = std::fs::remove_file(&image);
+                image = rotated;
+            }
+            Err(e) => println!("  ⚠️  Warning: Page {} left unrotated: {}", page, e),
+        }
+    }
+    let spread = if options.split_spreads {
+        preprocess::split_spread(&image, &options.work_dir)
+    } else {
+        Ok(None)
+    };
+    let halves = match spread {
+        Ok(Some((left, right))) => {
+            println!("  📖 Split page {} into two pages", page);
+            let _ = std::fs::remove_file(&image);
+            vec![(Some(Side::Left), left), (Some(Side::Right), right)]
+        }
+        Ok(None) => vec![(None, image)],
+        Err(e) => {
+            println!("  ⚠️  Warning: Page {} kept whole: {}", page, e);
+            vec![(None, image)]
+        }
+    };
+    let pages = halves
+        .into_iter()
+        .map(|(side, image)| {
+            let image = if options.crop_margins {
+                crop_page(image, page, &options.work_dir)
+            } else {
+                image
+            };
+            (side, PageContent::Image(image))
+        })
+        .collect();
+    (scale, pages)
+}
+
+/// Keeps the image a page was read from when `page_images` asks for it.
+fn keep_page_image(
+    image: &std::path::Path,
+    source_page: usize,
+    side: Option<Side>,
+    options: &ProcessingOptions,
+) {
+    if let Some(dir) = &options.page_images
+        && let Err(e) = page_images::keep(image, dir, source_page, side)
+    {
+        println!(
+            "  ⚠️  Warning: Image of page {} not kept: {}",
+            source_page, e
+        );
+    }
+}
+
+/// A page, or half of a spread, of a multi-page document as a page worker
+/// leaves it: read and processed but not yet numbered, since the pages before
+/// it may not be in.
+struct WorkedPage {
+    source_page: usize,
+    side: Option<Side>,
+    /// Kept for text-layer pages, whose searchable PDF page is cut from the
+    /// source document in page order.
+    content: PageContent,
+    read: Result<PageResult, String>,
+    /// The searchable PDF page, made on the worker for page images.
+    page_pdf: Option<Result<(PathBuf, Vec<String>), String>>,
+    commands: Vec<String>,
+    /// Whether the file's temporary files kept within `max_job_temp_bytes` and
+    /// the session's disk quota once the page was prepared, when they take the
+    /// most room.
+    within_budget: Result<(), Overrun>,
+}
+
+/// The pages one rendered page made, or the page number and error of a page
+/// that could not be rendered.
+type ReadPages = Result<Vec<WorkedPage>, (usize, String)>;
+
+/// Prepares, recognizes and processes one rendered page on a page worker.
+fn read_page(
+    source_page: usize,
+    content: PageContent,
+    dpi: Option<u32>,
+    file_path: &std::path::Path,
+    config: &Config,
+    options: &ProcessingOptions,
+    dictionary: &Dictionary,
+) -> Vec<WorkedPage> {
+    let mut worked = Vec::new();
+    let (scale, prepared) = prepare_page(source_page, content, config, options);
+    let within_budget = check_temp_space(options, config);
+    // The image's resolution went down with its size
+    let dpi = dpi.map(|dpi| scale.map_or(dpi, |scale| (dpi as f32 * scale).round() as u32));
+    for (side, content) in prepared {
+        let page_start = std::time::Instant::now();
+        let mut commands = Vec::new();
+        let recognized = match &content {
+            PageContent::Image(page_path)
+                if options.skip_blank_pages
+                    && preprocess::is_blank(page_path, config.blank_page_max_ink) =>
+            {
+                println!("  ⬜ Page {} is blank, skipping OCR", source_page);
+                Ok((
+                    ocr::PageText {
+                        text: String::new(),
+                        confidence: None,
+                        model: config
+                            .script_model(options.script)
+                            .unwrap_or_else(|| config.language.clone()),
+                        words: Vec::new(),
+                        commands: Vec::new(),
+                        hocr: None,
+                    },
+                    options.script,
+                    TextOrigin::Blank,
+                ))
+            }
+            PageContent::Image(page_path) => recognize(page_path, config, options, &mut commands)
+                .map(|(page, script)| (page, script, TextOrigin::Recognized)),
+            PageContent::Text(text) => Ok((
+                ocr::PageText {
+                    text: text.clone(),
+                    confidence: None,
+                    model: TEXT_LAYER_MODEL.to_string(),
+                    words: Vec::new(),
+                    commands: Vec::new(),
+                    hocr: None,
+                },
+                options.script,
+                TextOrigin::Extracted,
+            )),
+        };
+
+        let mut page_pdf = None;
+        let read = match recognized {
+            Ok((mut page, script, origin)) => {
+                record_commands(&mut commands, &mut page.commands);
+                let mut page = PageResult::new(0, page, script);
+                page.source_page = source_page;
+                page.spread_side = side;
+                page.scale = scale;
+                page.origin = origin;
+                page.process(options, config, dictionary);
+                page.seconds = Some(page_start.elapsed().as_secs_f64());
+                if options.searchable_pdf && matches!(content, PageContent::Image(_)) {
+                    page_pdf = Some(searchable_pdf_page(
+                        &content,
+                        source_page,
+                        &page.model,
+                        dpi,
+                        file_path,
+                        &mut None,
+                        options,
+                    ));
+                }
+                Ok(page)
+            }
+            Err(e) => {
+                println!("  ⚠️  Warning: Failed to OCR page {}: {}", source_page, e);
+                Err(e)
+            }
+        };
+
+        // The page image is done with as soon as the page is
+        if let PageContent::Image(page_path) = &content {
+            keep_page_image(page_path, source_page, side, options);
+            let _ = std::fs::remove_file(page_path);
+        }
+        worked.push(WorkedPage {
+            source_page,
+            side,
+            content,
+            read,
+            page_pdf,
+            commands,
+            within_budget: within_budget.clone(),
+        });
+    }
+    worked
+}
+
+/// Puts a multi-page document's result together from the pages the workers
+/// finish, in page order whatever order they finish in: numbers the pages,
+/// reports each and collects their text and searchable PDF pages.
+struct Assembly<'a> {
+    file_path: &'a std::path::Path,
+    total_pages: usize,
+    options: &'a ProcessingOptions,
+    on_progress: &'a dyn Fn(Progress),
+    pages: Vec<PageResult>,
+    page_pdfs: Vec<PathBuf>,
+    source_document: Option<lopdf::Document>,
+    text: String,
+    commands: Vec<String>,
+    times: PageTimes,
+    /// Rendered pages put in place so far.
+    done: usize,
+    /// Pages finished before one ahead of them, by their position.
+    waiting: BTreeMap<usize, ReadPages>,
+}
+
+impl Assembly<'_> {
+    /// Takes in the pages read before the job was interrupted, one rendered
+    /// page after another.
+    fn resume(&mut self, read_pages: Vec<PageResult>) {
+        let mut source_page = None;
+        for page in read_pages {
+            if source_page != Some(page.source_page) {
+                source_page = Some(page.source_page);
+                self.done += 1;
+            }
+            self.add_text(&page);
+            self.pages.push(page);
+        }
+    }
+
+    /// Takes in the rendered page at `position`, and whichever pages after it
+    /// were waiting for it.
+    fn finish(&mut self, position: usize, read: ReadPages) {
+        self.waiting.insert(position, read);
+        while let Some(read) = self.waiting.remove(&self.done) {
+            self.add(read, true);
+        }
+    }
+
+    /// Takes in the pages still waiting, with gaps where pages were never read.
+    /// They are not reported for picking the document up after, as the pages
+    /// in the gaps would be skipped.
+    fn flush(&mut self) {
+        for (_, read) in std::mem::take(&mut self.waiting) {
+            self.add(read, false);
+        }
+    }
+
+    fn add_text(&mut self, page: &PageResult) {
+        if !page.text.is_empty() {
+            self.text
+                .push_str(&format!("\n━━━ Page {} ━━━\n", page.page_number));
+            self.text.push_str(&page.text);
+        }
+    }
+
+    /// Puts the pages of the next rendered page in place, reporting them as
+    /// read pages when `checkpoint` is set.
+    fn add(&mut self, read: ReadPages, checkpoint: bool) {
+        let position = self.done;
+        self.done += 1;
+        let first = self.pages.len();
+        let options = self.options;
+        match read {
+            Err((source_page, e)) => {
+                println!("  ⚠️  Warning: {}", e);
+                let idx = self.pages.len() + 1;
+                self.pages
+                    .push(PageResult::failed(idx, source_page, None, e, options));
+            }
+            Ok(worked) => {
+                for mut worked in worked {
+                    record_commands(&mut self.commands, &mut worked.commands);
+                    let idx = self.pages.len() + 1;
+                    let mut page = match worked.read {
+                        Ok(page) => page,
+                        Err(e) => {
+                            self.pages.push(PageResult::failed(
+                                idx,
+                                worked.source_page,
+                                worked.side,
+                                e,
+                                options,
+                            ));
+                            continue;
+                        }
+                    };
+                    page.page_number = idx;
+                    let page_pdf = match (options.searchable_pdf, worked.page_pdf) {
+                        (false, _) => None,
+                        (true, Some(made)) => Some(made),
+                        (true, None) => Some(searchable_pdf_page(
+                            &worked.content,
+                            worked.source_page,
+                            &page.model,
+                            None,
+                            self.file_path,
+                            &mut self.source_document,
+                            options,
+                        )),
+                    };
+                    match page_pdf {
+                        Some(Ok((page_pdf, mut page_commands))) => {
+                            record_commands(&mut self.commands, &mut page_commands);
+                            self.page_pdfs.push(page_pdf);
+                        }
+                        Some(Err(e)) => println!(
+                            "  ⚠️  Warning: Page {} left out of the searchable PDF: {}",
+                            worked.source_page, e
+                        ),
+                        None => {}
+                    }
+                    self.add_text(&page);
+                    let mut progress = Progress::new(
+                        "OCR Processing",
+                        position + 1,
+                        self.total_pages,
+                        format!("Read page {}/{}", position + 1, self.total_pages),
+                    );
+                    progress.page = Some(FinishedPage {
+                        page_number: idx,
+                        source_page: worked.source_page,
+                        text: page.text.clone(),
+                        confidence: page.confidence,
+                    });
+                    progress.seconds_per_page = self.times.average();
+                    (self.on_progress)(progress);
+                    self.pages.push(page);
+                }
+            }
+        }
+
+        if checkpoint {
+            let mut progress = Progress::new(
+                "OCR Processing",
+                position + 1,
+                self.total_pages,
+                format!("Read page {}/{}", position + 1, self.total_pages),
+            );
+            progress.seconds_per_page = self.times.average();
+            progress.read_pages = self.pages[first..].to_vec();
+            (self.on_progress)(progress);
+        }
+
+        self.times.lap();
+        if position.is_multiple_of(10)
+            && let Some(seconds_per_page) = self.times.average()
+        {
+            let remaining = self.total_pages.saturating_sub(position + 1) as f64 * seconds_per_page;
+            println!(
+                "  ⏰ Avg: {:.1}s/page | Remaining: ~{:.1}s ({:.1} min)",
+                seconds_per_page,
+                remaining,
+                remaining / 60.0
+            );
+        }
+    }
+}
+
+/// Multi-page formats whose pages are converted to images before OCR.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PagedFormat {
+    Pdf,
+    Djvu,
+    /// An archive of page images.
+    Zip,
+}
+
+impl PagedFormat {
+    fn name(self) -> &'static str {
+        match self {
+            PagedFormat::Pdf => "PDF",
+            PagedFormat::Djvu => "DjVu",
+            PagedFormat::Zip => "ZIP",
+        }
+    }
+
+    /// The format of a file by its extension; `None` for single images.
+    fn of(file_path: &std::path::Path) -> Option<PagedFormat> {
+        let extension = file_path
+            .extension()
+            .and_then(|s| s.to_str())
+            .map(|s| s.to_lowercase())
+            .unwrap_or_default();
+        match extension.as_str() {
+            "pdf" => Some(PagedFormat::Pdf),
+            "djvu" | "djv" => Some(PagedFormat::Djvu),
+            "zip" => Some(PagedFormat::Zip),
+            _ => None,
+        }
+    }
+}
+
+/// Whether a file holds several pages (a PDF, DjVu document or ZIP of page
+/// images) rather than a single image.
+pub fn is_paged(file_path: &std::path::Path) -> bool {
+    PagedFormat::of(file_path).is_some()
+}
+
+/// How many rendered pages `pages` were made from, the halves of a two-page
+/// spread sharing one.
+fn rendered_pages(pages: &[PageResult]) -> usize {
+    pages
+        .chunk_by(|page, next| page.source_page == next.source_page)
+        .count()
+}
+
+/// Bytes the files directly in `dir` take.
+fn disk_usage(dir: &std::path::Path) -> u64 {
+    std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok()?.metadata().ok())
+                .filter(|metadata| metadata.is_file())
+                .map(|metadata| metadata.len())
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+/// Why a file's temporary files may grow no further, with the result's
+/// `error_code` saying so.
+#[derive(Clone)]
+struct Overrun {
+    error: String,
+    code: Option<&'static str>,
+}
+
+/// Fails once the files in a file's `work_dir` take more than
+/// `max_job_temp_bytes`, or more than the session's disk quota leaves.
+fn check_temp_space(options: &ProcessingOptions, config: &Config) -> Result<(), Overrun> {
+    let usage = disk_usage(&options.work_dir);
+    if config.max_job_temp_bytes > 0 && usage > config.max_job_temp_bytes {
+        return Err(Overrun {
+            error: format!(
+                "Temporary files took more than the {} bytes allowed per job",
+                config.max_job_temp_bytes
+            ),
+            code: None,
+        });
+    }
+    if let Some(quota) = options.disk_quota
+        && quota.used + usage > quota.limit
+    {
+        return Err(Overrun {
+            error: quota.exceeded(),
+            code: Some(QUOTA_EXCEEDED),
+        });
+    }
+    Ok(())
+}
+
+/// Recognizes an image or every page of a PDF, DjVu document or ZIP of page images
+/// and runs the per-page stages, reporting progress through `on_progress`.
+/// Document-level stages are left to [`OcrResult::postprocess`].
+///
+/// What is written on the way goes to a directory of the file's own under
+/// `work_dir`, held to `max_job_temp_bytes` and removed once the file is done;
+/// only the searchable PDF is moved out of it.
+pub fn process_file(
+    file_path: &std::path::Path,
+    original_filename: &str,
+    config: &Config,
+    options: &ProcessingOptions,
+    dictionary: &Dictionary,
+    on_progress: &dyn Fn(Progress),
+) -> OcrResult {
+    let scratch = options.work_dir.join(format!("ocr_job_{}", Uuid::new_v4()));
+    if let Err(e) = std::fs::create_dir_all(&scratch) {
+        return OcrResult::failed(
+            original_filename,
+            format!("Failed to create {}: {}", scratch.display(), e),
+        );
+    }
+    let mut file_options = options.clone();
+    file_options.work_dir = scratch.clone();
+    let mut result = read_file(
+        file_path,
+        original_filename,
+        config,
+        &file_options,
+        dictionary,
+        on_progress,
+    );
+
+    if let Some(made) = result.searchable_pdf.take() {
+        let made = PathBuf::from(made);
+        let kept = options.work_dir.join(made.file_name().unwrap_or_default());
+        match std::fs::rename(&made, &kept) {
+            Ok(()) => result.searchable_pdf = Some(kept.display().to_string()),
+            Err(e) => println!("  ⚠️  Warning: Failed to keep searchable PDF: {}", e),
+        }
+    }
+    let _ = std::fs::remove_dir_all(&scratch);
+    result
+}
+
+/// [`process_file`] in the file's own work directory.
+fn read_file(
+    file_path: &std::path::Path,
+    original_filename: &str,
+    config: &Config,
+    options: &ProcessingOptions,
+    dictionary: &Dictionary,
+    on_progress: &dyn Fn(Progress),
+) -> OcrResult {
+    let format = PagedFormat::of(file_path);
+
+    let mut commands = Vec::new();
+    let mut rendering = None;
+    // Pages read before an interruption are taken as they are, and rendering
+    // picks up after them
+    let read_pages = match options.searchable_pdf {
+        true => Vec::new(),
+        false => options.read_pages.clone(),
+    };
+    let resumed = rendered_pages(&read_pages);
+
+    // Multi-page documents are turned into page images one at a time as OCR
+    // goes, the next page rendered while the last is read
+    let paged = if let Some(format) = format {
+        let converting = format!("Converting {}", format.name());
+        // Initial status - we don't know page count yet
+        on_progress(Progress::new(
+            &converting,
+            0,
+            0,
+            format!("Converting {} '{}'...", format.name(), original_filename),
+        ));
+
+        println!(
+            "Converting {} '{}' to images...",
+            format.name(),
+            original_filename
+        );
+
+        let rendered: Result<PageSource, String> = match format {
+            PagedFormat::Pdf => pdf::pdfium(config)
+                .and_then(|pdfium| {
+                    pdf::render(
+                        file_path,
+                        &options.work_dir,
+                        pdfium,
+                        options.password.as_deref(),
+                        options.selection(),
+                        options.use_text_layer,
+                    )
+                })
+                .map(|pages| {
+                    rendering = Some(pages.parameters(config));
+                    let count = pages.len();
+                    (
+                        count,
+                        Box::new(pages.skip(resumed)) as Box<dyn Iterator<Item = _>>,
+                    )
+                }),
+            PagedFormat::Djvu => djvu::render(
+                file_path,
+                &options.work_dir,
+                options.selection(),
+                &options.cancel,
+            )
+            .map(|pages| {
+                commands.push(pages.command_line());
+                (pages.len(), page_images(pages.skip(resumed)))
+            }),
+            PagedFormat::Zip => archive::extract_pages(
+                file_path,
+                &options.work_dir,
+                options.selection(),
+                config.max_archive_bytes,
+            )
+            .map(|pages| {
+                record_commands(&mut commands, &mut pages.commands());
+                (pages.len(), page_images(pages.skip(resumed)))
+            }),
+        };
+        let (page_count, pages) = match rendered {
+            Ok((0, _)) => {
+                return OcrResult::failed(
+                    original_filename,
+                    format!(
+                        "{} conversion failed: the document has no pages",
+                        format.name()
+                    ),
+                );
+            }
+            Ok(rendered) => rendered,
+            Err(e) => {
+                let mut result = OcrResult::failed(
+                    original_filename,
+                    format!("{} conversion error: {}", format.name(), e),
+                );
+                result.cancelled = options.cancel.is_cancelled();
+                return result;
+            }
+        };
+
+        println!("Opened {} pages from {}", page_count, format.name());
+
+        // Update progress with actual page count
+        on_progress(Progress::new(
+            &format!("{} Converted", format.name()),
+            page_count,
+            page_count,
+            format!("Found {} pages, starting OCR...", page_count),
+        ));
+
+        Some((page_count, pages))
+    } else {
+        None
+    };
+
+    // Process pages or single image
+    if let Some((total_pages, pages)) = paged {
+        // Process multiple pages from PDF with time estimation
+        println!(
+            "Processing {} pages with Tesseract OCR (Sanskrit)...",
+            total_pages
+        );
+
+        let workers = options.page_workers.max(1);
+        let dpi = (format == Some(PagedFormat::Pdf)).then_some(pdf::RENDER_DPI as u32);
+        let mut assembly = Assembly {
+            file_path,
+            total_pages,
+            options,
+            on_progress,
+            pages: Vec::new(),
+            page_pdfs: Vec::new(),
+            source_document: None,
+            text: String::new(),
+            commands: std::mem::take(&mut commands),
+            times: PageTimes::start(),
+            done: 0,
+            waiting: BTreeMap::new(),
+        };
+        if resumed > 0 {
+            println!("  ⏩ Picking up after the {} pages read before", resumed);
+            assembly.resume(read_pages);
+        }
+        let start_time = std::time::Instant::now();
+        let mut cancelled = false;
+        let mut stopped = None;
+        // Why no more pages may be rendered: the file's temporary files went
+        // past `max_job_temp_bytes` or the session's disk quota
+        let mut over_budget: Option<Overrun> = None;
+
+        // Pages are rendered here, one at a time as pdfium needs, and read by
+        // `workers` threads at once; the queue between them holds a page per
+        // worker, so rendering stays just ahead of reading
+        let (work, queue) = mpsc::sync_channel::<(usize, usize, PageContent)>(workers);
+        let queue = Mutex::new(queue);
+        let (finish, finished) = mpsc::channel::<(usize, ReadPages)>();
+        std::thread::scope(|scope| {
+            for _ in 0..workers {
+                let (queue, finish) = (&queue, finish.clone());
+                scope.spawn(move || {
+                    loop {
+                        let next = match queue.lock() {
+                            Ok(queue) => queue.recv(),
+                            Err(_) => break,
+                        };
+                        let Ok((position, source_page, content)) = next else {
+                            break;
+                        };
+                        // Pages queued when the job was cancelled are dropped
+                        if options.cancel.is_cancelled() {
+                            if let PageContent::Image(image) = content {
+                                let _ = std::fs::remove_file(image);
+                            }
+                            continue;
+                        }
+                        let read = read_page(
+                            source_page,
+                            content,
+                            dpi,
+                            file_path,
+                            config,
+                            options,
+                            dictionary,
+                        );
+                        if finish.send((position, Ok(read))).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+            drop(finish);
+
+            for (position, rendered) in (resumed..).zip(pages) {
+                if options.cancel.is_cancelled() {
+                    println!("  🛑 Cancelled after {} pages", assembly.done);
+                    if let Ok((_, PageContent::Image(image))) = rendered {
+                        let _ = std::fs::remove_file(image);
+                    }
+                    cancelled = true;
+                    break;
+                }
+
+                // Update progress
+                let mut progress = Progress::new(
+                    "OCR Processing",
+                    assembly.done,
+                    total_pages,
+                    format!("Processing page {}/{}", position + 1, total_pages),
+                );
+                progress.seconds_per_page = assembly.times.average();
+                on_progress(progress);
+
+                let progress_percent = (position + 1) as f64 / total_pages as f64 * 100.0;
+                println!(
+                    "  [{:.1}%] Processing page {}/{}...",
+                    progress_percent,
+                    position + 1,
+                    total_pages
+                );
+
+                match rendered {
+                    Ok((source_page, content)) => {
+                        if over_budget.is_none() {
+                            over_budget = check_temp_space(options, config).err();
+                        }
+                        if let Some(overrun) = &over_budget {
+                            println!("  🛑 {}", overrun.error);
+                            if let PageContent::Image(image) = content {
+                                let _ = std::fs::remove_file(image);
+                            }
+                            stopped = Some(Overrun {
+                                error: format!(
+                                    "{}; stopped before page {}",
+                                    overrun.error, source_page
+                                ),
+                                code: overrun.code,
+                            });
+                            break;
+                        }
+                        // Waits while every worker is busy and the queue full
+                        if work.send((position, source_page, content)).is_err() {
+                            break;
+                        }
+                    }
+                    Err((source_page, e)) => assembly.finish(position, Err((source_page, e))),
+                }
+                while let Ok((position, read)) = finished.try_recv() {
+                    if let Ok(pages) = &read
+                        && over_budget.is_none()
+                    {
+                        over_budget = pages
+                            .iter()
+                            .find_map(|page| page.within_budget.clone().err());
+                    }
+                    assembly.finish(position, read);
+                }
+            }
+            drop(work);
+            for (position, read) in &finished {
+                assembly.finish(position, read);
+            }
+        });
+        // A cancelled or stopped job keeps the pages that got read, though
+        // some before them never will be
+        assembly.flush();
+
+        let Assembly {
+            pages: page_results,
+            page_pdfs,
+            text: all_text,
+            commands,
+            ..
+        } = assembly;
+        let idx = page_results.len();
+
+        let total_time = start_time.elapsed().as_secs_f64();
+        println!(
+            "✅ OCR completed for '{}': {} total characters in {:.1}s ({:.1} min)",
+            original_filename,
+            all_text.len(),
+            total_time,
+            total_time / 60.0
+        );
+
+        let mut result = OcrResult::completed(
+            original_filename,
+            all_text.trim().to_string(),
+            page_results,
+            total_time,
+        );
+        result.pages_processed = Some(idx);
+        result.total_pages = Some(idx);
+        result.commands = commands;
+        result.pdf_rendering = rendering;
+        if format == Some(PagedFormat::Pdf)
+            && let Some(version) = pdf::version(config)
+        {
+            result
+                .tool_versions
+                .insert("pdfium".to_string(), version.to_string());
+        }
+        result.cancelled = cancelled;
+        if let Some(overrun) = stopped {
+            result.success = false;
+            result.error = Some(overrun.error);
+            result.error_code = overrun.code.map(str::to_string);
+        }
+
+        if !page_pdfs.is_empty() {
+            let merged = options
+                .work_dir
+                .join(format!("searchable_{}.pdf", Uuid::new_v4()));
+            match pdf::merge(&page_pdfs, &merged) {
+                Ok(()) => result.searchable_pdf = Some(merged.display().to_string()),
+                Err(e) => println!("  ⚠️  Warning: Failed to build searchable PDF: {}", e),
+            }
+            for page_pdf in &page_pdfs {
+                let _ = std::fs::remove_file(page_pdf);
+            }
+        }
+        result
+    } else {
+        // Process single image file, converting formats tesseract cannot read
+        let start_time = std::time::Instant::now();
+        let converted = match convert::to_png(file_path, &options.work_dir) {
+            Some(Ok((png, command))) => {
+                commands.extend(command);
+                Some(png)
+            }
+            Some(Err(e)) => return OcrResult::failed(original_filename, e),
+            None => None,
+        };
+        let image = converted.as_deref().unwrap_or(file_path);
+        let (scaled, scale) = match preprocess::downscale(
+            image,
+            config.max_ocr_pixels,
+            config.max_ocr_dimension,
+            &options.work_dir,
+        ) {
+            Ok(Some((scaled, scale))) => {
+                println!("  🔍 Scaled image down to {:.0}%", scale * 100.0);
+                (Some(scaled), Some(scale))
+            }
+            Ok(None) => (None, None),
+            Err(e) => {
+                println!("  ⚠️  Warning: Image left at full size: {}", e);
+                (None, None)
+            }
+        };
+        let image = scaled.as_deref().unwrap_or(image);
+        let rotated = options.rotations.get(&1).and_then(|&rotation| {
+            preprocess::rotate(image, rotation, &options.work_dir)
+                .inspect_err(|e| println!("  ⚠️  Warning: Image left unrotated: {}", e))
+                .ok()
+        });
+        let image = rotated.as_deref().unwrap_or(image);
+        let cropped = if options.crop_margins {
+            preprocess::crop_margins(image, &options.work_dir).unwrap_or_else(|e| {
+                println!("  ⚠️  Warning: Image left uncropped: {}", e);
+                None
+            })
+        } else {
+            None
+        };
+        let image = cropped.as_deref().unwrap_or(image);
+
+        keep_page_image(image, 1, None, options);
+        let result = match recognize(image, config, options, &mut commands) {
+            Ok((mut page, script)) => {
+                let processing_time = start_time.elapsed().as_secs_f64();
+                println!(
+                    "OCR Success for '{}': {} chars extracted in {:.1}s (model '{}')",
+                    original_filename,
+                    page.text.len(),
+                    processing_time,
+                    page.model
+                );
+                if page.text.is_empty() {
+                    println!("  WARNING: Empty text extracted!");
+                }
+
+                record_commands(&mut commands, &mut page.commands);
+                let mut page = PageResult::new(1, page, script);
+                page.scale = scale;
+                page.process(options, config, dictionary);
+                page.seconds = Some(start_time.elapsed().as_secs_f64());
+                let searchable_pdf = options
+                    .searchable_pdf
+                    .then(|| {
+                        ocr::searchable_page(
+                            image,
+                            &page.model,
+                            None,
+                            &options.work_dir,
+                            &options.cancel,
+                        )
+                    })
+                    .and_then(|made| match made {
+                        Ok((path, command)) => {
+                            record_commands(&mut commands, &mut vec![command]);
+                            Some(path.display().to_string())
+                        }
+                        Err(e) => {
+                            println!("  ⚠️  Warning: Failed to build searchable PDF: {}", e);
+                            None
+                        }
+                    });
+                let mut result = OcrResult::completed(
+                    original_filename,
+                    page.text.clone(),
+                    vec![page],
+                    processing_time,
+                );
+                result.commands = commands;
+                result.searchable_pdf = searchable_pdf;
+                result
+            }
+            Err(e) => {
+                let mut result = OcrResult::failed(original_filename, e);
+                result.cancelled = options.cancel.is_cancelled();
+                result
+            }
+        };
+        for prepared in converted
+            .iter()
+            .chain(&scaled)
+            .chain(&rotated)
+            .chain(&cropped)
+        {
+            let _ = std::fs::remove_file(prepared);
+        }
+        result
+    }
+}
+
+/// A job on an [`OcrEngine`]: its processing options, applied to every
+/// [`Document`] it runs. Each run is [`process_file`] followed by
+/// [`OcrResult::postprocess`], as the server does for an uploaded file.
+pub struct Pipeline<'a> {
+    engine: &'a OcrEngine,
+    options: ProcessingOptions,
+}
+
+impl<'a> Pipeline<'a> {
+    pub fn new(engine: &'a OcrEngine, options: ProcessingOptions) -> Pipeline<'a> {
+        Pipeline { engine, options }
+    }
+
+    /// Sets a processing option by its upload form field name, e.g. `pages` or
+    /// `script`; see [`ProcessingOptions::set`].
+    pub fn option(mut self, name: &str, value: &str) -> Result<Pipeline<'a>, String> {
+        self.options.set(self.engine.config(), name, value)?;
+        Ok(self)
+    }
+
+    pub fn options(&self) -> &ProcessingOptions {
+        &self.options
+    }
+
+    pub fn options_mut(&mut self) -> &mut ProcessingOptions {
+        &mut self.options
+    }
+
+    /// Recognizes `document`. Failures are reported in the result, not as errors.
+    pub fn run(&self, document: &Document) -> OcrResult {
+        self.run_with_progress(document, &|_| {})
+    }
+
+    /// Recognizes `document`, reporting progress through `on_progress`; see
+    /// [`events::progress_callback`](crate::events::progress_callback) for
+    /// serializable events.
+    pub fn run_with_progress(
+        &self,
+        document: &Document,
+        on_progress: &dyn Fn(Progress),
+    ) -> OcrResult {
+        let mut options = self.options.clone();
+        if let Some(password) = document.password() {
+            options.password = Some(password.to_string());
+        }
+        if let Some(pages) = document.pages() {
+            options.page_order = Some(pages.clone());
+        }
+        let mut result = process_file(
+            document.path(),
+            document.filename(),
+            self.engine.config(),
+            &options,
+            self.engine.dictionary(),
+            on_progress,
+        );
+        result.postprocess(&options);
+        result
+    }
+}
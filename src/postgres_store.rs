@@ -0,0 +1,294 @@
+use crate::ProgressStatus;
+use crate::store::{self, Rows, STALE_AFTER, Store, StoredJob, StoredSession, json};
+use postgres::{Client, NoTls, Transaction};
+use sanskrit_ocr::pipeline::{OcrResult, PageResult};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use std::time::Duration;
+
+/// The same tables as the SQLite store keeps, in PostgreSQL types.
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS sessions (
+        id TEXT PRIMARY KEY,
+        status TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS results (
+        session_id TEXT NOT NULL,
+        file_index BIGINT NOT NULL,
+        result TEXT NOT NULL,
+        PRIMARY KEY (session_id, file_index)
+    );
+    CREATE TABLE IF NOT EXISTS pages (
+        session_id TEXT NOT NULL,
+        file_index BIGINT NOT NULL,
+        position BIGINT NOT NULL,
+        page TEXT NOT NULL,
+        PRIMARY KEY (session_id, file_index, position)
+    );
+    CREATE TABLE IF NOT EXISTS jobs (
+        session_id TEXT PRIMARY KEY,
+        job TEXT NOT NULL,
+        owner TEXT NOT NULL,
+        heartbeat BIGINT NOT NULL
+    );
+";
+
+const DISCONNECTED: &str = "The PostgreSQL connection thread stopped";
+
+/// How long the connection may take to answer before it is opened again.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Work for the connection, run in a transaction.
+type Call = Box<dyn FnOnce(&mut Client) + Send>;
+
+/// Sessions kept in a PostgreSQL database, which several server instances may
+/// share. The blocking client cannot run on the server's async threads, so the
+/// connection lives on a thread of its own and each call waits for it. The
+/// connection is checked before each call and opened again when lost, such as
+/// after the database restarted.
+pub struct PostgresStore {
+    calls: Sender<Call>,
+    instance: String,
+}
+
+impl PostgresStore {
+    /// Connects to the database at `url` and creates the tables it lacks.
+    pub fn connect(url: &str, instance: &str) -> Result<PostgresStore, String> {
+        let (calls, received) = mpsc::channel::<Call>();
+        let (connected, outcome) = mpsc::sync_channel(1);
+        let url = url.to_string();
+        thread::spawn(move || {
+            let mut client = match Client::connect(&url, NoTls)
+                .and_then(|mut client| client.batch_execute(SCHEMA).map(|_| client))
+            {
+                Ok(client) => client,
+                Err(e) => {
+                    let _ = connected.send(Err(describe(&e)));
+                    return;
+                }
+            };
+            let _ = connected.send(Ok(()));
+            for call in received {
+                if client.is_valid(CHECK_TIMEOUT).is_err() {
+                    match Client::connect(&url, NoTls) {
+                        Ok(reconnected) => client = reconnected,
+                        Err(e) => {
+                            println!(
+                                "  ⚠️  Warning: Failed to reconnect to PostgreSQL: {}",
+                                describe(&e)
+                            )
+                        }
+                    }
+                }
+                call(&mut client);
+            }
+        });
+        outcome.recv().map_err(|_| DISCONNECTED.to_string())??;
+        Ok(PostgresStore {
+            calls,
+            instance: instance.to_string(),
+        })
+    }
+
+    /// Runs `write` in a transaction on the connection thread, committed when
+    /// it succeeds, and waits for its outcome.
+    fn write<T: Send + 'static>(
+        &self,
+        write: impl FnOnce(&mut Transaction) -> Result<T, postgres::Error> + Send + 'static,
+    ) -> Result<T, String> {
+        let (reply, outcome) = mpsc::sync_channel(1);
+        let call: Call = Box::new(move |client| {
+            let written = client.transaction().and_then(|mut transaction| {
+                let written = write(&mut transaction)?;
+                transaction.commit().map(|_| written)
+            });
+            let _ = reply.send(written.map_err(|e| describe(&e)));
+        });
+        self.calls
+            .send(call)
+            .map_err(|_| DISCONNECTED.to_string())?;
+        outcome.recv().map_err(|_| DISCONNECTED.to_string())?
+    }
+}
+
+impl Store for PostgresStore {
+    fn save_statuses(&self, statuses: &[(&String, &ProgressStatus)]) -> Result<(), String> {
+        let rows = statuses
+            .iter()
+            .map(|(session_id, status)| Ok((session_id.to_string(), store::status_json(status)?)))
+            .collect::<Result<Vec<_>, String>>()?;
+        self.write(move |transaction| {
+            for (session_id, status) in rows {
+                transaction.execute(
+                    "INSERT INTO sessions (id, status) VALUES ($1, $2)
+                     ON CONFLICT (id) DO UPDATE SET status = EXCLUDED.status",
+                    &[&session_id, &status],
+                )?;
+            }
+            Ok(())
+        })
+    }
+
+    fn save_result(
+        &self,
+        session_id: &str,
+        index: usize,
+        result: &OcrResult,
+    ) -> Result<(), String> {
+        let (session_id, index) = (session_id.to_string(), index as i64);
+        let file = store::result_json(result)?;
+        let pages = result
+            .pages
+            .iter()
+            .map(json)
+            .collect::<Result<Vec<_>, String>>()?;
+        self.write(move |transaction| {
+            transaction.execute(
+                "DELETE FROM pages WHERE session_id = $1 AND file_index = $2",
+                &[&session_id, &index],
+            )?;
+            transaction.execute(
+                "INSERT INTO results (session_id, file_index, result) VALUES ($1, $2, $3)
+                 ON CONFLICT (session_id, file_index) DO UPDATE SET result = EXCLUDED.result",
+                &[&session_id, &index, &file],
+            )?;
+            for (position, page) in pages.iter().enumerate() {
+                transaction.execute(
+                    "INSERT INTO pages (session_id, file_index, position, page) VALUES ($1, $2, $3, $4)",
+                    &[&session_id, &index, &(position as i64), page],
+                )?;
+            }
+            Ok(())
+        })
+    }
+
+    fn save_page(
+        &self,
+        session_id: &str,
+        index: usize,
+        position: usize,
+        page: &PageResult,
+    ) -> Result<(), String> {
+        let (session_id, index, position) = (session_id.to_string(), index as i64, position as i64);
+        let page = json(page)?;
+        self.write(move |transaction| {
+            transaction
+                .execute(
+                    "INSERT INTO pages (session_id, file_index, position, page) VALUES ($1, $2, $3, $4)
+                     ON CONFLICT (session_id, file_index, position) DO UPDATE SET page = EXCLUDED.page",
+                    &[&session_id, &index, &position, &page],
+                )
+                .map(|_| ())
+        })
+    }
+
+    fn save_job(&self, session_id: &str, job: &StoredJob) -> Result<(), String> {
+        let (session_id, instance) = (session_id.to_string(), self.instance.clone());
+        let job = json(job)?;
+        self.write(move |transaction| {
+            transaction
+                .execute(
+                    "INSERT INTO jobs (session_id, job, owner, heartbeat) VALUES ($1, $2, $3, $4)
+                     ON CONFLICT (session_id) DO UPDATE
+                     SET job = EXCLUDED.job, owner = EXCLUDED.owner, heartbeat = EXCLUDED.heartbeat",
+                    &[&session_id, &job, &instance, &store::now()],
+                )
+                .map(|_| ())
+        })
+    }
+
+    fn remove_job(&self, session_id: &str) -> Result<(), String> {
+        let session_id = session_id.to_string();
+        self.write(move |transaction| {
+            transaction
+                .execute("DELETE FROM jobs WHERE session_id = $1", &[&session_id])
+                .map(|_| ())
+        })
+    }
+
+    fn delete(&self, session_id: &str) -> Result<(), String> {
+        let session_id = session_id.to_string();
+        self.write(move |transaction| {
+            for table in ["sessions", "results", "pages", "jobs"] {
+                let column = if table == "sessions" {
+                    "id"
+                } else {
+                    "session_id"
+                };
+                transaction.execute(
+                    &format!("DELETE FROM {} WHERE {} = $1", table, column),
+                    &[&session_id],
+                )?;
+            }
+            Ok(())
+        })
+    }
+
+    fn heartbeat(&self) -> Result<(), String> {
+        let instance = self.instance.clone();
+        self.write(move |transaction| {
+            transaction
+                .execute(
+                    "UPDATE jobs SET heartbeat = $2 WHERE owner = $1",
+                    &[&instance, &store::now()],
+                )
+                .map(|_| ())
+        })
+    }
+
+    fn load(&self) -> Result<Vec<StoredSession>, String> {
+        let instance = self.instance.clone();
+        let rows = self.write(move |transaction| {
+            let now = store::now();
+            transaction.execute(
+                "UPDATE jobs SET owner = $1, heartbeat = $2 WHERE owner = $1 OR heartbeat < $3",
+                &[&instance, &now, &(now - STALE_AFTER.as_secs() as i64)],
+            )?;
+            let sessions = transaction.query(
+                "SELECT id, status FROM sessions
+                 WHERE id NOT IN (SELECT session_id FROM jobs WHERE owner <> $1)",
+                &[&instance],
+            )?;
+            let results = transaction.query(
+                "SELECT session_id, file_index, result FROM results
+                 WHERE session_id NOT IN (SELECT session_id FROM jobs WHERE owner <> $1)",
+                &[&instance],
+            )?;
+            let pages = transaction.query(
+                "SELECT session_id, file_index, page FROM pages
+                 WHERE session_id NOT IN (SELECT session_id FROM jobs WHERE owner <> $1)
+                 ORDER BY session_id, file_index, position",
+                &[&instance],
+            )?;
+            let jobs = transaction.query(
+                "SELECT session_id, job FROM jobs WHERE owner = $1",
+                &[&instance],
+            )?;
+            Ok(Rows {
+                sessions: sessions
+                    .iter()
+                    .map(|row| (row.get(0), row.get(1)))
+                    .collect(),
+                results: results
+                    .iter()
+                    .map(|row| (row.get(0), row.get::<_, i64>(1) as usize, row.get(2)))
+                    .collect(),
+                pages: pages
+                    .iter()
+                    .map(|row| (row.get(0), row.get::<_, i64>(1) as usize, row.get(2)))
+                    .collect(),
+                jobs: jobs.iter().map(|row| (row.get(0), row.get(1))).collect(),
+            })
+        })?;
+        rows.into_sessions()
+    }
+}
+
+/// The database's own message for `error` when it has one; the client's
+/// alone only names the kind, such as "db error".
+fn describe(error: &postgres::Error) -> String {
+    match error.as_db_error() {
+        Some(db_error) => db_error.to_string(),
+        None => error.to_string(),
+    }
+}
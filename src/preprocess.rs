@@ -0,0 +1,301 @@
+use image::imageops::FilterType;
+use image::{GenericImageView, GrayImage};
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// Pixels darker than this gray level count as ink.
+const INK_LEVEL: u8 = 128;
+
+/// Share of each edge left out when measuring ink, where scanner borders, gutter
+/// shadows and punch holes sit even on blank pages.
+const EDGE_SHARE: u32 = 20;
+
+/// Share (0-100) of the page inside its edges that is ink.
+fn ink_share(page: &GrayImage) -> f32 {
+    let (width, height) = page.dimensions();
+    let (margin_x, margin_y) = (width / EDGE_SHARE, height / EDGE_SHARE);
+    let inner = image::imageops::crop_imm(
+        page,
+        margin_x,
+        margin_y,
+        width - 2 * margin_x,
+        height - 2 * margin_y,
+    );
+    let (inner_width, inner_height) = inner.dimensions();
+    let total = inner_width as u64 * inner_height as u64;
+    if total == 0 {
+        return 0.0;
+    }
+    let ink = inner
+        .pixels()
+        .filter(|(_, _, pixel)| pixel.0[0] < INK_LEVEL)
+        .count();
+    ink as f32 / total as f32 * 100.0
+}
+
+/// Whether a rendered page is blank: at most `max_ink` percent of it, edges aside,
+/// is ink. A page number or a few specks stay under the usual 0.1%; a single short
+/// heading does not. Images that cannot be decoded are never blank.
+pub fn is_blank(image: &Path, max_ink: f32) -> bool {
+    match image::open(image) {
+        Ok(page) => ink_share(&page.to_luma8()) <= max_ink,
+        Err(_) => false,
+    }
+}
+
+/// Images at least this much wider than tall may be two-page spreads.
+const MIN_SPREAD_ASPECT: f32 = 1.2;
+
+/// Share of the width, centred, searched for the gutter between the pages.
+const GUTTER_SEARCH_SHARE: u32 = 5;
+
+/// A column is part of the gutter when at most this share of it is ink (the
+/// paper between the pages) or at most this share is not (the binding's shadow).
+const MAX_GUTTER_INK: f32 = 0.02;
+
+/// Which half of a two-page spread a page was cut from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Left,
+    Right,
+}
+
+/// Column where the two pages of a spread meet: a column in the middle fifth of
+/// the image, edges aside, that is all paper or all shadow, taking the one
+/// nearest the centre. `None` when the image is not wide enough for a spread or no
+/// such column exists, as on a single page with lines running across it.
+pub fn find_gutter(page: &GrayImage) -> Option<u32> {
+    let (width, height) = page.dimensions();
+    if (width as f32) < height as f32 * MIN_SPREAD_ASPECT {
+        return None;
+    }
+    let margin_y = height / EDGE_SHARE;
+    let rows = height - 2 * margin_y;
+    if rows == 0 {
+        return None;
+    }
+
+    let centre = width / 2;
+    let reach = width / GUTTER_SEARCH_SHARE / 2;
+    (centre - reach..centre + reach)
+        .filter(|&x| {
+            let ink = (margin_y..height - margin_y)
+                .filter(|&y| page.get_pixel(x, y).0[0] < INK_LEVEL)
+                .count() as f32
+                / rows as f32;
+            ink <= MAX_GUTTER_INK || ink >= 1.0 - MAX_GUTTER_INK
+        })
+        .min_by_key(|&x| x.abs_diff(centre))
+}
+
+/// Cuts a two-page spread at its gutter into a left and a right page image under
+/// `work_dir`. `Ok(None)` when the image does not look like a spread.
+pub fn split_spread(image: &Path, work_dir: &Path) -> Result<Option<(PathBuf, PathBuf)>, String> {
+    let spread = image::open(image).map_err(|e| format!("Failed to read page image: {}", e))?;
+    let Some(gutter) = find_gutter(&spread.to_luma8()) else {
+        return Ok(None);
+    };
+
+    let prefix = format!("spread_{}", Uuid::new_v4());
+    let left = work_dir.join(format!("{}-left.png", prefix));
+    let right = work_dir.join(format!("{}-right.png", prefix));
+    let halves = [
+        (&left, 0, gutter),
+        (&right, gutter, spread.width() - gutter),
+    ];
+    for (path, x, width) in halves {
+        if let Err(e) = spread.crop_imm(x, 0, width, spread.height()).save(path) {
+            let _ = std::fs::remove_file(&left);
+            return Err(format!("Failed to write page image: {}", e));
+        }
+    }
+    Ok(Some((left, right)))
+}
+
+/// Rows and columns at the edge of a scan with more than this share of ink are
+/// the scanner's dark border rather than page.
+const BORDER_INK: f32 = 0.5;
+
+/// Paper kept around the text when cropping, as a fraction (1/n) of the page's
+/// shorter side.
+const CROP_PADDING_SHARE: u32 = 50;
+
+/// Crops smaller than this fraction (1/n) of either side are not worth a new image.
+const MIN_CROP_SHARE: u32 = 50;
+
+/// Dark pixels per row and per column of `page` within `columns` and `rows`.
+fn ink_profiles(page: &GrayImage, columns: Range<u32>, rows: Range<u32>) -> (Vec<u32>, Vec<u32>) {
+    let mut row_ink = vec![0; rows.len()];
+    let mut column_ink = vec![0; columns.len()];
+    for (i, y) in rows.clone().enumerate() {
+        for (j, x) in columns.clone().enumerate() {
+            if page.get_pixel(x, y).0[0] < INK_LEVEL {
+                row_ink[i] += 1;
+                column_ink[j] += 1;
+            }
+        }
+    }
+    (row_ink, column_ink)
+}
+
+/// Narrows `range` from both ends past the entries of `profile` (indexed from
+/// `range.start`) above `limit`.
+fn strip_edges(range: Range<u32>, profile: &[u32], limit: f32) -> Range<u32> {
+    let dark = |i: &usize| profile[*i] as f32 > limit;
+    let start = (0..profile.len())
+        .find(|i| !dark(i))
+        .unwrap_or(profile.len());
+    let end = (start..profile.len())
+        .rev()
+        .find(|i| !dark(i))
+        .map_or(start, |i| i + 1);
+    range.start + start as u32..range.start + end as u32
+}
+
+/// First and last entries of `profile` (indexed from `range.start`) with at
+/// least `min_ink` ink, as a range.
+fn ink_extent(range: &Range<u32>, profile: &[u32], min_ink: u32) -> Option<Range<u32>> {
+    let first = profile.iter().position(|&ink| ink >= min_ink)?;
+    let last = profile.iter().rposition(|&ink| ink >= min_ink)?;
+    Some(range.start + first as u32..range.start + last as u32 + 1)
+}
+
+/// Region of a page worth recognizing, as column and row ranges: the scanner's
+/// dark borders are stripped, then the page is cut down to its ink, specks aside,
+/// with a little paper around it. `None` for pages without any ink.
+pub fn content_bounds(page: &GrayImage) -> Option<(Range<u32>, Range<u32>)> {
+    let (width, height) = page.dimensions();
+
+    // Border columns run the page's height and border rows its width
+    let (_, column_ink) = ink_profiles(page, 0..width, 0..height);
+    let columns = strip_edges(0..width, &column_ink, height as f32 * BORDER_INK);
+    let (row_ink, _) = ink_profiles(page, columns.clone(), 0..height);
+    let rows = strip_edges(0..height, &row_ink, columns.len() as f32 * BORDER_INK);
+    if columns.is_empty() || rows.is_empty() {
+        return None;
+    }
+
+    // Ignore specks and scanner noise, as line segmentation does
+    let (row_ink, column_ink) = ink_profiles(page, columns.clone(), rows.clone());
+    let text_columns = ink_extent(&columns, &column_ink, (rows.len() as u32 / 200).max(1))?;
+    let text_rows = ink_extent(&rows, &row_ink, (columns.len() as u32 / 200).max(1))?;
+
+    let padding = width.min(height) / CROP_PADDING_SHARE;
+    Some((
+        text_columns
+            .start
+            .saturating_sub(padding)
+            .max(columns.start)..(text_columns.end + padding).min(columns.end),
+        text_rows.start.saturating_sub(padding).max(rows.start)
+            ..(text_rows.end + padding).min(rows.end),
+    ))
+}
+
+/// Crops a page image down to its [`content_bounds`], writing the result as a PNG
+/// under `work_dir`. `Ok(None)` when there is nothing worth cropping.
+pub fn crop_margins(image: &Path, work_dir: &Path) -> Result<Option<PathBuf>, String> {
+    let page = image::open(image).map_err(|e| format!("Failed to read page image: {}", e))?;
+    let Some((columns, rows)) = content_bounds(&page.to_luma8()) else {
+        return Ok(None);
+    };
+    let (width, height) = page.dimensions();
+    if width - columns.len() as u32 <= width / MIN_CROP_SHARE
+        && height - rows.len() as u32 <= height / MIN_CROP_SHARE
+    {
+        return Ok(None);
+    }
+
+    let cropped = work_dir.join(format!("crop_{}.png", Uuid::new_v4()));
+    page.crop_imm(
+        columns.start,
+        rows.start,
+        columns.len() as u32,
+        rows.len() as u32,
+    )
+    .save(&cropped)
+    .map_err(|e| format!("Failed to write page image: {}", e))?;
+    Ok(Some(cropped))
+}
+
+/// Factor a `width` by `height` image's sides are scaled by to have at most
+/// `max_pixels` pixels and no side longer than `max_dimension`, 0 lifting
+/// either limit; `None` when it fits as it is.
+pub fn fit_scale(width: u32, height: u32, max_pixels: u64, max_dimension: u32) -> Option<f32> {
+    let mut scale: f64 = 1.0;
+    if max_dimension > 0 {
+        scale = scale.min(max_dimension as f64 / width.max(height).max(1) as f64);
+    }
+    if max_pixels > 0 {
+        let pixels = (width as f64 * height as f64).max(1.0);
+        scale = scale.min((max_pixels as f64 / pixels).sqrt());
+    }
+    (scale < 1.0).then_some(scale as f32)
+}
+
+/// Shrinks a page image larger than [`fit_scale`] allows to fit, writing it as a
+/// PNG under `work_dir`. Returns it with the factor its sides were scaled by, or
+/// `Ok(None)` when the image fits as it is.
+pub fn downscale(
+    image: &Path,
+    max_pixels: u64,
+    max_dimension: u32,
+    work_dir: &Path,
+) -> Result<Option<(PathBuf, f32)>, String> {
+    // Only the header is read for images that fit
+    let (width, height) =
+        image::image_dimensions(image).map_err(|e| format!("Failed to read page image: {}", e))?;
+    let Some(scale) = fit_scale(width, height, max_pixels, max_dimension) else {
+        return Ok(None);
+    };
+    let page = image::open(image).map_err(|e| format!("Failed to read page image: {}", e))?;
+    let scaled = work_dir.join(format!("scale_{}.png", Uuid::new_v4()));
+    page.resize_exact(
+        ((width as f32 * scale) as u32).max(1),
+        ((height as f32 * scale) as u32).max(1),
+        FilterType::Triangle,
+    )
+    .save(&scaled)
+    .map_err(|e| format!("Failed to write page image: {}", e))?;
+    Ok(Some((scaled, scale)))
+}
+
+/// Clockwise turn applied to a page image before OCR, e.g. for pages scanned
+/// sideways.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Rotation {
+    Clockwise90,
+    Clockwise180,
+    Clockwise270,
+}
+
+impl Rotation {
+    /// From degrees clockwise; counter-clockwise turns are negative. `None` for
+    /// anything but a multiple of 90, and for no turn at all.
+    pub fn from_degrees(degrees: i32) -> Option<Rotation> {
+        match degrees.rem_euclid(360) {
+            90 => Some(Rotation::Clockwise90),
+            180 => Some(Rotation::Clockwise180),
+            270 => Some(Rotation::Clockwise270),
+            _ => None,
+        }
+    }
+}
+
+/// Writes `image` turned by `rotation` as a PNG under `work_dir`.
+pub fn rotate(image: &Path, rotation: Rotation, work_dir: &Path) -> Result<PathBuf, String> {
+    let page = image::open(image).map_err(|e| format!("Failed to read page image: {}", e))?;
+    let turned = match rotation {
+        Rotation::Clockwise90 => page.rotate90(),
+        Rotation::Clockwise180 => page.rotate180(),
+        Rotation::Clockwise270 => page.rotate270(),
+    };
+    let rotated = work_dir.join(format!("rotate_{}.png", Uuid::new_v4()));
+    turned
+        .save(&rotated)
+        .map_err(|e| format!("Failed to write page image: {}", e))?;
+    Ok(rotated)
+}
@@ -0,0 +1,141 @@
+use crate::cancel::Cancel;
+use serde::Deserialize;
+use std::ffi::OsStr;
+use std::io;
+use std::process::Command;
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+
+/// How often a wait for a slot is checked for cancellation.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// External programs running and the most allowed at once; 0 for no limit.
+struct Slots {
+    running: usize,
+    limit: usize,
+}
+
+static SLOTS: Mutex<Slots> = Mutex::new(Slots {
+    running: 0,
+    limit: 0,
+});
+static FREED: Condvar = Condvar::new();
+
+/// Caps the external programs (tesseract, OCR helpers reading a page, ddjvu,
+/// converters, analyzers) run at once across all jobs of the process; 0 lifts
+/// the cap.
+pub fn set_limit(limit: usize) {
+    if let Ok(mut slots) = SLOTS.lock() {
+        slots.limit = limit;
+    }
+    FREED.notify_all();
+}
+
+/// A place among the programs allowed to run, given back when dropped.
+pub struct Slot(());
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        if let Ok(mut slots) = SLOTS.lock() {
+            slots.running -= 1;
+        }
+        FREED.notify_one();
+    }
+}
+
+/// Waits for a slot to run a program in. Fails with
+/// [`io::ErrorKind::Interrupted`] when the job is cancelled, before or while it
+/// waits.
+pub fn acquire(cancel: &Cancel) -> io::Result<Slot> {
+    let mut slots = SLOTS.lock().map_err(|e| io::Error::other(e.to_string()))?;
+    loop {
+        if cancel.is_cancelled() {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "Cancelled"));
+        }
+        if slots.limit == 0 || slots.running < slots.limit {
+            break;
+        }
+        slots = FREED
+            .wait_timeout(slots, POLL_INTERVAL)
+            .map_err(|e| io::Error::other(e.to_string()))?
+            .0;
+    }
+    slots.running += 1;
+    Ok(Slot(()))
+}
+
+/// Disk scheduling class external programs are started in with `ionice`.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum IoClass {
+    /// Share the disk with other programs, at a priority following the
+    /// niceness.
+    BestEffort,
+    /// Use the disk only when no other program wants it.
+    Idle,
+}
+
+/// How external programs are started so OCR jobs leave room for the rest of
+/// the machine: under `nice` and `ionice`, and with `OMP_THREAD_LIMIT` capping
+/// the threads tesseract starts.
+#[derive(Clone, Copy, Default)]
+pub struct Priority {
+    /// Niceness programs run at; 0 leaves it as the server's.
+    pub niceness: i32,
+    pub io_class: Option<IoClass>,
+    /// Threads each program may start; 0 leaves `OMP_THREAD_LIMIT` unset.
+    pub thread_limit: usize,
+}
+
+static PRIORITY: Mutex<Priority> = Mutex::new(Priority {
+    niceness: 0,
+    io_class: None,
+    thread_limit: 0,
+});
+
+/// Sets the priority external programs (the ones [`acquire`] counts) are
+/// started at across all jobs of the process.
+pub fn set_priority(priority: Priority) {
+    if let Ok(mut current) = PRIORITY.lock() {
+        *current = priority;
+    }
+}
+
+/// `command` as it is to be started at the [`Priority`] set: its program and
+/// arguments behind `nice` and `ionice`, its environment and working directory
+/// kept. Both wrappers start the program in their own place, so killing the
+/// child kills the program.
+pub fn prioritized(command: &Command) -> Command {
+    let priority = PRIORITY
+        .lock()
+        .map(|priority| *priority)
+        .unwrap_or_default();
+    let niceness = priority.niceness.to_string();
+    let mut line: Vec<&OsStr> = Vec::new();
+    if priority.niceness != 0 {
+        line.extend(["nice", "-n", &niceness].map(OsStr::new));
+    }
+    match priority.io_class {
+        Some(IoClass::BestEffort) => line.extend(["ionice", "-c", "2"].map(OsStr::new)),
+        Some(IoClass::Idle) => line.extend(["ionice", "-c", "3"].map(OsStr::new)),
+        None => {}
+    }
+    line.push(command.get_program());
+    line.extend(command.get_args());
+
+    let mut prioritized = Command::new(line[0]);
+    prioritized.args(&line[1..]);
+    for (key, value) in command.get_envs() {
+        match value {
+            Some(value) => prioritized.env(key, value),
+            None => prioritized.env_remove(key),
+        };
+    }
+    if let Some(dir) = command.get_current_dir() {
+        prioritized.current_dir(dir);
+    }
+    if priority.thread_limit > 0 {
+        prioritized.env("OMP_THREAD_LIMIT", priority.thread_limit.to_string());
+    }
+    prioritized
+}
@@ -0,0 +1,153 @@
+use crate::ErrorResponse;
+use actix_web::body::{BoxBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header;
+use actix_web::middleware::Next;
+use actix_web::{Error, HttpResponse, web};
+use parking_lot::Mutex;
+use sanskrit_ocr::config::RateLimitConfig;
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// Clients tracked before those whose allowance has refilled are forgotten.
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// A client's allowance: tokens left, refilled at its rate up to the burst.
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Requests allowed per minute and at once for one client.
+#[derive(Clone, Copy)]
+struct Allowance {
+    per_minute: u32,
+    burst: u32,
+}
+
+impl Allowance {
+    fn capacity(self) -> f64 {
+        match self.burst {
+            0 => self.per_minute as f64,
+            burst => burst as f64,
+        }
+    }
+
+    fn per_second(self) -> f64 {
+        self.per_minute as f64 / 60.0
+    }
+}
+
+/// Token buckets of the clients of the rate-limited endpoints, shared by all
+/// of them, so a client uploading and splitting draws on one allowance.
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    buckets: Mutex<HashMap<String, (Bucket, Allowance)>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: &RateLimitConfig) -> RateLimiter {
+        RateLimiter {
+            config: config.clone(),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The bucket a request draws on and its allowance: that of its API key
+    /// when it carries a configured one, else that of its address. `None` for
+    /// requests that are not limited.
+    fn client(&self, req: &ServiceRequest) -> Option<(String, Allowance)> {
+        let key = self
+            .config
+            .key_header
+            .as_deref()
+            .and_then(|name| req.headers().get(name))
+            .and_then(|value| value.to_str().ok())
+            .and_then(|key| Some((key, *self.config.keys.get(key)?)));
+        if let Some((key, per_minute)) = key {
+            return (per_minute > 0).then(|| {
+                (
+                    format!("key:{}", key),
+                    Allowance {
+                        per_minute,
+                        burst: self.config.burst.min(per_minute),
+                    },
+                )
+            });
+        }
+
+        if self.config.requests_per_minute == 0 {
+            return None;
+        }
+        let info = req.connection_info();
+        let address = match self.config.trust_forwarded_for {
+            true => info.realip_remote_addr(),
+            false => info.peer_addr(),
+        }
+        .unwrap_or("unknown")
+        .to_string();
+        Some((
+            format!("ip:{}", address),
+            Allowance {
+                per_minute: self.config.requests_per_minute,
+                burst: self.config.burst,
+            },
+        ))
+    }
+
+    /// Takes a token from `client`'s bucket, or gives the seconds until one is
+    /// back.
+    fn take(&self, client: String, allowance: Allowance) -> Result<(), u64> {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock();
+        if buckets.len() >= MAX_TRACKED_CLIENTS {
+            buckets.retain(|_, (bucket, allowance)| {
+                let idle = now.duration_since(bucket.updated).as_secs_f64();
+                bucket.tokens + idle * allowance.per_second() < allowance.capacity()
+            });
+        }
+        let (bucket, _) = buckets.entry(client).or_insert_with(|| {
+            (
+                Bucket {
+                    tokens: allowance.capacity(),
+                    updated: now,
+                },
+                allowance,
+            )
+        });
+
+        let idle = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + idle * allowance.per_second()).min(allowance.capacity());
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+        Err(((1.0 - bucket.tokens) / allowance.per_second()).ceil() as u64)
+    }
+}
+
+/// Middleware answering HTTP 429 with `Retry-After` to clients that have used
+/// up their allowance, before the endpoint reads the request body.
+pub async fn limit(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let limited = req
+        .app_data::<web::Data<RateLimiter>>()
+        .and_then(|limiter| {
+            let (client, allowance) = limiter.client(&req)?;
+            limiter.take(client, allowance).err()
+        });
+    let Some(retry_after) = limited else {
+        return Ok(next.call(req).await?.map_into_boxed_body());
+    };
+
+    let response = HttpResponse::TooManyRequests()
+        .insert_header((header::RETRY_AFTER, retry_after.to_string()))
+        .json(ErrorResponse {
+            error: format!("Too many requests; try again in {} seconds", retry_after),
+            code: Some("RATE_LIMITED".to_string()),
+        });
+    Ok(req.into_response(response))
+}
@@ -0,0 +1,56 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+/// A find/replace rule for systematic misreadings of a particular print, e.g.
+/// `{"find": "रव", "replace": "ख"}`. `replace` may refer to capture groups as `$1`.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "RuleSpec", into = "RuleSpec")]
+pub struct Rule {
+    find: Regex,
+    replace: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RuleSpec {
+    find: String,
+    replace: String,
+}
+
+impl From<Rule> for RuleSpec {
+    fn from(rule: Rule) -> RuleSpec {
+        RuleSpec {
+            find: rule.find.as_str().to_string(),
+            replace: rule.replace,
+        }
+    }
+}
+
+impl TryFrom<RuleSpec> for Rule {
+    type Error = String;
+
+    fn try_from(spec: RuleSpec) -> Result<Rule, String> {
+        let find = Regex::new(&spec.find)
+            .map_err(|e| format!("Invalid replacement pattern '{}': {}", spec.find, e))?;
+        Ok(Rule {
+            find,
+            replace: spec.replace,
+        })
+    }
+}
+
+/// Parses a JSON array of rules sent with an upload.
+pub fn parse_rules(json: &str) -> Result<Vec<Rule>, String> {
+    serde_json::from_str(json).map_err(|e| format!("Invalid replacements: {}", e))
+}
+
+/// Applies `rules` in order, each to the output of the previous one.
+pub fn apply(text: &str, rules: &[Rule]) -> String {
+    let mut text = Cow::Borrowed(text);
+    for rule in rules {
+        if let Cow::Owned(replaced) = rule.find.replace_all(&text, rule.replace.as_str()) {
+            text = Cow::Owned(replaced);
+        }
+    }
+    text.into_owned()
+}
@@ -0,0 +1,127 @@
+use crate::cancel::Cancel;
+use crate::processes;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+const AVAGRAHA: char = 'ऽ';
+const ANUSVARA: char = 'ं';
+const VISARGA: char = 'ः';
+const SIGN_O: char = 'ो';
+
+fn is_consonant(c: char) -> bool {
+    ('\u{0915}'..='\u{0939}').contains(&c)
+}
+
+fn is_voiced_consonant(c: char) -> bool {
+    matches!(
+        c,
+        'ग' | 'घ'
+            | 'ङ'
+            | 'ज'
+            | 'झ'
+            | 'ञ'
+            | 'ड'
+            | 'ढ'
+            | 'ण'
+            | 'द'
+            | 'ध'
+            | 'न'
+            | 'ब'
+            | 'भ'
+            | 'म'
+            | 'य'
+            | 'र'
+            | 'ल'
+            | 'व'
+            | 'ह'
+    )
+}
+
+/// Splits a word at an avagraha, restoring the elided initial `a` of the second
+/// word and the visarga of `-aḥ` words (`सोऽहम्` → `सः अहम्`, `तेऽपि` → `ते अपि`).
+fn split_avagraha(word: &str, out: &mut Vec<String>) {
+    let mut rest = word.to_string();
+    while let Some(pos) = rest.find(AVAGRAHA) {
+        let mut first = rest[..pos].to_string();
+        if first.ends_with(SIGN_O) {
+            first.pop();
+            first.push(VISARGA);
+        }
+        if !first.is_empty() {
+            out.push(first);
+        }
+        rest = format!("अ{}", &rest[pos + AVAGRAHA.len_utf8()..]);
+    }
+    out.push(rest);
+}
+
+/// Undoes the most reliable external sandhi rules on one line of Devanagari:
+///
+/// - avagraha: the word is split and the elided `a` restored;
+/// - word-final anusvāra before a consonant is restored to `म्`;
+/// - word-final `-o` before a voiced consonant is restored to visarga (`-aḥ`).
+///
+/// Anything ambiguous (vowel coalescence, consonant assimilation inside compounds)
+/// is left alone; deployments that need full segmentation should configure an
+/// external splitter instead.
+pub fn split_line(line: &str) -> String {
+    let mut words = Vec::new();
+    for word in line.split_whitespace() {
+        split_avagraha(word, &mut words);
+    }
+
+    for i in 0..words.len().saturating_sub(1) {
+        let next = words[i + 1].chars().next();
+        let word = &mut words[i];
+        if word.ends_with(ANUSVARA) && next.is_some_and(is_consonant) {
+            word.pop();
+            word.push_str("म्");
+        } else if word.ends_with(SIGN_O) && next.is_some_and(is_voiced_consonant) {
+            word.pop();
+            word.push(VISARGA);
+        }
+    }
+
+    words.join(" ")
+}
+
+/// Segments every line of `text`, either with the built-in rules or by piping the
+/// text through an external splitter (`command[0]` with the remaining arguments)
+/// that must print exactly one output line per input line.
+pub fn split_text(text: &str, command: Option<&[String]>) -> Result<String, String> {
+    let Some((program, args)) = command.and_then(|c| c.split_first()) else {
+        return Ok(text.lines().map(split_line).collect::<Vec<_>>().join("\n"));
+    };
+
+    let _slot = processes::acquire(&Cancel::default()).map_err(|e| e.to_string())?;
+    let mut child = processes::prioritized(Command::new(program).args(args))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to execute sandhi splitter '{}': {}", program, e))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(text.as_bytes())
+            .map_err(|e| format!("Failed to write to sandhi splitter: {}", e))?;
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("Sandhi splitter failed: {}", e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "Sandhi splitter error: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    let segmented = String::from_utf8_lossy(&output.stdout)
+        .trim_end()
+        .to_string();
+    if segmented.lines().count() != text.lines().count() {
+        return Err("Sandhi splitter returned a different number of lines".to_string());
+    }
+    Ok(segmented)
+}
@@ -0,0 +1,162 @@
+use serde::{Deserialize, Serialize};
+use unicode_normalization::UnicodeNormalization;
+
+/// Scripts Sanskrit manuscripts are commonly written in.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum Script {
+    Devanagari,
+    Grantha,
+    Sharada,
+    Telugu,
+    Malayalam,
+    /// Latin-script portions such as English introductions; kept as recognized.
+    Latin,
+}
+
+/// Whether recognized text is converted to Devanagari or kept in the source script.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScriptOutput {
+    #[default]
+    Devanagari,
+    Source,
+}
+
+impl Script {
+    pub fn parse(name: &str) -> Option<Script> {
+        match name.trim().to_lowercase().as_str() {
+            "devanagari" | "deva" => Some(Script::Devanagari),
+            "grantha" | "gran" => Some(Script::Grantha),
+            "sharada" | "shrd" => Some(Script::Sharada),
+            "telugu" | "telu" => Some(Script::Telugu),
+            "malayalam" | "mlym" => Some(Script::Malayalam),
+            "latin" | "latn" => Some(Script::Latin),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Script::Devanagari => "devanagari",
+            Script::Grantha => "grantha",
+            Script::Sharada => "sharada",
+            Script::Telugu => "telugu",
+            Script::Malayalam => "malayalam",
+            Script::Latin => "latin",
+        }
+    }
+
+    /// Tesseract model used when the configuration names none. Grantha and
+    /// Sharada have no stock model, so a custom one must be configured.
+    pub fn default_model(self) -> Option<&'static str> {
+        match self {
+            Script::Devanagari => Some("san"),
+            Script::Telugu => Some("script/Telugu"),
+            Script::Malayalam => Some("script/Malayalam"),
+            Script::Latin => Some("eng"),
+            Script::Grantha | Script::Sharada => None,
+        }
+    }
+}
+
+impl ScriptOutput {
+    pub fn parse(name: &str) -> Option<ScriptOutput> {
+        match name.trim().to_lowercase().as_str() {
+            "devanagari" => Some(ScriptOutput::Devanagari),
+            "source" => Some(ScriptOutput::Source),
+            _ => None,
+        }
+    }
+}
+
+const DEVANAGARI: u32 = 0x0900;
+const VIRAMA: char = '्';
+
+/// Sharada letters in block order from U+11180, with their Devanagari equivalents.
+const SHARADA: &[&str] = &[
+    "ँ", "ं", "ः", "अ", "आ", "इ", "ई", "उ", "ऊ", "ऋ", "ॠ", "ऌ", "ॡ", "ए", "ऐ", "ओ", "औ", "क", "ख",
+    "ग", "घ", "ङ", "च", "छ", "ज", "झ", "ञ", "ट", "ठ", "ड", "ढ", "ण", "त", "थ", "द", "ध", "न", "प",
+    "फ", "ब", "भ", "म", "य", "र", "ल", "ळ", "व", "श", "ष", "स", "ह", "ा", "ि", "ी", "ु", "ू", "ृ", "ॄ",
+    "ॢ", "ॣ", "े", "ै", "ो", "ौ", "्", "ऽ", "ᳵ", "ᳶ", "ॐ", "।", "॥",
+];
+
+/// Converts text recognized in `script` to Devanagari. Telugu, Malayalam and
+/// Grantha share Devanagari's block layout, so most letters map by offset;
+/// Sharada is table-driven. Characters without an equivalent are kept.
+pub fn to_devanagari(text: &str, script: Script) -> String {
+    if matches!(script, Script::Devanagari | Script::Latin) {
+        return text.to_string();
+    }
+
+    // Compose two-part vowel signs first so each maps to a single sign
+    let text: String = text.nfc().collect();
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        // Explicit chillu sequences (consonant, virama, ZWJ) are plain half forms
+        if c == '\u{200D}' && out.ends_with(VIRAMA) {
+            continue;
+        }
+        match script {
+            Script::Telugu => out.push_str(&by_offset(c, 0x0C00)),
+            Script::Malayalam => out.push_str(&malayalam(c)),
+            Script::Grantha => out.push_str(&by_offset(c, 0x11300)),
+            Script::Sharada => out.push_str(&sharada(c)),
+            Script::Devanagari | Script::Latin => out.push(c),
+        }
+    }
+    out.nfc().collect()
+}
+
+fn by_offset(c: char, base: u32) -> String {
+    let code = c as u32;
+    if !(base..base + 0x80).contains(&code) {
+        return c.to_string();
+    }
+    let offset = code - base;
+    let mapped = match offset {
+        // Grantha's combining anusvara above (Telugu has a candrabindu here)
+        0x00 if base != 0x0C00 => Some('ं'),
+        // Telugu's combining anusvara above, Malayalam's Vedic anusvara
+        0x04 => Some('ं'),
+        // Length marks left over from decomposed vowel signs
+        0x56 => Some('ै'),
+        0x57 => Some('ौ'),
+        0x00..=0x53 | 0x60..=0x6F => char::from_u32(DEVANAGARI + offset),
+        _ => None,
+    };
+    mapped.map_or_else(|| c.to_string(), |m| m.to_string())
+}
+
+fn malayalam(c: char) -> String {
+    match c {
+        'ൺ' => "ण्".to_string(),
+        'ൻ' => "न्".to_string(),
+        'ർ' => "र्".to_string(),
+        'ൽ' => "ल्".to_string(),
+        'ൾ' => "ळ्".to_string(),
+        'ൿ' => "क्".to_string(),
+        'ൔ' => "म्".to_string(),
+        'ൕ' => "य्".to_string(),
+        // Dot reph
+        '\u{0D4E}' => "र्".to_string(),
+        // Vertical bar and circular viramas
+        '\u{0D3B}' | '\u{0D3C}' => VIRAMA.to_string(),
+        '\u{0D00}' => "ं".to_string(),
+        '\u{0D3A}' => c.to_string(),
+        _ => by_offset(c, 0x0D00),
+    }
+}
+
+fn sharada(c: char) -> String {
+    let code = c as u32;
+    match code {
+        0x11180..=0x111C6 => SHARADA[(code - 0x11180) as usize].to_string(),
+        0x111CA => "़".to_string(),
+        0x111D0..=0x111D9 => {
+            char::from_u32(0x0966 + code - 0x111D0).map_or_else(|| c.to_string(), |d| d.to_string())
+        }
+        _ => c.to_string(),
+    }
+}
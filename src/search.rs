@@ -0,0 +1,255 @@
+use crate::{ErrorResponse, Jobs};
+use actix_web::{HttpResponse, Result, get, web};
+use parking_lot::Mutex;
+use sanskrit_ocr::pipeline::OcrResult;
+use serde::{Deserialize, Serialize};
+use tantivy::collector::{Count, TopDocs};
+use tantivy::query::QueryParser;
+use tantivy::schema::{
+    Field, IndexRecordOption, STORED, STRING, Schema, TantivyDocument, TextFieldIndexing,
+    TextOptions, Value,
+};
+use tantivy::tokenizer::{LowerCaser, RegexTokenizer, TextAnalyzer};
+use tantivy::{Index, IndexReader, IndexWriter, ReloadPolicy, Term, doc};
+use utoipa::{IntoParams, ToSchema};
+
+/// Name the word tokenizer is registered under.
+const TOKENIZER: &str = "words";
+
+/// Runs of letters, combining marks, digits and the zero-width (non-)joiners
+/// make words. Tantivy's default splits at every virama and vowel sign, which
+/// would cut Devanagari words apart.
+const WORD: &str = r"[\p{L}\p{M}\p{N}\x{200C}\x{200D}]+";
+
+/// Memory the index writer buffers documents in before writing a segment.
+const WRITER_MEMORY: usize = 15_000_000;
+
+/// Hits `GET /search` returns per request unless asked for fewer.
+const MAX_HITS: usize = 100;
+
+/// Characters of page text around the matches in a hit's snippet.
+const SNIPPET_CHARS: usize = 200;
+
+struct Fields {
+    session_id: Field,
+    file: Field,
+    filename: Field,
+    page_number: Field,
+    source_page: Field,
+    text: Field,
+}
+
+/// In-memory full-text index of the pages of finished sessions, one document
+/// per page. Sessions live in memory, so the index does too and starts empty.
+pub struct SearchIndex {
+    index: Index,
+    reader: IndexReader,
+    writer: Mutex<IndexWriter>,
+    fields: Fields,
+}
+
+impl SearchIndex {
+    pub fn new() -> tantivy::Result<SearchIndex> {
+        let mut schema = Schema::builder();
+        let text = TextOptions::default()
+            .set_indexing_options(
+                TextFieldIndexing::default()
+                    .set_tokenizer(TOKENIZER)
+                    .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+            )
+            .set_stored();
+        let fields = Fields {
+            session_id: schema.add_text_field("session_id", STRING | STORED),
+            file: schema.add_u64_field("file", STORED),
+            filename: schema.add_text_field("filename", STORED),
+            page_number: schema.add_u64_field("page_number", STORED),
+            source_page: schema.add_u64_field("source_page", STORED),
+            text: schema.add_text_field("text", text),
+        };
+        let index = Index::create_in_ram(schema.build());
+        index.tokenizers().register(
+            TOKENIZER,
+            TextAnalyzer::builder(RegexTokenizer::new(WORD)?)
+                .filter(LowerCaser)
+                .build(),
+        );
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()?;
+        let writer = index.writer_with_num_threads(1, WRITER_MEMORY)?;
+        Ok(SearchIndex {
+            index,
+            reader,
+            writer: Mutex::new(writer),
+            fields,
+        })
+    }
+
+    /// Indexes the pages of a session's successful files, with the proofread
+    /// text where there is one, in place of what was indexed of it before.
+    pub fn index_session(&self, session_id: &str, results: &[OcrResult]) -> tantivy::Result<()> {
+        let mut writer = self.writer.lock();
+        writer.delete_term(Term::from_field_text(self.fields.session_id, session_id));
+        for (file, result) in results.iter().enumerate() {
+            if !result.success {
+                continue;
+            }
+            for page in &result.pages {
+                let text = page.proofread_text.as_deref().unwrap_or(&page.text);
+                if text.trim().is_empty() {
+                    continue;
+                }
+                writer.add_document(doc!(
+                    self.fields.session_id => session_id,
+                    self.fields.file => file as u64,
+                    self.fields.filename => result.filename.as_str(),
+                    self.fields.page_number => page.page_number as u64,
+                    self.fields.source_page => page.source_page as u64,
+                    self.fields.text => text,
+                ))?;
+            }
+        }
+        writer.commit()?;
+        self.reader.reload()
+    }
+
+    /// Drops a deleted session from the index.
+    pub fn remove_session(&self, session_id: &str) -> tantivy::Result<()> {
+        let mut writer = self.writer.lock();
+        writer.delete_term(Term::from_field_text(self.fields.session_id, session_id));
+        writer.commit()?;
+        self.reader.reload()
+    }
+
+    /// Pages matching `query`, best first: the count of all matches and those
+    /// from `offset` on, at most `limit`.
+    fn search(
+        &self,
+        query: &str,
+        offset: usize,
+        limit: usize,
+    ) -> std::result::Result<(usize, Vec<Hit>), String> {
+        let mut parser = QueryParser::for_index(&self.index, vec![self.fields.text]);
+        parser.set_conjunction_by_default();
+        let query = parser
+            .parse_query(query)
+            .map_err(|e| format!("Invalid query: {}", e))?;
+
+        let searcher = self.reader.searcher();
+        let (total, top) = searcher
+            .search(
+                &query,
+                &(Count, TopDocs::with_limit(limit).and_offset(offset)),
+            )
+            .map_err(|e| e.to_string())?;
+        let mut snippets =
+            tantivy::snippet::SnippetGenerator::create(&searcher, &*query, self.fields.text)
+                .map_err(|e| e.to_string())?;
+        snippets.set_max_num_chars(SNIPPET_CHARS);
+
+        let mut hits = Vec::with_capacity(top.len());
+        for (score, address) in top {
+            let document: TantivyDocument = searcher.doc(address).map_err(|e| e.to_string())?;
+            let text = |field| {
+                document
+                    .get_first(field)
+                    .and_then(|value| value.as_str())
+                    .unwrap_or_default()
+                    .to_string()
+            };
+            let number = |field| {
+                document
+                    .get_first(field)
+                    .and_then(|value| value.as_u64())
+                    .unwrap_or_default() as usize
+            };
+            hits.push(Hit {
+                session_id: text(self.fields.session_id),
+                title: None,
+                file: number(self.fields.file),
+                filename: text(self.fields.filename),
+                page_number: number(self.fields.page_number),
+                source_page: number(self.fields.source_page),
+                score,
+                snippet: snippets.snippet_from_doc(&document).to_html(),
+            });
+        }
+        Ok((total, hits))
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct SearchQuery {
+    /// Words to find, all of them by default; `"..."` for a phrase, `OR` and `-`
+    /// as in Tantivy's query syntax.
+    q: String,
+    /// Hits to skip, best first.
+    #[serde(default)]
+    offset: usize,
+    limit: Option<usize>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Hit {
+    session_id: String,
+    /// The session's title, when it was given one.
+    title: Option<String>,
+    /// Position of the file in the session's results.
+    file: usize,
+    filename: String,
+    page_number: usize,
+    /// Page number in the uploaded document.
+    source_page: usize,
+    score: f32,
+    /// The page text around the matches, HTML-escaped, with the matches in
+    /// `<b>`.
+    snippet: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SearchResponse {
+    /// Pages matching, across all ranges.
+    total: usize,
+    offset: usize,
+    limit: usize,
+    hits: Vec<Hit>,
+}
+
+/// Finds pages of finished sessions by their text: which volume and page a
+/// verse is on. Proofread pages are found by their proofread text.
+#[utoipa::path(
+    tag = "results",
+    params(SearchQuery),
+    responses(
+        (status = 200, body = SearchResponse),
+        (status = 400, description = "Invalid query", body = ErrorResponse)
+    )
+)]
+#[get("/search")]
+pub async fn search(query: web::Query<SearchQuery>, jobs: web::Data<Jobs>) -> Result<HttpResponse> {
+    let SearchQuery { q, offset, limit } = query.into_inner();
+    let limit = limit.unwrap_or(MAX_HITS).min(MAX_HITS);
+    let searched = {
+        let jobs = jobs.clone();
+        web::block(move || jobs.search.search(&q, offset, limit)).await?
+    };
+    let (total, mut hits) = match searched {
+        Ok(found) => found,
+        Err(error) => {
+            return Ok(HttpResponse::BadRequest().json(ErrorResponse { error, code: None }));
+        }
+    };
+    let tracker = jobs.tracker.read();
+    for hit in &mut hits {
+        hit.title = tracker
+            .get(&hit.session_id)
+            .and_then(|status| status.metadata.title.clone());
+    }
+    Ok(HttpResponse::Ok().json(SearchResponse {
+        total,
+        offset,
+        limit,
+        hits,
+    }))
+}
@@ -0,0 +1,80 @@
+use crate::ProgressStatus;
+use parking_lot::Mutex;
+use redis::{Client, Commands, Connection, RedisResult};
+use std::time::Duration;
+
+/// Prefix of the keys statuses are published under, one per session.
+const KEY_PREFIX: &str = "sanskrit-ocr:status:";
+
+/// How long Redis may take to connect or answer.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Session statuses published to Redis, so server instances behind a load
+/// balancer answer `/status` for sessions another instance runs. Each instance
+/// still tracks the sessions it runs in memory: it publishes their statuses as
+/// they change, as `/status` answers them, and looks up the published status
+/// of sessions it does not know.
+pub struct SharedStatuses {
+    client: Client,
+    /// The connection, opened again on the next call once lost.
+    connection: Mutex<Option<Connection>>,
+}
+
+fn key(session_id: &str) -> String {
+    format!("{}{}", KEY_PREFIX, session_id)
+}
+
+impl SharedStatuses {
+    /// Connects to the Redis server at `url`, e.g. `redis://cache:6379/0`.
+    pub fn connect(url: &str) -> Result<SharedStatuses, String> {
+        let client = Client::open(url).map_err(|e| e.to_string())?;
+        let connection = open(&client).map_err(|e| e.to_string())?;
+        Ok(SharedStatuses {
+            client,
+            connection: Mutex::new(Some(connection)),
+        })
+    }
+
+    /// Runs `call` on the connection, opening it first if need be, and drops
+    /// the connection when `call` found it broken.
+    fn with<T>(&self, call: impl FnOnce(&mut Connection) -> RedisResult<T>) -> Result<T, String> {
+        let mut connection = self.connection.lock();
+        let mut open_connection = match connection.take() {
+            Some(open_connection) => open_connection,
+            None => open(&self.client).map_err(|e| e.to_string())?,
+        };
+        let outcome = call(&mut open_connection);
+        match &outcome {
+            Err(e) if e.is_unrecoverable_error() || e.is_timeout() => {}
+            _ => *connection = Some(open_connection),
+        }
+        outcome.map_err(|e| e.to_string())
+    }
+
+    /// Publishes each session's status as `/status` answers it.
+    pub fn publish(&self, statuses: &[(&String, &ProgressStatus)]) -> Result<(), String> {
+        let mut pipeline = redis::pipe();
+        for (session_id, status) in statuses {
+            let body = serde_json::to_string(status).map_err(|e| e.to_string())?;
+            pipeline.set(key(session_id), body).ignore();
+        }
+        self.with(|connection| pipeline.query::<()>(connection))
+    }
+
+    /// The `/status` body last published for the session, if any instance has.
+    pub fn fetch(&self, session_id: &str) -> Result<Option<String>, String> {
+        self.with(|connection| connection.get(key(session_id)))
+    }
+
+    /// Withdraws the session's status, once it is deleted.
+    pub fn remove(&self, session_id: &str) -> Result<(), String> {
+        self.with(|connection| connection.del(key(session_id)))
+    }
+}
+
+fn open(client: &Client) -> RedisResult<Connection> {
+    let connection = client.get_connection_with_timeout(TIMEOUT)?;
+    connection.set_read_timeout(Some(TIMEOUT))?;
+    connection.set_write_timeout(Some(TIMEOUT))?;
+    Ok(connection)
+}
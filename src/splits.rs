@@ -0,0 +1,120 @@
+use crate::ChunkInfo;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+/// Name of the file describing a split, next to its chunks.
+const MANIFEST: &str = "split.json";
+
+/// Held while a manifest is read and rewritten, so a split handed out again
+/// and one being deleted agree on how many hold it.
+static MANIFESTS: Mutex<()> = Mutex::new(());
+
+/// How a split was asked to size its chunks: by page count, by size, or at
+/// the default size when neither is set.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Parameters {
+    pub pages_per_chunk: Option<usize>,
+    pub max_chunk_mb: Option<f64>,
+}
+
+/// What a split was made from and the chunks it made, so splitting the same
+/// PDF the same way again hands out this split instead of cutting new chunks.
+#[derive(Serialize, Deserialize)]
+pub struct Manifest {
+    /// SHA-256 of the uploaded PDF, in hex.
+    pub sha256: String,
+    #[serde(flatten)]
+    pub parameters: Parameters,
+    pub total_pages: usize,
+    pub chunks: Vec<ChunkInfo>,
+    /// How many `/split` requests were handed this split and have not deleted
+    /// it; its chunks are removed when the last one does.
+    #[serde(default = "one")]
+    pub holders: usize,
+}
+
+fn one() -> usize {
+    1
+}
+
+impl Manifest {
+    /// Writes the manifest into the split's directory `dir`.
+    pub fn save(&self, dir: &Path) -> Result<(), String> {
+        let json = serde_json::to_vec_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(dir.join(MANIFEST), json)
+            .map_err(|e| format!("Failed to write split manifest: {}", e))
+    }
+
+    fn load(dir: &Path) -> Option<Manifest> {
+        let json = std::fs::read(dir.join(MANIFEST)).ok()?;
+        serde_json::from_slice(&json).ok()
+    }
+}
+
+/// SHA-256 of the file at `path`, in hex.
+pub fn sha256(path: &Path) -> std::io::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// The split in `splits_dir` of the PDF hashed `sha256` with the same
+/// `parameters`, by id, when one is kept with all its chunks.
+fn find(splits_dir: &Path, sha256: &str, parameters: Parameters) -> Option<(String, Manifest)> {
+    std::fs::read_dir(splits_dir)
+        .ok()?
+        .flatten()
+        .find_map(|entry| {
+            let dir = entry.path();
+            let manifest = Manifest::load(&dir)?;
+            if manifest.sha256 != sha256 || manifest.parameters != parameters {
+                return None;
+            }
+            let whole = manifest.chunks.iter().all(|chunk| {
+                std::fs::metadata(dir.join(&chunk.filename))
+                    .is_ok_and(|metadata| metadata.len() == chunk.file_size)
+            });
+            whole.then_some((entry.file_name().into_string().ok()?, manifest))
+        })
+}
+
+/// Hands out again the kept split of the PDF hashed `sha256` with the same
+/// `parameters`: counts one more holder of it and returns its id and manifest,
+/// whose chunks keep their download paths.
+pub fn reuse(
+    splits_dir: &Path,
+    sha256: &str,
+    parameters: Parameters,
+) -> Option<(String, Manifest)> {
+    let _manifests = MANIFESTS.lock();
+    let (split_id, mut manifest) = find(splits_dir, sha256, parameters)?;
+    manifest.holders += 1;
+    if let Err(e) = manifest.save(&splits_dir.join(&split_id)) {
+        println!("  ⚠️  Warning: {}", e);
+        return None;
+    }
+    println!("♻️  Reusing split {}", split_id);
+    Some((split_id, manifest))
+}
+
+/// Lets go of the split in `dir` for one of its holders, returning whether it
+/// was the last, whose chunks can go. Their manifest is removed then, so the
+/// split is not handed out again while it is being deleted.
+pub fn release(dir: &Path) -> bool {
+    let _manifests = MANIFESTS.lock();
+    let Some(mut manifest) = Manifest::load(dir) else {
+        return true;
+    };
+    if manifest.holders <= 1 {
+        let _ = std::fs::remove_file(dir.join(MANIFEST));
+        return true;
+    }
+    manifest.holders -= 1;
+    if let Err(e) = manifest.save(dir) {
+        println!("  ⚠️  Warning: {}", e);
+    }
+    false
+}
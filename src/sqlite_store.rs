@@ -0,0 +1,246 @@
+use crate::ProgressStatus;
+use crate::store::{self, Rows, STALE_AFTER, Store, StoredJob, StoredSession, json};
+use parking_lot::Mutex;
+use rusqlite::{Connection, Params, Row, Transaction, params};
+use sanskrit_ocr::pipeline::{OcrResult, PageResult};
+use std::path::Path;
+
+/// Session statuses without their results, file results without their pages,
+/// the pages, and the work sessions have left with the instance running it,
+/// each stored as JSON.
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS sessions (
+        id TEXT PRIMARY KEY,
+        status TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS results (
+        session_id TEXT NOT NULL,
+        file_index INTEGER NOT NULL,
+        result TEXT NOT NULL,
+        PRIMARY KEY (session_id, file_index)
+    );
+    CREATE TABLE IF NOT EXISTS pages (
+        session_id TEXT NOT NULL,
+        file_index INTEGER NOT NULL,
+        position INTEGER NOT NULL,
+        page TEXT NOT NULL,
+        PRIMARY KEY (session_id, file_index, position)
+    );
+    CREATE TABLE IF NOT EXISTS jobs (
+        session_id TEXT PRIMARY KEY,
+        job TEXT NOT NULL,
+        owner TEXT NOT NULL,
+        heartbeat INTEGER NOT NULL
+    );
+";
+
+/// Sessions kept in an SQLite database file, for a single server instance.
+pub struct SqliteStore {
+    connection: Mutex<Connection>,
+    instance: String,
+}
+
+impl SqliteStore {
+    /// Opens the database at `path`, creating it and its directory if need be.
+    pub fn open(path: &Path, instance: &str) -> Result<SqliteStore, String> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let connection = Connection::open(path).map_err(|e| e.to_string())?;
+        connection
+            .pragma_update(None, "journal_mode", "WAL")
+            .and_then(|_| connection.execute_batch(SCHEMA))
+            .map_err(|e| e.to_string())?;
+        Ok(SqliteStore {
+            connection: Mutex::new(connection),
+            instance: instance.to_string(),
+        })
+    }
+
+    /// Runs `write` in a transaction, committed when it succeeds.
+    fn write<T>(&self, write: impl FnOnce(&Transaction) -> Result<T, String>) -> Result<T, String> {
+        let mut connection = self.connection.lock();
+        let transaction = connection.transaction().map_err(|e| e.to_string())?;
+        let written = write(&transaction)?;
+        transaction.commit().map_err(|e| e.to_string())?;
+        Ok(written)
+    }
+}
+
+impl Store for SqliteStore {
+    fn save_statuses(&self, statuses: &[(&String, &ProgressStatus)]) -> Result<(), String> {
+        self.write(|transaction| {
+            for (session_id, status) in statuses {
+                transaction
+                    .execute(
+                        "INSERT OR REPLACE INTO sessions (id, status) VALUES (?1, ?2)",
+                        params![session_id, store::status_json(status)?],
+                    )
+                    .map_err(|e| e.to_string())?;
+            }
+            Ok(())
+        })
+    }
+
+    fn save_result(
+        &self,
+        session_id: &str,
+        index: usize,
+        result: &OcrResult,
+    ) -> Result<(), String> {
+        self.write(|transaction| {
+            transaction
+                .execute(
+                    "DELETE FROM pages WHERE session_id = ?1 AND file_index = ?2",
+                    params![session_id, index as i64],
+                )
+                .map_err(|e| e.to_string())?;
+            transaction
+                .execute(
+                    "INSERT OR REPLACE INTO results (session_id, file_index, result) VALUES (?1, ?2, ?3)",
+                    params![session_id, index as i64, store::result_json(result)?],
+                )
+                .map_err(|e| e.to_string())?;
+            for (position, page) in result.pages.iter().enumerate() {
+                transaction
+                    .execute(
+                        "INSERT INTO pages (session_id, file_index, position, page) VALUES (?1, ?2, ?3, ?4)",
+                        params![session_id, index as i64, position as i64, json(page)?],
+                    )
+                    .map_err(|e| e.to_string())?;
+            }
+            Ok(())
+        })
+    }
+
+    fn save_page(
+        &self,
+        session_id: &str,
+        index: usize,
+        position: usize,
+        page: &PageResult,
+    ) -> Result<(), String> {
+        self.write(|transaction| {
+            transaction
+                .execute(
+                    "INSERT OR REPLACE INTO pages (session_id, file_index, position, page) VALUES (?1, ?2, ?3, ?4)",
+                    params![session_id, index as i64, position as i64, json(page)?],
+                )
+                .map_err(|e| e.to_string())
+                .map(|_| ())
+        })
+    }
+
+    fn save_job(&self, session_id: &str, job: &StoredJob) -> Result<(), String> {
+        self.write(|transaction| {
+            transaction
+                .execute(
+                    "INSERT OR REPLACE INTO jobs (session_id, job, owner, heartbeat) VALUES (?1, ?2, ?3, ?4)",
+                    params![session_id, json(job)?, self.instance, store::now()],
+                )
+                .map_err(|e| e.to_string())
+                .map(|_| ())
+        })
+    }
+
+    fn remove_job(&self, session_id: &str) -> Result<(), String> {
+        self.write(|transaction| {
+            transaction
+                .execute(
+                    "DELETE FROM jobs WHERE session_id = ?1",
+                    params![session_id],
+                )
+                .map_err(|e| e.to_string())
+                .map(|_| ())
+        })
+    }
+
+    fn delete(&self, session_id: &str) -> Result<(), String> {
+        self.write(|transaction| {
+            for table in ["sessions", "results", "pages", "jobs"] {
+                let column = if table == "sessions" {
+                    "id"
+                } else {
+                    "session_id"
+                };
+                transaction
+                    .execute(
+                        &format!("DELETE FROM {} WHERE {} = ?1", table, column),
+                        params![session_id],
+                    )
+                    .map_err(|e| e.to_string())?;
+            }
+            Ok(())
+        })
+    }
+
+    fn heartbeat(&self) -> Result<(), String> {
+        self.write(|transaction| {
+            transaction
+                .execute(
+                    "UPDATE jobs SET heartbeat = ?2 WHERE owner = ?1",
+                    params![self.instance, store::now()],
+                )
+                .map_err(|e| e.to_string())
+                .map(|_| ())
+        })
+    }
+
+    fn load(&self) -> Result<Vec<StoredSession>, String> {
+        let instance = &self.instance;
+        let rows = self.write(|transaction| {
+            let now = store::now();
+            transaction
+                .execute(
+                    "UPDATE jobs SET owner = ?1, heartbeat = ?2 WHERE owner = ?1 OR heartbeat < ?3",
+                    params![instance, now, now - STALE_AFTER.as_secs() as i64],
+                )
+                .map_err(|e| e.to_string())?;
+            Ok(Rows {
+                sessions: query(
+                    transaction,
+                    "SELECT id, status FROM sessions
+                     WHERE id NOT IN (SELECT session_id FROM jobs WHERE owner <> ?1)",
+                    params![instance],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )?,
+                results: query(
+                    transaction,
+                    "SELECT session_id, file_index, result FROM results
+                     WHERE session_id NOT IN (SELECT session_id FROM jobs WHERE owner <> ?1)",
+                    params![instance],
+                    |row| Ok((row.get(0)?, row.get::<_, i64>(1)? as usize, row.get(2)?)),
+                )?,
+                pages: query(
+                    transaction,
+                    "SELECT session_id, file_index, page FROM pages
+                     WHERE session_id NOT IN (SELECT session_id FROM jobs WHERE owner <> ?1)
+                     ORDER BY session_id, file_index, position",
+                    params![instance],
+                    |row| Ok((row.get(0)?, row.get::<_, i64>(1)? as usize, row.get(2)?)),
+                )?,
+                jobs: query(
+                    transaction,
+                    "SELECT session_id, job FROM jobs WHERE owner = ?1",
+                    params![instance],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )?,
+            })
+        })?;
+        rows.into_sessions()
+    }
+}
+
+/// The rows `sql` selects with `params`, each made into a `T` by `row`.
+fn query<T>(
+    connection: &Connection,
+    sql: &str,
+    params: impl Params,
+    row: impl FnMut(&Row) -> rusqlite::Result<T>,
+) -> Result<Vec<T>, String> {
+    let mut statement = connection.prepare(sql).map_err(|e| e.to_string())?;
+    statement
+        .query_map(params, row)
+        .and_then(|rows| rows.collect())
+        .map_err(|e| e.to_string())
+}
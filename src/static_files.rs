@@ -0,0 +1,89 @@
+use actix_files::NamedFile;
+use actix_web::http::Method;
+use actix_web::http::header::{self, ContentEncoding, HeaderValue};
+use actix_web::{HttpRequest, HttpResponse, Result, web};
+use sanskrit_ocr::config::Config;
+use std::path::PathBuf;
+
+/// Whether the client's `Accept-Encoding` header allows `encoding`.
+fn accepts(accept_encoding: &str, encoding: &str) -> bool {
+    accept_encoding.split(',').any(|item| {
+        let mut parts = item.split(';').map(str::trim);
+        parts.next() == Some(encoding) && !parts.any(|param| param == "q=0")
+    })
+}
+
+/// Serves the frontend from `public_dir` with cache headers.
+///
+/// HTML is marked `no-cache` so a redeployed UI is picked up on the next visit
+/// (the ETag makes revalidation a cheap 304); everything else is cacheable for
+/// `static_cache_max_age` seconds. When the client accepts it and a sibling
+/// `.br` or `.gz` file exists, the pre-compressed variant is sent instead.
+pub async fn serve(req: HttpRequest, config: web::Data<Config>) -> Result<HttpResponse> {
+    if req.method() != Method::GET && req.method() != Method::HEAD {
+        return Ok(HttpResponse::MethodNotAllowed().finish());
+    }
+
+    let mut relative = req.path().trim_start_matches('/').to_string();
+    if relative.is_empty() || relative.ends_with('/') {
+        relative.push_str("index.html");
+    }
+    if relative
+        .split('/')
+        .any(|segment| segment.is_empty() || segment.starts_with('.'))
+    {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+
+    let path = config.public_dir.join(&relative);
+    if !path.is_file() {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_string();
+    let accept_encoding = req
+        .headers()
+        .get(header::ACCEPT_ENCODING)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("");
+
+    let variant = [
+        ("br", ContentEncoding::Brotli),
+        ("gz", ContentEncoding::Gzip),
+    ]
+    .into_iter()
+    .filter(|_| config.precompressed_static)
+    .filter(|(_, encoding)| accepts(accept_encoding, encoding.as_str()))
+    .map(|(suffix, encoding)| {
+        let mut compressed = path.clone().into_os_string();
+        compressed.push(".");
+        compressed.push(suffix);
+        (PathBuf::from(compressed), encoding)
+    })
+    .find(|(compressed, _)| compressed.is_file());
+
+    let file = match variant {
+        Some((compressed, encoding)) => NamedFile::open(compressed)?
+            .set_content_type(actix_files::file_extension_to_mime(&extension))
+            .set_content_encoding(encoding),
+        None => NamedFile::open(&path)?,
+    };
+
+    let cache_control = if extension == "html" {
+        "no-cache".to_string()
+    } else {
+        format!("public, max-age={}", config.static_cache_max_age)
+    };
+
+    let mut response = file.into_response(&req);
+    let headers = response.headers_mut();
+    if let Ok(value) = HeaderValue::from_str(&cache_control) {
+        headers.insert(header::CACHE_CONTROL, value);
+    }
+    headers.insert(header::VARY, HeaderValue::from_static("accept-encoding"));
+    Ok(response)
+}
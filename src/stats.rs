@@ -0,0 +1,93 @@
+use crate::dictionary::{Dictionary, is_word_char};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Quick quality indicators for a recognized text.
+#[derive(Serialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct TextStats {
+    /// Whitespace-separated tokens, punctuation included.
+    pub tokens: usize,
+    pub words: usize,
+    pub unique_words: usize,
+    /// Share of words (0-100) found in the wordlist; unset without a wordlist.
+    pub dictionary_coverage: Option<f32>,
+    /// Most frequent words, most frequent first.
+    pub word_frequencies: Vec<WordCount>,
+    /// Every non-whitespace character, most frequent first.
+    pub characters: Vec<CharCount>,
+}
+
+#[derive(Serialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct WordCount {
+    pub word: String,
+    pub count: usize,
+}
+
+#[derive(Serialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct CharCount {
+    pub character: char,
+    pub count: usize,
+}
+
+impl TextStats {
+    /// Counts over all `texts`, listing at most `top_words` word frequencies.
+    pub fn compute<'a>(
+        texts: impl IntoIterator<Item = &'a str>,
+        dictionary: &Dictionary,
+        top_words: usize,
+    ) -> TextStats {
+        let mut tokens = 0;
+        let mut words = 0;
+        let mut known = 0;
+        let mut frequencies: HashMap<&str, usize> = HashMap::new();
+        let mut characters: HashMap<char, usize> = HashMap::new();
+
+        for text in texts {
+            for token in text.split_whitespace() {
+                tokens += 1;
+                for c in token.chars() {
+                    *characters.entry(c).or_default() += 1;
+                }
+                let word = token.trim_matches(|c: char| !is_word_char(c));
+                if word.is_empty() {
+                    continue;
+                }
+                words += 1;
+                if dictionary.contains(word) {
+                    known += 1;
+                }
+                *frequencies.entry(word).or_default() += 1;
+            }
+        }
+
+        let unique_words = frequencies.len();
+        let mut word_frequencies: Vec<WordCount> = frequencies
+            .into_iter()
+            .map(|(word, count)| WordCount {
+                word: word.to_string(),
+                count,
+            })
+            .collect();
+        word_frequencies.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.word.cmp(&b.word)));
+        word_frequencies.truncate(top_words);
+
+        let mut characters: Vec<CharCount> = characters
+            .into_iter()
+            .map(|(character, count)| CharCount { character, count })
+            .collect();
+        characters.sort_by(|a, b| b.count.cmp(&a.count).then(a.character.cmp(&b.character)));
+
+        TextStats {
+            tokens,
+            words,
+            unique_words,
+            dictionary_coverage: (!dictionary.is_empty() && words > 0)
+                .then(|| known as f32 / words as f32 * 100.0),
+            word_frequencies,
+            characters,
+        }
+    }
+}
@@ -0,0 +1,268 @@
+#[cfg(feature = "postgres")]
+use crate::postgres_store::PostgresStore;
+use crate::sqlite_store::SqliteStore;
+use crate::{Priority, ProgressStatus, UploadedFile};
+use sanskrit_ocr::config::StateStoreConfig;
+use sanskrit_ocr::pipeline::{OcrResult, PageResult, ProcessingOptions};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::panic::AssertUnwindSafe;
+use std::sync::mpsc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How long changed session statuses wait before they are written together.
+pub const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often an instance marks the jobs it runs as still its own.
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How long the jobs of an instance that stopped marking them wait before
+/// another instance takes them over on its start.
+pub const STALE_AFTER: Duration = Duration::from_secs(60);
+
+/// Where a session's unfinished work stands.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    /// Waiting for a worker or being processed; picked up again on restart.
+    Queued,
+    /// Held with `prepare=true` until its pages are arranged.
+    Held,
+    /// Finished with pages to retry.
+    Retained,
+}
+
+/// A session's unfinished work: its uploads still to read, each with its
+/// position among the session's results, and the settings to read them with.
+#[derive(Serialize, Deserialize)]
+pub struct StoredJob {
+    pub state: JobState,
+    pub files: Vec<(usize, UploadedFile)>,
+    pub options: ProcessingOptions,
+    pub priority: Priority,
+}
+
+/// A session as it was last saved, with its results and work left.
+pub struct StoredSession {
+    pub id: String,
+    pub status: ProgressStatus,
+    pub job: Option<StoredJob>,
+    /// Pages read of the files whose reading was interrupted, in page order,
+    /// by file position.
+    pub read_pages: BTreeMap<usize, Vec<PageResult>>,
+}
+
+/// Sessions, their results page by page, and their jobs in a database, so a
+/// restarted server still reports them and takes up the work it was doing.
+///
+/// Each job belongs to the instance that saved it. Instances sharing a
+/// database load the sessions of the others, but only resume their own jobs
+/// and those of instances that have not marked theirs for [`STALE_AFTER`].
+pub trait Store: Send + Sync {
+    /// Saves each session's status but for its results, which are saved with
+    /// [`Store::save_result`].
+    fn save_statuses(&self, statuses: &[(&String, &ProgressStatus)]) -> Result<(), String>;
+
+    /// Saves the result of the session's `index`-th file, replacing its pages.
+    fn save_result(&self, session_id: &str, index: usize, result: &OcrResult)
+    -> Result<(), String>;
+
+    /// Saves one page of the session's `index`-th file, such as after it was
+    /// proofread, or as it is read so an interrupted job can pick the file up
+    /// after it.
+    fn save_page(
+        &self,
+        session_id: &str,
+        index: usize,
+        position: usize,
+        page: &PageResult,
+    ) -> Result<(), String>;
+
+    /// Saves the work the session has left as this instance's, replacing what
+    /// was saved before.
+    fn save_job(&self, session_id: &str, job: &StoredJob) -> Result<(), String>;
+
+    /// Forgets the work the session had left, once it has none.
+    fn remove_job(&self, session_id: &str) -> Result<(), String>;
+
+    /// Forgets everything saved of the session.
+    fn delete(&self, session_id: &str) -> Result<(), String>;
+
+    /// Marks this instance's jobs as still its own, every
+    /// [`HEARTBEAT_INTERVAL`].
+    fn heartbeat(&self) -> Result<(), String>;
+
+    /// Takes over the jobs of this instance and of stale ones, then returns
+    /// every session saved but those whose jobs another live instance runs,
+    /// with results in file order and their work left.
+    fn load(&self) -> Result<Vec<StoredSession>, String>;
+}
+
+/// Opens the database `config` names, with jobs saved under `instance`.
+pub fn open(config: &StateStoreConfig, instance: &str) -> Result<Box<dyn Store>, String> {
+    match config {
+        StateStoreConfig::Sqlite { path } => SqliteStore::open(path, instance)
+            .map(|store| Box::new(store) as Box<dyn Store>)
+            .map_err(|e| {
+                format!(
+                    "Failed to open session database '{}': {}",
+                    path.display(),
+                    e
+                )
+            }),
+        #[cfg(feature = "postgres")]
+        StateStoreConfig::Postgres { url } => PostgresStore::connect(url, instance)
+            .map(|store| Box::new(store) as Box<dyn Store>)
+            .map_err(|e| format!("Failed to connect to the session database: {}", e)),
+        #[cfg(not(feature = "postgres"))]
+        StateStoreConfig::Postgres { .. } => {
+            Err("This build has no PostgreSQL support; rebuild with the postgres feature".into())
+        }
+    }
+}
+
+/// A write to the session store or the shared statuses, with what it writes.
+pub type Write = Box<dyn FnOnce() + Send>;
+
+/// Runs the writes to the session store and the shared statuses on a thread
+/// of their own, one at a time in the order they were queued, so neither
+/// request handlers nor workers wait on a database. A session deleted after
+/// its status was queued is deleted after that status is written.
+pub struct Writer {
+    queue: mpsc::Sender<Write>,
+}
+
+impl Writer {
+    pub fn start() -> std::io::Result<Writer> {
+        let (queue, writes) = mpsc::channel::<Write>();
+        std::thread::Builder::new()
+            .name("store-writer".into())
+            .spawn(move || {
+                for write in writes {
+                    // A write that panics loses only itself
+                    let _ = std::panic::catch_unwind(AssertUnwindSafe(write));
+                }
+            })?;
+        Ok(Writer { queue })
+    }
+
+    /// Queues `write` behind the writes queued before it.
+    pub fn queue(&self, write: impl FnOnce() + Send + 'static) {
+        // The thread runs for as long as the writer is kept
+        let _ = self.queue.send(Box::new(write));
+    }
+
+    /// Waits until the writes queued so far are done, such as before the
+    /// server exits.
+    pub fn wait(&self) {
+        let (done, finished) = mpsc::channel();
+        self.queue(move || {
+            let _ = done.send(());
+        });
+        let _ = finished.recv();
+    }
+}
+
+/// Seconds since the Unix epoch, the time heartbeats are stamped with.
+pub fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs() as i64)
+}
+
+pub fn json(value: &impl Serialize) -> Result<String, String> {
+    serde_json::to_string(value).map_err(|e| e.to_string())
+}
+
+/// `status` without its results, which are stored file by file.
+pub fn status_json(status: &ProgressStatus) -> Result<String, String> {
+    let mut value = serde_json::to_value(status).map_err(|e| e.to_string())?;
+    if let Some(fields) = value.as_object_mut() {
+        fields.remove("results");
+    }
+    json(&value)
+}
+
+/// `result` without its pages, which are stored one by one.
+pub fn result_json(result: &OcrResult) -> Result<String, String> {
+    let mut value = serde_json::to_value(result).map_err(|e| e.to_string())?;
+    if let Some(fields) = value.as_object_mut() {
+        fields.remove("pages");
+    }
+    json(&value)
+}
+
+/// The rows [`Store::load`] reads, as JSON, before they are put together.
+#[derive(Default)]
+pub struct Rows {
+    /// Session ids and statuses.
+    pub sessions: Vec<(String, String)>,
+    /// Session ids, file positions and results.
+    pub results: Vec<(String, usize, String)>,
+    /// Session ids, file positions and pages, in page order.
+    pub pages: Vec<(String, usize, String)>,
+    /// Session ids and jobs.
+    pub jobs: Vec<(String, String)>,
+}
+
+impl Rows {
+    /// Each session with its results in file order and its work left.
+    pub fn into_sessions(self) -> Result<Vec<StoredSession>, String> {
+        let mut pages: HashMap<(String, usize), Vec<serde_json::Value>> = HashMap::new();
+        for (session_id, index, page) in self.pages {
+            let page = serde_json::from_str(&page).map_err(|e| e.to_string())?;
+            pages.entry((session_id, index)).or_default().push(page);
+        }
+        let mut results: HashMap<String, BTreeMap<usize, OcrResult>> = HashMap::new();
+        for (session_id, index, result) in self.results {
+            let mut value: serde_json::Value =
+                serde_json::from_str(&result).map_err(|e| e.to_string())?;
+            let file_pages = pages
+                .remove(&(session_id.clone(), index))
+                .unwrap_or_default();
+            if let Some(fields) = value.as_object_mut() {
+                fields.insert("pages".to_string(), file_pages.into());
+            }
+            let result = serde_json::from_value(value).map_err(|e| e.to_string())?;
+            results.entry(session_id).or_default().insert(index, result);
+        }
+        // Pages left over belong to files whose reading was interrupted
+        let mut read_pages: HashMap<String, BTreeMap<usize, Vec<PageResult>>> = HashMap::new();
+        for ((session_id, index), file_pages) in pages {
+            let file_pages = file_pages
+                .into_iter()
+                .map(serde_json::from_value)
+                .collect::<Result<_, _>>()
+                .map_err(|e| e.to_string())?;
+            read_pages
+                .entry(session_id)
+                .or_default()
+                .insert(index, file_pages);
+        }
+        let mut jobs: HashMap<String, StoredJob> = HashMap::new();
+        for (session_id, job) in self.jobs {
+            let job = serde_json::from_str(&job).map_err(|e| e.to_string())?;
+            jobs.insert(session_id, job);
+        }
+
+        let mut sessions = Vec::new();
+        for (id, status) in self.sessions {
+            let mut status: ProgressStatus =
+                serde_json::from_str(&status).map_err(|e| e.to_string())?;
+            status.results = results
+                .remove(&id)
+                .unwrap_or_default()
+                .into_values()
+                .collect();
+            let job = jobs.remove(&id);
+            let read_pages = read_pages.remove(&id).unwrap_or_default();
+            sessions.push(StoredSession {
+                id,
+                status,
+                job,
+                read_pages,
+            });
+        }
+        Ok(sessions)
+    }
+}
@@ -0,0 +1,262 @@
+use serde::{Deserialize, Serialize};
+
+/// Romanization schemes supported for Devanagari output.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum Scheme {
+    Iast,
+    Slp1,
+    Hk,
+    Itrans,
+    Velthuis,
+}
+
+impl Scheme {
+    pub fn parse(name: &str) -> Option<Scheme> {
+        match name.trim().to_lowercase().as_str() {
+            "iast" => Some(Scheme::Iast),
+            "slp1" => Some(Scheme::Slp1),
+            "hk" | "harvard-kyoto" => Some(Scheme::Hk),
+            "itrans" => Some(Scheme::Itrans),
+            "velthuis" => Some(Scheme::Velthuis),
+            _ => None,
+        }
+    }
+
+    fn column(self) -> usize {
+        self as usize
+    }
+}
+
+/// Independent vowel, dependent sign, and romanizations in `Scheme` order.
+const VOWELS: &[(char, Option<char>, [&str; 5])] = &[
+    ('अ', None, ["a", "a", "a", "a", "a"]),
+    ('आ', Some('ा'), ["ā", "A", "A", "A", "aa"]),
+    ('इ', Some('ि'), ["i", "i", "i", "i", "i"]),
+    ('ई', Some('ी'), ["ī", "I", "I", "I", "ii"]),
+    ('उ', Some('ु'), ["u", "u", "u", "u", "u"]),
+    ('ऊ', Some('ू'), ["ū", "U", "U", "U", "uu"]),
+    ('ऋ', Some('ृ'), ["ṛ", "f", "R", "RRi", ".r"]),
+    ('ॠ', Some('ॄ'), ["ṝ", "F", "RR", "RRI", ".R"]),
+    ('ऌ', Some('ॢ'), ["ḷ", "x", "lR", "LLi", ".l"]),
+    ('ॡ', Some('ॣ'), ["ḹ", "X", "lRR", "LLI", ".L"]),
+    ('ए', Some('े'), ["e", "e", "e", "e", "e"]),
+    ('ऐ', Some('ै'), ["ai", "E", "ai", "ai", "ai"]),
+    ('ओ', Some('ो'), ["o", "o", "o", "o", "o"]),
+    ('औ', Some('ौ'), ["au", "O", "au", "au", "au"]),
+];
+
+const CONSONANTS: &[(char, [&str; 5])] = &[
+    ('क', ["k", "k", "k", "k", "k"]),
+    ('ख', ["kh", "K", "kh", "kh", "kh"]),
+    ('ग', ["g", "g", "g", "g", "g"]),
+    ('घ', ["gh", "G", "gh", "gh", "gh"]),
+    ('ङ', ["ṅ", "N", "G", "~N", "\"n"]),
+    ('च', ["c", "c", "c", "ch", "c"]),
+    ('छ', ["ch", "C", "ch", "Ch", "ch"]),
+    ('ज', ["j", "j", "j", "j", "j"]),
+    ('झ', ["jh", "J", "jh", "jh", "jh"]),
+    ('ञ', ["ñ", "Y", "J", "~n", "~n"]),
+    ('ट', ["ṭ", "w", "T", "T", ".t"]),
+    ('ठ', ["ṭh", "W", "Th", "Th", ".th"]),
+    ('ड', ["ḍ", "q", "D", "D", ".d"]),
+    ('ढ', ["ḍh", "Q", "Dh", "Dh", ".dh"]),
+    ('ण', ["ṇ", "R", "N", "N", ".n"]),
+    ('त', ["t", "t", "t", "t", "t"]),
+    ('थ', ["th", "T", "th", "th", "th"]),
+    ('द', ["d", "d", "d", "d", "d"]),
+    ('ध', ["dh", "D", "dh", "dh", "dh"]),
+    ('न', ["n", "n", "n", "n", "n"]),
+    ('प', ["p", "p", "p", "p", "p"]),
+    ('फ', ["ph", "P", "ph", "ph", "ph"]),
+    ('ब', ["b", "b", "b", "b", "b"]),
+    ('भ', ["bh", "B", "bh", "bh", "bh"]),
+    ('म', ["m", "m", "m", "m", "m"]),
+    ('य', ["y", "y", "y", "y", "y"]),
+    ('र', ["r", "r", "r", "r", "r"]),
+    ('ल', ["l", "l", "l", "l", "l"]),
+    ('ळ', ["ḻ", "L", "L", "L", "L"]),
+    ('व', ["v", "v", "v", "v", "v"]),
+    ('श', ["ś", "S", "z", "sh", "\"s"]),
+    ('ष', ["ṣ", "z", "S", "Sh", ".s"]),
+    ('स', ["s", "s", "s", "s", "s"]),
+    ('ह', ["h", "h", "h", "h", "h"]),
+];
+
+const MARKS: &[(char, [&str; 5])] = &[
+    ('ं', ["ṃ", "M", "M", "M", ".m"]),
+    ('ः', ["ḥ", "H", "H", "H", ".h"]),
+    ('ँ', ["m̐", "~", "~", ".N", "~"]),
+    ('ऽ', ["'", "'", "'", ".a", ".a"]),
+    ('ॐ', ["oṃ", "oM", "oM", "OM", "O.m"]),
+    ('।', ["|", ".", "|", "|", "|"]),
+    ('॥', ["||", "..", "||", "||", "||"]),
+];
+
+const VIRAMA: char = '्';
+const NUKTA: char = '़';
+
+fn consonant(c: char, column: usize) -> Option<&'static str> {
+    CONSONANTS
+        .iter()
+        .find(|(deva, _)| *deva == c)
+        .map(|(_, roman)| roman[column])
+}
+
+fn vowel_sign(c: char, column: usize) -> Option<&'static str> {
+    VOWELS
+        .iter()
+        .find(|(_, sign, _)| *sign == Some(c))
+        .map(|(_, _, roman)| roman[column])
+}
+
+fn independent(c: char, column: usize) -> Option<&'static str> {
+    VOWELS
+        .iter()
+        .find(|(deva, _, _)| *deva == c)
+        .map(|(_, _, roman)| roman[column])
+        .or_else(|| {
+            MARKS
+                .iter()
+                .find(|(deva, _)| *deva == c)
+                .map(|(_, roman)| roman[column])
+        })
+}
+
+/// Romanizes Devanagari text into `scheme`. Characters outside the Devanagari
+/// block (Latin text, whitespace, page markers) pass through unchanged.
+pub fn transliterate(text: &str, scheme: Scheme) -> String {
+    let column = scheme.column();
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if let Some(roman) = consonant(c, column) {
+            out.push_str(roman);
+            if chars.peek() == Some(&NUKTA) {
+                chars.next();
+            }
+            match chars.peek().copied() {
+                Some(VIRAMA) => {
+                    chars.next();
+                }
+                Some(next) if vowel_sign(next, column).is_some() => {
+                    chars.next();
+                    out.push_str(vowel_sign(next, column).unwrap_or_default());
+                }
+                _ => out.push('a'),
+            }
+        } else if let Some(roman) = independent(c, column) {
+            out.push_str(roman);
+        } else if ('०'..='९').contains(&c) {
+            out.push(char::from(b'0' + (c as u32 - '०' as u32) as u8));
+        } else if c == VIRAMA || c == NUKTA || c == '\u{200c}' || c == '\u{200d}' {
+            // stray combining marks and joiners carry no sound of their own
+        } else {
+            out.push(c);
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMES: [Scheme; 5] = [
+        Scheme::Iast,
+        Scheme::Slp1,
+        Scheme::Hk,
+        Scheme::Itrans,
+        Scheme::Velthuis,
+    ];
+
+    #[test]
+    fn romanizes_into_every_scheme() {
+        let text = "धर्मक्षेत्रे संस्कृतम् रामः ॥";
+        let expected = [
+            "dharmakṣetre saṃskṛtam rāmaḥ ||",
+            "Darmakzetre saMskftam rAmaH ..",
+            "dharmakSetre saMskRtam rAmaH ||",
+            "dharmakShetre saMskRRitam rAmaH ||",
+            "dharmak.setre sa.msk.rtam raama.h ||",
+        ];
+        for (scheme, expected) in SCHEMES.into_iter().zip(expected) {
+            assert_eq!(transliterate(text, scheme), expected, "{:?}", scheme);
+        }
+    }
+
+    #[test]
+    fn scheme_names_round_trip() {
+        for scheme in SCHEMES {
+            let name = serde_json::to_value(scheme).unwrap();
+            assert_eq!(Scheme::parse(name.as_str().unwrap()), Some(scheme));
+        }
+        assert_eq!(Scheme::parse(" Harvard-Kyoto "), Some(Scheme::Hk));
+        assert_eq!(Scheme::parse("iso15919"), None);
+    }
+
+    /// Reads SLP1, one letter per sound, back into Devanagari with the same
+    /// tables, for the words in these tests.
+    fn from_slp1(text: &str) -> String {
+        let slp1 = Scheme::Slp1.column();
+        let vowel = |c: char| {
+            VOWELS
+                .iter()
+                .find(|(_, _, roman)| roman[slp1] == c.to_string())
+        };
+        let mut out = String::new();
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            let letter = c.to_string();
+            if let Some((deva, _)) = CONSONANTS.iter().find(|(_, roman)| roman[slp1] == letter) {
+                out.push(*deva);
+                match chars.peek().copied().and_then(vowel) {
+                    Some((_, sign, _)) => {
+                        chars.next();
+                        out.extend(*sign);
+                    }
+                    None => out.push(VIRAMA),
+                }
+            } else if let Some((deva, _, _)) = vowel(c) {
+                out.push(*deva);
+            } else if let Some((deva, _)) = MARKS.iter().find(|(_, roman)| roman[slp1] == letter) {
+                out.push(*deva);
+            } else {
+                out.push(c);
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn slp1_round_trips() {
+        for text in ["धर्मक्षेत्रे कुरुक्षेत्रे समवेता युयुत्सवः", "संस्कृतम् अइ ऐ शिवं षष्ठी ऋतौ"]
+        {
+            let slp1 = transliterate(text, Scheme::Slp1);
+            assert_eq!(from_slp1(&slp1), text, "{}", slp1);
+        }
+    }
+
+    #[test]
+    fn iast_runs_together_what_slp1_keeps_apart() {
+        assert_eq!(
+            transliterate("अइ", Scheme::Iast),
+            transliterate("ऐ", Scheme::Iast)
+        );
+        assert_ne!(
+            transliterate("अइ", Scheme::Slp1),
+            transliterate("ऐ", Scheme::Slp1)
+        );
+    }
+
+    #[test]
+    fn passes_other_text_through() {
+        assert_eq!(
+            transliterate("Page १२: गीता", Scheme::Iast),
+            "Page 12: gītā"
+        );
+    }
+}
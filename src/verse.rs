@@ -0,0 +1,176 @@
+use crate::chandas::MeterAnalysis;
+use serde::{Deserialize, Serialize};
+
+const DANDA: char = '।';
+const DOUBLE_DANDA: char = '॥';
+
+/// One verse (śloka), split into its pāda lines at daṇḍa boundaries.
+#[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct Verse {
+    /// Verse number as printed between the closing double daṇḍas, with Devanagari
+    /// digits converted to ASCII (`"12"`, `"1.12"`).
+    pub number: Option<String>,
+    /// Page on which the verse starts.
+    pub page_number: usize,
+    pub lines: Vec<String>,
+    /// Meter scan, when meter identification was requested.
+    pub meter: Option<MeterAnalysis>,
+}
+
+fn ascii_digit(c: char) -> Option<char> {
+    match c {
+        '0'..='9' => Some(c),
+        '०'..='९' => char::from_u32(c as u32 - '०' as u32 + '0' as u32),
+        _ => None,
+    }
+}
+
+/// Reads a verse number (`१२`, `1.12`, `२-३`) at the start of `chars`, returning it in
+/// ASCII along with the number of characters consumed, including any spaces and the
+/// closing double daṇḍa.
+fn verse_number(chars: &[char]) -> Option<(String, usize)> {
+    let mut i = 0;
+    while chars.get(i).is_some_and(|c| c.is_whitespace()) {
+        i += 1;
+    }
+
+    let mut number = String::new();
+    while let Some(&c) = chars.get(i) {
+        match ascii_digit(c) {
+            Some(digit) => number.push(digit),
+            None if matches!(c, '.' | '-' | ':') && !number.is_empty() => number.push(c),
+            None => break,
+        }
+        i += 1;
+    }
+    if number.is_empty() {
+        return None;
+    }
+
+    while chars.get(i).is_some_and(|c| c.is_whitespace()) {
+        i += 1;
+    }
+    (chars.get(i) == Some(&DOUBLE_DANDA)).then(|| (number, i + 1))
+}
+
+/// Accumulates verses across page boundaries.
+struct Builder {
+    verses: Vec<Verse>,
+    line: String,
+    lines: Vec<String>,
+    start_page: Option<usize>,
+}
+
+impl Builder {
+    fn push_char(&mut self, c: char, page_number: usize) {
+        if c.is_whitespace() {
+            if !self.line.is_empty() && !self.line.ends_with(' ') {
+                self.line.push(' ');
+            }
+            return;
+        }
+        self.start_page.get_or_insert(page_number);
+        self.line.push(c);
+    }
+
+    fn end_line(&mut self) {
+        let line = self.line.trim();
+        if !line.is_empty() {
+            self.lines.push(line.to_string());
+        }
+        self.line.clear();
+    }
+
+    fn end_verse(&mut self, number: Option<String>, page_number: usize) {
+        self.end_line();
+        if !self.lines.is_empty() {
+            self.verses.push(Verse {
+                number,
+                page_number: self.start_page.unwrap_or(page_number),
+                lines: std::mem::take(&mut self.lines),
+                meter: None,
+            });
+        }
+        self.start_page = None;
+    }
+}
+
+/// Splits page texts into verses: a single daṇḍa ends a pāda line, a double daṇḍa
+/// ends the verse, and a number enclosed by double daṇḍas (`॥ १२ ॥`) labels it.
+/// Pipes are accepted for daṇḍas since tesseract often emits them. Text after the
+/// last double daṇḍa is not a complete verse and is left out.
+pub fn detect<'a>(pages: impl IntoIterator<Item = (usize, &'a str)>) -> Vec<Verse> {
+    let mut builder = Builder {
+        verses: Vec::new(),
+        line: String::new(),
+        lines: Vec::new(),
+        start_page: None,
+    };
+
+    for (page_number, text) in pages {
+        let text = text.replace("||", "॥").replace('|', "।");
+        let chars: Vec<char> = text.chars().collect();
+        let mut i = 0;
+
+        while i < chars.len() {
+            match chars[i] {
+                DANDA => builder.end_line(),
+                DOUBLE_DANDA => match verse_number(&chars[i + 1..]) {
+                    Some((number, consumed)) => {
+                        builder.end_verse(Some(number), page_number);
+                        i += consumed;
+                    }
+                    None => builder.end_verse(None, page_number),
+                },
+                c => builder.push_char(c, page_number),
+            }
+            i += 1;
+        }
+        builder.push_char(' ', page_number);
+    }
+
+    builder.verses
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_verses_at_dandas() {
+        let verses = detect([(
+            1,
+            "धर्मक्षेत्रे कुरुक्षेत्रे समवेता युयुत्सवः ।\nमामकाः पाण्डवाश्चैव किमकुर्वत सञ्जय ॥ १-१॥",
+        )]);
+        assert_eq!(verses.len(), 1);
+        assert_eq!(verses[0].number.as_deref(), Some("1-1"));
+        assert_eq!(
+            verses[0].lines,
+            [
+                "धर्मक्षेत्रे कुरुक्षेत्रे समवेता युयुत्सवः",
+                "मामकाः पाण्डवाश्चैव किमकुर्वत सञ्जय",
+            ]
+        );
+    }
+
+    #[test]
+    fn carries_a_verse_across_a_page_break() {
+        let verses = detect([
+            (4, "रामो राजमणिः सदा विजयते |"),
+            (5, "रामं रमेशं भजे || 2 ||\nइति"),
+        ]);
+        assert_eq!(verses.len(), 1);
+        assert_eq!(verses[0].page_number, 4);
+        assert_eq!(verses[0].number.as_deref(), Some("2"));
+        assert_eq!(verses[0].lines.len(), 2);
+    }
+
+    #[test]
+    fn leaves_out_unnumbered_text_after_the_last_verse() {
+        let verses = detect([(1, "प्रथमः ॥\nअपूर्णः ।")]);
+        assert_eq!(verses.len(), 1);
+        assert_eq!(verses[0].number, None);
+        assert_eq!(verses[0].lines, ["प्रथमः"]);
+    }
+}